@@ -0,0 +1,90 @@
+//! Formats source files in place.
+//!
+//!     agfmt [--check] [--width N] [FILE]...
+//!
+//! Without files, formats standard input to standard output.  With `--check`
+//! nothing is written: the files that are not formatted are listed, and the
+//! exit status is 1 if there are any.  Errors exit with status 2.
+
+extern crate argentum;
+
+use std::env;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::process;
+
+use argentum::syntax::fmt::{self, Config};
+
+const USAGE: &str = "usage: agfmt [--check] [--width N] [FILE]...";
+
+fn main() {
+    let mut config = Config::default();
+    let mut check = false;
+    let mut files = Vec::new();
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--check" => check = true,
+            "--width" => match args.next().and_then(|width| width.parse().ok()) {
+                Some(width) => config.width = width,
+                None        => fail(USAGE),
+            },
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return;
+            },
+            _ if arg.starts_with('-') && arg != "-" => fail(USAGE),
+            _ => files.push(arg),
+        }
+    }
+
+    if files.is_empty() {
+        let mut text = String::new();
+        if let Err(err) = io::stdin().read_to_string(&mut text) {
+            fail(&format!("<stdin>: {}", err));
+        }
+        let formatted = format("<stdin>", &text, &config);
+        if check {
+            if formatted != text {
+                println!("<stdin>");
+                process::exit(1);
+            }
+        } else if let Err(err) = io::stdout().write_all(formatted.as_bytes()) {
+            fail(&format!("<stdout>: {}", err));
+        }
+        return;
+    }
+
+    let mut unformatted = false;
+    for file in &files {
+        let text = match fs::read_to_string(file) {
+            Ok(text) => text,
+            Err(err) => fail(&format!("{}: {}", file, err)),
+        };
+        let formatted = format(file, &text, &config);
+        if formatted == text {
+            continue;
+        }
+        if check {
+            println!("{}", file);
+            unformatted = true;
+        } else if let Err(err) = fs::write(file, formatted) {
+            fail(&format!("{}: {}", file, err));
+        }
+    }
+    if unformatted {
+        process::exit(1);
+    }
+}
+
+fn format(source: &str, text: &str, config: &Config) -> String {
+    match fmt::format_source(source, text, config) {
+        Ok(formatted) => formatted,
+        Err(err)      => fail(&err),
+    }
+}
+
+fn fail(msg: &str) -> ! {
+    eprintln!("agfmt: {}", msg);
+    process::exit(2);
+}
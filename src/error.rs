@@ -2,8 +2,10 @@
 // Error includes LexerError and LexerErrorKind.
 // It implements the From trait for LexicalDiagnostic struct which displays
 // source filename, span position, severity of error and message struct fields.
+// ModuleError, ResolveError, TypeError, ConstError and RuntimeError follow the
+// same layout and convert into a Diagnostic.
 //
-use utils::{LexicalDiagnostic, Severity, Span};
+use utils::{Diagnostic, LexicalDiagnostic, Position, Severity, Span};
 
 /// LexerError includes all field items required by the LexicalDiagnostic struct.
 #[derive(PartialEq, Eq, Clone, Debug)]
@@ -21,6 +23,10 @@ pub enum LexerErrorKind {
     Unreachable,
     InvalidNumericLiteral,
     UnterminatedStringLiteral,
+    UnterminatedCharLiteral,
+    UnterminatedInterpolation,
+    UnterminatedBlockComment,
+    InvalidRawStringDelimiter,
     InvalidEscapeChar,
     InvalidUnicodeEscSeqChar,
 }
@@ -31,6 +37,10 @@ impl From<LexerError> for LexicalDiagnostic {
             LexerErrorKind::UnknownChar               => "unknown character",
             LexerErrorKind::InvalidNumericLiteral     => "invalid numeric literal",
             LexerErrorKind::UnterminatedStringLiteral => "unexpected EOF while scanning string literal",
+            LexerErrorKind::UnterminatedCharLiteral   => "expected `'` to close a char literal",
+            LexerErrorKind::UnterminatedInterpolation => "unexpected EOF while scanning string interpolation",
+            LexerErrorKind::UnterminatedBlockComment  => "unexpected EOF while scanning block comment",
+            LexerErrorKind::InvalidRawStringDelimiter => "expected `\"` after the `#`s of a raw string",
             LexerErrorKind::InvalidEscapeChar         => "invalid escape character",
             LexerErrorKind::InvalidUnicodeEscSeqChar  => "invalid unicode escape sequence character",
             LexerErrorKind::Unreachable               => "unreachable error"
@@ -43,3 +53,376 @@ impl From<LexerError> for LexicalDiagnostic {
         }
     }
 }
+
+/// ModuleError is raised while loading the modules reachable from an entry
+/// file and resolving their `use` declarations.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct ModuleError {
+    pub source:   String,
+    pub span:     Span,
+    pub severity: Severity,
+    pub kind:     ModuleErrorKind
+}
+
+/// ModuleErrorKind holds all the error variants for module resolution.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum ModuleErrorKind {
+    /// The module file could not be read.
+    Io(String),
+    /// The module file failed to parse.
+    Parse(String),
+    /// No module or item matches the imported path.
+    UnresolvedImport(String),
+    /// No item matches the qualified name used in an expression.
+    UnresolvedPath(String),
+    /// The imported item exists but is not declared `pub`.
+    PrivateItem(String),
+    /// The chain of module files that import each other.
+    ImportCycle(Vec<String>),
+}
+
+impl From<ModuleError> for Diagnostic {
+    fn from(err: ModuleError) -> Diagnostic {
+        let message = match err.kind {
+            ModuleErrorKind::Io(ref msg)              => format!("cannot read module: {}", msg),
+            ModuleErrorKind::Parse(ref msg)           => format!("cannot parse module: {}", msg),
+            ModuleErrorKind::UnresolvedImport(ref p)  => format!("unresolved import `{}`", p),
+            ModuleErrorKind::UnresolvedPath(ref p)    => format!("cannot find `{}`", p),
+            ModuleErrorKind::PrivateItem(ref p)       => format!("`{}` is private", p),
+            ModuleErrorKind::ImportCycle(ref chain)   => format!("import cycle: {}", chain.join(" -> ")),
+        };
+        Diagnostic {
+            source:   err.source,
+            span:     err.span,
+            severity: err.severity,
+            msg:      message
+        }
+    }
+}
+
+/// ResolveError is reported while binding the names used in a module to
+/// their declarations.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct ResolveError {
+    pub source:   String,
+    pub span:     Span,
+    pub severity: Severity,
+    pub kind:     ResolveErrorKind
+}
+
+/// ResolveErrorKind holds all the error variants for name resolution.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum ResolveErrorKind {
+    /// No declaration of the name is in scope.
+    UndefinedName(String),
+    /// No type alias or type parameter of the name is in scope.
+    UndefinedType(String),
+    /// The name is declared twice in the same scope; `previous` locates the
+    /// first declaration.
+    DuplicateDefinition { name: String, previous: Span },
+    /// A variable is used before the `let` that declares it.
+    UseBeforeDeclaration(String),
+}
+
+impl From<ResolveError> for Diagnostic {
+    fn from(err: ResolveError) -> Diagnostic {
+        let message = match err.kind {
+            ResolveErrorKind::UndefinedName(ref name) => {
+                format!("cannot find `{}` in this scope", name)
+            },
+            ResolveErrorKind::UndefinedType(ref name) => {
+                format!("cannot find type `{}` in this scope", name)
+            },
+            ResolveErrorKind::DuplicateDefinition { ref name, previous } => {
+                let Position(row, col) = previous.0;
+                format!("`{}` is defined more than once, first at {}:{}", name, row, col)
+            },
+            ResolveErrorKind::UseBeforeDeclaration(ref name) => {
+                format!("`{}` is used before its declaration", name)
+            },
+        };
+        Diagnostic {
+            source:   err.source,
+            span:     err.span,
+            severity: err.severity,
+            msg:      message
+        }
+    }
+}
+
+/// TypeError is reported by the type checker.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct TypeError {
+    pub source:   String,
+    pub span:     Span,
+    pub severity: Severity,
+    pub kind:     TypeErrorKind
+}
+
+/// TypeErrorKind holds all the error variants for type checking.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum TypeErrorKind {
+    /// An expression has a different type than its context requires.
+    Mismatch { expected: String, found: String },
+    /// The branches of a conditional expression have different types.
+    BranchMismatch { then: String, otherwise: String },
+    /// A cast between types that do not convert into each other.
+    InvalidCast { from: String, to: String },
+    /// A value of optional type is used before checking it for nil.
+    UncheckedOptional(String),
+    /// A `for` loop over a type that is not iterable.
+    NotIterable(String),
+    /// Indexing into a type that is neither an array nor a map.
+    NotIndexable(String),
+    /// A tuple or struct field that does not exist on the type.
+    UnknownField { ty: String, field: String },
+    /// A struct literal that leaves out a field of the struct.
+    MissingField { ty: String, field: String },
+    /// A struct literal that gives a field more than once.
+    DuplicateField(String),
+    /// A struct literal naming a type that is not a struct.
+    NotAStruct(String),
+    /// An annotation names a type that is not declared.
+    UnknownType(String),
+    /// `break` or `continue` outside of a loop of the current function.
+    OutsideLoop(String),
+    /// A `break` or `continue` label that no enclosing loop has.
+    UndeclaredLabel(String),
+    /// `break` with a value out of a `for` or `while` loop.
+    BreakWithValue,
+    /// A format string whose placeholders do not match the arguments.
+    FormatArgs { placeholders: usize, args: usize },
+    /// A type parameter bound names a model that is not declared.
+    UnknownModel(String),
+    /// A type argument that does not mount a model its parameter is bounded by.
+    Unmounted { ty: String, model: String },
+    /// A struct, spec or alias given more or fewer type arguments than it has
+    /// type parameters.
+    TypeArgCount { name: String, expected: usize, found: usize },
+    /// A `match` on a value that is not of a spec type.
+    NotASpec(String),
+    /// A pattern naming a variant its spec does not have.
+    UnknownVariant(String),
+    /// A variant pattern with more or fewer patterns than the variant has values.
+    VariantArity { variant: String, expected: usize, found: usize },
+    /// A `match` without arms for the listed variants.
+    NonExhaustive(Vec<String>),
+    /// The chain of type aliases that refer to each other.
+    AliasCycle(Vec<String>),
+    /// An operator applied to an operand of a type it does not take.
+    InvalidOperand { op: String, ty: String },
+    /// A function body that may end without returning a value of the type.
+    MissingReturn(String),
+    /// A call with more or fewer arguments than the function has parameters.
+    ArgCount { expected: usize, found: usize },
+    /// A call of a value that is not a function, named by its type.
+    NotCallable(String),
+    /// An integer literal that does not fit in its integer type.
+    LiteralOutOfRange { literal: String, ty: String },
+    /// Like `Mismatch`, for an annotation written with a type alias.
+    AliasMismatch { alias: String, expected: String, found: String },
+}
+
+impl From<TypeError> for Diagnostic {
+    fn from(err: TypeError) -> Diagnostic {
+        let message = match err.kind {
+            TypeErrorKind::Mismatch { ref expected, ref found } => {
+                format!("mismatched types: expected `{}`, found `{}`", expected, found)
+            },
+            TypeErrorKind::BranchMismatch { ref then, ref otherwise } => {
+                format!("conditional branches have different types: `{}` and `{}`", then, otherwise)
+            },
+            TypeErrorKind::InvalidCast { ref from, ref to } => {
+                format!("invalid cast from `{}` to `{}`", from, to)
+            },
+            TypeErrorKind::UncheckedOptional(ref ty) => {
+                format!("optional `{}` used without checking for nil", ty)
+            },
+            TypeErrorKind::NotIterable(ref ty)  => format!("type `{}` is not iterable", ty),
+            TypeErrorKind::NotIndexable(ref ty) => format!("type `{}` cannot be indexed", ty),
+            TypeErrorKind::UnknownField { ref ty, ref field } => {
+                format!("no field `{}` on type `{}`", field, ty)
+            },
+            TypeErrorKind::MissingField { ref ty, ref field } => {
+                format!("missing field `{}` in struct `{}`", field, ty)
+            },
+            TypeErrorKind::DuplicateField(ref field) => {
+                format!("field `{}` is given more than once", field)
+            },
+            TypeErrorKind::NotAStruct(ref name) => format!("`{}` is not a struct", name),
+            TypeErrorKind::UnknownType(ref name) => format!("cannot find type `{}`", name),
+            TypeErrorKind::UnknownModel(ref name) => format!("cannot find model `{}`", name),
+            TypeErrorKind::Unmounted { ref ty, ref model } => {
+                format!("type `{}` does not mount model `{}`", ty, model)
+            },
+            TypeErrorKind::TypeArgCount { ref name, expected, found } => {
+                format!("`{}` takes {} type argument{} but {} {} supplied",
+                        name, expected, if expected == 1 { "" } else { "s" },
+                        found, if found == 1 { "was" } else { "were" })
+            },
+            TypeErrorKind::NotASpec(ref ty) => format!("cannot match on type `{}`", ty),
+            TypeErrorKind::UnknownVariant(ref path) => format!("cannot find variant `{}`", path),
+            TypeErrorKind::VariantArity { ref variant, expected, found } => {
+                format!("variant `{}` holds {} value{} but the pattern has {}",
+                        variant, expected, if expected == 1 { "" } else { "s" }, found)
+            },
+            TypeErrorKind::NonExhaustive(ref variants) => {
+                let variants: Vec<_> = variants.iter().map(|v| format!("`{}`", v)).collect();
+                format!("non-exhaustive match: {} not covered", variants.join(", "))
+            },
+            TypeErrorKind::FormatArgs { placeholders, args } => {
+                format!("format string has {} placeholder{} but {} argument{} given",
+                        placeholders, if placeholders == 1 { "" } else { "s" },
+                        args, if args == 1 { " was" } else { "s were" })
+            },
+            TypeErrorKind::OutsideLoop(ref keyword) => format!("`{}` outside of a loop", keyword),
+            TypeErrorKind::UndeclaredLabel(ref label) => format!("use of undeclared label `'{}`", label),
+            TypeErrorKind::BreakWithValue => {
+                "`break` with a value is only allowed in `loop`".to_string()
+            },
+            TypeErrorKind::AliasCycle(ref chain) => {
+                format!("type alias `{}` refers to itself: {}", chain[0], chain.join(" -> "))
+            },
+            TypeErrorKind::AliasMismatch { ref alias, ref expected, ref found } => {
+                format!("mismatched types: expected `{}` (`{}`), found `{}`", alias, expected, found)
+            },
+            TypeErrorKind::InvalidOperand { ref op, ref ty } => {
+                format!("cannot apply `{}` to `{}`", op, ty)
+            },
+            TypeErrorKind::MissingReturn(ref ty) => {
+                format!("function may end without returning a value of type `{}`", ty)
+            },
+            TypeErrorKind::ArgCount { expected, found } => {
+                format!("function takes {} argument{} but {} {} supplied",
+                        expected, if expected == 1 { "" } else { "s" },
+                        found, if found == 1 { "was" } else { "were" })
+            },
+            TypeErrorKind::NotCallable(ref ty) => format!("type `{}` is not a function", ty),
+            TypeErrorKind::LiteralOutOfRange { ref literal, ref ty } => {
+                format!("literal `{}` does not fit in `{}`", literal, ty)
+            },
+        };
+        Diagnostic {
+            source:   err.source,
+            span:     err.span,
+            severity: err.severity,
+            msg:      message
+        }
+    }
+}
+
+/// ConstError is raised while evaluating `const` items at compile time.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct ConstError {
+    pub source:   String,
+    pub span:     Span,
+    pub severity: Severity,
+    pub kind:     ConstErrorKind
+}
+
+/// ConstErrorKind holds all the error variants for constant evaluation.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum ConstErrorKind {
+    /// The initializer of the named constant uses an expression that cannot
+    /// be evaluated at compile time, e.g. a function call.
+    NotConstant { name: String, reason: String },
+    /// The chain of constants whose initializers refer to each other.
+    Cycle(Vec<String>),
+    /// Integer arithmetic in the initializer of the named constant leaves
+    /// the range of `i128`.
+    Overflow(String),
+}
+
+impl From<ConstError> for Diagnostic {
+    fn from(err: ConstError) -> Diagnostic {
+        let message = match err.kind {
+            ConstErrorKind::NotConstant { ref name, ref reason } => {
+                format!("initializer of `{}` is not constant: {}", name, reason)
+            },
+            ConstErrorKind::Cycle(ref chain) => {
+                format!("constants refer to each other: {}", chain.join(" -> "))
+            },
+            ConstErrorKind::Overflow(ref name) => {
+                format!("evaluating `{}` overflows", name)
+            },
+        };
+        Diagnostic {
+            source:   err.source,
+            span:     err.span,
+            severity: err.severity,
+            msg:      message
+        }
+    }
+}
+
+/// RuntimeError is raised by the interpreter while evaluating a module.
+#[derive(PartialEq, Clone, Debug)]
+pub struct RuntimeError {
+    pub source:   String,
+    pub span:     Span,
+    pub severity: Severity,
+    pub kind:     RuntimeErrorKind
+}
+
+/// RuntimeErrorKind holds all the error variants for the evaluation state.
+#[derive(PartialEq, Clone, Debug)]
+pub enum RuntimeErrorKind {
+    /// No variable with the name is in scope.
+    UndefinedVariable(String),
+    /// The left hand side of an assignment is not a variable.
+    InvalidAssignment,
+    /// The callee is not a function, named by its type.
+    NotCallable(String),
+    /// A function was called with the wrong number of arguments.
+    ArityMismatch { expected: usize, found: usize },
+    /// An operator or method was applied to values of the wrong type.
+    TypeMismatch(String),
+    /// No builtin method with the name exists for the receiver.
+    UnknownMethod(String),
+    /// A `for` loop over a value that is not iterable, named by its type.
+    NotIterable(String),
+    /// An array index past the end of the array.
+    IndexOutOfBounds { index: i128, len: usize },
+    DivisionByZero,
+    /// Integer arithmetic whose result does not fit its type, named.
+    Overflow(String),
+    /// `return` outside of a function body.
+    ReturnOutsideFn,
+    /// `break` or `continue` outside of a loop, or to a label no enclosing
+    /// loop has.
+    OutsideLoop(String),
+    /// Writing the output of `print` failed.
+    Output(String),
+}
+
+impl From<RuntimeError> for Diagnostic {
+    fn from(err: RuntimeError) -> Diagnostic {
+        let message = match err.kind {
+            RuntimeErrorKind::UndefinedVariable(ref name) => format!("undefined variable `{}`", name),
+            RuntimeErrorKind::InvalidAssignment           => "invalid assignment target".to_string(),
+            RuntimeErrorKind::NotCallable(ref ty)         => format!("value of type `{}` is not callable", ty),
+            RuntimeErrorKind::ArityMismatch { expected, found } => {
+                format!("expected {} arguments, found {}", expected, found)
+            },
+            RuntimeErrorKind::TypeMismatch(ref msg)       => format!("type mismatch: {}", msg),
+            RuntimeErrorKind::UnknownMethod(ref name)     => format!("unknown method `{}`", name),
+            RuntimeErrorKind::NotIterable(ref ty)         => format!("value of type `{}` is not iterable", ty),
+            RuntimeErrorKind::IndexOutOfBounds { index, len } => {
+                format!("index {} out of bounds for array of length {}", index, len)
+            },
+            RuntimeErrorKind::DivisionByZero              => "division by zero".to_string(),
+            RuntimeErrorKind::Overflow(ref ty)            => {
+                format!("arithmetic overflow: the result does not fit in `{}`", ty)
+            },
+            RuntimeErrorKind::ReturnOutsideFn             => "`return` outside of a function".to_string(),
+            RuntimeErrorKind::OutsideLoop(ref exit)       => format!("`{}` outside of a loop", exit),
+            RuntimeErrorKind::Output(ref err)             => format!("cannot write output: {}", err),
+        };
+        Diagnostic {
+            source:   err.source,
+            span:     err.span,
+            severity: err.severity,
+            msg:      message
+        }
+    }
+}
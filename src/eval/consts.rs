@@ -0,0 +1,404 @@
+use std::collections::HashMap;
+use std::mem;
+
+use error::{ConstError, ConstErrorKind};
+use syntax::ast::binop::{ArithmeticOp, BitWiseOp, LogicalOp};
+use syntax::ast::fold::{noop_fold_arm, noop_fold_block, noop_fold_closure, noop_fold_expr,
+                       noop_fold_fn_decl, noop_fold_for_loop, noop_fold_item, noop_fold_pattern,
+                       Folder};
+use syntax::ast::{Arena, Arm, BinaryOp, Block, Closure, ConstDecl, Expr, ExprKind, FnDecl,
+                  ForLoop, Fragment, Item, ItemKind, Literal, Module, Pattern, PatternKind};
+use utils::{Diagnostic, Severity};
+
+use super::Interpreter;
+use super::value::Value;
+
+/// Values of the module level constants, by name.
+pub type Consts = HashMap<String, Literal>;
+
+/// Evaluates the `const` items of `module`.  Constants may refer to each
+/// other in any order; initializers are limited to literals, other constants,
+/// operators, casts and conditionals.  Integer arithmetic that overflows the
+/// `i128` the interpreter computes with is an error; whether the value fits
+/// the declared type is left to the type checker, which sees it once the
+/// constants are inlined.
+pub fn eval_consts(source: &str, module: &Module) -> Result<Consts, Vec<Diagnostic>> {
+    let mut eval = ConstEval {
+        source:     source.to_string(),
+        interp:     Interpreter::new(source),
+        decls:      HashMap::new(),
+        values:     HashMap::new(),
+        evaluating: Vec::new(),
+        errors:     Vec::new(),
+    };
+    let mut names = Vec::new();
+    for item in module.items() {
+        if let ItemKind::Const(ref decl) = item.kind {
+            eval.decls.insert(decl.name.as_str(), decl);
+            names.push(decl.name.as_str());
+        }
+    }
+    for name in names {
+        eval.constant(name);
+    }
+    if eval.errors.is_empty() {
+        Ok(eval.values.into_iter().filter_map(|(name, value)| value.map(|lit| (name, lit))).collect())
+    } else {
+        Err(eval.errors)
+    }
+}
+
+/// Replaces the uses of constants in `module`, and their initializers, by
+/// their values.  Variables and parameters shadowing a constant are left
+/// alone.  The folded nodes are allocated in `arena`.
+pub fn inline_consts<'ast>(arena: &'ast Arena<'ast>, module: Module<'ast>, consts: &Consts)
+    -> Module<'ast>
+{
+    Inliner { arena, consts, shadowed: Vec::new() }.fold_module(module)
+}
+
+struct ConstEval<'a> {
+    source:     String,
+    interp:     Interpreter<'a>,
+    decls:      HashMap<&'a str, &'a ConstDecl<'a>>,
+    /// Evaluated constants, `None` for those that failed.
+    values:     HashMap<String, Option<Literal>>,
+    /// Constants whose initializers are being evaluated, used to detect cycles.
+    evaluating: Vec<&'a str>,
+    errors:     Vec<Diagnostic>,
+}
+
+impl<'a> ConstEval<'a> {
+    fn constant(&mut self, name: &'a str) -> Option<Literal> {
+        if let Some(value) = self.values.get(name) {
+            return value.clone();
+        }
+        if let Some(start) = self.evaluating.iter().position(|&n| n == name) {
+            let mut chain: Vec<String> = self.evaluating[start..].iter().map(|n| n.to_string()).collect();
+            chain.push(name.to_string());
+            self.error(ConstErrorKind::Cycle(chain));
+            return None;
+        }
+        let decl = self.decls[name];
+        self.evaluating.push(name);
+        let value = self.eval(&decl.value, name).and_then(|value| self.literal(value, name));
+        self.evaluating.pop();
+        // Members of a cycle are only reported once.
+        self.values.insert(name.to_string(), value.clone());
+        value
+    }
+
+    /// Evaluates `expr`, with errors pointing at it.  The location is kept by
+    /// the interpreter, which reports errors of the operators too.
+    fn eval(&mut self, expr: &Expr<'a>, name: &'a str) -> Option<Value<'a>> {
+        let outer = mem::replace(&mut self.interp.span, expr.span);
+        let value = self.eval_kind(&expr.kind, name);
+        self.interp.span = outer;
+        value
+    }
+
+    fn eval_kind(&mut self, expr: &ExprKind<'a>, name: &'a str) -> Option<Value<'a>> {
+        let result = match *expr {
+            ExprKind::Literal(ref lit) => return Some(Value::from(lit)),
+            ExprKind::Interpolated(fragments) => {
+                let mut text = String::new();
+                for fragment in fragments {
+                    match *fragment {
+                        Fragment::Str(ref value, _) => text.push_str(value),
+                        Fragment::Expr(ref expr, _) => {
+                            text.push_str(&self.eval(expr, name)?.to_string())
+                        },
+                    }
+                }
+                return Some(Value::Str(text));
+            },
+            ExprKind::Identifier(ref ident) => {
+                let decl = self.decls.get(ident.as_str()).cloned();
+                return match decl {
+                    Some(decl) => self.constant(&decl.name).map(|lit| Value::from(&lit)),
+                    None => self.not_constant(name, format!("`{}` is not a constant", ident)),
+                };
+            },
+            ExprKind::Unary(op, operand) => {
+                let operand = self.eval(operand, name)?;
+                self.interp.unary(op, operand)
+            },
+            ExprKind::Binary(BinaryOp::Logical(op), lhs, rhs) => {
+                let lhs = self.eval(lhs, name)?;
+                match (op, self.interp.truthy(lhs)) {
+                    (LogicalOp::And, Ok(false)) => Ok(Value::Bool(false)),
+                    (LogicalOp::Or, Ok(true))   => Ok(Value::Bool(true)),
+                    (_, Ok(_))                  => {
+                        let rhs = self.eval(rhs, name)?;
+                        self.interp.truthy(rhs).map(Value::Bool)
+                    },
+                    (_, Err(err))               => Err(err),
+                }
+            },
+            ExprKind::Binary(op, lhs, rhs) => {
+                let lhs = self.eval(lhs, name)?;
+                let rhs = self.eval(rhs, name)?;
+                if let (&Value::Int(a), &Value::Int(b)) = (&lhs, &rhs) {
+                    if overflows(op, a, b) {
+                        self.error(ConstErrorKind::Overflow(name.to_string()));
+                        return None;
+                    }
+                }
+                self.interp.binary(op, lhs, rhs)
+            },
+            ExprKind::Cast(operand, target) => {
+                let operand = self.eval(operand, name)?;
+                self.interp.cast(operand, target)
+            },
+            ExprKind::Ternary(cond, then, otherwise) => {
+                let cond = self.eval(cond, name)?;
+                match self.interp.truthy(cond) {
+                    Ok(true)  => return self.eval(then, name),
+                    Ok(false) => return self.eval(otherwise, name),
+                    Err(err)  => Err(err),
+                }
+            },
+            ref other => return self.not_constant(name, describe(other).to_string()),
+        };
+        match result {
+            Ok(value) => Some(value),
+            Err(err)  => {
+                self.errors.push(Diagnostic::from(err));
+                None
+            },
+        }
+    }
+
+    /// Converts the value of a constant back to a literal.
+    fn literal(&mut self, value: Value<'a>, name: &'a str) -> Option<Literal> {
+        match value {
+            Value::Nil      => Some(Literal::Nil),
+            Value::Bool(b)  => Some(Literal::Boolean(b)),
+            Value::Char(c)  => Some(Literal::Char(c)),
+            Value::Int(i)   => Some(Literal::Int(i)),
+            Value::Float(x) => Some(Literal::Float(x)),
+            Value::Str(s)   => Some(Literal::Str(s)),
+            other           => self.not_constant(name, format!("values of type `{}`", other.type_name())),
+        }
+    }
+
+    fn not_constant<T>(&mut self, name: &str, reason: String) -> Option<T> {
+        self.error(ConstErrorKind::NotConstant { name: name.to_string(), reason });
+        None
+    }
+
+    fn error(&mut self, kind: ConstErrorKind) {
+        self.errors.push(Diagnostic::from(ConstError {
+            source:   self.source.clone(),
+            span:     self.interp.span,
+            severity: Severity::Error,
+            kind
+        }));
+    }
+}
+
+/// Whether applying `op` to the integers `a` and `b` leaves the range of
+/// `i128`, reported as an error of the constant rather than at runtime.
+fn overflows(op: BinaryOp, a: i128, b: i128) -> bool {
+    match op {
+        BinaryOp::Arithmetic(ArithmeticOp::Add) => a.checked_add(b).is_none(),
+        BinaryOp::Arithmetic(ArithmeticOp::Sub) => a.checked_sub(b).is_none(),
+        BinaryOp::Arithmetic(ArithmeticOp::Mul) => a.checked_mul(b).is_none(),
+        BinaryOp::Arithmetic(ArithmeticOp::Div) => a.checked_div(b).is_none() && b != 0,
+        BinaryOp::BitWise(BitWiseOp::BwShftL)   => {
+            !(0..128).contains(&b) || a.wrapping_shl(b as u32).wrapping_shr(b as u32) != a
+        },
+        BinaryOp::BitWise(BitWiseOp::BwShftR)   => !(0..128).contains(&b),
+        _                                       => false,
+    }
+}
+
+/// Names the kind of expression that is not allowed in a constant.
+fn describe(expr: &ExprKind) -> &'static str {
+    match *expr {
+        ExprKind::Path(_)                                           => "imported names",
+        ExprKind::Call(..) | ExprKind::MethodCall(..) |
+        ExprKind::SafeMethodCall(..)                                => "function calls",
+        ExprKind::Assign(..)                                        => "assignments",
+        ExprKind::Range(..)                                         => "ranges",
+        ExprKind::Array(_) | ExprKind::Map(_) | ExprKind::Index(..) => "collections",
+        ExprKind::Tuple(_) | ExprKind::Field(..)                    => "tuples",
+        ExprKind::Struct(..) | ExprKind::Member(..)                 => "structs",
+        ExprKind::Closure(_)                                        => "closures",
+        ExprKind::If(..) | ExprKind::Loop(..) | ExprKind::Block(_) |
+        ExprKind::Match(..)                                         => "blocks",
+        _                                                           => "this expression",
+    }
+}
+
+struct Inliner<'a, 'ast> {
+    arena:    &'ast Arena<'ast>,
+    consts:   &'a Consts,
+    /// Local names declared in the enclosing scopes, innermost last.
+    shadowed: Vec<String>,
+}
+
+impl<'a, 'ast> Folder<'ast> for Inliner<'a, 'ast> {
+    fn arena(&self) -> &'ast Arena<'ast> {
+        self.arena
+    }
+
+    fn fold_item(&mut self, item: Item<'ast>) -> Item<'ast> {
+        let mut item = noop_fold_item(item, self);
+        if let ItemKind::Const(ref mut decl) = item.kind {
+            if let Some(lit) = self.consts.get(&decl.name) {
+                let value = &decl.value;
+                decl.value = Expr::new(value.id, ExprKind::Literal(lit.clone()), value.span,
+                                       value.file);
+            }
+        }
+        item
+    }
+
+    fn fold_fn_decl(&mut self, decl: FnDecl<'ast>) -> FnDecl<'ast> {
+        let mark = self.shadowed.len();
+        let decl = noop_fold_fn_decl(decl, self);
+        self.shadowed.truncate(mark);
+        decl
+    }
+
+    fn fold_closure(&mut self, closure: Closure<'ast>) -> Closure<'ast> {
+        let mark = self.shadowed.len();
+        let closure = noop_fold_closure(closure, self);
+        self.shadowed.truncate(mark);
+        closure
+    }
+
+    fn fold_for_loop(&mut self, for_loop: ForLoop<'ast>) -> ForLoop<'ast> {
+        let mark = self.shadowed.len();
+        let for_loop = noop_fold_for_loop(for_loop, self);
+        self.shadowed.truncate(mark);
+        for_loop
+    }
+
+    fn fold_arm(&mut self, arm: Arm<'ast>) -> Arm<'ast> {
+        let mark = self.shadowed.len();
+        let arm = noop_fold_arm(arm, self);
+        self.shadowed.truncate(mark);
+        arm
+    }
+
+    fn fold_block(&mut self, block: Block<'ast>) -> Block<'ast> {
+        let mark = self.shadowed.len();
+        let block = noop_fold_block(block, self);
+        self.shadowed.truncate(mark);
+        block
+    }
+
+    /// Parameters, `let`, `for` and arm patterns are folded before the scope
+    /// they declare their names in.
+    fn fold_pattern(&mut self, pat: Pattern<'ast>) -> Pattern<'ast> {
+        if let PatternKind::Ident(ref name) = pat.kind {
+            self.shadowed.push(name.clone());
+        }
+        noop_fold_pattern(pat, self)
+    }
+
+    fn fold_expr(&mut self, expr: Expr<'ast>) -> Expr<'ast> {
+        let value = match expr.kind {
+            ExprKind::Identifier(ref name) if !self.shadowed.contains(name) => {
+                self.consts.get(name).cloned()
+            },
+            _ => None,
+        };
+        match value {
+            Some(lit) => Expr::new(expr.id, ExprKind::Literal(lit), expr.span, expr.file),
+            None      => noop_fold_expr(expr, self),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use syntax::parser::parse_module;
+
+    fn consts(src: &str) -> Result<Consts, Vec<Diagnostic>> {
+        let arena = Arena::new();
+        let module = parse_module(&arena, src).unwrap();
+        eval_consts("test", &module)
+    }
+
+    #[test]
+    fn evaluation() {
+        let consts = consts("
+            const HALF: i32 = SIZE / 2;
+            const SIZE: i32 = 1 << 4;
+            const MASK: u8 = (SIZE - 1 | 0x30) -> u8;
+            const BIG: bool = SIZE > 10 && HALF == 8;
+            const NAME: str = \"arg\" + \"entum\";
+            const PICK: i32 = BIG ? HALF : SIZE;
+            const LABEL: str = \"${NAME} has ${SIZE / 4} sides\";
+        ").unwrap();
+        assert_eq!(consts["LABEL"], Literal::Str("argentum has 4 sides".to_string()));
+        assert_eq!(consts["SIZE"], Literal::Int(16));
+        assert_eq!(consts["HALF"], Literal::Int(8));
+        assert_eq!(consts["MASK"], Literal::Int(0x3f));
+        assert_eq!(consts["BIG"], Literal::Boolean(true));
+        assert_eq!(consts["NAME"], Literal::Str("argentum".to_string()));
+        assert_eq!(consts["PICK"], Literal::Int(8));
+    }
+
+    #[test]
+    fn non_constant_initializers() {
+        let errors = consts("
+            fn f() -> i32 { 1 }
+            const A: i32 = f();
+            const B: i32 = x + 1;
+        ").unwrap_err();
+        assert_eq!(errors.len(), 2);
+        assert!(errors.iter().any(|err| err.msg.contains("`A`") && err.msg.contains("function calls")));
+        assert!(errors.iter().any(|err| err.msg.contains("`x` is not a constant")));
+    }
+
+    #[test]
+    fn overflow() {
+        let errors = consts("
+            const A: i64 = 1 << 130;
+            const B: u64 = 18446744073709551615 * 18446744073709551615 * 2;
+            const C: i64 = 1 << 70;
+        ").unwrap_err();
+        assert_eq!(errors.len(), 2);
+        assert!(errors.iter().any(|err| err.msg.contains("`A` overflows")));
+        assert!(errors.iter().any(|err| err.msg.contains("`B` overflows")));
+    }
+
+    #[test]
+    fn cycles() {
+        let errors = consts("
+            const A: i32 = B + 1;
+            const B: i32 = A * 2;
+        ").unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].msg.contains("A -> B -> A"));
+    }
+
+    #[test]
+    fn inlining_respects_shadowing() {
+        let arena = Arena::new();
+        let module = parse_module(&arena, "
+            const N: i32 = 3;
+            fn f(x: i32) -> i32 { x + N }
+            fn g(N: i32) -> i32 { N }
+            fn h() -> i32 { let N = 1; N }
+        ").unwrap();
+        let consts = eval_consts("test", &module).unwrap();
+        let module = inline_consts(&arena, module, &consts);
+        let bodies: Vec<&Expr> = module.items().iter().filter_map(|item| match item.kind {
+            ItemKind::Fn(ref decl) => decl.body.expr,
+            _                      => None,
+        }).collect();
+        let ident = |name: &str| Expr::from(ExprKind::Identifier(name.to_string()));
+        assert_eq!(*bodies[0], Expr::from(ExprKind::Binary(
+                BinaryOp::Arithmetic(::syntax::ast::binop::ArithmeticOp::Add),
+                &ident("x"),
+                &Expr::from(ExprKind::Literal(Literal::Int(3))))));
+        assert_eq!(*bodies[1], ident("N"));
+        assert_eq!(*bodies[2], ident("N"));
+    }
+}
@@ -0,0 +1,51 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use super::value::Value;
+
+/// Shared handle to a scope.  Closures keep the handle of the scope they were
+/// created in, so captured variables are shared rather than copied.
+pub type Env<'ast> = Rc<RefCell<Scope<'ast>>>;
+
+/// Variables of a block or function body, chained to the enclosing scope.
+#[derive(Debug, Default)]
+pub struct Scope<'ast> {
+    vars:   HashMap<String, Value<'ast>>,
+    parent: Option<Env<'ast>>,
+}
+
+impl<'ast> Scope<'ast> {
+    /// Creates a scope nested in `parent`, or a global scope.
+    pub fn new(parent: Option<Env<'ast>>) -> Env<'ast> {
+        Rc::new(RefCell::new(Scope { vars: HashMap::new(), parent }))
+    }
+
+    /// Declares `name` in this scope, shadowing outer declarations.
+    pub fn define(&mut self, name: &str, value: Value<'ast>) {
+        self.vars.insert(name.to_string(), value);
+    }
+
+    /// Looks `name` up through the chain of enclosing scopes.
+    pub fn get(env: &Env<'ast>, name: &str) -> Option<Value<'ast>> {
+        let scope = env.borrow();
+        match scope.vars.get(name) {
+            Some(value) => Some(value.clone()),
+            None        => scope.parent.as_ref().and_then(|parent| Scope::get(parent, name)),
+        }
+    }
+
+    /// Updates the innermost declaration of `name`.  Returns `false` when no
+    /// scope declares it.
+    pub fn assign(env: &Env<'ast>, name: &str, value: Value<'ast>) -> bool {
+        let mut scope = env.borrow_mut();
+        if let Some(slot) = scope.vars.get_mut(name) {
+            *slot = value;
+            return true;
+        }
+        match scope.parent {
+            Some(ref parent) => Scope::assign(parent, name, value),
+            None             => false,
+        }
+    }
+}
@@ -0,0 +1,48 @@
+use std::ops;
+use std::vec;
+
+use syntax::ast::RangeLimits;
+
+use super::value::Value;
+
+/// Iteration protocol of the `for` loop.  Ranges yield their integers,
+/// arrays their elements, strings their chars and maps `(key, value)` tuples
+/// in insertion order.
+pub enum Iter<'ast> {
+    Range(ops::Range<i128>),
+    RangeInclusive(ops::RangeInclusive<i128>),
+    Values(vec::IntoIter<Value<'ast>>),
+}
+
+impl<'ast> Iterator for Iter<'ast> {
+    type Item = Value<'ast>;
+
+    fn next(&mut self) -> Option<Value<'ast>> {
+        match *self {
+            Iter::Range(ref mut range)          => range.next().map(Value::Int),
+            Iter::RangeInclusive(ref mut range) => range.next().map(Value::Int),
+            Iter::Values(ref mut values)        => values.next(),
+        }
+    }
+}
+
+impl<'ast> Value<'ast> {
+    /// Iterates over the value, or returns `None` when it is not iterable.
+    pub fn iterate(self) -> Option<Iter<'ast>> {
+        let values: Vec<Value<'ast>> = match self {
+            Value::Range(start, end, RangeLimits::HalfOpen) => {
+                return Some(Iter::Range(start..end))
+            },
+            Value::Range(start, end, RangeLimits::Closed) => {
+                return Some(Iter::RangeInclusive(start..=end))
+            },
+            Value::Array(elems)      => elems,
+            Value::Map(entries)      => entries.into_iter()
+                .map(|(key, value)| Value::Tuple(vec![key, value]))
+                .collect(),
+            Value::Str(s)            => s.chars().map(Value::Char).collect(),
+            _                        => return None,
+        };
+        Some(Iter::Values(values.into_iter()))
+    }
+}
@@ -0,0 +1,1328 @@
+//! The "eval" module is a tree walking interpreter over the syntax tree.
+//!
+//! ## Scopes
+//! Every block and function call runs in a fresh `Scope` chained to its
+//! enclosing one.  Functions and closures keep a handle to the scope they are
+//! created in, which is how closures capture variables: assignments made
+//! through a closure are visible to the enclosing scope and vice versa.
+//!
+//! ## Control flow
+//! `return` unwinds through nested expressions and blocks as `Unwind::Return`
+//! until it reaches the function call that catches it.  `break` and
+//! `continue` unwind the same way to the loop they target.
+//!
+//! ## Integers
+//! Every integer is an `i128`, wide enough for the values of all the integer
+//! types, `u64` included.  Integer literals range up to `u64::MAX`.  Casting
+//! keeps the bits of an integer that fit the target, so `-1 -> u64` is
+//! `u64::MAX`, and clamps floats to the bounds of the target.
+//!
+//! ## Overflow
+//! Arithmetic never wraps around.  The checker gives the interpreter the
+//! integer type of each arithmetic expression, and a result outside of the
+//! range of that type is an overflow error:  with `a: u8` holding 255, `a + 1`
+//! fails, as does `-a`.  A shift drops the bits that leave the type instead,
+//! but shifting by its width or more is an overflow too.  Expressions of
+//! literals and unannotated variables alone have no such type; they compute
+//! with the full `i128`, where only leaving that range is an overflow.
+//!
+//! ## Output
+//! `print` writes to the interpreter's output, standard output unless
+//! replaced with `set_output`.  Values are written as their `Display`
+//! rendering: strings and characters without quotes, floats always with a
+//! decimal point, collections and tuples with their elements in brackets.
+mod consts;
+mod env;
+mod iter;
+mod value;
+
+pub use self::consts::{eval_consts, inline_consts, Consts};
+pub use self::env::{Env, Scope};
+pub use self::iter::Iter;
+pub use self::value::{Function, Value};
+
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::mem;
+use std::rc::Rc;
+
+use error::{RuntimeError, RuntimeErrorKind};
+use syntax::ast::binop::{ArithmeticOp, BitWiseOp, ComparisonOp, LogicalOp};
+use syntax::ast::unop::UnaryOp;
+use syntax::ast::{Arm, BinaryOp, Block, Expr, ExprKind, Fragment, ItemKind, Module, NodeId,
+                  Pattern, PatternKind, Piece, PrimType, Print, Spec, Stmt, StmtKind};
+use utils::{FileId, Severity, Span};
+
+/// Non local exits from evaluation.
+enum Unwind<'ast> {
+    Return(Value<'ast>),
+    /// `break` out of the innermost loop, or of the one with the label.
+    Break(Option<String>, Value<'ast>),
+    Continue(Option<String>),
+    Error(RuntimeError),
+}
+
+impl<'ast> From<RuntimeError> for Unwind<'ast> {
+    fn from(err: RuntimeError) -> Unwind<'ast> {
+        Unwind::Error(err)
+    }
+}
+
+type Eval<'ast, T> = Result<T, Unwind<'ast>>;
+
+/// Interpreter struct holds the global scope shared by successive modules.
+pub struct Interpreter<'ast> {
+    source:  String,
+    /// Names of the files the evaluated nodes may come from, for the errors
+    /// raised in functions of other modules.
+    files:   HashMap<FileId, String>,
+    globals: Env<'ast>,
+    output:  Box<dyn Write>,
+    /// Integer types of the arithmetic expressions, by file and node.
+    ints:    HashMap<(FileId, NodeId), PrimType>,
+    /// Location of the evaluated node, where errors point.
+    span:    Span,
+    file:    FileId,
+    id:      NodeId,
+}
+
+impl<'ast> Interpreter<'ast> {
+    /// Creates an interpreter; `source` names the evaluated file in errors.
+    pub fn new<S: Into<String>>(source: S) -> Interpreter<'ast> {
+        Interpreter {
+            source:  source.into(),
+            files:   HashMap::new(),
+            globals: Scope::new(None),
+            output:  Box::new(io::stdout()),
+            ints:    HashMap::new(),
+            span:    Span::default(),
+            file:    FileId::default(),
+            id:      NodeId::DUMMY,
+        }
+    }
+
+    /// Names the files of the evaluated nodes in errors.  Nodes of files left
+    /// out are named after the module being evaluated.
+    pub fn set_files(&mut self, files: HashMap<FileId, String>) {
+        self.files = files;
+    }
+
+    /// Checks the results of arithmetic expressions against the integer types
+    /// the checker found for them.  Without, only `i128` overflows.
+    pub fn set_int_types(&mut self, ints: HashMap<(FileId, NodeId), PrimType>) {
+        self.ints = ints;
+    }
+
+    /// Sends the output of `print` statements to `output`.
+    pub fn set_output(&mut self, output: Box<dyn Write>) {
+        self.output = output;
+    }
+
+    /// Runs the statements of `module` in the global scope and returns the
+    /// value of the last expression statement.
+    pub fn eval_module(&mut self, module: &Module<'ast>) -> Result<Value<'ast>, RuntimeError> {
+        let globals = self.globals.clone();
+        let mut last = Value::Nil;
+        for stmt in module.stmts {
+            self.span = stmt.span;
+            self.file = stmt.file;
+            let result = match stmt.kind {
+                StmtKind::Expr(ref expr) => self.eval(expr, &globals).map(|value| last = value),
+                _                        => self.exec(stmt, &globals),
+            };
+            match result.map_err(|unwind| self.stray(unwind)) {
+                Ok(())                    => {},
+                Err(Unwind::Return(_))    => return Err(self.error(RuntimeErrorKind::ReturnOutsideFn)),
+                Err(Unwind::Error(err))   => return Err(err),
+                Err(_)                    => unreachable!("loop exits are converted to errors"),
+            }
+        }
+        Ok(last)
+    }
+
+    /// Reads a global variable.
+    pub fn global(&self, name: &str) -> Option<Value<'ast>> {
+        Scope::get(&self.globals, name)
+    }
+
+    /// Defines a global variable, such as a name imported from another module.
+    pub fn define_global(&mut self, name: &str, value: Value<'ast>) {
+        self.globals.borrow_mut().define(name, value);
+    }
+
+    /// The global scope of the module being evaluated.
+    pub fn globals(&self) -> Env<'ast> {
+        self.globals.clone()
+    }
+
+    /// Starts evaluating the module named `source` in errors, with fresh
+    /// globals.  Functions of the previous modules keep their own.
+    pub fn next_module<S: Into<String>>(&mut self, source: S) {
+        self.source = source.into();
+        self.globals = Scope::new(None);
+    }
+
+    fn exec(&mut self, stmt: &Stmt<'ast>, env: &Env<'ast>) -> Eval<'ast, ()> {
+        let outer = mem::replace(&mut self.span, stmt.span);
+        let outer_file = mem::replace(&mut self.file, stmt.file);
+        let result = self.exec_kind(&stmt.kind, env);
+        self.span = outer;
+        self.file = outer_file;
+        result
+    }
+
+    fn exec_kind(&mut self, stmt: &StmtKind<'ast>, env: &Env<'ast>) -> Eval<'ast, ()> {
+        match *stmt {
+            StmtKind::Item(ref item) => match item.kind {
+                ItemKind::Fn(ref decl) => {
+                    let function = Function {
+                        name:   Some(decl.name.clone()),
+                        params: decl.params,
+                        body:   decl.body,
+                        env:    env.clone(),
+                    };
+                    env.borrow_mut().define(&decl.name, Value::Function(Rc::new(function)));
+                },
+                // Constants are inlined before the module runs, which leaves a
+                // literal as the initializer; it is still bound for importers.
+                ItemKind::Const(ref decl) => {
+                    let value = self.eval(&decl.value, env)?;
+                    env.borrow_mut().define(&decl.name, value);
+                },
+                // The variants of a spec are values, or functions building
+                // them when they hold values.
+                ItemKind::Spec(ref decl) => {
+                    for variant in decl.variants {
+                        let path = format!("{}::{}", decl.name, variant.name);
+                        let value = match variant.fields.len() {
+                            0     => Value::Variant(path.clone(), Vec::new()),
+                            arity => Value::Constructor(path.clone(), arity),
+                        };
+                        env.borrow_mut().define(&path, value);
+                    }
+                },
+                // Imports are bound by the module loader, and type aliases
+                // and structs only matter to the checker.
+                ItemKind::Use(_) | ItemKind::Type(_) | ItemKind::Struct(_) => {},
+            },
+            StmtKind::Let(ref local) => {
+                let value = match local.init {
+                    Some(ref init) => self.eval(init, env)?,
+                    None           => Value::Nil,
+                };
+                self.bind(&local.pat, value, env)?;
+            },
+            StmtKind::Expr(ref expr) => {
+                self.eval(expr, env)?;
+            },
+            StmtKind::For(ref for_loop) => {
+                let iterable = self.eval(&for_loop.iter, env)?;
+                let iter = match iterable.clone().iterate() {
+                    Some(iter) => iter,
+                    None       => return Err(self.unwind(RuntimeErrorKind::NotIterable(
+                                iterable.type_name().to_string()))),
+                };
+                for value in iter {
+                    let scope = Scope::new(Some(env.clone()));
+                    self.bind(&for_loop.pat, value, &scope)?;
+                    if self.iteration(&for_loop.label, &for_loop.body, &scope)?.is_some() {
+                        break;
+                    }
+                }
+            },
+            StmtKind::While(ref while_loop) => loop {
+                let cond = self.eval(&while_loop.cond, env)?;
+                if !self.truthy(cond)? {
+                    break;
+                }
+                if self.iteration(&while_loop.label, &while_loop.body, env)?.is_some() {
+                    break;
+                }
+            },
+            StmtKind::Break(ref label, ref value) => {
+                let value = match *value {
+                    Some(ref value) => self.eval(value, env)?,
+                    None            => Value::Nil,
+                };
+                return Err(Unwind::Break(label.clone(), value));
+            },
+            StmtKind::Continue(ref label) => return Err(Unwind::Continue(label.clone())),
+            StmtKind::Print(ref print) => {
+                let values = self.eval_args(print.args, env)?;
+                let text = self.render(print, values)?;
+                self.output.write_all(text.as_bytes())
+                    .and_then(|_| self.output.flush())
+                    .map_err(|err| self.unwind(RuntimeErrorKind::Output(err.to_string())))?;
+            },
+            StmtKind::Return(ref value) => {
+                let value = match *value {
+                    Some(ref value) => self.eval(value, env)?,
+                    None            => Value::Nil,
+                };
+                return Err(Unwind::Return(value));
+            },
+        }
+        Ok(())
+    }
+
+    /// Renders the arguments of a `print` statement.
+    fn render(&self, print: &Print<'ast>, values: Vec<Value<'ast>>)
+        -> Result<String, RuntimeError>
+    {
+        let mut text = String::new();
+        match print.format {
+            Some(ref format) => {
+                if format.args() != values.len() {
+                    return Err(self.error(RuntimeErrorKind::ArityMismatch {
+                        expected: format.args(),
+                        found:    values.len(),
+                    }));
+                }
+                let mut values = values.into_iter();
+                for piece in &format.pieces {
+                    match *piece {
+                        Piece::Str(ref piece) => text.push_str(piece),
+                        Piece::Arg(spec)      => text.push_str(&pad(&values.next().unwrap(), spec)),
+                    }
+                }
+            },
+            None => {
+                let values: Vec<String> = values.iter().map(Value::to_string).collect();
+                text.push_str(&values.join(" "));
+            },
+        }
+        if print.newline {
+            text.push('\n');
+        }
+        Ok(text)
+    }
+
+    /// Runs one iteration of the body of the loop labeled `label`.  Returns
+    /// the value the loop breaks with, if it does.
+    fn iteration(&mut self, label: &Option<String>, body: &Block<'ast>, env: &Env<'ast>)
+        -> Eval<'ast, Option<Value<'ast>>>
+    {
+        match self.eval_block(body, env) {
+            Ok(_) => Ok(None),
+            Err(Unwind::Break(ref target, ref value)) if exits(target, label) => {
+                Ok(Some(value.clone()))
+            },
+            Err(Unwind::Continue(ref target)) if exits(target, label) => Ok(None),
+            Err(unwind) => Err(unwind),
+        }
+    }
+
+    /// Runs `block` in a new scope nested in `env`.
+    fn eval_block(&mut self, block: &Block<'ast>, env: &Env<'ast>) -> Eval<'ast, Value<'ast>> {
+        let scope = Scope::new(Some(env.clone()));
+        for stmt in block.stmts {
+            self.exec(stmt, &scope)?;
+        }
+        match block.expr {
+            Some(expr) => self.eval(expr, &scope),
+            None       => Ok(Value::Nil),
+        }
+    }
+
+    fn eval(&mut self, expr: &Expr<'ast>, env: &Env<'ast>) -> Eval<'ast, Value<'ast>> {
+        let outer = mem::replace(&mut self.span, expr.span);
+        let outer_file = mem::replace(&mut self.file, expr.file);
+        let outer_id = mem::replace(&mut self.id, expr.id);
+        let result = self.eval_kind(&expr.kind, env);
+        self.span = outer;
+        self.file = outer_file;
+        self.id = outer_id;
+        result
+    }
+
+    fn eval_kind(&mut self, expr: &ExprKind<'ast>, env: &Env<'ast>) -> Eval<'ast, Value<'ast>> {
+        match *expr {
+            ExprKind::Literal(ref lit) => Ok(Value::from(lit)),
+            ExprKind::Interpolated(fragments) => {
+                let mut text = String::new();
+                for fragment in fragments {
+                    match *fragment {
+                        Fragment::Str(ref value, _) => text.push_str(value),
+                        Fragment::Expr(ref expr, _) => {
+                            text.push_str(&self.eval(expr, env)?.to_string())
+                        },
+                    }
+                }
+                Ok(Value::Str(text))
+            },
+            ExprKind::Identifier(ref name) => match Scope::get(env, name) {
+                Some(value) => Ok(value),
+                None        => Err(self.unwind(RuntimeErrorKind::UndefinedVariable(name.clone()))),
+            },
+            // The items of imported modules are defined as globals named after
+            // their paths, i.e. `shapes::area`, and spec variants where their
+            // spec is declared, i.e. `Shape::Circle`.
+            ExprKind::Path(ref path) => {
+                let name = path.to_string();
+                match Scope::get(env, &name).or_else(|| Scope::get(&self.globals, &name)) {
+                    Some(value) => Ok(value),
+                    None        => Err(self.unwind(RuntimeErrorKind::UndefinedVariable(name))),
+                }
+            },
+            ExprKind::Unary(op, operand) => {
+                let operand = self.eval(operand, env)?;
+                Ok(self.unary(op, operand)?)
+            },
+            ExprKind::Binary(BinaryOp::Logical(op), lhs, rhs) => {
+                let lhs = self.eval(lhs, env)?;
+                match (op, self.truthy(lhs)?) {
+                    (LogicalOp::And, false) => Ok(Value::Bool(false)),
+                    (LogicalOp::Or, true)   => Ok(Value::Bool(true)),
+                    _                       => {
+                        let rhs = self.eval(rhs, env)?;
+                        Ok(Value::Bool(self.truthy(rhs)?))
+                    },
+                }
+            },
+            ExprKind::Binary(op, lhs, rhs) => {
+                let lhs = self.eval(lhs, env)?;
+                let rhs = self.eval(rhs, env)?;
+                Ok(self.binary(op, lhs, rhs)?)
+            },
+            ExprKind::Cast(operand, target) => {
+                let operand = self.eval(operand, env)?;
+                Ok(self.cast(operand, target)?)
+            },
+            ExprKind::Call(callee, args) => {
+                let callee = self.eval(callee, env)?;
+                let values = self.eval_args(args, env)?;
+                self.call(callee, values)
+            },
+            ExprKind::SafeMethodCall(receiver, ref name, args) => {
+                let receiver = self.eval(receiver, env)?;
+                if receiver == Value::Nil {
+                    return Ok(Value::Nil);
+                }
+                let values = self.eval_args(args, env)?;
+                Ok(self.method(receiver, name, values)?)
+            },
+            ExprKind::MethodCall(receiver, ref name, args) => {
+                let receiver = self.eval(receiver, env)?;
+                let values = self.eval_args(args, env)?;
+                Ok(self.method(receiver, name, values)?)
+            },
+            ExprKind::Assign(_, target, value) => {
+                let value = self.eval(value, env)?;
+                self.store(target, value.clone(), env)?;
+                Ok(value)
+            },
+            ExprKind::Range(start, end, limits) => {
+                let start = self.eval(start, env)?;
+                let end = self.eval(end, env)?;
+                match (&start, &end) {
+                    (&Value::Int(a), &Value::Int(b)) => Ok(Value::Range(a, b, limits)),
+                    _ => Err(self.mismatch(limits, &start, &end).into()),
+                }
+            },
+            ExprKind::Array(elems) => Ok(Value::Array(self.eval_args(elems, env)?)),
+            ExprKind::Map(entries) => {
+                let mut map: Vec<(Value<'ast>, Value<'ast>)> = Vec::with_capacity(entries.len());
+                for (key, value) in entries {
+                    let key = self.eval(key, env)?;
+                    let value = self.eval(value, env)?;
+                    // A repeated key keeps its position and takes the later value.
+                    match map.iter().position(|entry| entry.0 == key) {
+                        Some(i) => map[i].1 = value,
+                        None    => map.push((key, value)),
+                    }
+                }
+                Ok(Value::Map(map))
+            },
+            ExprKind::Index(indexed, index) => {
+                let indexed = self.eval(indexed, env)?;
+                let index = self.eval(index, env)?;
+                Ok(self.index(indexed, index)?)
+            },
+            ExprKind::Tuple(elems) => Ok(Value::Tuple(self.eval_args(elems, env)?)),
+            ExprKind::Field(tuple, index) => match self.eval(tuple, env)? {
+                Value::Tuple(ref elems) if index < elems.len() => Ok(elems[index].clone()),
+                value => Err(self.unwind(RuntimeErrorKind::TypeMismatch(
+                            format!("no field `{}` on `{}`", index, value.type_name())))),
+            },
+            ExprKind::Member(object, ref field) => match self.eval(object, env)? {
+                Value::Struct(_, fields) => match fields.into_iter().find(|f| f.0 == *field) {
+                    Some((_, value)) => Ok(value),
+                    None => Err(self.unwind(RuntimeErrorKind::TypeMismatch(
+                                format!("no field `{}` on `struct`", field)))),
+                },
+                value => Err(self.unwind(RuntimeErrorKind::TypeMismatch(
+                            format!("no field `{}` on `{}`", field, value.type_name())))),
+            },
+            ExprKind::Struct(ref name, fields) => {
+                let mut values = Vec::with_capacity(fields.len());
+                for (field, value) in fields {
+                    values.push((field.clone(), self.eval(value, env)?));
+                }
+                Ok(Value::Struct(name.clone(), values))
+            },
+            ExprKind::Closure(closure) => Ok(Value::Function(Rc::new(Function {
+                name:   None,
+                params: closure.params,
+                body:   closure.body,
+                env:    env.clone(),
+            }))),
+            ExprKind::Ternary(cond, then, otherwise) => {
+                let cond = self.eval(cond, env)?;
+                if self.truthy(cond)? {
+                    self.eval(then, env)
+                } else {
+                    self.eval(otherwise, env)
+                }
+            },
+            ExprKind::If(cond, ref then, ref otherwise) => {
+                let cond = self.eval(cond, env)?;
+                match (self.truthy(cond)?, otherwise) {
+                    (true, _)                 => self.eval_block(then, env),
+                    (false, Some(other))      => self.eval(other, env),
+                    (false, None)             => Ok(Value::Nil),
+                }
+            },
+            ExprKind::Loop(ref label, ref body) => loop {
+                if let Some(value) = self.iteration(label, body, env)? {
+                    return Ok(value);
+                }
+            },
+            ExprKind::Match(scrutinee, arms) => {
+                let value = self.eval(scrutinee, env)?;
+                self.eval_match(value, arms, env)
+            },
+            ExprKind::Block(ref block) => self.eval_block(block, env),
+        }
+    }
+
+    /// Stores `value` into the variable or struct field `target`.  A field
+    /// is set on a copy of its struct, which is then stored in turn.
+    fn store(&mut self, target: &Expr<'ast>, value: Value<'ast>, env: &Env<'ast>)
+        -> Eval<'ast, ()>
+    {
+        match target.kind {
+            ExprKind::Identifier(ref name) => {
+                if !Scope::assign(env, name, value) {
+                    return Err(self.unwind(RuntimeErrorKind::UndefinedVariable(name.clone())));
+                }
+                Ok(())
+            },
+            ExprKind::Member(object, ref field) => match self.eval(object, env)? {
+                Value::Struct(name, mut fields) => {
+                    match fields.iter_mut().find(|entry| entry.0 == *field) {
+                        Some(entry) => entry.1 = value,
+                        None        => fields.push((field.clone(), value)),
+                    }
+                    self.store(object, Value::Struct(name, fields), env)
+                },
+                value => Err(self.unwind(RuntimeErrorKind::TypeMismatch(
+                            format!("no field `{}` on `{}`", field, value.type_name())))),
+            },
+            _ => Err(self.unwind(RuntimeErrorKind::InvalidAssignment)),
+        }
+    }
+
+    /// Evaluates the body of the first arm whose pattern matches `value`, in
+    /// a scope holding the names the pattern binds.
+    fn eval_match(&mut self, value: Value<'ast>, arms: &[Arm<'ast>], env: &Env<'ast>)
+        -> Eval<'ast, Value<'ast>>
+    {
+        for arm in arms {
+            let scope = Scope::new(Some(env.clone()));
+            if self.matches(&arm.pat, &value, &scope)? {
+                return self.eval(&arm.body, &scope);
+            }
+        }
+        Err(self.unwind(RuntimeErrorKind::TypeMismatch(format!("no arm matches `{}`", value))))
+    }
+
+    /// Whether `pat` matches `value`, defining the names it binds in `env`.
+    fn matches(&self, pat: &Pattern<'ast>, value: &Value<'ast>, env: &Env<'ast>)
+        -> Result<bool, RuntimeError>
+    {
+        match (&pat.kind, value) {
+            (PatternKind::Variant(path, pats), Value::Variant(variant, values)) => {
+                if path.to_string() != *variant || pats.len() != values.len() {
+                    return Ok(false);
+                }
+                for (pat, value) in pats.iter().zip(values) {
+                    if !self.matches(pat, value, env)? {
+                        return Ok(false);
+                    }
+                }
+                Ok(true)
+            },
+            (PatternKind::Variant(..), _) => Ok(false),
+            _ => self.bind(pat, value.clone(), env).map(|_| true),
+        }
+    }
+
+    fn call(&mut self, callee: Value<'ast>, args: Vec<Value<'ast>>) -> Eval<'ast, Value<'ast>> {
+        let function = match callee {
+            Value::Function(function) => function,
+            Value::Constructor(path, arity) => {
+                if arity != args.len() {
+                    return Err(self.unwind(RuntimeErrorKind::ArityMismatch {
+                        expected: arity,
+                        found:    args.len(),
+                    }));
+                }
+                return Ok(Value::Variant(path, args));
+            },
+            other => return Err(self.unwind(RuntimeErrorKind::NotCallable(other.type_name().to_string()))),
+        };
+        if function.params.len() != args.len() {
+            return Err(self.unwind(RuntimeErrorKind::ArityMismatch {
+                expected: function.params.len(),
+                found:    args.len(),
+            }));
+        }
+        let scope = Scope::new(Some(function.env.clone()));
+        for (param, arg) in function.params.iter().zip(args) {
+            self.bind(&param.pat, arg, &scope)?;
+        }
+        match self.eval_block(&function.body, &scope) {
+            Ok(value) | Err(Unwind::Return(value)) => Ok(value),
+            Err(err)                               => Err(self.stray(err)),
+        }
+    }
+
+    /// Defines the names of `pat` in `env`, destructuring tuples.
+    fn bind(&self, pat: &Pattern<'ast>, value: Value<'ast>, env: &Env<'ast>)
+        -> Result<(), RuntimeError>
+    {
+        match (&pat.kind, value) {
+            (PatternKind::Ident(name), value) => env.borrow_mut().define(name, value),
+            (PatternKind::Tuple(pats), Value::Tuple(ref elems)) if pats.len() == elems.len() => {
+                for (pat, elem) in pats.iter().zip(elems) {
+                    self.bind(pat, elem.clone(), env)?;
+                }
+            },
+            (pat, value) => return Err(self.error(RuntimeErrorKind::TypeMismatch(
+                        format!("cannot destructure `{}` as `{}`", value, pat)))),
+        }
+        Ok(())
+    }
+
+    fn index(&self, indexed: Value<'ast>, index: Value<'ast>) -> Result<Value<'ast>, RuntimeError> {
+        match (indexed, index) {
+            (Value::Array(elems), Value::Int(i)) => {
+                if i < 0 || i as usize >= elems.len() {
+                    return Err(self.error(RuntimeErrorKind::IndexOutOfBounds {
+                        index: i,
+                        len:   elems.len(),
+                    }));
+                }
+                Ok(elems[i as usize].clone())
+            },
+            // Missing keys read as nil.
+            (Value::Map(entries), key) => Ok(entries.into_iter()
+                .find(|entry| entry.0 == key)
+                .map_or(Value::Nil, |entry| entry.1)),
+            (indexed, index) => Err(self.error(RuntimeErrorKind::TypeMismatch(format!(
+                            "cannot index `{}` with `{}`", indexed.type_name(), index.type_name())))),
+        }
+    }
+
+    fn method(&self, receiver: Value<'ast>, name: &str, args: Vec<Value<'ast>>)
+        -> Result<Value<'ast>, RuntimeError>
+    {
+        match (receiver, name) {
+            (Value::Str(ref s), "len") if args.is_empty() => Ok(Value::Int(s.chars().count() as i128)),
+            (Value::Array(ref elems), "len") if args.is_empty() => Ok(Value::Int(elems.len() as i128)),
+            (Value::Map(ref entries), "len") if args.is_empty() => Ok(Value::Int(entries.len() as i128)),
+            (receiver, _) => {
+                Err(self.error(RuntimeErrorKind::UnknownMethod(
+                            format!("{}.{}", receiver.type_name(), name))))
+            },
+        }
+    }
+
+    fn eval_args(&mut self, args: &[Expr<'ast>], env: &Env<'ast>) -> Eval<'ast, Vec<Value<'ast>>> {
+        let mut values = Vec::with_capacity(args.len());
+        for arg in args {
+            values.push(self.eval(arg, env)?);
+        }
+        Ok(values)
+    }
+
+    fn unary(&self, op: UnaryOp, operand: Value<'ast>) -> Result<Value<'ast>, RuntimeError> {
+        match (op, operand) {
+            (UnaryOp::Minus, Value::Int(i))   => self.int(i.checked_neg()),
+            (UnaryOp::Minus, Value::Float(x)) => Ok(Value::Float(-x)),
+            (UnaryOp::Not, Value::Bool(b))    => Ok(Value::Bool(!b)),
+            (op, operand) => Err(self.error(RuntimeErrorKind::TypeMismatch(
+                        format!("cannot apply `{}` to `{}`", op, operand.type_name())))),
+        }
+    }
+
+    /// Converts a value to a primitive type.  Integers are truncated to the
+    /// width of the target, keeping the low bits, and floats saturate at the
+    /// bounds of the target with NaN converting to zero.  `Int` is wider than
+    /// every integer type, so `u64` values are stored as they are.
+    fn cast(&self, value: Value<'ast>, target: PrimType) -> Result<Value<'ast>, RuntimeError> {
+        let value = match (value, target) {
+            (Value::Int(i), PrimType::F32)    => Value::Float(i as f32 as f64),
+            (Value::Int(i), PrimType::F64)    => Value::Float(i as f64),
+            (Value::Int(i), PrimType::Char)   => Value::Char(i as u8 as char),
+            (Value::Int(i), _) if is_int(target) => Value::Int(truncate(i, target)),
+            (Value::Float(x), PrimType::F32)  => Value::Float(x as f32 as f64),
+            (Value::Float(x), PrimType::F64)  => Value::Float(x),
+            (Value::Float(x), _) if is_int(target) => Value::Int(saturate(x, target)),
+            (Value::Char(c), PrimType::Char)  => Value::Char(c),
+            (Value::Char(c), _) if is_int(target) => Value::Int(truncate(c as i128, target)),
+            (Value::Bool(b), PrimType::Bool)  => Value::Bool(b),
+            (Value::Bool(b), _) if is_int(target) => Value::Int(b as i128),
+            (Value::Str(s), PrimType::Str)    => Value::Str(s),
+            (value, target) => return Err(self.error(RuntimeErrorKind::TypeMismatch(
+                        format!("cannot cast `{}` to `{}`", value.type_name(), target)))),
+        };
+        Ok(value)
+    }
+
+    fn binary(&self, op: BinaryOp, lhs: Value<'ast>, rhs: Value<'ast>)
+        -> Result<Value<'ast>, RuntimeError>
+    {
+        use self::Value::*;
+
+        let value = match (op, &lhs, &rhs) {
+            (BinaryOp::Arithmetic(op), &Int(a), &Int(b)) => match op {
+                ArithmeticOp::Add => self.int(a.checked_add(b))?,
+                ArithmeticOp::Sub => self.int(a.checked_sub(b))?,
+                ArithmeticOp::Mul => self.int(a.checked_mul(b))?,
+                ArithmeticOp::Div | ArithmeticOp::Modulo if b == 0 => {
+                    return Err(self.error(RuntimeErrorKind::DivisionByZero))
+                },
+                ArithmeticOp::Div    => self.int(a.checked_div(b))?,
+                ArithmeticOp::Modulo => self.int(a.checked_rem(b))?,
+                ArithmeticOp::Incr | ArithmeticOp::Decr => return Err(self.mismatch(op, &lhs, &rhs)),
+            },
+            (BinaryOp::Arithmetic(op), &Float(a), &Float(b)) => match op {
+                ArithmeticOp::Add    => Float(a + b),
+                ArithmeticOp::Sub    => Float(a - b),
+                ArithmeticOp::Mul    => Float(a * b),
+                ArithmeticOp::Div    => Float(a / b),
+                ArithmeticOp::Modulo => Float(a % b),
+                ArithmeticOp::Incr | ArithmeticOp::Decr => return Err(self.mismatch(op, &lhs, &rhs)),
+            },
+            (BinaryOp::Arithmetic(ArithmeticOp::Add), Str(a), Str(b)) => {
+                Str(format!("{}{}", a, b))
+            },
+            (BinaryOp::Arithmetic(ArithmeticOp::Add), Array(a), Array(b)) => {
+                Array(a.iter().chain(b).cloned().collect())
+            },
+            (BinaryOp::BitWise(op), &Int(a), &Int(b)) => match op {
+                BitWiseOp::BwAnd   => Int(a & b),
+                BitWiseOp::BwOr    => Int(a | b),
+                BitWiseOp::BwXor   => Int(a ^ b),
+                BitWiseOp::BwShftL | BitWiseOp::BwShftR => self.shift(op, a, b)?,
+                BitWiseOp::BwCompl => return Err(self.mismatch(op, &lhs, &rhs)),
+            },
+            (BinaryOp::Comparison(ComparisonOp::Eql), _, _)    => Bool(lhs == rhs),
+            (BinaryOp::Comparison(ComparisonOp::NotEql), _, _) => Bool(lhs != rhs),
+            (BinaryOp::Comparison(op), _, _) => {
+                let ordering = match (&lhs, &rhs) {
+                    (&Int(a), &Int(b))         => a.partial_cmp(&b),
+                    (&Float(a), &Float(b))     => a.partial_cmp(&b),
+                    (&Char(a), &Char(b))       => a.partial_cmp(&b),
+                    (Str(a), Str(b))           => a.partial_cmp(b),
+                    _ => return Err(self.mismatch(op, &lhs, &rhs)),
+                };
+                Bool(match ordering {
+                    Some(ordering) => match op {
+                        ComparisonOp::Gt    => ordering.is_gt(),
+                        ComparisonOp::GtEql => ordering.is_ge(),
+                        ComparisonOp::Lt    => ordering.is_lt(),
+                        ComparisonOp::LtEql => ordering.is_le(),
+                        ComparisonOp::Eql | ComparisonOp::NotEql => unreachable!(),
+                    },
+                    // NaN compares false with everything.
+                    None => false,
+                })
+            },
+            (op, _, _) => return Err(self.mismatch(op, &lhs, &rhs)),
+        };
+        Ok(value)
+    }
+
+    /// The integer type the checker found for the evaluated node, if any.
+    fn int_type(&self) -> Option<PrimType> {
+        self.ints.get(&(self.file, self.id)).cloned()
+    }
+
+    /// The result of integer arithmetic, an overflow error if it has none or
+    /// it does not fit the integer type of the evaluated node.
+    fn int(&self, value: Option<i128>) -> Result<Value<'ast>, RuntimeError> {
+        let ty = self.int_type();
+        match value {
+            Some(i) if ty.into_iter().all(|prim| fits(i, prim)) => Ok(Value::Int(i)),
+            _ => Err(self.error(RuntimeErrorKind::Overflow(
+                        ty.map_or("i128".to_string(), |prim| prim.to_string())))),
+        }
+    }
+
+    /// Shifts `a` by `b` bits, dropping the bits that leave the integer type
+    /// of the evaluated node.  Without a type, no bit may be lost.
+    fn shift(&self, op: BitWiseOp, a: i128, b: i128) -> Result<Value<'ast>, RuntimeError> {
+        let ty = self.int_type();
+        let width = ty.map_or(128, bits);
+        if b < 0 || b >= width {
+            return self.int(None);
+        }
+        let shifted = match op {
+            BitWiseOp::BwShftL => a << b,
+            _                  => a >> b,
+        };
+        match ty {
+            Some(prim) => Ok(Value::Int(truncate(shifted, prim))),
+            None if op == BitWiseOp::BwShftL && shifted >> b != a => self.int(None),
+            None => Ok(Value::Int(shifted)),
+        }
+    }
+
+    fn truthy(&self, value: Value<'ast>) -> Result<bool, RuntimeError> {
+        match value {
+            Value::Bool(b) => Ok(b),
+            other => Err(self.error(RuntimeErrorKind::TypeMismatch(
+                        format!("expected `bool`, found `{}`", other.type_name())))),
+        }
+    }
+
+    fn mismatch<T: ::std::fmt::Display>(&self, op: T, lhs: &Value<'ast>, rhs: &Value<'ast>)
+        -> RuntimeError
+    {
+        self.error(RuntimeErrorKind::TypeMismatch(format!(
+                    "cannot apply `{}` to `{}` and `{}`", op, lhs.type_name(), rhs.type_name())))
+    }
+
+    fn error(&self, kind: RuntimeErrorKind) -> RuntimeError {
+        RuntimeError {
+            source:   self.files.get(&self.file).cloned().unwrap_or_else(|| self.source.clone()),
+            span:     self.span,
+            severity: Severity::Error,
+            kind
+        }
+    }
+
+    fn unwind(&self, kind: RuntimeErrorKind) -> Unwind<'ast> {
+        Unwind::Error(self.error(kind))
+    }
+
+    /// Turns a `break` or `continue` that no loop caught into an error.
+    fn stray(&self, unwind: Unwind<'ast>) -> Unwind<'ast> {
+        let (keyword, label) = match unwind {
+            Unwind::Break(label, _) => ("break", label),
+            Unwind::Continue(label) => ("continue", label),
+            unwind                  => return unwind,
+        };
+        let exit = match label {
+            Some(label) => format!("{} '{}", keyword, label),
+            None        => keyword.to_string(),
+        };
+        self.unwind(RuntimeErrorKind::OutsideLoop(exit))
+    }
+}
+
+/// Renders `value` for a placeholder with the specifiers `spec`.
+fn pad(value: &Value, spec: Spec) -> String {
+    let text = match (value, spec.precision) {
+        (&Value::Float(x), Some(precision)) => format!("{:.*}", precision, x),
+        (Value::Str(s), Some(precision)) => s.chars().take(precision).collect(),
+        (value, _) => value.to_string(),
+    };
+    match (value, spec.width) {
+        (&Value::Int(_), Some(width)) | (&Value::Float(_), Some(width)) => {
+            format!("{:>1$}", text, width)
+        },
+        (_, Some(width)) => format!("{:<1$}", text, width),
+        (_, None)        => text,
+    }
+}
+
+/// Whether a `break` or `continue` to `target` leaves the loop labeled `label`.
+fn exits(target: &Option<String>, label: &Option<String>) -> bool {
+    target.is_none() || target == label
+}
+
+fn is_int(prim: PrimType) -> bool {
+    matches!(prim, PrimType::I8 | PrimType::U8 | PrimType::I16 | PrimType::U16 |
+                   PrimType::I32 | PrimType::U32 | PrimType::I64 | PrimType::U64)
+}
+
+/// Whether `i` is within the range of the integer type `prim`.
+fn fits(i: i128, prim: PrimType) -> bool {
+    truncate(i, prim) == i
+}
+
+/// The width in bits of the integer type `prim`.
+fn bits(prim: PrimType) -> i128 {
+    match prim {
+        PrimType::I8 | PrimType::U8   => 8,
+        PrimType::I16 | PrimType::U16 => 16,
+        PrimType::I32 | PrimType::U32 => 32,
+        _                             => 64,
+    }
+}
+
+/// Keeps the bits of `i` that fit in the integer type `prim`.
+fn truncate(i: i128, prim: PrimType) -> i128 {
+    match prim {
+        PrimType::I8  => i as i8 as i128,
+        PrimType::U8  => i as u8 as i128,
+        PrimType::I16 => i as i16 as i128,
+        PrimType::U16 => i as u16 as i128,
+        PrimType::I32 => i as i32 as i128,
+        PrimType::U32 => i as u32 as i128,
+        PrimType::U64 => i as u64 as i128,
+        _             => i as i64 as i128,
+    }
+}
+
+/// Rounds `x` toward zero, clamped to the range of the integer type `prim`.
+fn saturate(x: f64, prim: PrimType) -> i128 {
+    match prim {
+        PrimType::I8  => x as i8 as i128,
+        PrimType::U8  => x as u8 as i128,
+        PrimType::I16 => x as i16 as i128,
+        PrimType::U16 => x as u16 as i128,
+        PrimType::I32 => x as i32 as i128,
+        PrimType::U32 => x as u32 as i128,
+        PrimType::U64 => x as u64 as i128,
+        _             => x as i64 as i128,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use syntax::ast::Arena;
+    use syntax::parser::parse_module;
+    use typeck::Checker;
+
+    /// Parses `src` into an arena that is never freed, so that interpreters
+    /// can be returned along with the functions they hold.
+    fn parse(src: &str) -> Module<'static> {
+        let arena: &'static Arena<'static> = Box::leak(Box::default());
+        parse_module(arena, src).unwrap()
+    }
+
+    fn run(src: &str) -> Interpreter<'static> {
+        let module = parse(src);
+        let mut interp = Interpreter::new("test.ag");
+        interp.eval_module(&module).unwrap();
+        interp
+    }
+
+    fn run_err(src: &str) -> RuntimeErrorKind {
+        let module = parse(src);
+        Interpreter::new("test.ag").eval_module(&module).unwrap_err().kind
+    }
+
+    /// Runs `src` with the integer types found by checking it.
+    fn run_checked(src: &str) -> Result<Interpreter<'static>, RuntimeErrorKind> {
+        let module = parse(src);
+        let mut checker = Checker::new("test.ag");
+        checker.check_module(&module).unwrap();
+        let mut interp = Interpreter::new("test.ag");
+        interp.set_int_types(checker.int_types().clone());
+        interp.eval_module(&module).map_err(|err| err.kind)?;
+        Ok(interp)
+    }
+
+    /// Output sink whose contents remain readable after the interpreter
+    /// takes it.
+    #[derive(Clone, Default)]
+    struct Buffer(Rc<::std::cell::RefCell<Vec<u8>>>);
+
+    impl Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn output(src: &str) -> String {
+        let module = parse(src);
+        let buffer = Buffer::default();
+        let mut interp = Interpreter::new("test.ag");
+        interp.set_output(Box::new(buffer.clone()));
+        interp.eval_module(&module).unwrap();
+        let bytes = buffer.0.borrow().clone();
+        String::from_utf8(bytes).unwrap()
+    }
+
+    #[test]
+    fn arithmetic_and_functions() {
+        let interp = run("
+            fn sq(x: i32) -> i32 { x * x }
+            fn early(x: i32) -> i32 { return x + 1; }
+            let a = sq(3) + 1;
+            let b = early(a) % 4;
+            let s = \"ab\" + \"c\";
+        ");
+        assert_eq!(interp.global("a"), Some(Value::Int(10)));
+        assert_eq!(interp.global("b"), Some(Value::Int(3)));
+        assert_eq!(interp.global("s"), Some(Value::Str("abc".to_string())));
+    }
+
+    #[test]
+    fn closures_capture_enclosing_scope() {
+        let interp = run("
+            fn counter() -> fn() -> i32 {
+                let count = 0;
+                fn() -> i32 { count = count + 1; count }
+            }
+            let next = counter();
+            next();
+            next();
+            let third = next();
+            let other = counter()();
+        ");
+        assert_eq!(interp.global("third"), Some(Value::Int(3)));
+        assert_eq!(interp.global("other"), Some(Value::Int(1)));
+    }
+
+    #[test]
+    fn closures_as_callbacks() {
+        let interp = run("
+            fn apply(f: fn(i32) -> i32, x: i32) -> i32 { f(x) }
+            let offset = 10;
+            let a = apply(|x| x + offset, 1);
+            let b = apply(fn(x: i32) -> i32 { x * 2 }, 4);
+            let add = |x, y| x + y;
+            let c = add(1, 2);
+            let d = apply(|x| { let y = x * 3; y + 1 }, 2);
+            let count = 0;
+            let bump = || { count = count + 1; };
+            bump();
+            bump();
+        ");
+        assert_eq!(interp.global("a"), Some(Value::Int(11)));
+        assert_eq!(interp.global("b"), Some(Value::Int(8)));
+        assert_eq!(interp.global("c"), Some(Value::Int(3)));
+        assert_eq!(interp.global("d"), Some(Value::Int(7)));
+        assert_eq!(interp.global("count"), Some(Value::Int(2)));
+    }
+
+    #[test]
+    fn conditional_expressions() {
+        let interp = run("
+            fn sign(x: i32) -> i32 {
+                if x < 0 { -1 } else if x == 0 { 0 } else { 1 }
+            }
+            let a = sign(-5) + sign(0) + sign(7);
+            let b = a > 0 ? \"pos\" : a < 0 ? \"neg\" : \"zero\";
+            let c = false || true ? 1 : 2;
+            let d = if false { 1 };
+        ");
+        assert_eq!(interp.global("a"), Some(Value::Int(0)));
+        assert_eq!(interp.global("b"), Some(Value::Str("zero".to_string())));
+        assert_eq!(interp.global("c"), Some(Value::Int(1)));
+        assert_eq!(interp.global("d"), Some(Value::Nil));
+    }
+
+    #[test]
+    fn casts() {
+        let interp = run("
+            let a = 300 -> u8;
+            let b = -1 -> u16;
+            let c = 3000000000.0 -> i32;
+            let d = -2.9 -> u8;
+            let e = 65 -> char;
+            let f = 'a' -> i32 + (true -> i32);
+            let g = 7 -> f64 / 2.0;
+            let h = 0.1 -> f32 -> f64;
+            let n: i32 = -1;
+            let i = n -> u64;
+            let j = 100000000000000000000.0 -> u64;
+            let k = -5.5 -> u64;
+            let l = 18446744073709551615 -> i64;
+        ");
+        assert_eq!(interp.global("a"), Some(Value::Int(44)));
+        assert_eq!(interp.global("b"), Some(Value::Int(65535)));
+        assert_eq!(interp.global("c"), Some(Value::Int(i32::MAX as i128)));
+        assert_eq!(interp.global("d"), Some(Value::Int(0)));
+        assert_eq!(interp.global("e"), Some(Value::Char('A')));
+        assert_eq!(interp.global("f"), Some(Value::Int(98)));
+        assert_eq!(interp.global("g"), Some(Value::Float(3.5)));
+        assert_eq!(interp.global("h"), Some(Value::Float(0.1f32 as f64)));
+        assert_eq!(interp.global("i"), Some(Value::Int(u64::MAX as i128)));
+        assert_eq!(interp.global("j"), Some(Value::Int(u64::MAX as i128)));
+        assert_eq!(interp.global("k"), Some(Value::Int(0)));
+        assert_eq!(interp.global("l"), Some(Value::Int(-1)));
+    }
+
+    #[test]
+    fn optionals() {
+        let interp = run("
+            let name: str? = nil;
+            let a = name?.len();
+            name = \"four\";
+            let b = name?.len();
+            let c = name != nil ? name.len() : 0;
+        ");
+        assert_eq!(interp.global("a"), Some(Value::Nil));
+        assert_eq!(interp.global("b"), Some(Value::Int(4)));
+        assert_eq!(interp.global("c"), Some(Value::Int(4)));
+    }
+
+    #[test]
+    fn tuples() {
+        let interp = run("
+            fn swap((a, b): (i32, str)) -> (str, i32) { (b, a) }
+            let pair = swap((1, \"one\"));
+            let (name, (n, unit)) = (pair.0, (pair.1 + 1, ()));
+            let single = (name,);
+            let apply = |(x, y)| x * y;
+            let area = apply((3, 4));
+        ");
+        assert_eq!(interp.global("pair").unwrap().to_string(), "(one, 1)");
+        assert_eq!(interp.global("name"), Some(Value::Str("one".to_string())));
+        assert_eq!(interp.global("n"), Some(Value::Int(2)));
+        assert_eq!(interp.global("unit"), Some(Value::Tuple(vec![])));
+        assert_eq!(interp.global("single").unwrap().to_string(), "(one,)");
+        assert_eq!(interp.global("area"), Some(Value::Int(12)));
+    }
+
+    #[test]
+    fn for_loops() {
+        let interp = run("
+            let sum = 0;
+            for i in 1..=4 { sum = sum + i; }
+            let skipped = 0;
+            for i in 3..3 { skipped = skipped + 1; }
+            let codes = 0;
+            for c in \"ab\" { codes = codes + (c -> i32); }
+            let total = 0;
+            for (name, n) in [\"a\": 1, \"b\": 2, \"a\": 3] { total = total + n; }
+            let xs = [10, 20, 30];
+            let last = 0;
+            for x in xs { last = x; }
+            let top = 0;
+            for i in 9223372036854775806..=9223372036854775807 { top = i; }
+            let closed = 1..=3;
+        ");
+        assert_eq!(interp.global("sum"), Some(Value::Int(10)));
+        assert_eq!(interp.global("skipped"), Some(Value::Int(0)));
+        assert_eq!(interp.global("codes"), Some(Value::Int(195)));
+        assert_eq!(interp.global("total"), Some(Value::Int(5)));
+        assert_eq!(interp.global("last"), Some(Value::Int(30)));
+        assert_eq!(interp.global("top"), Some(Value::Int(i64::MAX as i128)));
+        assert_eq!(interp.global("closed").unwrap().to_string(), "1..=3");
+    }
+
+    #[test]
+    fn arrays_and_maps() {
+        let interp = run("
+            let xs = [1, 2, 3];
+            let second = xs[1];
+            let ages = [\"ann\": 31, \"bob\": 42];
+            let bob = ages[\"bob\"];
+            let eve = ages[\"eve\"];
+            let sizes = (xs.len(), ages.len(), [:].len());
+            let joined = xs + [4] + [];
+        ");
+        assert_eq!(interp.global("second"), Some(Value::Int(2)));
+        assert_eq!(interp.global("bob"), Some(Value::Int(42)));
+        assert_eq!(interp.global("eve"), Some(Value::Nil));
+        assert_eq!(interp.global("ages").unwrap().to_string(), "[ann: 31, bob: 42]");
+        assert_eq!(interp.global("sizes").unwrap().to_string(), "(3, 2, 0)");
+        assert_eq!(interp.global("joined").unwrap().to_string(), "[1, 2, 3, 4]");
+        assert_eq!(interp.global("xs").unwrap().to_string(), "[1, 2, 3]");
+    }
+
+    #[test]
+    fn print() {
+        assert_eq!(output("
+            print 1, 2.0, 'c', \"s\";
+            println;
+            println [1, 2], (nil,), [\"a\": true];
+            let name = \"argentum\";
+            println \"{} has {} letters, {{braces}}\", name, name.len();
+            println \"[{:5}|{:10}|{:6.2}|{:.3}]\", 42, name, 3.14159, name;
+            print \"no newline\";
+        "), "1 2.0 c s\n[1, 2] (nil,) [a: true]\nargentum has 8 letters, {braces}\n\
+             [   42|argentum  |  3.14|arg]\nno newline");
+        assert_eq!(run_err("println \"{} {}\", 1;"),
+                   RuntimeErrorKind::ArityMismatch { expected: 2, found: 1 });
+    }
+
+    #[test]
+    fn interpolation() {
+        assert_eq!(output("
+            let name = \"ag\";
+            let n: i32? = nil;
+            println \"hello ${name}, you have ${name.len() + 1} items, ${n}\";
+            print \"${[1, 2]}${ if true { \"{}\" } else { \"\" } } costs \\$5\";
+        "), "hello ag, you have 3 items, nil\n[1, 2]{} costs $5");
+    }
+
+    #[test]
+    fn loops() {
+        let interp = run("
+            let i = 0;
+            while i < 5 { i = i + 1; }
+            let pairs = 0;
+            'outer: for x in 0..4 {
+                for y in 0..4 {
+                    if y > x { continue 'outer; }
+                    if x == 3 { break 'outer; }
+                    pairs = pairs + 1;
+                }
+            }
+            let n = 1;
+            let power = loop { n = n * 2; if n > 100 { break n; } };
+            let empty = loop { break; };
+        ");
+        assert_eq!(interp.global("i"), Some(Value::Int(5)));
+        assert_eq!(interp.global("pairs"), Some(Value::Int(6)));
+        assert_eq!(interp.global("power"), Some(Value::Int(128)));
+        assert_eq!(interp.global("empty"), Some(Value::Nil));
+    }
+
+    #[test]
+    fn generic_functions() {
+        let interp = run("
+            fn pick<T>(first: bool, a: T, b: T) -> T { first ? a : b }
+            let n = pick(false, 3, 7);
+            let c = pick(false, 'a', 'z');
+        ");
+        assert_eq!(interp.global("n"), Some(Value::Int(7)));
+        assert_eq!(interp.global("c"), Some(Value::Char('z')));
+    }
+
+    #[test]
+    fn structs_and_specs() {
+        let interp = run_checked("
+            struct Point { x: i32, y: i32 }
+            struct Line<T> { from: T, to: T }
+            spec Shape { Circle(i32), Rect(i32, i32), Empty }
+            spec Option<T> { Some(T), None }
+            fn area(s: Shape) -> i32 {
+                match s {
+                    Shape::Circle(r) => 3 * r * r,
+                    Shape::Rect(w, h) => w * h,
+                    Shape::Empty => 0,
+                }
+            }
+            fn max<T: Ord>(xs: [T]) -> Option<T> {
+                let best = Option::None;
+                for x in xs {
+                    best = match best {
+                        Option::Some(b) => Option::Some(b < x ? x : b),
+                        Option::None => Option::Some(x),
+                    };
+                }
+                best
+            }
+            let line = Line { from: Point { x: 1, y: 2 }, to: Point { y: 4, x: 3 } };
+            line.to.x = 5;
+            let x = line.to.x;
+            let same = line.from == Point { y: 2, x: 1 };
+            let areas = area(Shape::Circle(2)) + area(Shape::Rect(2, 3)) + area(Shape::Empty);
+            let top = max([3, 9, 4]);
+            let none = max([]);
+            let empty = match none { Option::Some(n) => n, Option::None => -1 };
+        ").unwrap();
+        assert_eq!(interp.global("x"), Some(Value::Int(5)));
+        assert_eq!(interp.global("same"), Some(Value::Bool(true)));
+        assert_eq!(interp.global("areas"), Some(Value::Int(18)));
+        assert_eq!(interp.global("top").unwrap().to_string(), "Option::Some(9)");
+        assert_eq!(interp.global("empty"), Some(Value::Int(-1)));
+        assert_eq!(interp.global("line").unwrap().to_string(),
+                   "Line { from: Point { x: 1, y: 2 }, to: Point { y: 4, x: 5 } }");
+        assert_eq!(run_err("spec S { A(i32) } S::A(1, 2);"),
+                   RuntimeErrorKind::ArityMismatch { expected: 1, found: 2 });
+        assert_eq!(run_err("spec S { A, B } match S::B { S::A => 1 };"),
+                   RuntimeErrorKind::TypeMismatch("no arm matches `S::B`".to_string()));
+    }
+
+    #[test]
+    fn consts() {
+        let arena = Arena::new();
+        let module = parse_module(&arena, "
+            fn twice() -> i32 { LIMIT * 2 }
+            let doubled = twice();
+            const LIMIT: i32 = 1 << 3;
+            const A: i32 = B + 1;
+            const B: i32 = 2;
+            let a = A;
+        ").unwrap();
+        let consts = eval_consts("test.ag", &module).unwrap();
+        let mut interp = Interpreter::new("test.ag");
+        interp.eval_module(&inline_consts(&arena, module, &consts)).unwrap();
+        assert_eq!(interp.global("LIMIT"), Some(Value::Int(8)));
+        assert_eq!(interp.global("doubled"), Some(Value::Int(16)));
+        assert_eq!(interp.global("a"), Some(Value::Int(3)));
+    }
+
+    #[test]
+    fn integer_overflow() {
+        let overflow = |ty: &str| Some(RuntimeErrorKind::Overflow(ty.to_string()));
+        let err = |src| run_checked(src).err();
+        assert_eq!(err("let a: u8 = 255; let b: u8 = a + 1;"), overflow("u8"));
+        assert_eq!(err("let m: i32 = 2147483647; let n = m + 1;"), overflow("i32"));
+        assert_eq!(err("let a: u8 = 1; let b = -a;"), overflow("u8"));
+        assert_eq!(err("let a: i8 = -128; let b = a / -1;"), overflow("i8"));
+        assert_eq!(err("let c: u8 = 200 + 100;"), overflow("u8"));
+        assert_eq!(err("fn sq(x: u16) -> u16 { x * x } sq(300);"), overflow("u16"));
+        assert_eq!(err("let a: u32 = 1; let b = a << 32;"), overflow("u32"));
+        assert_eq!(run_err("18446744073709551615 * 18446744073709551615;"),
+                   RuntimeErrorKind::Overflow("i128".to_string()));
+        assert_eq!(run_err("1 << 128;"), RuntimeErrorKind::Overflow("i128".to_string()));
+
+        let interp = run_checked("
+            let a: u8 = 255;
+            let b = a - 1;
+            let c = a << 4;
+            let d: i64 = -9223372036854775807 - 1;
+            let e = 200 + 100;
+        ").ok().unwrap();
+        assert_eq!(interp.global("b"), Some(Value::Int(254)));
+        assert_eq!(interp.global("c"), Some(Value::Int(240)));
+        assert_eq!(interp.global("d"), Some(Value::Int(i64::MIN as i128)));
+        assert_eq!(interp.global("e"), Some(Value::Int(300)));
+    }
+
+    #[test]
+    fn runtime_errors() {
+        assert_eq!(run_err("missing;"), RuntimeErrorKind::UndefinedVariable("missing".to_string()));
+        assert_eq!(run_err("1 / 0;"), RuntimeErrorKind::DivisionByZero);
+        assert_eq!(run_err("let f = |x| x; f();"),
+                   RuntimeErrorKind::ArityMismatch { expected: 1, found: 0 });
+        assert_eq!(run_err("1();"), RuntimeErrorKind::NotCallable("int".to_string()));
+        assert_eq!(run_err("return 1;"), RuntimeErrorKind::ReturnOutsideFn);
+        assert_eq!(run_err("\"1\" -> i32;"),
+                   RuntimeErrorKind::TypeMismatch("cannot cast `str` to `i32`".to_string()));
+        assert_eq!(run_err("let (a, b) = (1, 2, 3);"),
+                   RuntimeErrorKind::TypeMismatch("cannot destructure `(1, 2, 3)` as `(a, b)`".to_string()));
+        assert_eq!(run_err("(1, 2).2;"),
+                   RuntimeErrorKind::TypeMismatch("no field `2` on `tuple`".to_string()));
+        assert_eq!(run_err("for x in 1 { }"), RuntimeErrorKind::NotIterable("int".to_string()));
+        assert_eq!(run_err("[1][1];"), RuntimeErrorKind::IndexOutOfBounds { index: 1, len: 1 });
+        assert_eq!(run_err("1..2.5;"),
+                   RuntimeErrorKind::TypeMismatch("cannot apply `..` to `int` and `float`".to_string()));
+        assert_eq!(run_err("fn f() { break; } loop { f(); }"),
+                   RuntimeErrorKind::OutsideLoop("break".to_string()));
+        assert_eq!(run_err("'a: loop { continue 'b; }"),
+                   RuntimeErrorKind::OutsideLoop("continue 'b".to_string()));
+        assert_eq!(run_err("1 ? 2 : 3;"),
+                   RuntimeErrorKind::TypeMismatch("expected `bool`, found `int`".to_string()));
+    }
+
+    #[test]
+    fn error_locations() {
+        use utils::Position;
+
+        let span = |src: &str| {
+            let module = parse(src);
+            Interpreter::new("test.ag").eval_module(&module).unwrap_err().span
+        };
+        assert_eq!(span("let x = 1;\nx + 2 * (3 / 0);"), Span(Position(2, 9), Position(2, 16)));
+        assert_eq!(span("fn f() { }\nf(1);"), Span(Position(2, 1), Position(2, 5)));
+        assert_eq!(span("let x = 2;\nreturn x;"), Span(Position(2, 1), Position(2, 10)));
+    }
+}
@@ -0,0 +1,194 @@
+use std::fmt;
+use std::rc::Rc;
+
+use syntax::ast::{Block, Literal, Param, RangeLimits};
+
+use super::env::Env;
+
+/// Runtime values produced by the interpreter.
+#[derive(Clone, Debug)]
+pub enum Value<'ast> {
+    Nil,
+    Bool(bool),
+    Char(char),
+    Int(i128),
+    Float(f64),
+    Str(String),
+    Tuple(Vec<Value<'ast>>),
+    Array(Vec<Value<'ast>>),
+    /// Entries in insertion order, keys are unique.
+    Map(Vec<(Value<'ast>, Value<'ast>)>),
+    /// Range of integers, which includes its upper bound when `Closed`.
+    Range(i128, i128, RangeLimits),
+    /// Named function or closure, sharing the scope it was created in.
+    Function(Rc<Function<'ast>>),
+    /// Struct with the values of its fields, by name:  Pair { first: 1, second: 2 }
+    Struct(String, Vec<(String, Value<'ast>)>),
+    /// Variant of a spec, named by its path, with its values:  Option::Some(1)
+    Variant(String, Vec<Value<'ast>>),
+    /// Function building the variant with the path, given its number of values.
+    Constructor(String, usize),
+}
+
+impl<'ast> Value<'ast> {
+    /// Name of the value's runtime type, used in error messages.
+    pub fn type_name(&self) -> &'static str {
+        match *self {
+            Value::Nil         => "nil",
+            Value::Bool(_)     => "bool",
+            Value::Char(_)     => "char",
+            Value::Int(_)      => "int",
+            Value::Float(_)    => "float",
+            Value::Str(_)      => "str",
+            Value::Tuple(_)    => "tuple",
+            Value::Array(_)    => "array",
+            Value::Map(_)      => "map",
+            Value::Range(..)   => "range",
+            Value::Function(_) | Value::Constructor(..) => "fn",
+            Value::Struct(..)  => "struct",
+            Value::Variant(..) => "spec",
+        }
+    }
+}
+
+impl<'a, 'ast> From<&'a Literal> for Value<'ast> {
+    fn from(lit: &'a Literal) -> Value<'ast> {
+        match *lit {
+            Literal::Nil        => Value::Nil,
+            Literal::Boolean(b) => Value::Bool(b),
+            Literal::Char(c)    => Value::Char(c),
+            Literal::Int(i)     => Value::Int(i),
+            Literal::Float(x)   => Value::Float(x),
+            Literal::Str(ref s) => Value::Str(s.clone()),
+        }
+    }
+}
+
+impl<'ast> PartialEq for Value<'ast> {
+    fn eq(&self, other: &Value<'ast>) -> bool {
+        match (self, other) {
+            (&Value::Nil, &Value::Nil)                 => true,
+            (&Value::Bool(a), &Value::Bool(b))         => a == b,
+            (&Value::Char(a), &Value::Char(b))         => a == b,
+            (&Value::Int(a), &Value::Int(b))           => a == b,
+            (&Value::Float(a), &Value::Float(b))       => a == b,
+            (Value::Str(a), Value::Str(b))             => a == b,
+            (Value::Tuple(a), Value::Tuple(b))         => a == b,
+            (Value::Array(a), Value::Array(b))         => a == b,
+            // Maps are equal when they hold the same entries in any order.
+            (Value::Map(a), Value::Map(b)) => {
+                a.len() == b.len() && a.iter().all(|entry| b.contains(entry))
+            },
+            (&Value::Range(a, b, x), &Value::Range(c, d, y)) => a == c && b == d && x == y,
+            // Functions are equal only to themselves.
+            (Value::Function(a), Value::Function(b))   => Rc::ptr_eq(a, b),
+            // Fields may be given in any order.
+            (Value::Struct(a, fields), Value::Struct(b, others)) => {
+                a == b && fields.len() == others.len() &&
+                    fields.iter().all(|field| others.contains(field))
+            },
+            (Value::Variant(a, values), Value::Variant(b, others)) => a == b && values == others,
+            (Value::Constructor(a, _), Value::Constructor(b, _)) => a == b,
+            _                                          => false,
+        }
+    }
+}
+
+impl<'ast> fmt::Display for Value<'ast> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Value::Nil              => write!(f, "nil"),
+            Value::Bool(b)          => write!(f, "{}", b),
+            Value::Char(c)          => write!(f, "{}", c),
+            Value::Int(i)           => write!(f, "{}", i),
+            Value::Float(x)         => write!(f, "{:?}", x),
+            Value::Str(ref s)       => write!(f, "{}", s),
+            Value::Array(ref elems) => {
+                write!(f, "[")?;
+                for (i, elem) in elems.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    elem.fmt(f)?;
+                }
+                write!(f, "]")
+            },
+            Value::Map(ref entries) if entries.is_empty() => write!(f, "[:]"),
+            Value::Map(ref entries) => {
+                write!(f, "[")?;
+                for (i, (key, value)) in entries.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}: {}", key, value)?;
+                }
+                write!(f, "]")
+            },
+            Value::Range(start, end, limits) => write!(f, "{}{}{}", start, limits, end),
+            Value::Tuple(ref elems) => {
+                write!(f, "(")?;
+                for (i, elem) in elems.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    elem.fmt(f)?;
+                }
+                if elems.len() == 1 {
+                    write!(f, ",")?;
+                }
+                write!(f, ")")
+            },
+            Value::Function(ref fun) => fun.fmt(f),
+            Value::Struct(ref name, ref fields) => {
+                write!(f, "{} {{", name)?;
+                for (i, (field, value)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, " {}: {}", field, value)?;
+                }
+                write!(f, " }}")
+            },
+            Value::Variant(ref path, ref values) => {
+                write!(f, "{}", path)?;
+                if !values.is_empty() {
+                    write!(f, "(")?;
+                    for (i, value) in values.iter().enumerate() {
+                        if i > 0 {
+                            write!(f, ", ")?;
+                        }
+                        value.fmt(f)?;
+                    }
+                    write!(f, ")")?;
+                }
+                Ok(())
+            },
+            Value::Constructor(ref path, _) => write!(f, "<fn {}>", path),
+        }
+    }
+}
+
+/// A callable body together with the scope it closes over.
+pub struct Function<'ast> {
+    /// Declared name, `None` for closures.
+    pub name:   Option<String>,
+    pub params: &'ast [Param<'ast>],
+    pub body:   Block<'ast>,
+    pub env:    Env<'ast>,
+}
+
+// The captured scope may hold the function itself, so it is left out.
+impl<'ast> fmt::Debug for Function<'ast> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self)
+    }
+}
+
+impl<'ast> fmt::Display for Function<'ast> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.name {
+            Some(ref name) => write!(f, "<fn {}>", name),
+            None           => write!(f, "<closure>"),
+        }
+    }
+}
@@ -1,8 +1,14 @@
 #[cfg_attr(test, macro_use)]
 extern crate pest;
 #[macro_use] extern crate pest_derive;
-// #[macro_use] extern crate lazy_static;
+#[macro_use] extern crate lazy_static;
+extern crate fnv;
 
 pub mod error;
 pub mod utils;
 pub mod syntax;
+pub mod loader;
+pub mod program;
+pub mod resolve;
+pub mod typeck;
+pub mod eval;
@@ -0,0 +1,341 @@
+//! The "loader" module turns an entry file into the set of modules reachable
+//! from it through `use` declarations.
+//!
+//! ## Resolution
+//! A `use a::b::c;` declaration is resolved against a base directory: the
+//! loader root for absolute paths (`use ::a::b::c;`) and the directory of the
+//! importing file otherwise.  The path first names a module file, `a/b/c.ag`,
+//! which binds the module as `c`.  Failing that, the last segment names an
+//! item of the module file `a/b.ag`, which must be declared `pub`.
+//!
+//! A qualified name in an expression, `shapes::area`, is resolved the same
+//! way once the `use` declarations of its module are.  A relative path starts
+//! at a whole module import and may pass through the `pub` whole module
+//! imports of that module; an absolute path, `::geometry::shapes::area`,
+//! names a module file below the root.  Either way the last segment must be
+//! a `pub` item of the module reached.
+//!
+//! Every module is parsed once, into the arena of the loader; later imports
+//! of the same file share it.
+//! Importing a module that is still being loaded is reported as a cycle.
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path as FilePath, PathBuf};
+
+use error::{ModuleError, ModuleErrorKind};
+use syntax::ast::{Arena, Expr, ExprKind, Item, ItemKind, Module, UseDecl, Visibility};
+use syntax::ast::visit::{self, Visitor};
+use syntax::parser;
+use utils::{Diagnostic, FileId, Path, Position, Severity, Span};
+
+/// Index of a loaded module.
+pub type ModuleId = usize;
+
+/// A parsed module file along with its resolved imports.
+#[derive(Debug)]
+pub struct LoadedModule<'ast> {
+    pub file:    PathBuf,
+    /// The file the nodes of `ast` are located in.
+    pub file_id: FileId,
+    pub ast:     Module<'ast>,
+    pub imports: Vec<Import>,
+    /// The qualified names used in the expressions of the module.
+    pub paths:   Vec<PathImport>,
+}
+
+/// A name bound by a `use` declaration.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Import {
+    /// The bound name, i.e. the last segment of the path.
+    pub name:   String,
+    /// `pub use` re-exports the name from the importing module.
+    pub vis:    Visibility,
+    /// The module the path resolved to.
+    pub module: ModuleId,
+    /// The imported item, or `None` when the whole module is imported.
+    pub item:   Option<String>,
+}
+
+/// An item named by a qualified name in an expression.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PathImport {
+    /// The path as written, i.e. `shapes::area`.
+    pub path:   String,
+    /// The module declaring or re-exporting the item.
+    pub module: ModuleId,
+    pub item:   String,
+}
+
+/// Loader struct keeps track of loaded modules and of the chain of modules
+/// currently being loaded.
+pub struct Loader<'ast> {
+    arena:   &'ast Arena<'ast>,
+    root:    PathBuf,
+    modules: Vec<LoadedModule<'ast>>,
+    by_file: HashMap<PathBuf, ModuleId>,
+    /// Every file read, indexed by `FileId`.
+    files:   Vec<PathBuf>,
+    loading: Vec<PathBuf>,
+    errors:  Vec<Diagnostic>,
+}
+
+impl<'ast> Loader<'ast> {
+    /// Creates a loader resolving absolute `use` paths below `root`, which
+    /// allocates the syntax trees of the modules in `arena`.
+    pub fn new<P: AsRef<FilePath>>(arena: &'ast Arena<'ast>, root: P) -> Loader<'ast> {
+        Loader {
+            arena,
+            root:    root.as_ref().to_path_buf(),
+            modules: Vec::new(),
+            by_file: HashMap::new(),
+            files:   Vec::new(),
+            loading: Vec::new(),
+            errors:  Vec::new(),
+        }
+    }
+
+    /// Loads `entry` and every module it imports, directly or not.
+    pub fn load<P: AsRef<FilePath>>(&mut self, entry: P) -> Result<ModuleId, Vec<Diagnostic>> {
+        let entry = entry.as_ref().to_path_buf();
+        let source = entry.display().to_string();
+        let id = self.load_file(entry, &source, Span(Position(0, 0), Position(0, 0)));
+        match id {
+            Some(id) if self.errors.is_empty() => Ok(id),
+            _ => Err(self.errors.drain(..).collect()),
+        }
+    }
+
+    pub fn module(&self, id: ModuleId) -> &LoadedModule<'ast> {
+        &self.modules[id]
+    }
+
+    pub fn modules(&self) -> &[LoadedModule<'ast>] {
+        &self.modules
+    }
+
+    /// The path of the file that nodes located in `id` were parsed from.
+    pub fn file(&self, id: FileId) -> &FilePath {
+        &self.files[id.0 as usize]
+    }
+
+    /// Looks up a name exported by `module`, returning its visibility.
+    pub fn lookup(&self, module: ModuleId, name: &str) -> Option<Visibility> {
+        let module = &self.modules[module];
+        let declared = module.ast.items().into_iter().find(|item| {
+            match item.kind {
+                ItemKind::Use(_) => false,
+                _                => item.name() == name,
+            }
+        });
+        match declared {
+            Some(item) => Some(item.vis),
+            None => module.imports.iter()
+                .find(|import| import.name == name)
+                .map(|import| import.vis),
+        }
+    }
+
+    fn load_file(&mut self, file: PathBuf, importer: &str, span: Span) -> Option<ModuleId> {
+        let file = match fs::canonicalize(&file) {
+            Ok(file) => file,
+            Err(err) => return self.error(importer, span, ModuleErrorKind::Io(
+                    format!("{}: {}", file.display(), err))),
+        };
+        if let Some(&id) = self.by_file.get(&file) {
+            return Some(id);
+        }
+        if let Some(start) = self.loading.iter().position(|loading| *loading == file) {
+            let mut chain: Vec<String> = self.loading[start..].iter()
+                .map(|loading| loading.display().to_string())
+                .collect();
+            chain.push(file.display().to_string());
+            return self.error(importer, span, ModuleErrorKind::ImportCycle(chain));
+        }
+
+        let source = file.display().to_string();
+        let text = match fs::read_to_string(&file) {
+            Ok(text) => text,
+            Err(err) => return self.error(importer, span, ModuleErrorKind::Io(
+                    format!("{}: {}", source, err))),
+        };
+        let file_id = FileId(self.files.len() as u32);
+        self.files.push(file.clone());
+        let ast = match parser::parse_file(self.arena, &text, file_id) {
+            Ok(ast)  => ast,
+            Err(err) => {
+                let kind = ModuleErrorKind::Parse(err.message());
+                return self.error(&source, err.span(&text), kind);
+            },
+        };
+
+        self.loading.push(file.clone());
+        let mut imports = Vec::new();
+        for item in ast.items() {
+            if let ItemKind::Use(ref decl) = item.kind {
+                if let Some(import) = self.resolve_use(&file, decl, item.vis) {
+                    imports.push(import);
+                }
+            }
+        }
+        let mut collector = PathCollector { paths: Vec::new(), specs: Vec::new() };
+        for stmt in ast.stmts {
+            collector.visit_stmt(stmt);
+        }
+        let mut paths: Vec<PathImport> = Vec::new();
+        for (path, span) in collector.paths {
+            if paths.iter().any(|known| known.path == path.to_string()) {
+                continue;
+            }
+            // `Shape::Circle` names a variant of a spec of the module.
+            let parent = path.parent().map(|parent| parent.to_string());
+            let variant = collector.specs.iter().any(|&spec| parent.as_deref() == Some(spec));
+            if variant && !path.is_absolute() {
+                continue;
+            }
+            if let Some(import) = self.resolve_path(&file, &imports, path, span) {
+                paths.push(import);
+            }
+        }
+        self.loading.pop();
+
+        let id = self.modules.len();
+        self.modules.push(LoadedModule { file: file.clone(), file_id, ast, imports, paths });
+        self.by_file.insert(file, id);
+        Some(id)
+    }
+
+    fn resolve_use(&mut self, importer: &FilePath, decl: &UseDecl, vis: Visibility)
+        -> Option<Import>
+    {
+        let source = importer.display().to_string();
+        let base = if decl.path.is_absolute() {
+            self.root.clone()
+        } else {
+            importer.parent().map(FilePath::to_path_buf).unwrap_or_default()
+        };
+        let name = decl.path.last_str().to_string();
+        let rendered = decl.path.to_string();
+
+        // `use a::b::c;` naming the module file `a/b/c.ag`.
+        let module_file = decl.path.to_file_path(&base);
+        if module_file.is_file() {
+            let module = self.load_file(module_file, &source, decl.span)?;
+            return Some(Import { name, vis, module, item: None });
+        }
+
+        // `use a::b::c;` naming the item `c` of the module file `a/b.ag`.
+        let item_file = match decl.path.parent() {
+            Some(ref parent) if !parent.is_empty() => parent.to_file_path(&base),
+            _ => return self.error(&source, decl.span, ModuleErrorKind::UnresolvedImport(rendered)),
+        };
+        if !item_file.is_file() {
+            return self.error(&source, decl.span, ModuleErrorKind::UnresolvedImport(rendered));
+        }
+        let module = self.load_file(item_file, &source, decl.span)?;
+        match self.lookup(module, &name) {
+            Some(Visibility::Public)  => Some(Import { name: name.clone(), vis, module, item: Some(name) }),
+            Some(Visibility::Private) => {
+                self.error(&source, decl.span, ModuleErrorKind::PrivateItem(rendered))
+            },
+            None => self.error(&source, decl.span, ModuleErrorKind::UnresolvedImport(rendered)),
+        }
+    }
+
+    /// Resolves a qualified name used in the module `importer`, whose `use`
+    /// declarations resolved to `imports`.
+    fn resolve_path(&mut self, importer: &FilePath, imports: &[Import], path: &Path, span: Span)
+        -> Option<PathImport>
+    {
+        let source = importer.display().to_string();
+        let rendered = path.to_string();
+        let segments: Vec<&str> = path.segments().map(|sym| sym.as_str()).collect();
+        let (last, prefix) = match segments.split_last() {
+            Some((last, prefix)) if !prefix.is_empty() => (*last, prefix),
+            _ => return self.error(&source, span, ModuleErrorKind::UnresolvedPath(rendered)),
+        };
+
+        let module = if path.is_absolute() {
+            // `::a::b::c` naming the item `c` of the module file `a/b.ag`.
+            let file = path.parent()?.to_file_path(&self.root);
+            if !file.is_file() {
+                return self.error(&source, span, ModuleErrorKind::UnresolvedPath(rendered));
+            }
+            self.load_file(file, &source, span)?
+        } else {
+            // `a::b::c` starting at the whole module import `a`, through the
+            // `pub` whole module import `b` of `a`.
+            let first = imports.iter()
+                .find(|import| import.name == prefix[0] && import.item.is_none());
+            let mut module = match first {
+                Some(import) => import.module,
+                None => return self.error(&source, span, ModuleErrorKind::UnresolvedPath(rendered)),
+            };
+            for (i, segment) in prefix.iter().enumerate().skip(1) {
+                let next = self.modules[module].imports.iter()
+                    .find(|import| import.name == *segment && import.item.is_none())
+                    .map(|import| (import.module, import.vis));
+                module = match next {
+                    Some((next, Visibility::Public))  => next,
+                    Some((_, Visibility::Private)) => {
+                        let private = prefix[..=i].join("::");
+                        return self.error(&source, span, ModuleErrorKind::PrivateItem(private));
+                    },
+                    None => {
+                        return self.error(&source, span, ModuleErrorKind::UnresolvedPath(rendered))
+                    },
+                };
+            }
+            module
+        };
+
+        // Whole module imports are not values.
+        let is_module = self.modules[module].imports.iter()
+            .any(|import| import.name == last && import.item.is_none());
+        if is_module {
+            return self.error(&source, span, ModuleErrorKind::UnresolvedPath(rendered));
+        }
+        match self.lookup(module, last) {
+            Some(Visibility::Public)  => {
+                Some(PathImport { path: rendered, module, item: last.to_string() })
+            },
+            Some(Visibility::Private) => {
+                self.error(&source, span, ModuleErrorKind::PrivateItem(rendered))
+            },
+            None => self.error(&source, span, ModuleErrorKind::UnresolvedPath(rendered)),
+        }
+    }
+
+    /// Records a module error, returning `None` for the caller to bail out.
+    fn error<T>(&mut self, source: &str, span: Span, kind: ModuleErrorKind) -> Option<T> {
+        self.errors.push(From::from(ModuleError {
+            source:   source.to_string(),
+            span,
+            severity: Severity::Error,
+            kind
+        }));
+        None
+    }
+}
+
+/// Collects the qualified names used in expressions, with their spans, and
+/// the names of the specs declared in the module.
+struct PathCollector<'ast> {
+    paths: Vec<(&'ast Path, Span)>,
+    specs: Vec<&'ast str>,
+}
+
+impl<'ast> Visitor<'ast> for PathCollector<'ast> {
+    fn visit_item(&mut self, item: &'ast Item<'ast>) {
+        if let ItemKind::Spec(ref decl) = item.kind {
+            self.specs.push(&decl.name);
+        }
+        visit::walk_item(self, item);
+    }
+
+    fn visit_expr(&mut self, expr: &'ast Expr<'ast>) {
+        match expr.kind {
+            ExprKind::Path(ref path) => self.paths.push((path, expr.span)),
+            _                        => visit::walk_expr(self, expr),
+        }
+    }
+}
@@ -0,0 +1,92 @@
+//! Runs a script, or dumps its syntax tree.
+//!
+//!     argentum [--emit=ast-json|ast-sexp] FILE
+//!
+//! Without `--emit` the file is loaded along with the modules it imports,
+//! whose `use` paths are resolved from the directory of the file.  The names
+//! of every module are resolved, its constants folded, then they are checked
+//! and evaluated, each module after the ones it imports.  Diagnostics go to standard error and
+//! exit with status 1; usage errors exit with status 2.
+
+extern crate argentum;
+
+use std::env;
+use std::fs;
+use std::io;
+use std::process;
+
+use argentum::program::Program;
+use argentum::syntax::ast::Arena;
+use argentum::syntax::{dump, parser};
+use argentum::utils::Diagnostic;
+
+const USAGE: &str = "usage: argentum [--emit=ast-json|ast-sexp] FILE";
+
+/// What to do with the parsed file.
+enum Emit {
+    Run,
+    AstJson,
+    AstSexp,
+}
+
+fn main() {
+    let mut emit = Emit::Run;
+    let mut file = None;
+    for arg in env::args().skip(1) {
+        match arg.as_str() {
+            "--emit=ast-json" => emit = Emit::AstJson,
+            "--emit=ast-sexp" => emit = Emit::AstSexp,
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return;
+            },
+            _ if arg.starts_with('-') || file.is_some() => usage(),
+            _ => file = Some(arg),
+        }
+    }
+    let file = file.unwrap_or_else(|| usage());
+    let dump = match emit {
+        Emit::Run     => return run(&file),
+        Emit::AstJson => dump::module_to_json,
+        Emit::AstSexp => dump::module_to_sexp,
+    };
+
+    let text = match fs::read_to_string(&file) {
+        Ok(text) => text,
+        Err(err) => fail(&format!("{}: {}", file, err)),
+    };
+    let arena = Arena::new();
+    match parser::parse_module(&arena, &text) {
+        Ok(module) => print!("{}", dump(&module)),
+        Err(err)   => fail(&format!("{}: {}", file, err)),
+    }
+}
+
+/// Loads, checks and evaluates `file` and the modules it imports.
+fn run(file: &str) {
+    let arena = Arena::new();
+    let mut program = Program::load(&arena, file).unwrap_or_else(|errors| report(errors));
+    if let Err(errors) = program.check() {
+        report(errors);
+    }
+    if let Err(err) = program.run(Box::new(io::stdout())) {
+        fail(&err.to_string());
+    }
+}
+
+fn usage() -> ! {
+    eprintln!("{}", USAGE);
+    process::exit(2);
+}
+
+fn report(errors: Vec<Diagnostic>) -> ! {
+    for err in errors {
+        eprintln!("{}", err);
+    }
+    process::exit(1);
+}
+
+fn fail(msg: &str) -> ! {
+    eprintln!("{}", msg);
+    process::exit(1);
+}
@@ -0,0 +1,149 @@
+//! The "program" module runs a script along with the modules it imports.
+//!
+//! ## Pipeline
+//! The `Loader` reads the entry file and every module reachable from it,
+//! parsing them into one arena that the program borrows.  The
+//! names of all the modules are resolved, their constants evaluated and
+//! inlined, and their types checked before any of them runs, so that a
+//! program with errors has no effect.  Checking the inlined module range
+//! checks the values of constants against their types.  The modules are
+//! checked in load order too, so that the types of imported names are known.
+//! The modules then run one after another in the order they were loaded,
+//! which puts every module after the modules it imports, all of them printing
+//! to one output.  The integer types the checker found for the arithmetic
+//! expressions of all the modules are handed to the interpreter, which checks
+//! the results against them.
+//!
+//! ## Imports
+//! A module runs with fresh globals.  An imported item is bound to the value
+//! the exporting module left it with, under the imported name.  The items
+//! named by qualified names, `shapes::area` or `::geometry::shapes::area`, are
+//! bound the same way under the path as written.  A runtime error raised in
+//! a function of an imported module points into the file of that module.
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::Path as FilePath;
+
+use eval::{eval_consts, inline_consts, Env, Interpreter, Scope};
+use loader::{Loader, ModuleId};
+use resolve::Resolver;
+use syntax::ast::{Arena, Module, NodeId, PrimType};
+use typeck::Checker;
+use utils::{Diagnostic, FileId};
+
+/// A loaded entry file and the modules it imports.
+pub struct Program<'ast> {
+    arena:  &'ast Arena<'ast>,
+    loader: Loader<'ast>,
+    entry:  ModuleId,
+    /// The entry file as given, which names it in diagnostics.
+    file:   String,
+    /// Integer types of the arithmetic expressions, found by `check`.
+    ints:   HashMap<(FileId, NodeId), PrimType>,
+}
+
+impl<'ast> Program<'ast> {
+    /// Loads `file` and the modules it imports into `arena`.  Absolute `use`
+    /// paths are resolved from the directory of `file`.
+    pub fn load<P>(arena: &'ast Arena<'ast>, file: P) -> Result<Program<'ast>, Vec<Diagnostic>>
+        where P: AsRef<FilePath>
+    {
+        let file = file.as_ref();
+        let root = match file.parent() {
+            Some(parent) if parent != FilePath::new("") => parent,
+            _                                          => FilePath::new("."),
+        };
+        let mut loader = Loader::new(arena, root);
+        let entry = loader.load(file)?;
+        Ok(Program { arena, loader, entry, file: file.display().to_string(), ints: HashMap::new() })
+    }
+
+    pub fn loader(&self) -> &Loader<'ast> {
+        &self.loader
+    }
+
+    /// Names module `id` in diagnostics:  the entry file as given, the other
+    /// modules by their full path.
+    pub fn source(&self, id: ModuleId) -> String {
+        if id == self.entry {
+            self.file.clone()
+        } else {
+            self.loader.module(id).file.display().to_string()
+        }
+    }
+
+    /// Resolves the names and checks the types of every module, returning the
+    /// errors of all of them.  Imported names have the types their modules,
+    /// checked before, gave them.
+    pub fn check(&mut self) -> Result<(), Vec<Diagnostic>> {
+        let mut errors = Vec::new();
+        // The checker of each module, by module id.
+        let mut checkers: Vec<Checker> = Vec::new();
+        for (id, module) in self.loader.modules().iter().enumerate() {
+            let mut checker = Checker::new(self.source(id));
+            for import in &module.imports {
+                if let Some(ref item) = import.item {
+                    checker.define_import(&import.name, checkers[import.module].global(item));
+                }
+            }
+            for import in &module.paths {
+                let ty = checkers[import.module].global(&import.item);
+                checker.define_import(&import.path, ty);
+            }
+            let resolved = Resolver::new(self.source(id)).resolve_module(&module.ast);
+            let checked = resolved
+                .and_then(|_| self.fold(id))
+                .and_then(|ast| checker.check_module(&ast));
+            if let Err(found) = checked {
+                errors.extend(found);
+            }
+            self.ints.extend(checker.int_types().iter().map(|(&node, &ty)| (node, ty)));
+            checkers.push(checker);
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Module `id` with its constants inlined.
+    fn fold(&self, id: ModuleId) -> Result<Module<'ast>, Vec<Diagnostic>> {
+        let ast = self.loader.module(id).ast;
+        let consts = eval_consts(&self.source(id), &ast)?;
+        Ok(inline_consts(self.arena, ast, &consts))
+    }
+
+    /// Runs every module, stopping at the first runtime error.  The program
+    /// is expected to have been checked.
+    pub fn run(&self, output: Box<dyn Write>) -> Result<(), Diagnostic> {
+        let mut interp = Interpreter::new(self.source(self.entry));
+        interp.set_output(output);
+        let files = (0..self.loader.modules().len())
+            .map(|id| (self.loader.module(id).file_id, self.source(id)))
+            .collect();
+        interp.set_files(files);
+        interp.set_int_types(self.ints.clone());
+        // The globals each module was left with, by module id.
+        let mut exports: Vec<Env> = Vec::new();
+        for (id, module) in self.loader.modules().iter().enumerate() {
+            interp.next_module(self.source(id));
+            for import in &module.imports {
+                if let Some(ref item) = import.item {
+                    if let Some(value) = Scope::get(&exports[import.module], item) {
+                        interp.define_global(&import.name, value);
+                    }
+                }
+            }
+            for import in &module.paths {
+                if let Some(value) = Scope::get(&exports[import.module], &import.item) {
+                    interp.define_global(&import.path, value);
+                }
+            }
+            let ast = self.fold(id).map_err(|mut errors| errors.remove(0))?;
+            interp.eval_module(&ast)?;
+            exports.push(interp.globals());
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,550 @@
+//! The "resolve" module binds each name used in a module to its declaration.
+//!
+//! ## Scopes
+//! The module, each function and closure, each block, each `for` loop and
+//! each `match` arm open a scope.  The scope of a function or closure holds
+//! its parameters, and the scope of a `for` loop or an arm its pattern.  A
+//! struct or spec has a scope of its own for its type parameters.  Names are looked up from the
+//! innermost scope outward.
+//!
+//! ## Declarations
+//! Items, i.e. `fn`, `const`, `type`, `struct`, `spec` and `use`
+//! declarations, are visible in their whole scope, before their declaration
+//! as well as after it.  A `let` binding is visible from the end of its
+//! statement to the end of its scope, and may shadow an earlier binding.
+//! Using a variable before its `let` is an error, unless the use is in a
+//! function or closure declared in between, which may well run after the
+//! `let`.
+//!
+//! ## Namespaces
+//! Types and values have separate namespaces, as in the checker:  type
+//! aliases, structs, specs and type parameters name types, and the other
+//! declarations values, so `type A = i32; let A = 1;` declares both.  A
+//! `use` declaration may import either, and binds its name in both.  The
+//! names of type annotations and struct literals are looked up among types
+//! only.  The variants of a spec are reached through paths, which the
+//! loader checks.
+//!
+//! ## Duplicates
+//! The items of a scope, the parameters of a function and the names of a
+//! pattern must all differ; so must a variable and an item of the same scope.
+use std::collections::HashMap;
+use std::mem;
+
+use error::{ResolveError, ResolveErrorKind};
+use syntax::ast::{Arm, Block, Closure, Expr, ExprKind, FnDecl, ForLoop, Item, ItemKind, Local,
+                  Module, NodeId, Param, Pattern, PatternKind, Stmt, StmtKind, Type, TypeKind,
+                  TypeParam};
+use syntax::ast::visit::{self, Visitor};
+use utils::{Diagnostic, Severity, Span};
+
+/// What declares a name.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DeclKind {
+    Fn,
+    Const,
+    Type,
+    Struct,
+    Spec,
+    Use,
+    /// Parameter of a function or closure.
+    Param,
+    /// Variable of a `let` or `for` pattern.
+    Local,
+}
+
+/// The declaration a name refers to:  an item, or the identifier pattern
+/// binding a parameter or variable.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Decl {
+    pub id:   NodeId,
+    pub kind: DeclKind,
+    pub span: Span,
+}
+
+impl Decl {
+    fn is_item(&self) -> bool {
+        match self.kind {
+            DeclKind::Fn | DeclKind::Const | DeclKind::Type | DeclKind::Struct |
+            DeclKind::Spec | DeclKind::Use     => true,
+            DeclKind::Param | DeclKind::Local => false,
+        }
+    }
+}
+
+/// The declarations the identifier expressions, struct literals and named
+/// types of a module refer to, indexed by the id of the expression or type.  Type parameters
+/// have no declaration node, so the types naming them are left out.
+#[derive(Debug, Default)]
+pub struct Resolutions {
+    decls: HashMap<NodeId, Decl>,
+}
+
+impl Resolutions {
+    pub fn get(&self, id: NodeId) -> Option<&Decl> {
+        self.decls.get(&id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.decls.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.decls.is_empty()
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum ScopeKind {
+    Module,
+    Fn,
+    Block,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Namespace {
+    Values,
+    Types,
+}
+
+struct Scope {
+    kind:     ScopeKind,
+    names:    HashMap<String, Decl>,
+    types:    HashMap<String, Decl>,
+    /// Type parameters of the generic function the scope belongs to.
+    generics: Vec<String>,
+    /// Variables of the `let` statements of the scope not reached yet.
+    later:    Vec<(String, Decl)>,
+}
+
+impl Scope {
+    fn new(kind: ScopeKind) -> Scope {
+        Scope {
+            kind,
+            names:    HashMap::new(),
+            types:    HashMap::new(),
+            generics: Vec::new(),
+            later:    Vec::new(),
+        }
+    }
+
+    fn namespace(&self, ns: Namespace) -> &HashMap<String, Decl> {
+        match ns {
+            Namespace::Values => &self.names,
+            Namespace::Types  => &self.types,
+        }
+    }
+}
+
+/// Resolver struct holds the scopes enclosing the visited node.
+pub struct Resolver {
+    source:      String,
+    scopes:      Vec<Scope>,
+    resolutions: Resolutions,
+    errors:      Vec<Diagnostic>,
+}
+
+impl Resolver {
+    /// Creates a resolver; `source` names the resolved file in diagnostics.
+    pub fn new<S: Into<String>>(source: S) -> Resolver {
+        Resolver {
+            source:      source.into(),
+            scopes:      Vec::new(),
+            resolutions: Resolutions::default(),
+            errors:      Vec::new(),
+        }
+    }
+
+    /// Resolves the names used in `module`, returning all diagnostics found.
+    pub fn resolve_module(&mut self, module: &Module) -> Result<Resolutions, Vec<Diagnostic>> {
+        self.visit_module(module);
+        let resolutions = mem::take(&mut self.resolutions);
+        if self.errors.is_empty() {
+            Ok(resolutions)
+        } else {
+            Err(self.errors.drain(..).collect())
+        }
+    }
+
+    /// Runs `body` in a new scope declaring the items of `stmts`.
+    fn scope<F: FnOnce(&mut Resolver)>(&mut self, kind: ScopeKind, stmts: &[Stmt], body: F) {
+        self.scopes.push(Scope::new(kind));
+        self.declare(stmts);
+        body(self);
+        self.scopes.pop();
+    }
+
+    /// Declares the items of a statement list up front, and records the
+    /// variables its `let` statements will declare.
+    fn declare(&mut self, stmts: &[Stmt]) {
+        for stmt in stmts {
+            match stmt.kind {
+                StmtKind::Item(ref item) => {
+                    let (kind, namespaces) = match item.kind {
+                        ItemKind::Use(_)   => (DeclKind::Use, BOTH),
+                        ItemKind::Const(_) => (DeclKind::Const, VALUES),
+                        ItemKind::Type(_)  => (DeclKind::Type, TYPES),
+                        ItemKind::Struct(_) => (DeclKind::Struct, TYPES),
+                        ItemKind::Spec(_)  => (DeclKind::Spec, TYPES),
+                        ItemKind::Fn(_)    => (DeclKind::Fn, VALUES),
+                    };
+                    // An import clashing in both namespaces is reported once.
+                    for &ns in namespaces {
+                        let decl = Decl { id: item.id, kind, span: item.span };
+                        if !self.define(ns, item.name(), decl) {
+                            break;
+                        }
+                    }
+                },
+                StmtKind::Let(ref local) => {
+                    let mut names = Vec::new();
+                    pattern_names(&local.pat, &mut names);
+                    let scope = self.scopes.last_mut().expect("declarations are in a scope");
+                    for (name, pat) in names {
+                        let decl = Decl { id: pat.id, kind: DeclKind::Local, span: pat.span };
+                        scope.later.push((name.to_string(), decl));
+                    }
+                },
+                _ => {},
+            }
+        }
+    }
+
+    /// Binds the names of `pat` in the innermost scope.
+    fn bind(&mut self, pat: &Pattern, kind: DeclKind) {
+        let mut names = Vec::new();
+        pattern_names(pat, &mut names);
+        for (index, &(name, pat)) in names.iter().enumerate() {
+            if let Some(&(_, first)) = names[..index].iter().find(|&&(other, _)| other == name) {
+                self.error(pat.span, ResolveErrorKind::DuplicateDefinition {
+                    name:     name.to_string(),
+                    previous: first.span,
+                });
+                continue;
+            }
+            self.scopes.last_mut().expect("bindings are in a scope")
+                .later.retain(|(_, later)| later.id != pat.id);
+            self.define(Namespace::Values, name, Decl { id: pat.id, kind, span: pat.span });
+        }
+    }
+
+    /// Defines `name` in namespace `ns` of the innermost scope.  A variable
+    /// may shadow another variable, other declarations of the same name
+    /// clash; the one that comes last in the source is reported.  Returns
+    /// whether there was no clash.
+    fn define(&mut self, ns: Namespace, name: &str, decl: Decl) -> bool {
+        let previous = self.scopes.last()
+            .and_then(|scope| scope.namespace(ns).get(name))
+            .cloned();
+        if let Some(previous) = previous {
+            let params = previous.kind == DeclKind::Param && decl.kind == DeclKind::Param;
+            if previous.is_item() || decl.is_item() || params {
+                let (first, second) = if before(previous.span, decl.span) {
+                    (previous, decl)
+                } else {
+                    (decl, previous)
+                };
+                self.error(second.span, ResolveErrorKind::DuplicateDefinition {
+                    name:     name.to_string(),
+                    previous: first.span,
+                });
+                return false;
+            }
+        }
+        let scope = self.scopes.last_mut().expect("definitions are in a scope");
+        match ns {
+            Namespace::Values => scope.names.insert(name.to_string(), decl),
+            Namespace::Types  => scope.types.insert(name.to_string(), decl),
+        };
+        true
+    }
+
+    /// Binds the named type `id` to the declaration of `name`.
+    fn resolve_type(&mut self, id: NodeId, name: &str, span: Span) {
+        for scope in self.scopes.iter().rev() {
+            if let Some(&decl) = scope.types.get(name) {
+                self.resolutions.decls.insert(id, decl);
+                return;
+            }
+            if scope.generics.iter().any(|param| param == name) {
+                return;
+            }
+        }
+        self.error(span, ResolveErrorKind::UndefinedType(name.to_string()));
+    }
+
+    /// Binds the identifier expression `id` to the declaration of `name`.
+    fn resolve(&mut self, id: NodeId, name: &str, span: Span) {
+        let visible = self.scopes.iter().rev().filter_map(|scope| scope.names.get(name)).next();
+        if let Some(&decl) = visible {
+            self.resolutions.decls.insert(id, decl);
+            return;
+        }
+        // A variable declared later may only be used in a function declared
+        // in between.
+        let mut deferred = false;
+        for scope in self.scopes.iter().rev() {
+            if let Some(&(_, decl)) = scope.later.iter().find(|&(later, _)| later == name) {
+                if deferred {
+                    self.resolutions.decls.insert(id, decl);
+                } else {
+                    self.error(span, ResolveErrorKind::UseBeforeDeclaration(name.to_string()));
+                }
+                return;
+            }
+            deferred = deferred || scope.kind == ScopeKind::Fn;
+        }
+        self.error(span, ResolveErrorKind::UndefinedName(name.to_string()));
+    }
+
+    fn error(&mut self, span: Span, kind: ResolveErrorKind) {
+        self.errors.push(From::from(ResolveError {
+            source:   self.source.clone(),
+            span,
+            severity: Severity::Error,
+            kind
+        }));
+    }
+}
+
+impl<'ast> Visitor<'ast> for Resolver {
+    fn visit_module(&mut self, module: &'ast Module) {
+        self.scope(ScopeKind::Module, module.stmts, |resolver| {
+            visit::walk_module(resolver, module)
+        });
+    }
+
+    fn visit_item(&mut self, item: &'ast Item) {
+        match item.kind {
+            ItemKind::Struct(_) | ItemKind::Spec(_) => {
+                self.scope(ScopeKind::Block, &[], |resolver| visit::walk_item(resolver, item))
+            },
+            _ => visit::walk_item(self, item),
+        }
+    }
+
+    fn visit_fn_decl(&mut self, decl: &'ast FnDecl) {
+        self.scope(ScopeKind::Fn, &[], |resolver| visit::walk_fn_decl(resolver, decl));
+    }
+
+    fn visit_closure(&mut self, closure: &'ast Closure) {
+        self.scope(ScopeKind::Fn, &[], |resolver| visit::walk_closure(resolver, closure));
+    }
+
+    fn visit_type_param(&mut self, param: &'ast TypeParam) {
+        self.scopes.last_mut().expect("type parameters are in a scope")
+            .generics.push(param.name.clone());
+    }
+
+    fn visit_type(&mut self, ty: &'ast Type) {
+        match ty.kind {
+            TypeKind::Named(ref name, _) => {
+                self.resolve_type(ty.id, name, ty.span);
+                visit::walk_type(self, ty);
+            },
+            _ => visit::walk_type(self, ty),
+        }
+    }
+
+    fn visit_param(&mut self, param: &'ast Param) {
+        if let Some(ref ty) = param.ty {
+            self.visit_type(ty);
+        }
+        self.bind(&param.pat, DeclKind::Param);
+    }
+
+    fn visit_block(&mut self, block: &'ast Block) {
+        self.scope(ScopeKind::Block, block.stmts, |resolver| visit::walk_block(resolver, block));
+    }
+
+    fn visit_local(&mut self, local: &'ast Local) {
+        if let Some(ref ty) = local.ty {
+            self.visit_type(ty);
+        }
+        if let Some(ref init) = local.init {
+            self.visit_expr(init);
+        }
+        self.bind(&local.pat, DeclKind::Local);
+    }
+
+    fn visit_for_loop(&mut self, for_loop: &'ast ForLoop) {
+        self.visit_expr(&for_loop.iter);
+        self.scope(ScopeKind::Block, &[], |resolver| {
+            resolver.bind(&for_loop.pat, DeclKind::Local);
+            resolver.visit_block(&for_loop.body);
+        });
+    }
+
+    fn visit_expr(&mut self, expr: &'ast Expr) {
+        match expr.kind {
+            ExprKind::Identifier(ref name) => self.resolve(expr.id, name, expr.span),
+            ExprKind::Struct(ref name, _)  => {
+                self.resolve_type(expr.id, name, expr.span);
+                visit::walk_expr(self, expr);
+            },
+            _                              => visit::walk_expr(self, expr),
+        }
+    }
+
+    fn visit_arm(&mut self, arm: &'ast Arm) {
+        self.scope(ScopeKind::Block, &[], |resolver| {
+            resolver.bind(&arm.pat, DeclKind::Local);
+            resolver.visit_expr(&arm.body);
+        });
+    }
+}
+
+const VALUES: &[Namespace] = &[Namespace::Values];
+const TYPES: &[Namespace] = &[Namespace::Types];
+const BOTH: &[Namespace] = &[Namespace::Values, Namespace::Types];
+
+/// The names a pattern binds, with the identifier patterns binding them.
+fn pattern_names<'a>(pat: &'a Pattern<'a>, names: &mut Vec<(&'a str, &'a Pattern<'a>)>) {
+    match pat.kind {
+        PatternKind::Ident(ref name) => names.push((name, pat)),
+        PatternKind::Tuple(pats) |
+        PatternKind::Variant(_, pats) => for pat in pats {
+            pattern_names(pat, names);
+        },
+    }
+}
+
+/// Whether `a` starts before `b` in the source.
+fn before(a: Span, b: Span) -> bool {
+    ((a.0).0, (a.0).1) <= ((b.0).0, (b.0).1)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use syntax::ast::{Arena, NodeMap};
+    use syntax::ast::map::Node;
+    use syntax::parser::parse_module;
+    use utils::Position;
+
+    fn resolve(src: &str) -> Result<(), Vec<String>> {
+        let arena = Arena::new();
+        let module = parse_module(&arena, src).unwrap();
+        Resolver::new("test.ag").resolve_module(&module)
+            .map(|_| ())
+            .map_err(|errors| errors.into_iter().map(|err| err.msg).collect())
+    }
+
+    /// The kind and the text of the declaration of each identifier, in the
+    /// order of the ids.
+    fn bindings(src: &str) -> Vec<(String, DeclKind, Position)> {
+        let arena = Arena::new();
+        let module = parse_module(&arena, src).unwrap();
+        let resolutions = Resolver::new("test.ag").resolve_module(&module).unwrap();
+        let map = NodeMap::new(&module);
+        (0..map.len()).filter_map(|index| match map.get(NodeId(index as u32)) {
+            Some(Node::Expr(expr)) => match expr.kind {
+                ExprKind::Identifier(ref name) => {
+                    let decl = resolutions.get(expr.id).expect("identifiers are resolved");
+                    Some((name.clone(), decl.kind, decl.span.0))
+                },
+                _ => None,
+            },
+            _ => None,
+        }).collect()
+    }
+
+    #[test]
+    fn binds_names_to_declarations() {
+        let bindings = bindings("
+            let total = twice(LIMIT);
+            fn twice(x: i32) -> i32 { x * 2 }
+            const LIMIT: i32 = 10;
+            for (i, n) in [(0, 1)] { let total = i + n; print total; }
+            let add = |a, b| a + b + total;
+        ");
+        assert_eq!(bindings, vec![
+            ("twice".to_string(), DeclKind::Fn, Position(3, 13)),
+            ("LIMIT".to_string(), DeclKind::Const, Position(4, 13)),
+            ("x".to_string(), DeclKind::Param, Position(3, 22)),
+            ("i".to_string(), DeclKind::Local, Position(5, 18)),
+            ("n".to_string(), DeclKind::Local, Position(5, 21)),
+            ("total".to_string(), DeclKind::Local, Position(5, 42)),
+            ("a".to_string(), DeclKind::Param, Position(6, 24)),
+            ("b".to_string(), DeclKind::Param, Position(6, 27)),
+            ("total".to_string(), DeclKind::Local, Position(2, 17)),
+        ]);
+    }
+
+    #[test]
+    fn undefined_names() {
+        assert_eq!(resolve("fn f(a: i32) -> i32 { a + b } let c = |x| x; x;"), Err(vec![
+            "cannot find `b` in this scope".to_string(),
+            "cannot find `x` in this scope".to_string(),
+        ]));
+        assert_eq!(resolve("if true { let a = 1; } a;"),
+                   Err(vec!["cannot find `a` in this scope".to_string()]));
+    }
+
+    #[test]
+    fn type_names() {
+        assert_eq!(resolve("
+            type A = i32;
+            let A: A = 1;
+            fn id<T>(x: T) -> T { let y: [T?] = [x]; x }
+            let f: fn(Later) -> A = |x| x;
+            type Later = str;
+        "), Ok(()));
+        assert_eq!(resolve("let x: Missing = 1; fn f<T>() {} let y: (i32, T) = (1, 2);"), Err(vec![
+            "cannot find type `Missing` in this scope".to_string(),
+            "cannot find type `T` in this scope".to_string(),
+        ]));
+        assert_eq!(resolve("type A = i32;\ntype A = str;"),
+                   Err(vec!["`A` is defined more than once, first at 1:1".to_string()]));
+    }
+
+    #[test]
+    fn structs_and_specs() {
+        assert_eq!(resolve("
+            struct Pair<T> { first: T, second: Option<T> }
+            spec Option<T> { Some(T), None }
+            let p = Pair { first: 1, second: Option::Some(2) };
+            let n = match p.second { Option::Some(x) => x, other => p.first };
+        "), Ok(()));
+        assert_eq!(resolve("struct S<T> { a: T } let b: T = Missing { a: 1 }; match b { x => y }"),
+                   Err(vec![
+            "cannot find type `T` in this scope".to_string(),
+            "cannot find type `Missing` in this scope".to_string(),
+            "cannot find `y` in this scope".to_string(),
+        ]));
+    }
+
+    #[test]
+    fn duplicate_definitions() {
+        assert_eq!(resolve("fn F() {}\nconst F: i32 = 1;"),
+                   Err(vec!["`F` is defined more than once, first at 1:1".to_string()]));
+        assert_eq!(resolve("fn f(a: i32, (b, a): (i32, i32)) {}"),
+                   Err(vec!["`a` is defined more than once, first at 1:6".to_string()]));
+        assert_eq!(resolve("let (x, x) = (1, 2);"),
+                   Err(vec!["`x` is defined more than once, first at 1:6".to_string()]));
+        assert_eq!(resolve("let g = 1;\nfn g() {}"),
+                   Err(vec!["`g` is defined more than once, first at 1:5".to_string()]));
+        assert_eq!(resolve("use lib::area;\nuse lib::area;"),
+                   Err(vec!["`area` is defined more than once, first at 1:1".to_string()]));
+        // Shadowing, and declarations in different scopes.
+        assert_eq!(resolve("let a = 1; let a = a + 1; fn f(a: i32) { let a = a; }"), Ok(()));
+    }
+
+    #[test]
+    fn use_before_declaration() {
+        assert_eq!(resolve("print a; let a = 1;"),
+                   Err(vec!["`a` is used before its declaration".to_string()]));
+        assert_eq!(resolve("let b = b;"),
+                   Err(vec!["`b` is used before its declaration".to_string()]));
+        assert_eq!(resolve("let c = 1; if c > 0 { print c; let c = 2; }"), Ok(()));
+        assert_eq!(resolve("fn f() -> i32 { d } let g = || d; let d = 1;"), Ok(()));
+    }
+
+    #[test]
+    fn errors_point_at_the_use() {
+        let arena = Arena::new();
+        let module = parse_module(&arena, "let a = 1;\nlet b = a + c;").unwrap();
+        let errors = Resolver::new("test.ag").resolve_module(&module).unwrap_err();
+        assert_eq!(errors[0].span, Span(Position(2, 13), Position(2, 14)));
+    }
+}
@@ -0,0 +1,173 @@
+//! Storage of the nodes of the syntax tree.
+//!
+//! The parser allocates every node in an `Arena` rather than in a box of its
+//! own, and a node refers to its children, and to the lists of its children,
+//! by references into the arena.  The nodes live as long as the arena, so a
+//! tree is `Copy` to hand around and is freed all at once.  The arena holds
+//! the nodes of every module of a program, which may then refer to each
+//! other's nodes: a function value of one module runs in another.
+
+use std::cell::RefCell;
+use std::cmp;
+use std::fmt;
+use std::slice;
+
+use super::expr::{Arm, Closure, Expr, Fragment};
+use super::item::{FieldDecl, Param, TypeParam, Variant};
+use super::pat::Pattern;
+use super::stmt::Stmt;
+use super::ty::Type;
+
+/// Number of values the first chunk of an arena has room for.
+const FIRST_CHUNK: usize = 64;
+
+/// Storage for values of a single type, handing out references that live as
+/// long as the arena.  Values are stored in chunks that never grow, so that
+/// they do not move once allocated.
+pub struct TypedArena<T> {
+  chunks: RefCell<Vec<Vec<T>>>,
+}
+
+impl<T> Default for TypedArena<T> {
+  fn default() -> TypedArena<T> {
+    TypedArena { chunks: RefCell::new(Vec::new()) }
+  }
+}
+
+impl<T> TypedArena<T> {
+  pub fn alloc(&self, value: T) -> &T {
+    &self.alloc_vec(vec![value])[0]
+  }
+
+  /// Moves `values` into the arena, next to each other.
+  pub fn alloc_vec(&self, values: Vec<T>) -> &[T] {
+    if values.is_empty() {
+      return &[];
+    }
+    let mut chunks = self.chunks.borrow_mut();
+    let full = chunks.last().is_none_or(|chunk| chunk.capacity() - chunk.len() < values.len());
+    if full {
+      let last = chunks.last().map_or(FIRST_CHUNK / 2, |chunk| chunk.capacity());
+      chunks.push(Vec::with_capacity(cmp::max(last * 2, values.len())));
+    }
+    let chunk = chunks.last_mut().unwrap();
+    let start = chunk.len();
+    chunk.extend(values);
+    // The chunk has room for the values, so extending it did not move the
+    // values allocated before, and nothing moves or drops the values until
+    // the arena is dropped.
+    unsafe { slice::from_raw_parts(chunk.as_ptr().add(start), chunk.len() - start) }
+  }
+
+  /// The number of values allocated.
+  pub fn len(&self) -> usize {
+    self.chunks.borrow().iter().map(Vec::len).sum()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.len() == 0
+  }
+}
+
+/// Node types allocated in an arena.
+pub trait Alloc<'ast>: Sized {
+  fn arena(arena: &'ast Arena<'ast>) -> &'ast TypedArena<Self>;
+}
+
+// Declares the `Arena` with a typed arena per node type, and the `Alloc`
+// implementations picking the typed arena of each type.
+macro_rules! declare_arena {(
+  $( $field: ident: $ty: ty, )*
+) => {
+  /// The nodes of the syntax trees parsed into it, by type.
+  #[derive(Default)]
+  pub struct Arena<'ast> {
+    $( $field: TypedArena<$ty>, )*
+  }
+
+  $(
+    impl<'ast> Alloc<'ast> for $ty {
+      fn arena(arena: &'ast Arena<'ast>) -> &'ast TypedArena<$ty> {
+        &arena.$field
+      }
+    }
+  )*
+
+  impl<'ast> Arena<'ast> {
+    /// The number of nodes allocated, of every type.
+    pub fn len(&self) -> usize {
+      0 $( + self.$field.len() )*
+    }
+  }
+}}
+
+declare_arena! {
+  exprs:       Expr<'ast>,
+  stmts:       Stmt<'ast>,
+  patterns:    Pattern<'ast>,
+  types:       Type<'ast>,
+  type_params: TypeParam,
+  params:      Param<'ast>,
+  fields:      FieldDecl<'ast>,
+  variants:    Variant<'ast>,
+  closures:    Closure<'ast>,
+  arms:        Arm<'ast>,
+  fragments:   Fragment<'ast>,
+  entries:     (Expr<'ast>, Expr<'ast>),
+  inits:       (String, Expr<'ast>),
+}
+
+impl<'ast> Arena<'ast> {
+  pub fn new() -> Arena<'ast> {
+    Arena::default()
+  }
+
+  pub fn alloc<T: Alloc<'ast>>(&'ast self, value: T) -> &'ast T {
+    T::arena(self).alloc(value)
+  }
+
+  /// Moves the nodes of a list into the arena.
+  pub fn alloc_vec<T: Alloc<'ast>>(&'ast self, values: Vec<T>) -> &'ast [T] {
+    T::arena(self).alloc_vec(values)
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.len() == 0
+  }
+}
+
+impl<'ast> fmt::Debug for Arena<'ast> {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "Arena({} nodes)", self.len())
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use syntax::ast::{ExprKind, Literal};
+
+  #[test]
+  fn values_do_not_move() {
+    let arena = TypedArena::default();
+    let first = arena.alloc(String::from("first"));
+    let many: Vec<&[String]> = (0..100).map(|n| arena.alloc_vec(vec![n.to_string(); n])).collect();
+    assert_eq!(first, "first");
+    for (n, values) in many.iter().enumerate() {
+      assert_eq!(values.len(), n);
+      assert!(values.iter().all(|value| *value == n.to_string()));
+    }
+    assert_eq!(arena.len(), 1 + 99 * 100 / 2);
+  }
+
+  #[test]
+  fn nodes_by_type() {
+    let arena = Arena::new();
+    let one = arena.alloc(Expr::from(ExprKind::Literal(Literal::Int(1))));
+    let pair = arena.alloc_vec(vec![one.clone(), one.clone()]);
+    let tuple = arena.alloc(Expr::from(ExprKind::Tuple(pair)));
+    assert_eq!(tuple.kind, ExprKind::Tuple(&[one.clone(), one.clone()]));
+    assert!(arena.alloc_vec::<Stmt>(vec![]).is_empty());
+    assert_eq!(arena.len(), 4);
+  }
+}
@@ -0,0 +1,195 @@
+use std::fmt;
+
+use utils::{FileId, Path, Span};
+
+use super::{AssignmentOp, NodeId};
+use super::binop::{ArithmeticOp, BitWiseOp, ComparisonOp, LogicalOp};
+use super::item::Param;
+use super::pat::Pattern;
+use super::stmt::Block;
+use super::ty::{PrimType, Type};
+use super::unop::UnaryOp;
+
+/// Expression node with its location in the source.
+#[derive(Clone, Debug)]
+pub struct Expr<'ast> {
+  pub id:   NodeId,
+  pub kind: ExprKind<'ast>,
+  pub span: Span,
+  pub file: FileId,
+}
+
+impl<'ast> Expr<'ast> {
+  pub fn new(id: NodeId, kind: ExprKind<'ast>, span: Span, file: FileId) -> Expr<'ast> {
+    Expr { id, kind, span, file }
+  }
+}
+
+/// Nodes built by hand, rather than parsed, have no location.
+impl<'ast> From<ExprKind<'ast>> for Expr<'ast> {
+  fn from(kind: ExprKind<'ast>) -> Expr<'ast> {
+    Expr::new(NodeId::DUMMY, kind, Span::default(), FileId::default())
+  }
+}
+
+/// Nodes are equal when their trees are, wherever they appear in the source.
+impl<'ast> PartialEq for Expr<'ast> {
+  fn eq(&self, other: &Expr<'ast>) -> bool {
+    self.kind == other.kind
+  }
+}
+
+/// Expressions built from the grammar's `expr` and `term` rules.  Children
+/// and lists of children are allocated in the arena of the tree.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ExprKind<'ast> {
+  /// Literal value:  true  'c'  1  2.5  "str"
+  Literal(Literal),
+  /// String with embedded expressions:  "hello ${name}, ${n + 1} items"
+  Interpolated(&'ast [Fragment<'ast>]),
+  /// Variable or function name.
+  Identifier(String),
+  /// Qualified name:  ::std::io::print  Shape::Circle
+  Path(Path),
+  /// Prefix operation:  -a  !a
+  Unary(UnaryOp, &'ast Expr<'ast>),
+  /// Infix operation:  a + b
+  Binary(BinaryOp, &'ast Expr<'ast>, &'ast Expr<'ast>),
+  /// Conversion to a primitive type:  a -> u8
+  Cast(&'ast Expr<'ast>, PrimType),
+  /// Call of a callee with its arguments:  f(a, b)
+  Call(&'ast Expr<'ast>, &'ast [Expr<'ast>]),
+  /// Method call on a receiver:  a.f(b)
+  MethodCall(&'ast Expr<'ast>, String, &'ast [Expr<'ast>]),
+  /// Method call skipped when the receiver is nil:  a?.f(b)
+  SafeMethodCall(&'ast Expr<'ast>, String, &'ast [Expr<'ast>]),
+  /// Assignment to a place:  a = b
+  Assign(AssignmentOp, &'ast Expr<'ast>, &'ast Expr<'ast>),
+  /// Range of integers:  a..b  a..=b
+  Range(&'ast Expr<'ast>, &'ast Expr<'ast>, RangeLimits),
+  /// Array of values:  [a, b]
+  Array(&'ast [Expr<'ast>]),
+  /// Map from keys to values:  [:]  ["a": 1, "b": 2]
+  Map(&'ast [(Expr<'ast>, Expr<'ast>)]),
+  /// Array element or map value:  a[i]
+  Index(&'ast Expr<'ast>, &'ast Expr<'ast>),
+  /// Tuple of values:  ()  (a,)  (a, b)
+  Tuple(&'ast [Expr<'ast>]),
+  /// Positional tuple field:  a.0
+  Field(&'ast Expr<'ast>, usize),
+  /// Named struct field:  p.first
+  Member(&'ast Expr<'ast>, String),
+  /// Struct literal giving every field:  Pair { first: 1, second: 2 }
+  Struct(String, &'ast [(String, Expr<'ast>)]),
+  /// Anonymous function:  fn(x: i32) -> i32 { x + 1 }  |x| x + 1
+  Closure(&'ast Closure<'ast>),
+  /// Conditional expression:  cond ? a : b
+  Ternary(&'ast Expr<'ast>, &'ast Expr<'ast>, &'ast Expr<'ast>),
+  /// If expression:  if cond { a } else { b }
+  /// The else branch is either a `Block` or a nested `If`.
+  If(&'ast Expr<'ast>, Block<'ast>, Option<&'ast Expr<'ast>>),
+  /// Infinite loop, evaluating to the value it breaks with:  'outer: loop { break x; }
+  Loop(Option<String>, Block<'ast>),
+  /// Value of the first arm matching the variant of a spec:
+  /// match s { Shape::Circle(r) => r * r, _ => 0.0 }
+  Match(&'ast Expr<'ast>, &'ast [Arm<'ast>]),
+  /// Braced block evaluating to its trailing expression.
+  Block(Block<'ast>),
+}
+
+/// Arm of a `match`, its body is evaluated when its pattern matches.
+#[derive(Clone, Debug)]
+pub struct Arm<'ast> {
+  pub pat:  Pattern<'ast>,
+  pub body: Expr<'ast>,
+  pub span: Span,
+}
+
+impl<'ast> PartialEq for Arm<'ast> {
+  fn eq(&self, other: &Arm<'ast>) -> bool {
+    self.pat == other.pat && self.body == other.body
+  }
+}
+
+/// Piece of an interpolated string, with its location in the source.
+#[derive(Clone, Debug)]
+pub enum Fragment<'ast> {
+  /// Text between the embedded expressions, with its escapes decoded.
+  Str(String, Span),
+  /// Embedded expression, the span covers `${` to `}`.
+  Expr(Expr<'ast>, Span),
+}
+
+impl<'ast> PartialEq for Fragment<'ast> {
+  fn eq(&self, other: &Fragment<'ast>) -> bool {
+    match (self, other) {
+      (Fragment::Str(a, _), Fragment::Str(b, _))   => a == b,
+      (Fragment::Expr(a, _), Fragment::Expr(b, _)) => a == b,
+      _                                            => false,
+    }
+  }
+}
+
+/// Whether a range includes its upper bound.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum RangeLimits {
+  /// a..b
+  HalfOpen,
+  /// a..=b
+  Closed,
+}
+
+impl fmt::Display for RangeLimits {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match *self {
+      RangeLimits::HalfOpen => write!(f, ".."),
+      RangeLimits::Closed   => write!(f, "..="),
+    }
+  }
+}
+
+/// Anonymous function capturing the variables of its enclosing scopes.
+#[derive(Clone, Debug)]
+pub struct Closure<'ast> {
+  pub params: &'ast [Param<'ast>],
+  pub ret:    Option<Type<'ast>>,
+  pub body:   Block<'ast>,
+  pub span:   Span,
+}
+
+impl<'ast> PartialEq for Closure<'ast> {
+  fn eq(&self, other: &Closure<'ast>) -> bool {
+    self.params == other.params && self.ret == other.ret && self.body == other.body
+  }
+}
+
+/// Literal values.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Literal {
+  Nil,
+  Boolean(bool),
+  Char(char),
+  Int(i128),
+  Float(f64),
+  Str(String),
+}
+
+/// Binary operators, grouped by the operator families in `binop.rs`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum BinaryOp {
+  Arithmetic(ArithmeticOp),
+  BitWise(BitWiseOp),
+  Comparison(ComparisonOp),
+  Logical(LogicalOp),
+}
+
+impl fmt::Display for BinaryOp {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match *self {
+      BinaryOp::Arithmetic(ref op) => op.fmt(f),
+      BinaryOp::BitWise(ref op)    => op.fmt(f),
+      BinaryOp::Comparison(ref op) => op.fmt(f),
+      BinaryOp::Logical(ref op)    => op.fmt(f),
+    }
+  }
+}
@@ -0,0 +1,520 @@
+//! Rewriting traversal of the syntax tree.
+//!
+//! A `Folder` takes each node by value and returns its replacement.  Every
+//! method defaults to the matching `noop_fold_*` function, which rebuilds
+//! the node from its folded children and keeps its id and location, so a
+//! folder that overrides nothing returns the tree unchanged.  The children
+//! of a node are in the arena, so they are folded from copies of them, and
+//! the folded children are allocated in the arena of the folder; the tree
+//! that was folded is left as it was.
+
+use utils::Path;
+
+use super::AssignmentOp;
+use super::arena::{Alloc, Arena};
+use super::binop::{ArithmeticOp, BitWiseOp, ComparisonOp, LogicalOp};
+use super::expr::{Arm, BinaryOp, Closure, Expr, ExprKind, Fragment, Literal};
+use super::item::{ConstDecl, FieldDecl, FnDecl, Item, ItemKind, Module, Param, SpecDecl,
+                  StructDecl, TypeAlias, TypeParam, UseDecl, Variant};
+use super::pat::{Pattern, PatternKind};
+use super::stmt::{Block, ForLoop, Local, Print, Stmt, StmtKind, WhileLoop};
+use super::ty::{PrimType, Type, TypeKind};
+use super::unop::UnaryOp;
+
+pub trait Folder<'ast>: Sized {
+  /// The arena the folded nodes are allocated in.
+  fn arena(&self) -> &'ast Arena<'ast>;
+
+  fn fold_module(&mut self, module: Module<'ast>) -> Module<'ast> {
+    noop_fold_module(module, self)
+  }
+
+  fn fold_item(&mut self, item: Item<'ast>) -> Item<'ast> {
+    noop_fold_item(item, self)
+  }
+
+  fn fold_fn_decl(&mut self, decl: FnDecl<'ast>) -> FnDecl<'ast> {
+    noop_fold_fn_decl(decl, self)
+  }
+
+  fn fold_type_param(&mut self, param: TypeParam) -> TypeParam {
+    param
+  }
+
+  fn fold_param(&mut self, param: Param<'ast>) -> Param<'ast> {
+    noop_fold_param(param, self)
+  }
+
+  fn fold_stmt(&mut self, stmt: Stmt<'ast>) -> Stmt<'ast> {
+    noop_fold_stmt(stmt, self)
+  }
+
+  fn fold_local(&mut self, local: Local<'ast>) -> Local<'ast> {
+    noop_fold_local(local, self)
+  }
+
+  fn fold_for_loop(&mut self, for_loop: ForLoop<'ast>) -> ForLoop<'ast> {
+    noop_fold_for_loop(for_loop, self)
+  }
+
+  fn fold_while_loop(&mut self, while_loop: WhileLoop<'ast>) -> WhileLoop<'ast> {
+    noop_fold_while_loop(while_loop, self)
+  }
+
+  fn fold_print(&mut self, print: Print<'ast>) -> Print<'ast> {
+    noop_fold_print(print, self)
+  }
+
+  fn fold_block(&mut self, block: Block<'ast>) -> Block<'ast> {
+    noop_fold_block(block, self)
+  }
+
+  fn fold_expr(&mut self, expr: Expr<'ast>) -> Expr<'ast> {
+    noop_fold_expr(expr, self)
+  }
+
+  fn fold_fragment(&mut self, fragment: Fragment<'ast>) -> Fragment<'ast> {
+    noop_fold_fragment(fragment, self)
+  }
+
+  fn fold_closure(&mut self, closure: Closure<'ast>) -> Closure<'ast> {
+    noop_fold_closure(closure, self)
+  }
+
+  fn fold_arm(&mut self, arm: Arm<'ast>) -> Arm<'ast> {
+    noop_fold_arm(arm, self)
+  }
+
+  fn fold_literal(&mut self, lit: Literal) -> Literal {
+    lit
+  }
+
+  fn fold_path(&mut self, path: Path) -> Path {
+    path
+  }
+
+  fn fold_pattern(&mut self, pat: Pattern<'ast>) -> Pattern<'ast> {
+    noop_fold_pattern(pat, self)
+  }
+
+  fn fold_type(&mut self, ty: Type<'ast>) -> Type<'ast> {
+    noop_fold_type(ty, self)
+  }
+
+  fn fold_prim_type(&mut self, prim: PrimType) -> PrimType {
+    prim
+  }
+
+  fn fold_unary_op(&mut self, op: UnaryOp) -> UnaryOp {
+    op
+  }
+
+  fn fold_binary_op(&mut self, op: BinaryOp) -> BinaryOp {
+    noop_fold_binary_op(op, self)
+  }
+
+  fn fold_arithmetic_op(&mut self, op: ArithmeticOp) -> ArithmeticOp {
+    op
+  }
+
+  fn fold_bitwise_op(&mut self, op: BitWiseOp) -> BitWiseOp {
+    op
+  }
+
+  fn fold_comparison_op(&mut self, op: ComparisonOp) -> ComparisonOp {
+    op
+  }
+
+  fn fold_logical_op(&mut self, op: LogicalOp) -> LogicalOp {
+    op
+  }
+
+  fn fold_assignment_op(&mut self, op: AssignmentOp) -> AssignmentOp {
+    op
+  }
+}
+
+pub fn noop_fold_module<'ast, F>(module: Module<'ast>, folder: &mut F) -> Module<'ast>
+  where F: Folder<'ast>
+{
+  Module { stmts: fold_list(module.stmts, folder, F::fold_stmt) }
+}
+
+pub fn noop_fold_item<'ast, F: Folder<'ast>>(item: Item<'ast>, folder: &mut F) -> Item<'ast> {
+  let kind = match item.kind {
+    ItemKind::Use(decl) => ItemKind::Use(UseDecl {
+      path: folder.fold_path(decl.path),
+      span: decl.span,
+    }),
+    ItemKind::Const(decl) => ItemKind::Const(ConstDecl {
+      name:  decl.name,
+      ty:    folder.fold_type(decl.ty),
+      value: folder.fold_expr(decl.value),
+    }),
+    ItemKind::Type(alias) => ItemKind::Type(TypeAlias {
+      name: alias.name,
+      ty:   folder.fold_type(alias.ty),
+    }),
+    ItemKind::Struct(decl) => ItemKind::Struct(StructDecl {
+      name:     decl.name,
+      generics: fold_list(decl.generics, folder, F::fold_type_param),
+      fields:   fold_list(decl.fields, folder, |folder, field| FieldDecl {
+        name: field.name,
+        ty:   folder.fold_type(field.ty),
+        span: field.span,
+      }),
+    }),
+    ItemKind::Spec(decl) => ItemKind::Spec(SpecDecl {
+      name:     decl.name,
+      generics: fold_list(decl.generics, folder, F::fold_type_param),
+      variants: fold_list(decl.variants, folder, |folder, variant| Variant {
+        name:   variant.name,
+        fields: fold_list(variant.fields, folder, F::fold_type),
+        span:   variant.span,
+      }),
+    }),
+    ItemKind::Fn(decl) => ItemKind::Fn(folder.fold_fn_decl(decl)),
+  };
+  Item { id: item.id, vis: item.vis, kind, span: item.span, file: item.file }
+}
+
+pub fn noop_fold_fn_decl<'ast, F>(decl: FnDecl<'ast>, folder: &mut F) -> FnDecl<'ast>
+  where F: Folder<'ast>
+{
+  FnDecl {
+    name:     decl.name,
+    generics: fold_list(decl.generics, folder, F::fold_type_param),
+    params:   fold_list(decl.params, folder, F::fold_param),
+    ret:      decl.ret.map(|ret| folder.fold_type(ret)),
+    body:     folder.fold_block(decl.body),
+  }
+}
+
+pub fn noop_fold_param<'ast, F: Folder<'ast>>(param: Param<'ast>, folder: &mut F) -> Param<'ast> {
+  Param {
+    pat:  folder.fold_pattern(param.pat),
+    ty:   param.ty.map(|ty| folder.fold_type(ty)),
+    span: param.span,
+  }
+}
+
+pub fn noop_fold_stmt<'ast, F: Folder<'ast>>(stmt: Stmt<'ast>, folder: &mut F) -> Stmt<'ast> {
+  let kind = match stmt.kind {
+    StmtKind::Item(item)             => StmtKind::Item(folder.fold_item(item)),
+    StmtKind::Let(local)             => StmtKind::Let(folder.fold_local(local)),
+    StmtKind::Expr(expr)             => StmtKind::Expr(folder.fold_expr(expr)),
+    StmtKind::Return(value)          => {
+      StmtKind::Return(value.map(|value| folder.fold_expr(value)))
+    },
+    StmtKind::For(for_loop)          => StmtKind::For(folder.fold_for_loop(for_loop)),
+    StmtKind::While(while_loop)      => StmtKind::While(folder.fold_while_loop(while_loop)),
+    StmtKind::Break(label, value)    => {
+      StmtKind::Break(label, value.map(|value| folder.fold_expr(value)))
+    },
+    StmtKind::Continue(label)        => StmtKind::Continue(label),
+    StmtKind::Print(print)           => StmtKind::Print(folder.fold_print(print)),
+  };
+  Stmt::new(stmt.id, kind, stmt.span, stmt.file)
+}
+
+/// Folds the initializer before the pattern, which is only in scope after
+/// the declaration.
+pub fn noop_fold_local<'ast, F: Folder<'ast>>(local: Local<'ast>, folder: &mut F) -> Local<'ast> {
+  let init = local.init.map(|init| folder.fold_expr(init));
+  let ty = local.ty.map(|ty| folder.fold_type(ty));
+  Local { pat: folder.fold_pattern(local.pat), ty, init }
+}
+
+pub fn noop_fold_for_loop<'ast, F>(for_loop: ForLoop<'ast>, folder: &mut F) -> ForLoop<'ast>
+  where F: Folder<'ast>
+{
+  let iter = folder.fold_expr(for_loop.iter);
+  ForLoop {
+    label: for_loop.label,
+    pat:   folder.fold_pattern(for_loop.pat),
+    iter,
+    body:  folder.fold_block(for_loop.body),
+  }
+}
+
+pub fn noop_fold_while_loop<'ast, F>(while_loop: WhileLoop<'ast>, folder: &mut F) -> WhileLoop<'ast>
+  where F: Folder<'ast>
+{
+  WhileLoop {
+    label: while_loop.label,
+    cond:  folder.fold_expr(while_loop.cond),
+    body:  folder.fold_block(while_loop.body),
+  }
+}
+
+pub fn noop_fold_print<'ast, F: Folder<'ast>>(print: Print<'ast>, folder: &mut F) -> Print<'ast> {
+  Print {
+    newline: print.newline,
+    format:  print.format,
+    args:    fold_list(print.args, folder, F::fold_expr),
+  }
+}
+
+pub fn noop_fold_block<'ast, F: Folder<'ast>>(block: Block<'ast>, folder: &mut F) -> Block<'ast> {
+  Block {
+    stmts: fold_list(block.stmts, folder, F::fold_stmt),
+    expr:  block.expr.map(|expr| fold_node(expr, folder, F::fold_expr)),
+    span:  block.span,
+  }
+}
+
+pub fn noop_fold_expr<'ast, F: Folder<'ast>>(expr: Expr<'ast>, folder: &mut F) -> Expr<'ast> {
+  let kind = match expr.kind {
+    ExprKind::Literal(lit) => ExprKind::Literal(folder.fold_literal(lit)),
+    ExprKind::Interpolated(fragments) => {
+      ExprKind::Interpolated(fold_list(fragments, folder, F::fold_fragment))
+    },
+    ExprKind::Identifier(name) => ExprKind::Identifier(name),
+    ExprKind::Path(path) => ExprKind::Path(folder.fold_path(path)),
+    ExprKind::Unary(op, operand) => {
+      let op = folder.fold_unary_op(op);
+      ExprKind::Unary(op, fold_expr(operand, folder))
+    },
+    ExprKind::Binary(op, lhs, rhs) => {
+      let lhs = fold_expr(lhs, folder);
+      let op = folder.fold_binary_op(op);
+      ExprKind::Binary(op, lhs, fold_expr(rhs, folder))
+    },
+    ExprKind::Cast(operand, target) => {
+      let operand = fold_expr(operand, folder);
+      ExprKind::Cast(operand, folder.fold_prim_type(target))
+    },
+    ExprKind::Call(callee, args) => {
+      let callee = fold_expr(callee, folder);
+      ExprKind::Call(callee, fold_list(args, folder, F::fold_expr))
+    },
+    ExprKind::MethodCall(receiver, name, args) => {
+      let receiver = fold_expr(receiver, folder);
+      ExprKind::MethodCall(receiver, name, fold_list(args, folder, F::fold_expr))
+    },
+    ExprKind::SafeMethodCall(receiver, name, args) => {
+      let receiver = fold_expr(receiver, folder);
+      ExprKind::SafeMethodCall(receiver, name, fold_list(args, folder, F::fold_expr))
+    },
+    ExprKind::Assign(op, target, value) => {
+      let target = fold_expr(target, folder);
+      let op = folder.fold_assignment_op(op);
+      ExprKind::Assign(op, target, fold_expr(value, folder))
+    },
+    ExprKind::Range(start, end, limits) => {
+      let start = fold_expr(start, folder);
+      ExprKind::Range(start, fold_expr(end, folder), limits)
+    },
+    ExprKind::Array(elems) => ExprKind::Array(fold_list(elems, folder, F::fold_expr)),
+    ExprKind::Map(entries) => ExprKind::Map(fold_list(entries, folder, |folder, (key, value)| {
+      let key = folder.fold_expr(key);
+      (key, folder.fold_expr(value))
+    })),
+    ExprKind::Index(indexed, index) => {
+      let indexed = fold_expr(indexed, folder);
+      ExprKind::Index(indexed, fold_expr(index, folder))
+    },
+    ExprKind::Tuple(elems) => ExprKind::Tuple(fold_list(elems, folder, F::fold_expr)),
+    ExprKind::Field(tuple, index) => ExprKind::Field(fold_expr(tuple, folder), index),
+    ExprKind::Member(object, name) => ExprKind::Member(fold_expr(object, folder), name),
+    ExprKind::Struct(name, fields) => {
+      let fields = fold_list(fields, folder, |folder, (field, value)| {
+        (field, folder.fold_expr(value))
+      });
+      ExprKind::Struct(name, fields)
+    },
+    ExprKind::Closure(closure) => ExprKind::Closure(fold_node(closure, folder, F::fold_closure)),
+    ExprKind::Ternary(cond, then, otherwise) => {
+      let cond = fold_expr(cond, folder);
+      let then = fold_expr(then, folder);
+      ExprKind::Ternary(cond, then, fold_expr(otherwise, folder))
+    },
+    ExprKind::If(cond, then, otherwise) => {
+      let cond = fold_expr(cond, folder);
+      let then = folder.fold_block(then);
+      ExprKind::If(cond, then, otherwise.map(|otherwise| fold_expr(otherwise, folder)))
+    },
+    ExprKind::Match(scrutinee, arms) => {
+      let scrutinee = fold_expr(scrutinee, folder);
+      ExprKind::Match(scrutinee, fold_list(arms, folder, F::fold_arm))
+    },
+    ExprKind::Loop(label, body) => ExprKind::Loop(label, folder.fold_block(body)),
+    ExprKind::Block(block) => ExprKind::Block(folder.fold_block(block)),
+  };
+  Expr::new(expr.id, kind, expr.span, expr.file)
+}
+
+pub fn noop_fold_fragment<'ast, F>(fragment: Fragment<'ast>, folder: &mut F) -> Fragment<'ast>
+  where F: Folder<'ast>
+{
+  match fragment {
+    Fragment::Str(text, span)  => Fragment::Str(text, span),
+    Fragment::Expr(expr, span) => Fragment::Expr(folder.fold_expr(expr), span),
+  }
+}
+
+pub fn noop_fold_closure<'ast, F>(closure: Closure<'ast>, folder: &mut F) -> Closure<'ast>
+  where F: Folder<'ast>
+{
+  Closure {
+    params: fold_list(closure.params, folder, F::fold_param),
+    ret:    closure.ret.map(|ret| folder.fold_type(ret)),
+    body:   folder.fold_block(closure.body),
+    span:   closure.span,
+  }
+}
+
+/// Folds the pattern before the body, in which its bindings are in scope.
+pub fn noop_fold_arm<'ast, F: Folder<'ast>>(arm: Arm<'ast>, folder: &mut F) -> Arm<'ast> {
+  let pat = folder.fold_pattern(arm.pat);
+  Arm { pat, body: folder.fold_expr(arm.body), span: arm.span }
+}
+
+pub fn noop_fold_pattern<'ast, F>(pat: Pattern<'ast>, folder: &mut F) -> Pattern<'ast>
+  where F: Folder<'ast>
+{
+  let kind = match pat.kind {
+    PatternKind::Ident(name) => PatternKind::Ident(name),
+    PatternKind::Tuple(pats) => PatternKind::Tuple(fold_list(pats, folder, F::fold_pattern)),
+    PatternKind::Variant(path, pats) => {
+      let path = folder.fold_path(path);
+      PatternKind::Variant(path, fold_list(pats, folder, F::fold_pattern))
+    },
+  };
+  Pattern::new(pat.id, kind, pat.span, pat.file)
+}
+
+pub fn noop_fold_type<'ast, F: Folder<'ast>>(ty: Type<'ast>, folder: &mut F) -> Type<'ast> {
+  let kind = match ty.kind {
+    TypeKind::Prim(prim) => TypeKind::Prim(folder.fold_prim_type(prim)),
+    TypeKind::Fn(params, ret) => {
+      let params = fold_list(params, folder, F::fold_type);
+      TypeKind::Fn(params, fold_node(ret, folder, F::fold_type))
+    },
+    TypeKind::Array(elem) => TypeKind::Array(fold_node(elem, folder, F::fold_type)),
+    TypeKind::Map(key, value) => {
+      let key = fold_node(key, folder, F::fold_type);
+      TypeKind::Map(key, fold_node(value, folder, F::fold_type))
+    },
+    TypeKind::Tuple(elems) => TypeKind::Tuple(fold_list(elems, folder, F::fold_type)),
+    TypeKind::Optional(ty) => TypeKind::Optional(fold_node(ty, folder, F::fold_type)),
+    TypeKind::Named(name, args) => TypeKind::Named(name, fold_list(args, folder, F::fold_type)),
+  };
+  Type::new(ty.id, kind, ty.span, ty.file)
+}
+
+pub fn noop_fold_binary_op<'ast, F: Folder<'ast>>(op: BinaryOp, folder: &mut F) -> BinaryOp {
+  match op {
+    BinaryOp::Arithmetic(op) => BinaryOp::Arithmetic(folder.fold_arithmetic_op(op)),
+    BinaryOp::BitWise(op)    => BinaryOp::BitWise(folder.fold_bitwise_op(op)),
+    BinaryOp::Comparison(op) => BinaryOp::Comparison(folder.fold_comparison_op(op)),
+    BinaryOp::Logical(op)    => BinaryOp::Logical(folder.fold_logical_op(op)),
+  }
+}
+
+/// Folds a copy of a node of the tree into a node of the arena of the folder.
+fn fold_node<'ast, T, F, G>(node: &'ast T, folder: &mut F, mut fold: G) -> &'ast T
+  where T: Alloc<'ast> + Clone, F: Folder<'ast>, G: FnMut(&mut F, T) -> T
+{
+  let node = fold(folder, node.clone());
+  folder.arena().alloc(node)
+}
+
+/// Folds copies of a list of nodes into a list in the arena of the folder.
+fn fold_list<'ast, T, F, G>(nodes: &'ast [T], folder: &mut F, mut fold: G) -> &'ast [T]
+  where T: Alloc<'ast> + Clone, F: Folder<'ast>, G: FnMut(&mut F, T) -> T
+{
+  let nodes = nodes.iter().cloned().map(|node| fold(folder, node)).collect();
+  folder.arena().alloc_vec(nodes)
+}
+
+fn fold_expr<'ast, F: Folder<'ast>>(expr: &'ast Expr<'ast>, folder: &mut F) -> &'ast Expr<'ast> {
+  fold_node(expr, folder, F::fold_expr)
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use syntax::parser::parse_module;
+
+  /// Source using every kind of node.
+  const EVERYTHING: &str = r#"
+    use shapes::area;
+    pub const MAX: u8 = 1 << 4 & 0xff;
+    type Pair = (i32, [str: f64?]);
+    struct Cell<T> { value: T, next: Option<Cell<T>>, }
+    spec Option<T: Eq> { Some(T), None }
+    fn pick<T: Ord, U>(a: T, (b, c): (U, [bool]), f: fn(T) -> U) -> T {
+      let g = |x| x + 1;
+      let h: fn() = fn() { };
+      'outer: for i in 0..=10 {
+        while i >= 2 && !done || false { continue 'outer; }
+        println "{:4.1} {}", 2.5, 'c';
+        print i;
+      }
+      let v = ["k": (1,), "j": ()][r"k"].0;
+      a = if b != nil { loop { break 3; } } else if c { -1 -> i64 } else { 2 };
+      s?.len(b).trim();
+      ::std::io::print("${v} and ${a + 1}\t");
+      let n = Cell { value: a, next: Option::None }.value;
+      match Option::Some(n) { Option::Some((x, y)) => x, Option::None => { b }, other => a }
+      return c ? a : b;
+    }
+  "#;
+
+  struct Identity<'ast> {
+    arena: &'ast Arena<'ast>,
+  }
+
+  impl<'ast> Folder<'ast> for Identity<'ast> {
+    fn arena(&self) -> &'ast Arena<'ast> {
+      self.arena
+    }
+  }
+
+  #[test]
+  fn noop_fold_is_identity() {
+    let arena = Arena::new();
+    let module = parse_module(&arena, EVERYTHING).unwrap();
+    let folded = Identity { arena: &arena }.fold_module(module);
+    assert_eq!(folded, module);
+    // Equality ignores locations, the debug output shows them.
+    assert_eq!(format!("{:?}", folded), format!("{:?}", module));
+  }
+
+  /// Turns subtractions into additions and counts the integers it meets.
+  struct Rewrite<'ast> {
+    arena: &'ast Arena<'ast>,
+    ints:  usize,
+  }
+
+  impl<'ast> Folder<'ast> for Rewrite<'ast> {
+    fn arena(&self) -> &'ast Arena<'ast> {
+      self.arena
+    }
+
+    fn fold_arithmetic_op(&mut self, op: ArithmeticOp) -> ArithmeticOp {
+      match op {
+        ArithmeticOp::Sub => ArithmeticOp::Add,
+        op                => op,
+      }
+    }
+
+    fn fold_literal(&mut self, lit: Literal) -> Literal {
+      if let Literal::Int(_) = lit {
+        self.ints += 1;
+      }
+      lit
+    }
+  }
+
+  #[test]
+  fn rewriting() {
+    let arena = Arena::new();
+    let module = parse_module(&arena, "let a = 1 - (2 - x) * 3; a - 4;").unwrap();
+    let mut rewrite = Rewrite { arena: &arena, ints: 0 };
+    let folded = rewrite.fold_module(module);
+    assert_eq!(folded, parse_module(&arena, "let a = 1 + (2 + x) * 3; a + 4;").unwrap());
+    assert_eq!(rewrite.ints, 4);
+  }
+}
@@ -0,0 +1,170 @@
+use std::fmt;
+use std::mem;
+use std::str::FromStr;
+
+/// Format string of a `print` statement:  "x = {}, y = {:8.3}"
+/// Braces are written `{{` and `}}` outside of placeholders.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Format {
+  pub pieces: Vec<Piece>,
+}
+
+/// Literal text, or a placeholder taking the next argument.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Piece {
+  Str(String),
+  Arg(Spec),
+}
+
+/// Placeholder specifiers:  {:width.precision}
+/// Numbers are aligned right within the width and everything else left.
+/// The precision is the number of decimals of a float, or the maximum
+/// number of characters of a string.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Spec {
+  pub width:     Option<usize>,
+  pub precision: Option<usize>,
+}
+
+impl Format {
+  /// The number of arguments the placeholders take.
+  pub fn args(&self) -> usize {
+    self.pieces.iter().filter(|piece| match **piece {
+      Piece::Arg(_) => true,
+      Piece::Str(_) => false,
+    }).count()
+  }
+}
+
+/// Error returned when a format string is malformed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FormatError(pub String);
+
+impl fmt::Display for FormatError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "invalid format string: {}", self.0)
+  }
+}
+
+impl FromStr for Format {
+  type Err = FormatError;
+
+  fn from_str(source: &str) -> Result<Format, FormatError> {
+    let mut pieces = Vec::new();
+    let mut text = String::new();
+    let mut chars = source.chars().peekable();
+    while let Some(chr) = chars.next() {
+      match chr {
+        '{' if chars.peek() == Some(&'{') => {
+          chars.next();
+          text.push('{');
+        },
+        '}' if chars.peek() == Some(&'}') => {
+          chars.next();
+          text.push('}');
+        },
+        '{' => {
+          let mut spec = String::new();
+          loop {
+            match chars.next() {
+              Some('}') => break,
+              Some(chr) => spec.push(chr),
+              None      => return Err(FormatError("unclosed `{`".to_string())),
+            }
+          }
+          if !text.is_empty() {
+            pieces.push(Piece::Str(mem::take(&mut text)));
+          }
+          pieces.push(Piece::Arg(parse_spec(&spec)?));
+        },
+        '}' => return Err(FormatError("unmatched `}`, write `}}` for a brace".to_string())),
+        chr => text.push(chr),
+      }
+    }
+    if !text.is_empty() {
+      pieces.push(Piece::Str(text));
+    }
+    Ok(Format { pieces })
+  }
+}
+
+/// Parses the inside of a placeholder:  ``  `:8`  `:.2`  `:8.2`
+fn parse_spec(spec: &str) -> Result<Spec, FormatError> {
+  if spec.is_empty() {
+    return Ok(Spec::default());
+  }
+  let invalid = || FormatError(format!("unknown specifier `{{{}}}`", spec));
+  if !spec.starts_with(':') {
+    return Err(invalid());
+  }
+  let spec_body = &spec[1..];
+  let (width, precision) = match spec_body.find('.') {
+    Some(dot) => (&spec_body[..dot], Some(&spec_body[dot + 1..])),
+    None      => (spec_body, None),
+  };
+  let number = |digits: &str| digits.parse::<usize>().map_err(|_| invalid());
+  Ok(Spec {
+    width:     if width.is_empty() { None } else { Some(number(width)?) },
+    precision: match precision {
+      Some(digits) => Some(number(digits)?),
+      None         => None,
+    },
+  })
+}
+
+impl fmt::Display for Format {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    for piece in &self.pieces {
+      match *piece {
+        Piece::Str(ref text) => write!(f, "{}", text.replace("{", "{{").replace("}", "}}"))?,
+        Piece::Arg(ref spec) => spec.fmt(f)?,
+      }
+    }
+    Ok(())
+  }
+}
+
+impl fmt::Display for Spec {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{{")?;
+    if self.width.is_some() || self.precision.is_some() {
+      write!(f, ":")?;
+    }
+    if let Some(width) = self.width {
+      write!(f, "{}", width)?;
+    }
+    if let Some(precision) = self.precision {
+      write!(f, ".{}", precision)?;
+    }
+    write!(f, "}}")
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn parse_and_display() {
+    let format: Format = "x = {}, y = {:8.3} {{literal}} {:.2}{:4}".parse().unwrap();
+    assert_eq!(format.pieces, vec![
+      Piece::Str("x = ".to_string()),
+      Piece::Arg(Spec::default()),
+      Piece::Str(", y = ".to_string()),
+      Piece::Arg(Spec { width: Some(8), precision: Some(3) }),
+      Piece::Str(" {literal} ".to_string()),
+      Piece::Arg(Spec { width: None, precision: Some(2) }),
+      Piece::Arg(Spec { width: Some(4), precision: None }),
+    ]);
+    assert_eq!(format.args(), 4);
+    assert_eq!(format.to_string(), "x = {}, y = {:8.3} {{literal}} {:.2}{:4}");
+  }
+
+  #[test]
+  fn malformed() {
+    assert_eq!("{".parse::<Format>(), Err(FormatError("unclosed `{`".to_string())));
+    assert!("}".parse::<Format>().is_err());
+    assert_eq!("{x}".parse::<Format>(), Err(FormatError("unknown specifier `{x}`".to_string())));
+    assert!("{:8.}".parse::<Format>().is_err());
+  }
+}
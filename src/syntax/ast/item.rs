@@ -0,0 +1,204 @@
+use std::fmt;
+
+use utils::{FileId, Path, Span};
+
+use super::NodeId;
+use super::expr::Expr;
+use super::pat::Pattern;
+use super::stmt::{Block, Stmt, StmtKind};
+use super::ty::Type;
+
+/// A parsed source file.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Module<'ast> {
+  pub stmts: &'ast [Stmt<'ast>],
+}
+
+impl<'ast> Module<'ast> {
+  /// Iterates over the module level items, skipping other statements.
+  pub fn items(&self) -> Vec<&'ast Item<'ast>> {
+    self.stmts.iter().filter_map(|stmt| match stmt.kind {
+      StmtKind::Item(ref item) => Some(item),
+      _                        => None,
+    }).collect()
+  }
+}
+
+/// Module level declaration with its visibility and location.
+#[derive(Clone, Debug)]
+pub struct Item<'ast> {
+  pub id:   NodeId,
+  pub vis:  Visibility,
+  pub kind: ItemKind<'ast>,
+  pub span: Span,
+  pub file: FileId,
+}
+
+impl<'ast> PartialEq for Item<'ast> {
+  fn eq(&self, other: &Item<'ast>) -> bool {
+    self.vis == other.vis && self.kind == other.kind
+  }
+}
+
+impl<'ast> Item<'ast> {
+  /// The name an item binds in its module.  For `use a::b::c;` this is `c`.
+  pub fn name(&self) -> &str {
+    match self.kind {
+      ItemKind::Use(ref decl) => decl.path.last_str(),
+      ItemKind::Const(ref decl) => &decl.name,
+      ItemKind::Type(ref decl)  => &decl.name,
+      ItemKind::Struct(ref decl) => &decl.name,
+      ItemKind::Spec(ref decl) => &decl.name,
+      ItemKind::Fn(ref decl)  => &decl.name,
+    }
+  }
+
+  pub fn is_pub(&self) -> bool {
+    self.vis == Visibility::Public
+  }
+}
+
+/// Item visibility.  Items are private to their module unless marked `pub`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Visibility {
+  Private,
+  Public,
+}
+
+impl fmt::Display for Visibility {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match *self {
+      Visibility::Private => Ok(()),
+      Visibility::Public  => write!(f, "pub"),
+    }
+  }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ItemKind<'ast> {
+  /// Import:  use a::b::c;
+  Use(UseDecl),
+  /// Constant:  const MAX: i32 = 1 << 8;
+  Const(ConstDecl<'ast>),
+  /// Type alias:  type Callback = fn(i32) -> bool;
+  Type(TypeAlias<'ast>),
+  /// Structure:  struct Pair<T> { first: T, second: T }
+  Struct(StructDecl<'ast>),
+  /// Enumeration of variants:  spec Option<T> { Some(T), None }
+  Spec(SpecDecl<'ast>),
+  /// Function declaration:  fn f(a: i32) -> i32 { ... }
+  Fn(FnDecl<'ast>),
+}
+
+/// Constant declaration.  The initializer is evaluated at compile time.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConstDecl<'ast> {
+  pub name:  String,
+  pub ty:    Type<'ast>,
+  pub value: Expr<'ast>,
+}
+
+/// Type alias declaration.  Aliases are resolved by the type checker and
+/// may refer to other aliases, but not to themselves.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TypeAlias<'ast> {
+  pub name: String,
+  pub ty:   Type<'ast>,
+}
+
+/// Structure declaration, generic over the types of its fields.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StructDecl<'ast> {
+  pub name:     String,
+  pub generics: &'ast [TypeParam],
+  pub fields:   &'ast [FieldDecl<'ast>],
+}
+
+/// Named field of a structure:  first: T
+#[derive(Clone, Debug)]
+pub struct FieldDecl<'ast> {
+  pub name: String,
+  pub ty:   Type<'ast>,
+  pub span: Span,
+}
+
+impl<'ast> PartialEq for FieldDecl<'ast> {
+  fn eq(&self, other: &FieldDecl<'ast>) -> bool {
+    self.name == other.name && self.ty == other.ty
+  }
+}
+
+/// Spec declaration.  A spec value is one of its variants, each holding the
+/// values of its types.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SpecDecl<'ast> {
+  pub name:     String,
+  pub generics: &'ast [TypeParam],
+  pub variants: &'ast [Variant<'ast>],
+}
+
+/// Variant of a spec and the types of its values:  Some(T)  None
+#[derive(Clone, Debug)]
+pub struct Variant<'ast> {
+  pub name:   String,
+  pub fields: &'ast [Type<'ast>],
+  pub span:   Span,
+}
+
+impl<'ast> PartialEq for Variant<'ast> {
+  fn eq(&self, other: &Variant<'ast>) -> bool {
+    self.name == other.name && self.fields == other.fields
+  }
+}
+
+/// Import declaration.  The span covers the whole `use` item.
+#[derive(Clone, Debug)]
+pub struct UseDecl {
+  pub path: Path,
+  pub span: Span,
+}
+
+impl PartialEq for UseDecl {
+  fn eq(&self, other: &UseDecl) -> bool {
+    self.path == other.path
+  }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct FnDecl<'ast> {
+  pub name:     String,
+  pub generics: &'ast [TypeParam],
+  pub params:   &'ast [Param<'ast>],
+  pub ret:      Option<Type<'ast>>,
+  pub body:     Block<'ast>,
+}
+
+/// Type parameter of a generic function, struct or spec, with the models it must mount:
+/// T: Ord + Show
+#[derive(Clone, Debug)]
+pub struct TypeParam {
+  pub name:   String,
+  pub bounds: Vec<String>,
+  pub span:   Span,
+}
+
+impl PartialEq for TypeParam {
+  fn eq(&self, other: &TypeParam) -> bool {
+    self.name == other.name && self.bounds == other.bounds
+  }
+}
+
+/// Function parameter:  name: type  (x, y): (i32, i32)
+/// The type is only optional for closure parameters:  |x| x + 1
+#[derive(Clone, Debug)]
+pub struct Param<'ast> {
+  pub pat:  Pattern<'ast>,
+  pub ty:   Option<Type<'ast>>,
+  pub span: Span,
+}
+
+impl<'ast> PartialEq for Param<'ast> {
+  fn eq(&self, other: &Param<'ast>) -> bool {
+    self.pat == other.pat && self.ty == other.ty
+  }
+}
@@ -0,0 +1,222 @@
+//! Index of the nodes of a module by id.
+//!
+//! Analyses keep their results in side tables keyed by `NodeId`, and turn
+//! ids back into nodes, locations and parents through a `NodeMap`.
+//!
+//! The nodes themselves live in the `Arena` the parser allocates them in,
+//! and the map only borrows them from it, so a map lives no longer than the
+//! arena of the module.
+
+use utils::{FileId, Span};
+
+use super::NodeId;
+use super::expr::Expr;
+use super::item::{Item, Module};
+use super::pat::Pattern;
+use super::stmt::Stmt;
+use super::ty::Type;
+use super::visit::{self, Visitor};
+
+/// A node of the syntax tree that has an id.
+#[derive(Copy, Clone, Debug)]
+pub enum Node<'ast> {
+  Item(&'ast Item<'ast>),
+  Stmt(&'ast Stmt<'ast>),
+  Expr(&'ast Expr<'ast>),
+  Pattern(&'ast Pattern<'ast>),
+  Type(&'ast Type<'ast>),
+}
+
+impl<'ast> Node<'ast> {
+  pub fn id(&self) -> NodeId {
+    match *self {
+      Node::Item(item)   => item.id,
+      Node::Stmt(stmt)   => stmt.id,
+      Node::Expr(expr)   => expr.id,
+      Node::Pattern(pat) => pat.id,
+      Node::Type(ty)     => ty.id,
+    }
+  }
+
+  pub fn span(&self) -> Span {
+    match *self {
+      Node::Item(item)   => item.span,
+      Node::Stmt(stmt)   => stmt.span,
+      Node::Expr(expr)   => expr.span,
+      Node::Pattern(pat) => pat.span,
+      Node::Type(ty)     => ty.span,
+    }
+  }
+
+  pub fn file(&self) -> FileId {
+    match *self {
+      Node::Item(item)   => item.file,
+      Node::Stmt(stmt)   => stmt.file,
+      Node::Expr(expr)   => expr.file,
+      Node::Pattern(pat) => pat.file,
+      Node::Type(ty)     => ty.file,
+    }
+  }
+}
+
+#[derive(Copy, Clone, Debug)]
+struct Entry<'ast> {
+  node:   Node<'ast>,
+  parent: Option<NodeId>,
+}
+
+/// The nodes of a module, indexed by id, with the id of their parent.  The
+/// parent of a node is the closest enclosing item, statement, expression,
+/// pattern or type; module level statements have none.  Lookups index a
+/// vector, in constant time.
+#[derive(Debug, Default)]
+pub struct NodeMap<'ast> {
+  entries: Vec<Option<Entry<'ast>>>,
+}
+
+impl<'ast> NodeMap<'ast> {
+  /// Indexes the nodes of `module`.  Nodes built by hand, without an id, are
+  /// left out but their descendants are not.
+  pub fn new(module: &'ast Module<'ast>) -> NodeMap<'ast> {
+    let mut collector = Collector { map: NodeMap::default(), parent: None };
+    collector.visit_module(module);
+    collector.map
+  }
+
+  pub fn get(&self, id: NodeId) -> Option<Node<'ast>> {
+    self.entry(id).map(|entry| entry.node)
+  }
+
+  /// The location of node `id`, which must be in the map.
+  pub fn span(&self, id: NodeId) -> Span {
+    match self.get(id) {
+      Some(node) => node.span(),
+      None       => panic!("{} is not in the map", id),
+    }
+  }
+
+  pub fn parent(&self, id: NodeId) -> Option<NodeId> {
+    self.entry(id).and_then(|entry| entry.parent)
+  }
+
+  /// The number of ids the map has room for.  Every id below it is in the
+  /// map when all the nodes of the module were parsed.
+  pub fn len(&self) -> usize {
+    self.entries.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.entries.is_empty()
+  }
+
+  fn entry(&self, id: NodeId) -> Option<&Entry<'ast>> {
+    self.entries.get(id.0 as usize).and_then(|entry| entry.as_ref())
+  }
+
+  fn insert(&mut self, node: Node<'ast>, parent: Option<NodeId>) {
+    let index = node.id().0 as usize;
+    if index >= self.entries.len() {
+      self.entries.resize(index + 1, None);
+    }
+    self.entries[index] = Some(Entry { node, parent });
+  }
+}
+
+/// Fills a map, tracking the innermost node with an id.
+struct Collector<'ast> {
+  map:    NodeMap<'ast>,
+  parent: Option<NodeId>,
+}
+
+impl<'ast> Collector<'ast> {
+  fn node<F: FnOnce(&mut Collector<'ast>)>(&mut self, node: Node<'ast>, walk: F) {
+    if node.id() == NodeId::DUMMY {
+      return walk(self);
+    }
+    self.map.insert(node, self.parent);
+    let outer = self.parent.replace(node.id());
+    walk(self);
+    self.parent = outer;
+  }
+}
+
+impl<'ast> Visitor<'ast> for Collector<'ast> {
+  fn visit_item(&mut self, item: &'ast Item<'ast>) {
+    self.node(Node::Item(item), |collector| visit::walk_item(collector, item))
+  }
+
+  fn visit_stmt(&mut self, stmt: &'ast Stmt<'ast>) {
+    self.node(Node::Stmt(stmt), |collector| visit::walk_stmt(collector, stmt))
+  }
+
+  fn visit_expr(&mut self, expr: &'ast Expr<'ast>) {
+    self.node(Node::Expr(expr), |collector| visit::walk_expr(collector, expr))
+  }
+
+  fn visit_pattern(&mut self, pat: &'ast Pattern<'ast>) {
+    self.node(Node::Pattern(pat), |collector| visit::walk_pattern(collector, pat))
+  }
+
+  fn visit_type(&mut self, ty: &'ast Type<'ast>) {
+    self.node(Node::Type(ty), |collector| visit::walk_type(collector, ty))
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use syntax::ast::{Arena, ExprKind, Literal, StmtKind};
+  use syntax::parser::parse_module;
+  use utils::Position;
+
+  #[test]
+  fn every_parsed_node_has_an_id() {
+    let arena = Arena::new();
+    let module = parse_module(&arena, "
+      fn f((a, b): (i32, fn() -> str?)) -> [i32] {
+        let c: i32 = a + b * 2;
+        for x in 0..c { println \"${x}\"; }
+        [c, |y| y][0]
+      }
+      f((1, fn() { nil }));
+    ").unwrap();
+    let map = NodeMap::new(&module);
+    assert!(!map.is_empty());
+    for index in 0..map.len() {
+      let id = NodeId(index as u32);
+      let node = map.get(id).expect("ids are dense");
+      assert_eq!(node.id(), id);
+      assert!(map.parent(id).is_none_or(|parent| map.get(parent).is_some()));
+    }
+  }
+
+  #[test]
+  fn parents_and_spans() {
+    let arena = Arena::new();
+    let module = parse_module(&arena, "let a = 1;\nlet b = -(a + 2);").unwrap();
+    let map = NodeMap::new(&module);
+    let stmt = &module.stmts[1];
+    let init = match stmt.kind {
+      StmtKind::Let(ref local) => local.init.as_ref().unwrap(),
+      _                        => panic!("expected a let statement"),
+    };
+    let sum = match init.kind {
+      ExprKind::Unary(_, operand) => operand,
+      _                           => panic!("expected a negation"),
+    };
+    let two = match sum.kind {
+      ExprKind::Binary(_, _, rhs) => rhs,
+      _                           => panic!("expected an addition"),
+    };
+    assert_eq!(two.kind, ExprKind::Literal(Literal::Int(2)));
+    assert_eq!(map.span(two.id), Span(Position(2, 15), Position(2, 16)));
+    assert_eq!(map.parent(two.id), Some(sum.id));
+    assert_eq!(map.parent(sum.id), Some(init.id));
+    assert_eq!(map.parent(init.id), Some(stmt.id));
+    assert_eq!(map.parent(stmt.id), None);
+    match map.get(sum.id) {
+      Some(Node::Expr(expr)) => assert_eq!(expr.span, Span(Position(2, 10), Position(2, 17))),
+      other                  => panic!("expected an expression, found {:?}", other),
+    }
+  }
+}
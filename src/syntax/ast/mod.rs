@@ -1,11 +1,48 @@
+pub mod arena;
 pub mod binop;
 pub mod unop;
+pub mod expr;
+pub mod fold;
+pub mod format;
+pub mod item;
+pub mod map;
+pub mod pat;
+pub mod stmt;
+pub mod ty;
+pub mod visit;
 
 use std::fmt;
 
 use self::binop::{ArithmeticOp, BitWiseOp, ComparisonOp, LogicalOp};
 use self::unop::UnaryOp;
 
+pub use self::arena::Arena;
+pub use self::expr::{Arm, BinaryOp, Closure, Expr, ExprKind, Fragment, Literal, RangeLimits};
+pub use self::item::{ConstDecl, FieldDecl, FnDecl, Item, ItemKind, Module, Param, SpecDecl,
+                     StructDecl, TypeAlias, TypeParam, UseDecl, Variant, Visibility};
+pub use self::pat::{Pattern, PatternKind};
+pub use self::format::{Format, FormatError, Piece, Spec};
+pub use self::stmt::{Block, ForLoop, Local, Print, Stmt, StmtKind, WhileLoop};
+pub use self::map::{Node, NodeMap};
+pub use self::ty::{PrimType, Type, TypeKind};
+
+/// Identifies a node of the syntax tree among the nodes of its module.  The
+/// parser numbers the items, statements, expressions, patterns and types of
+/// a module from 0, in the order it builds them.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct NodeId(pub u32);
+
+impl NodeId {
+  /// Id of the nodes built by hand rather than parsed.
+  pub const DUMMY: NodeId = NodeId(u32::MAX);
+}
+
+impl fmt::Display for NodeId {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "node {}", self.0)
+  }
+}
+
 //use pest::prec_climber::{Assoc, Operator, PrecClimber};
 
 /// The core AST node enumerator value.
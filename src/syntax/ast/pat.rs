@@ -0,0 +1,86 @@
+use std::fmt;
+
+use utils::{FileId, Path, Span};
+
+use super::NodeId;
+
+/// Pattern node with its location in the source.
+#[derive(Clone, Debug)]
+pub struct Pattern<'ast> {
+  pub id:   NodeId,
+  pub kind: PatternKind<'ast>,
+  pub span: Span,
+  pub file: FileId,
+}
+
+impl<'ast> Pattern<'ast> {
+  pub fn new(id: NodeId, kind: PatternKind<'ast>, span: Span, file: FileId) -> Pattern<'ast> {
+    Pattern { id, kind, span, file }
+  }
+}
+
+impl<'ast> From<PatternKind<'ast>> for Pattern<'ast> {
+  fn from(kind: PatternKind<'ast>) -> Pattern<'ast> {
+    Pattern::new(NodeId::DUMMY, kind, Span::default(), FileId::default())
+  }
+}
+
+impl<'ast> PartialEq for Pattern<'ast> {
+  fn eq(&self, other: &Pattern<'ast>) -> bool {
+    self.kind == other.kind
+  }
+}
+
+impl<'ast> fmt::Display for Pattern<'ast> {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    self.kind.fmt(f)
+  }
+}
+
+/// Binding patterns of `let` declarations and parameters, and the patterns
+/// of `match` arms.
+#[derive(Clone, Debug, PartialEq)]
+pub enum PatternKind<'ast> {
+  /// Binds the whole value to a name:  x
+  Ident(String),
+  /// Binds the elements of a tuple:  (x, (y, z))
+  Tuple(&'ast [Pattern<'ast>]),
+  /// Matches a variant of a spec, binding its values:  Shape::Circle(r)  Option::None
+  /// Only valid in `match` arms.
+  Variant(Path, &'ast [Pattern<'ast>]),
+}
+
+impl<'ast> fmt::Display for PatternKind<'ast> {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match *self {
+      PatternKind::Ident(ref name) => f.write_str(name),
+      PatternKind::Tuple(pats) => {
+        write!(f, "(")?;
+        for (i, pat) in pats.iter().enumerate() {
+          if i > 0 {
+            write!(f, ", ")?;
+          }
+          pat.fmt(f)?;
+        }
+        if pats.len() == 1 {
+          write!(f, ",")?;
+        }
+        write!(f, ")")
+      },
+      PatternKind::Variant(ref path, pats) => {
+        write!(f, "{}", path)?;
+        if !pats.is_empty() {
+          write!(f, "(")?;
+          for (i, pat) in pats.iter().enumerate() {
+            if i > 0 {
+              write!(f, ", ")?;
+            }
+            pat.fmt(f)?;
+          }
+          write!(f, ")")?;
+        }
+        Ok(())
+      },
+    }
+  }
+}
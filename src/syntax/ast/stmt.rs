@@ -0,0 +1,110 @@
+use utils::{FileId, Span};
+
+use super::NodeId;
+use super::expr::Expr;
+use super::format::Format;
+use super::item::Item;
+use super::pat::Pattern;
+use super::ty::Type;
+
+/// Statement node with its location in the source.
+#[derive(Clone, Debug)]
+pub struct Stmt<'ast> {
+  pub id:   NodeId,
+  pub kind: StmtKind<'ast>,
+  pub span: Span,
+  pub file: FileId,
+}
+
+impl<'ast> Stmt<'ast> {
+  pub fn new(id: NodeId, kind: StmtKind<'ast>, span: Span, file: FileId) -> Stmt<'ast> {
+    Stmt { id, kind, span, file }
+  }
+}
+
+impl<'ast> From<StmtKind<'ast>> for Stmt<'ast> {
+  fn from(kind: StmtKind<'ast>) -> Stmt<'ast> {
+    Stmt::new(NodeId::DUMMY, kind, Span::default(), FileId::default())
+  }
+}
+
+impl<'ast> PartialEq for Stmt<'ast> {
+  fn eq(&self, other: &Stmt<'ast>) -> bool {
+    self.kind == other.kind
+  }
+}
+
+/// Statements, as accepted by the grammar's `statement` rule.
+#[derive(Clone, Debug, PartialEq)]
+pub enum StmtKind<'ast> {
+  /// Module level item:  fn, use
+  Item(Item<'ast>),
+  /// Variable declaration:  let x: i32 = 1;
+  Let(Local<'ast>),
+  /// Expression followed by a semi colon.
+  Expr(Expr<'ast>),
+  /// Return from the enclosing function:  return x;
+  Return(Option<Expr<'ast>>),
+  /// Loop over the elements of an iterable:  for x in 0..n { }
+  For(ForLoop<'ast>),
+  /// Loop while a condition holds:  while i < n { }
+  While(WhileLoop<'ast>),
+  /// Exit from the innermost or the labeled loop:  break;  break 'outer;  break x;
+  Break(Option<String>, Option<Expr<'ast>>),
+  /// Next iteration of the innermost or the labeled loop:  continue 'outer;
+  Continue(Option<String>),
+  /// Output of values:  print x;  println "{} of {}", i, n;
+  Print(Print<'ast>),
+}
+
+/// Braced sequence of statements.  The block evaluates to its trailing
+/// expression, or to nil when there is none.
+#[derive(Copy, Clone, Debug)]
+pub struct Block<'ast> {
+  pub stmts: &'ast [Stmt<'ast>],
+  pub expr:  Option<&'ast Expr<'ast>>,
+  pub span:  Span,
+}
+
+impl<'ast> PartialEq for Block<'ast> {
+  fn eq(&self, other: &Block<'ast>) -> bool {
+    self.stmts == other.stmts && self.expr == other.expr
+  }
+}
+
+/// A `for` loop binding each element of `iter` to `pat` in turn.  Labels
+/// are stored without their leading quote.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ForLoop<'ast> {
+  pub label: Option<String>,
+  pub pat:   Pattern<'ast>,
+  pub iter:  Expr<'ast>,
+  pub body:  Block<'ast>,
+}
+
+/// A `while` loop running `body` as long as `cond` is true.
+#[derive(Clone, Debug, PartialEq)]
+pub struct WhileLoop<'ast> {
+  pub label: Option<String>,
+  pub cond:  Expr<'ast>,
+  pub body:  Block<'ast>,
+}
+
+/// A `print` or `println` statement.  When the first argument is a string
+/// literal it is the format of the other arguments; otherwise the arguments
+/// are printed separated by spaces.  An interpolated string may only be
+/// printed on its own.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Print<'ast> {
+  pub newline: bool,
+  pub format:  Option<Format>,
+  pub args:    &'ast [Expr<'ast>],
+}
+
+/// A `let` binding with optional type annotation and initializer.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Local<'ast> {
+  pub pat:  Pattern<'ast>,
+  pub ty:   Option<Type<'ast>>,
+  pub init: Option<Expr<'ast>>,
+}
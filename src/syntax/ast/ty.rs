@@ -0,0 +1,148 @@
+use std::fmt;
+
+use utils::{FileId, Span};
+
+use super::NodeId;
+
+/// Type annotation node with its location in the source.
+#[derive(Clone, Debug)]
+pub struct Type<'ast> {
+  pub id:   NodeId,
+  pub kind: TypeKind<'ast>,
+  pub span: Span,
+  pub file: FileId,
+}
+
+impl<'ast> Type<'ast> {
+  pub fn new(id: NodeId, kind: TypeKind<'ast>, span: Span, file: FileId) -> Type<'ast> {
+    Type { id, kind, span, file }
+  }
+}
+
+impl<'ast> From<TypeKind<'ast>> for Type<'ast> {
+  fn from(kind: TypeKind<'ast>) -> Type<'ast> {
+    Type::new(NodeId::DUMMY, kind, Span::default(), FileId::default())
+  }
+}
+
+impl<'ast> PartialEq for Type<'ast> {
+  fn eq(&self, other: &Type<'ast>) -> bool {
+    self.kind == other.kind
+  }
+}
+
+impl<'ast> fmt::Display for Type<'ast> {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    self.kind.fmt(f)
+  }
+}
+
+/// Type annotations accepted in `type_dcl` and function return positions.
+#[derive(Clone, Debug, PartialEq)]
+pub enum TypeKind<'ast> {
+  /// One of the built-in `prim_type` names.
+  Prim(PrimType),
+  /// Function type:  fn(i32, i32) -> bool
+  Fn(&'ast [Type<'ast>], &'ast Type<'ast>),
+  /// Array type:  [i32]
+  Array(&'ast Type<'ast>),
+  /// Map type:  [str: i32]
+  Map(&'ast Type<'ast>, &'ast Type<'ast>),
+  /// Tuple type:  (i32, str)
+  Tuple(&'ast [Type<'ast>]),
+  /// Type admitting nil besides its own values:  i32?
+  Optional(&'ast Type<'ast>),
+  /// Name of a type alias, struct or spec with its type arguments:  Callback  Option<i32>
+  Named(String, &'ast [Type<'ast>]),
+}
+
+impl<'ast> fmt::Display for TypeKind<'ast> {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match *self {
+      TypeKind::Prim(ref prim) => prim.fmt(f),
+      TypeKind::Fn(params, ref ret) => {
+        write!(f, "fn(")?;
+        for (i, param) in params.iter().enumerate() {
+          if i > 0 {
+            write!(f, ", ")?;
+          }
+          param.fmt(f)?;
+        }
+        write!(f, ") -> {}", ret)
+      },
+      TypeKind::Array(ref elem) => write!(f, "[{}]", elem),
+      TypeKind::Map(ref key, ref value) => write!(f, "[{}: {}]", key, value),
+      TypeKind::Tuple(elems) => {
+        write!(f, "(")?;
+        for (i, elem) in elems.iter().enumerate() {
+          if i > 0 {
+            write!(f, ", ")?;
+          }
+          elem.fmt(f)?;
+        }
+        if elems.len() == 1 {
+          write!(f, ",")?;
+        }
+        write!(f, ")")
+      },
+      TypeKind::Optional(ref ty) => write!(f, "{}?", ty),
+      TypeKind::Named(ref name, args) => {
+        write!(f, "{}", name)?;
+        if !args.is_empty() {
+          write!(f, "<")?;
+          for (i, arg) in args.iter().enumerate() {
+            if i > 0 {
+              write!(f, ", ")?;
+            }
+            arg.fmt(f)?;
+          }
+          write!(f, ">")?;
+        }
+        Ok(())
+      },
+    }
+  }
+}
+
+/// Primitive types, mirroring the grammar's `prim_type` rule.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum PrimType {
+  Void,
+  Str,
+  Char,
+  Bool,
+  I8,
+  U8,
+  I16,
+  U16,
+  I32,
+  U32,
+  I64,
+  U64,
+  F32,
+  F64,
+  Nil,
+}
+
+impl fmt::Display for PrimType {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    let prim = match *self {
+      PrimType::Void => "void",
+      PrimType::Str  => "str",
+      PrimType::Char => "char",
+      PrimType::Bool => "bool",
+      PrimType::I8   => "i8",
+      PrimType::U8   => "u8",
+      PrimType::I16  => "i16",
+      PrimType::U16  => "u16",
+      PrimType::I32  => "i32",
+      PrimType::U32  => "u32",
+      PrimType::I64  => "i64",
+      PrimType::U64  => "u64",
+      PrimType::F32  => "f32",
+      PrimType::F64  => "f64",
+      PrimType::Nil  => "nil",
+    };
+    write!(f, "{}", prim)
+  }
+}
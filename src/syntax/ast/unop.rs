@@ -22,7 +22,3 @@ impl fmt::Display for UnaryOp {
     write!(f, "{}", unary_op)
   }
 }
-
-// TODO:
-// Type cast expressions - Cast operator to build cast expr for conversion to a given type.
-// e.g.   type_cast_expr : value "as" type ;
@@ -0,0 +1,441 @@
+//! Read-only traversal of the syntax tree.
+//!
+//! A `Visitor` has a method per node kind, each defaulting to the matching
+//! `walk_*` function which visits the children of the node.  Analyses
+//! override the methods of the nodes they care about and call the `walk_*`
+//! function to keep descending.
+
+use utils::Path;
+
+use super::AssignmentOp;
+use super::binop::{ArithmeticOp, BitWiseOp, ComparisonOp, LogicalOp};
+use super::expr::{Arm, BinaryOp, Closure, Expr, ExprKind, Fragment, Literal};
+use super::item::{FnDecl, Item, ItemKind, Module, Param, TypeParam};
+use super::pat::{Pattern, PatternKind};
+use super::stmt::{Block, ForLoop, Local, Print, Stmt, StmtKind, WhileLoop};
+use super::ty::{PrimType, Type, TypeKind};
+use super::unop::UnaryOp;
+
+pub trait Visitor<'ast>: Sized {
+  fn visit_module(&mut self, module: &'ast Module<'ast>) {
+    walk_module(self, module)
+  }
+
+  fn visit_item(&mut self, item: &'ast Item<'ast>) {
+    walk_item(self, item)
+  }
+
+  fn visit_fn_decl(&mut self, decl: &'ast FnDecl<'ast>) {
+    walk_fn_decl(self, decl)
+  }
+
+  fn visit_type_param(&mut self, _param: &'ast TypeParam) {}
+
+  fn visit_param(&mut self, param: &'ast Param<'ast>) {
+    walk_param(self, param)
+  }
+
+  fn visit_stmt(&mut self, stmt: &'ast Stmt<'ast>) {
+    walk_stmt(self, stmt)
+  }
+
+  fn visit_local(&mut self, local: &'ast Local<'ast>) {
+    walk_local(self, local)
+  }
+
+  fn visit_for_loop(&mut self, for_loop: &'ast ForLoop<'ast>) {
+    walk_for_loop(self, for_loop)
+  }
+
+  fn visit_while_loop(&mut self, while_loop: &'ast WhileLoop<'ast>) {
+    walk_while_loop(self, while_loop)
+  }
+
+  fn visit_print(&mut self, print: &'ast Print<'ast>) {
+    walk_print(self, print)
+  }
+
+  fn visit_block(&mut self, block: &'ast Block<'ast>) {
+    walk_block(self, block)
+  }
+
+  fn visit_expr(&mut self, expr: &'ast Expr<'ast>) {
+    walk_expr(self, expr)
+  }
+
+  fn visit_fragment(&mut self, fragment: &'ast Fragment<'ast>) {
+    walk_fragment(self, fragment)
+  }
+
+  fn visit_closure(&mut self, closure: &'ast Closure<'ast>) {
+    walk_closure(self, closure)
+  }
+
+  fn visit_arm(&mut self, arm: &'ast Arm<'ast>) {
+    walk_arm(self, arm)
+  }
+
+  fn visit_literal(&mut self, _lit: &'ast Literal) {}
+
+  fn visit_path(&mut self, _path: &'ast Path) {}
+
+  fn visit_pattern(&mut self, pat: &'ast Pattern<'ast>) {
+    walk_pattern(self, pat)
+  }
+
+  fn visit_type(&mut self, ty: &'ast Type<'ast>) {
+    walk_type(self, ty)
+  }
+
+  fn visit_prim_type(&mut self, _prim: &'ast PrimType) {}
+
+  fn visit_unary_op(&mut self, _op: &'ast UnaryOp) {}
+
+  fn visit_binary_op(&mut self, op: &'ast BinaryOp) {
+    walk_binary_op(self, op)
+  }
+
+  fn visit_arithmetic_op(&mut self, _op: &'ast ArithmeticOp) {}
+
+  fn visit_bitwise_op(&mut self, _op: &'ast BitWiseOp) {}
+
+  fn visit_comparison_op(&mut self, _op: &'ast ComparisonOp) {}
+
+  fn visit_logical_op(&mut self, _op: &'ast LogicalOp) {}
+
+  fn visit_assignment_op(&mut self, _op: &'ast AssignmentOp) {}
+}
+
+pub fn walk_module<'ast, V: Visitor<'ast>>(visitor: &mut V, module: &'ast Module<'ast>) {
+  for stmt in module.stmts {
+    visitor.visit_stmt(stmt);
+  }
+}
+
+pub fn walk_item<'ast, V: Visitor<'ast>>(visitor: &mut V, item: &'ast Item<'ast>) {
+  match item.kind {
+    ItemKind::Use(ref decl)   => visitor.visit_path(&decl.path),
+    ItemKind::Const(ref decl) => {
+      visitor.visit_type(&decl.ty);
+      visitor.visit_expr(&decl.value);
+    },
+    ItemKind::Type(ref alias) => visitor.visit_type(&alias.ty),
+    ItemKind::Struct(ref decl) => {
+      for param in decl.generics {
+        visitor.visit_type_param(param);
+      }
+      for field in decl.fields {
+        visitor.visit_type(&field.ty);
+      }
+    },
+    ItemKind::Spec(ref decl) => {
+      for param in decl.generics {
+        visitor.visit_type_param(param);
+      }
+      for variant in decl.variants {
+        for ty in variant.fields {
+          visitor.visit_type(ty);
+        }
+      }
+    },
+    ItemKind::Fn(ref decl)    => visitor.visit_fn_decl(decl),
+  }
+}
+
+pub fn walk_fn_decl<'ast, V: Visitor<'ast>>(visitor: &mut V, decl: &'ast FnDecl<'ast>) {
+  for param in decl.generics {
+    visitor.visit_type_param(param);
+  }
+  for param in decl.params {
+    visitor.visit_param(param);
+  }
+  if let Some(ref ret) = decl.ret {
+    visitor.visit_type(ret);
+  }
+  visitor.visit_block(&decl.body);
+}
+
+pub fn walk_param<'ast, V: Visitor<'ast>>(visitor: &mut V, param: &'ast Param<'ast>) {
+  visitor.visit_pattern(&param.pat);
+  if let Some(ref ty) = param.ty {
+    visitor.visit_type(ty);
+  }
+}
+
+pub fn walk_stmt<'ast, V: Visitor<'ast>>(visitor: &mut V, stmt: &'ast Stmt<'ast>) {
+  match stmt.kind {
+    StmtKind::Item(ref item)          => visitor.visit_item(item),
+    StmtKind::Let(ref local)          => visitor.visit_local(local),
+    StmtKind::Expr(ref expr)          => visitor.visit_expr(expr),
+    StmtKind::Return(ref value) |
+    StmtKind::Break(_, ref value)     => {
+      if let Some(ref value) = *value {
+        visitor.visit_expr(value);
+      }
+    },
+    StmtKind::For(ref for_loop)       => visitor.visit_for_loop(for_loop),
+    StmtKind::While(ref while_loop)   => visitor.visit_while_loop(while_loop),
+    StmtKind::Continue(_)             => {},
+    StmtKind::Print(ref print)        => visitor.visit_print(print),
+  }
+}
+
+/// Visits the initializer before the pattern, which is only in scope after
+/// the declaration.
+pub fn walk_local<'ast, V: Visitor<'ast>>(visitor: &mut V, local: &'ast Local<'ast>) {
+  if let Some(ref init) = local.init {
+    visitor.visit_expr(init);
+  }
+  if let Some(ref ty) = local.ty {
+    visitor.visit_type(ty);
+  }
+  visitor.visit_pattern(&local.pat);
+}
+
+pub fn walk_for_loop<'ast, V: Visitor<'ast>>(visitor: &mut V, for_loop: &'ast ForLoop<'ast>) {
+  visitor.visit_expr(&for_loop.iter);
+  visitor.visit_pattern(&for_loop.pat);
+  visitor.visit_block(&for_loop.body);
+}
+
+pub fn walk_while_loop<'ast, V: Visitor<'ast>>(visitor: &mut V, while_loop: &'ast WhileLoop<'ast>) {
+  visitor.visit_expr(&while_loop.cond);
+  visitor.visit_block(&while_loop.body);
+}
+
+pub fn walk_print<'ast, V: Visitor<'ast>>(visitor: &mut V, print: &'ast Print<'ast>) {
+  for arg in print.args {
+    visitor.visit_expr(arg);
+  }
+}
+
+pub fn walk_block<'ast, V: Visitor<'ast>>(visitor: &mut V, block: &'ast Block<'ast>) {
+  for stmt in block.stmts {
+    visitor.visit_stmt(stmt);
+  }
+  if let Some(expr) = block.expr {
+    visitor.visit_expr(expr);
+  }
+}
+
+pub fn walk_expr<'ast, V: Visitor<'ast>>(visitor: &mut V, expr: &'ast Expr<'ast>) {
+  match expr.kind {
+    ExprKind::Literal(ref lit) => visitor.visit_literal(lit),
+    ExprKind::Interpolated(fragments) => for fragment in fragments {
+      visitor.visit_fragment(fragment);
+    },
+    ExprKind::Identifier(_) => {},
+    ExprKind::Path(ref path) => visitor.visit_path(path),
+    ExprKind::Unary(ref op, operand) => {
+      visitor.visit_unary_op(op);
+      visitor.visit_expr(operand);
+    },
+    ExprKind::Binary(ref op, lhs, rhs) => {
+      visitor.visit_expr(lhs);
+      visitor.visit_binary_op(op);
+      visitor.visit_expr(rhs);
+    },
+    ExprKind::Cast(operand, ref target) => {
+      visitor.visit_expr(operand);
+      visitor.visit_prim_type(target);
+    },
+    ExprKind::Call(callee, args) |
+    ExprKind::MethodCall(callee, _, args) |
+    ExprKind::SafeMethodCall(callee, _, args) => {
+      visitor.visit_expr(callee);
+      for arg in args {
+        visitor.visit_expr(arg);
+      }
+    },
+    ExprKind::Assign(ref op, target, value) => {
+      visitor.visit_expr(target);
+      visitor.visit_assignment_op(op);
+      visitor.visit_expr(value);
+    },
+    ExprKind::Range(lhs, rhs, _) | ExprKind::Index(lhs, rhs) => {
+      visitor.visit_expr(lhs);
+      visitor.visit_expr(rhs);
+    },
+    ExprKind::Array(elems) | ExprKind::Tuple(elems) => for elem in elems {
+      visitor.visit_expr(elem);
+    },
+    ExprKind::Map(entries) => for (key, value) in entries {
+      visitor.visit_expr(key);
+      visitor.visit_expr(value);
+    },
+    ExprKind::Field(tuple, _) => visitor.visit_expr(tuple),
+    ExprKind::Member(object, _) => visitor.visit_expr(object),
+    ExprKind::Struct(_, fields) => for (_, value) in fields {
+      visitor.visit_expr(value);
+    },
+    ExprKind::Closure(closure) => visitor.visit_closure(closure),
+    ExprKind::Ternary(cond, then, otherwise) => {
+      visitor.visit_expr(cond);
+      visitor.visit_expr(then);
+      visitor.visit_expr(otherwise);
+    },
+    ExprKind::If(cond, ref then, ref otherwise) => {
+      visitor.visit_expr(cond);
+      visitor.visit_block(then);
+      if let Some(otherwise) = *otherwise {
+        visitor.visit_expr(otherwise);
+      }
+    },
+    ExprKind::Match(scrutinee, arms) => {
+      visitor.visit_expr(scrutinee);
+      for arm in arms {
+        visitor.visit_arm(arm);
+      }
+    },
+    ExprKind::Loop(_, ref block) | ExprKind::Block(ref block) => visitor.visit_block(block),
+  }
+}
+
+pub fn walk_fragment<'ast, V: Visitor<'ast>>(visitor: &mut V, fragment: &'ast Fragment<'ast>) {
+  if let Fragment::Expr(ref expr, _) = *fragment {
+    visitor.visit_expr(expr);
+  }
+}
+
+pub fn walk_closure<'ast, V: Visitor<'ast>>(visitor: &mut V, closure: &'ast Closure<'ast>) {
+  for param in closure.params {
+    visitor.visit_param(param);
+  }
+  if let Some(ref ret) = closure.ret {
+    visitor.visit_type(ret);
+  }
+  visitor.visit_block(&closure.body);
+}
+
+/// Visits the pattern before the body, in which its bindings are in scope.
+pub fn walk_arm<'ast, V: Visitor<'ast>>(visitor: &mut V, arm: &'ast Arm<'ast>) {
+  visitor.visit_pattern(&arm.pat);
+  visitor.visit_expr(&arm.body);
+}
+
+pub fn walk_pattern<'ast, V: Visitor<'ast>>(visitor: &mut V, pat: &'ast Pattern<'ast>) {
+  match pat.kind {
+    PatternKind::Ident(_)    => {},
+    PatternKind::Tuple(pats) => for pat in pats {
+      visitor.visit_pattern(pat);
+    },
+    PatternKind::Variant(ref path, pats) => {
+      visitor.visit_path(path);
+      for pat in pats {
+        visitor.visit_pattern(pat);
+      }
+    },
+  }
+}
+
+pub fn walk_type<'ast, V: Visitor<'ast>>(visitor: &mut V, ty: &'ast Type<'ast>) {
+  match ty.kind {
+    TypeKind::Prim(ref prim) => visitor.visit_prim_type(prim),
+    TypeKind::Fn(params, ret) => {
+      for param in params {
+        visitor.visit_type(param);
+      }
+      visitor.visit_type(ret);
+    },
+    TypeKind::Array(elem) | TypeKind::Optional(elem) => visitor.visit_type(elem),
+    TypeKind::Map(key, value) => {
+      visitor.visit_type(key);
+      visitor.visit_type(value);
+    },
+    TypeKind::Tuple(elems) => for elem in elems {
+      visitor.visit_type(elem);
+    },
+    TypeKind::Named(_, args) => for arg in args {
+      visitor.visit_type(arg);
+    },
+  }
+}
+
+pub fn walk_binary_op<'ast, V: Visitor<'ast>>(visitor: &mut V, op: &'ast BinaryOp) {
+  match *op {
+    BinaryOp::Arithmetic(ref op) => visitor.visit_arithmetic_op(op),
+    BinaryOp::BitWise(ref op)    => visitor.visit_bitwise_op(op),
+    BinaryOp::Comparison(ref op) => visitor.visit_comparison_op(op),
+    BinaryOp::Logical(ref op)    => visitor.visit_logical_op(op),
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use syntax::ast::Arena;
+  use syntax::parser::parse_module;
+
+  /// Records the names of the nodes it meets, in visiting order.
+  #[derive(Default)]
+  struct Trace(Vec<String>);
+
+  impl<'ast> Visitor<'ast> for Trace {
+    fn visit_expr(&mut self, expr: &'ast Expr<'ast>) {
+      if let ExprKind::Identifier(ref name) = expr.kind {
+        self.0.push(name.clone());
+      }
+      walk_expr(self, expr)
+    }
+
+    fn visit_pattern(&mut self, pat: &'ast Pattern<'ast>) {
+      if let PatternKind::Ident(ref name) = pat.kind {
+        self.0.push(format!("let {}", name));
+      }
+      walk_pattern(self, pat)
+    }
+
+    fn visit_type(&mut self, ty: &'ast Type<'ast>) {
+      if let TypeKind::Named(ref name, _) = ty.kind {
+        self.0.push(format!("type {}", name));
+      }
+      walk_type(self, ty)
+    }
+
+    fn visit_literal(&mut self, lit: &'ast Literal) {
+      if let Literal::Int(i) = *lit {
+        self.0.push(i.to_string());
+      }
+    }
+
+    fn visit_unary_op(&mut self, op: &'ast UnaryOp) {
+      self.0.push(op.to_string());
+    }
+
+    fn visit_arithmetic_op(&mut self, op: &'ast ArithmeticOp) {
+      self.0.push(op.to_string());
+    }
+
+    fn visit_comparison_op(&mut self, op: &'ast ComparisonOp) {
+      self.0.push(op.to_string());
+    }
+
+    fn visit_logical_op(&mut self, op: &'ast LogicalOp) {
+      self.0.push(op.to_string());
+    }
+
+    fn visit_assignment_op(&mut self, op: &'ast AssignmentOp) {
+      self.0.push(op.to_string());
+    }
+  }
+
+  #[test]
+  fn visiting_order() {
+    let arena = Arena::new();
+    let module = parse_module(&arena, "
+      fn f(a: Num) -> Num {
+        let (b, c) = (a * 2, -a);
+        for i in 0..b { c = c + \"${i}\"; }
+        b > c || !done ? |x: Num| x : g
+      }
+    ").unwrap();
+    let mut trace = Trace::default();
+    trace.visit_module(&module);
+    assert_eq!(trace.0, vec![
+      "let a", "type Num", "type Num",
+      "a", "*", "2", "-", "a", "let b", "let c",
+      "0", "b", "let i", "c", "=", "c", "+", "i",
+      "b", ">", "c", "||", "!", "done", "let x", "type Num", "x", "g",
+    ]);
+  }
+}
@@ -0,0 +1,154 @@
+//! Green nodes: the immutable, position independent half of the tree.
+//!
+//! A green node knows its kind, its length in bytes and its children, but
+//! neither its parent nor where it starts.  Identical subtrees can then be
+//! shared between trees, and an edit only rebuilds the nodes on the path
+//! from the edited node to the root.
+
+use std::rc::Rc;
+
+use syntax::lexer::lossless::RawKind;
+use syntax::parser::Rule;
+
+/// A token with its text:  whitespace, a comment, a word or punctuation.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct GreenToken {
+    kind: RawKind,
+    text: String,
+}
+
+impl GreenToken {
+    pub fn new<S: Into<String>>(kind: RawKind, text: S) -> GreenToken {
+        GreenToken { kind, text: text.into() }
+    }
+
+    pub fn kind(&self) -> RawKind {
+        self.kind
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn len(&self) -> usize {
+        self.text.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty()
+    }
+}
+
+/// A node for a grammar rule, covering its children end to end.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct GreenNode {
+    kind:     Rule,
+    len:      usize,
+    children: Vec<GreenElement>,
+}
+
+impl GreenNode {
+    pub fn new(kind: Rule, children: Vec<GreenElement>) -> GreenNode {
+        let len = children.iter().map(GreenElement::len).sum();
+        GreenNode { kind, len, children }
+    }
+
+    pub fn kind(&self) -> Rule {
+        self.kind
+    }
+
+    /// Length of the text of the node, in bytes.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn children(&self) -> &[GreenElement] {
+        &self.children
+    }
+
+    /// The text of the node:  the text of its tokens, in order.
+    pub fn text(&self) -> String {
+        let mut text = String::with_capacity(self.len);
+        self.write_text(&mut text);
+        text
+    }
+
+    fn write_text(&self, text: &mut String) {
+        for child in &self.children {
+            match *child {
+                GreenElement::Node(ref node)   => node.write_text(text),
+                GreenElement::Token(ref token) => text.push_str(token.text()),
+            }
+        }
+    }
+
+    /// A copy of the node whose child `index` is `child`.  The other children
+    /// are shared.
+    pub fn replace_child(&self, index: usize, child: GreenElement) -> GreenNode {
+        let mut children = self.children.clone();
+        children[index] = child;
+        GreenNode::new(self.kind, children)
+    }
+}
+
+/// A child of a green node.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum GreenElement {
+    Node(Rc<GreenNode>),
+    Token(Rc<GreenToken>),
+}
+
+impl GreenElement {
+    pub fn len(&self) -> usize {
+        match *self {
+            GreenElement::Node(ref node)   => node.len(),
+            GreenElement::Token(ref token) => token.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl From<GreenNode> for GreenElement {
+    fn from(node: GreenNode) -> GreenElement {
+        GreenElement::Node(Rc::new(node))
+    }
+}
+
+impl From<GreenToken> for GreenElement {
+    fn from(token: GreenToken) -> GreenElement {
+        GreenElement::Token(Rc::new(token))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn replacing_a_child_shares_the_others() {
+        let name: GreenElement = GreenToken::new(RawKind::Word, "x").into();
+        let space: GreenElement = GreenToken::new(RawKind::Whitespace, " ").into();
+        let ident: GreenElement = GreenNode::new(Rule::ident, vec![name.clone()]).into();
+        let node = GreenNode::new(Rule::pattern, vec![ident.clone(), space.clone()]);
+        assert_eq!(node.len(), 2);
+        assert_eq!(node.text(), "x ");
+
+        let abc = GreenToken::new(RawKind::Word, "abc").into();
+        let longer = GreenNode::new(Rule::ident, vec![abc]);
+        let edited = node.replace_child(0, longer.into());
+        assert_eq!(edited.text(), "abc ");
+        assert_eq!(edited.len(), 4);
+        match (&edited.children()[1], &space) {
+            (GreenElement::Token(a), GreenElement::Token(b)) => assert!(Rc::ptr_eq(a, b)),
+            _ => unreachable!(),
+        }
+        assert_eq!(node.text(), "x ");
+    }
+}
@@ -0,0 +1,213 @@
+//! Concrete syntax tree.
+//!
+//! Unlike the AST, the concrete tree keeps every token of the source,
+//! whitespace and comments included, so that its text is the source text.
+//! It is made of two layers:  immutable green nodes, shared freely between
+//! trees, and red nodes built on top of them on demand, which know their
+//! parent and their position.  Typed views over red nodes give access to the
+//! parts of the constructs by name.  After an edit, `reparse` rebuilds only
+//! the part of the tree around it.
+//!
+//! Nodes are kinded by the grammar rules they were parsed with, and tokens by
+//! the kinds of the lossless tokenizer.  Rules that do not start and end on
+//! token boundaries get no node, their inner rules take their place; the
+//! pieces of a string literal, which is a single token, get none at all,
+//! and neither do the silent rules of the grammar.  Whitespace and comments
+//! belong to the innermost node around them.
+
+pub mod green;
+pub mod red;
+pub mod reparse;
+pub mod typed;
+
+use std::rc::Rc;
+
+use pest::iterators::Pair;
+
+use syntax::lexer::lossless::{self, RawToken};
+use syntax::parser::{self, ParseError, Rule};
+
+pub use self::green::{GreenElement, GreenNode, GreenToken};
+pub use self::red::{SyntaxElement, SyntaxNode, SyntaxToken};
+pub use self::reparse::{reparse, TextEdit};
+pub use self::typed::AstNode;
+
+/// Parses `input` into a concrete tree whose root is a `top_lvl` node.
+pub fn parse(input: &str) -> Result<SyntaxNode, ParseError<'_>> {
+    parse_green(input).map(|green| SyntaxNode::new_root(Rc::new(green)))
+}
+
+/// Parses `input` into the green node of a `top_lvl` rule.
+pub fn parse_green(input: &str) -> Result<GreenNode, ParseError<'_>> {
+    parse_rule(Rule::top_lvl, input)
+}
+
+/// Parses `input` as a whole `rule`, such as a `statement`.  Whitespace and
+/// comments around the rule are kept in the returned node.
+pub fn parse_rule(rule: Rule, input: &str) -> Result<GreenNode, ParseError<'_>> {
+    let tokens = lossless::tokenize("<input>", input).map_err(ParseError::Lexical)?;
+    let tokens = located(&tokens);
+    // The grammar skips whitespace between the parts of a rule only, so the
+    // rule is parsed from its first token on.
+    let start = tokens.iter().find(|&(_, token)| !token.is_trivia())
+        .map_or(input.len(), |&(start, _)| start);
+    let end = tokens.iter().rev().find(|&(_, token)| !token.is_trivia())
+        .map_or(start, |&(start, ref token)| start + token.text.len());
+
+    let pair = parser::parse_rule(rule, &input[start..])?.next()
+        .expect("a successful parse has a pair");
+    let pair_end = start + pair.clone().into_span().end();
+    if pair_end < end {
+        return Err(ParseError::TrailingInput(pair_end));
+    }
+    let mut builder = Builder { tokens, next: 0, base: start };
+    let mut children = builder.children(pair, end);
+    // Trivia after the rule.
+    while builder.next < builder.tokens.len() {
+        children.push(builder.token());
+    }
+    Ok(GreenNode::new(rule, children))
+}
+
+/// The tokens with their byte offsets.
+fn located<'a>(tokens: &[RawToken<'a>]) -> Vec<(usize, RawToken<'a>)> {
+    let mut offset = 0;
+    tokens.iter().map(|token| {
+        let start = offset;
+        offset += token.text.len();
+        (start, token.clone())
+    }).collect()
+}
+
+/// Builds green nodes from the pairs of a parse and the tokens of the same
+/// input, handing out the tokens in order.
+struct Builder<'a> {
+    tokens: Vec<(usize, RawToken<'a>)>,
+    next:   usize,
+    /// Offset of the parsed text in the input.
+    base:   usize,
+}
+
+impl<'a> Builder<'a> {
+    fn token(&mut self) -> GreenElement {
+        let token = &self.tokens[self.next].1;
+        self.next += 1;
+        GreenToken::new(token.kind, token.text).into()
+    }
+
+    /// Whether `offset` falls between two tokens.
+    fn is_boundary(&self, offset: usize) -> bool {
+        let len = self.tokens.last().map_or(0, |&(start, ref token)| start + token.text.len());
+        offset == len
+            || self.tokens.binary_search_by_key(&offset, |&(start, _)| start).is_ok()
+    }
+
+    /// The offset where the trivia that ends the text `start..end` starts.
+    /// The grammar may let a rule end with whitespace, but nodes end with a
+    /// token of their own.
+    fn trim_end(&self, start: usize, mut end: usize) -> usize {
+        let mut index = match self.tokens.binary_search_by_key(&end, |&(start, _)| start) {
+            Ok(index) => index,
+            Err(_)    => self.tokens.len(),
+        };
+        while index > 0 && end > start && self.tokens[index - 1].1.is_trivia() {
+            index -= 1;
+            end = self.tokens[index].0;
+        }
+        end
+    }
+
+    /// The children of the node for `pair`, which ends at `end`:  the nodes of
+    /// its inner pairs, and the tokens around them.
+    fn children(&mut self, pair: Pair<Rule>, end: usize) -> Vec<GreenElement> {
+        let mut children = vec![];
+        self.inner(pair, &mut children);
+        while self.next < self.tokens.len() && self.tokens[self.next].0 < end {
+            children.push(self.token());
+        }
+        children
+    }
+
+    /// Pushes the nodes of the inner pairs of `pair` to `children`.  Pairs that
+    /// do not start and end on token boundaries are replaced by their own
+    /// inner pairs.
+    fn inner(&mut self, pair: Pair<Rule>, children: &mut Vec<GreenElement>) {
+        for inner in pair.into_inner() {
+            let span = inner.clone().into_span();
+            let (start, end) = (self.base + span.start(), self.base + span.end());
+            if inner.as_rule() == Rule::comment || start == end {
+                continue;
+            }
+            if !self.is_boundary(start) || !self.is_boundary(end) {
+                self.inner(inner, children);
+                continue;
+            }
+            while self.tokens[self.next].0 < start {
+                children.push(self.token());
+            }
+            let kind = inner.as_rule();
+            let end = self.trim_end(start, end);
+            children.push(GreenNode::new(kind, self.children(inner, end)).into());
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::fs;
+
+    /// The kinds of the nodes, in preorder, with their text.
+    fn outline(input: &str) -> Vec<(Rule, String)> {
+        parse(input).unwrap().descendants().into_iter()
+            .map(|node| (node.kind(), node.text()))
+            .collect()
+    }
+
+    #[test]
+    fn keeps_every_token() {
+        let inputs = vec![
+            "",
+            "  \n",
+            "# only a comment",
+            "let s = \"a ${b + 1} c\";  # trailing\n## block ##\nfn f() { s.len() }\n\n",
+            "'outer: for i in 0..=10 { x = (1, 2.5e3).0 -> u8; }",
+        ];
+        for input in inputs {
+            assert_eq!(parse(input).unwrap().text(), input);
+        }
+        for file in &["comment", "modules/main", "modules/geometry/shapes", "modules/util"] {
+            let path = format!("tests/fixtures/{}.ag", file);
+            let input = fs::read_to_string(&path).unwrap();
+            let root = parse(&input).unwrap();
+            assert_eq!(root.text(), input, "{}", path);
+            assert_eq!(root.range(), 0..input.len());
+        }
+    }
+
+    #[test]
+    fn nodes_follow_the_grammar() {
+        let outline = outline("let x = -a;");
+        assert_eq!(outline[0], (Rule::top_lvl, "let x = -a;".to_string()));
+        assert_eq!(outline[1], (Rule::statement, "let x = -a;".to_string()));
+        assert_eq!(outline[2], (Rule::var_dcl, "let x = -a;".to_string()));
+        assert!(outline.contains(&(Rule::pattern, "x".to_string())));
+        assert!(outline.contains(&(Rule::expr, "-a".to_string())));
+        // Strings are single tokens, their pieces get no node.
+        let outline = self::outline("\"a ${b}\";");
+        assert!(outline.iter().all(|&(rule, _)| rule != Rule::interpolation));
+    }
+
+    #[test]
+    fn parses_single_rules() {
+        let node = parse_rule(Rule::statement, "  let x = 1; # one\n").unwrap();
+        assert_eq!(node.kind(), Rule::statement);
+        assert_eq!(node.text(), "  let x = 1; # one\n");
+        assert!(parse_rule(Rule::statement, "let x = 1; let y = 2;").is_err());
+    }
+
+    #[test]
+    fn reports_syntax_errors() {
+        assert!(parse("let = 1;").is_err());
+    }
+}
@@ -0,0 +1,264 @@
+//! Red nodes: green nodes seen from a position in a tree.
+//!
+//! A red node pairs a green node with its parent and its offset in the
+//! source, so that the tree can be walked up as well as down.  Red nodes are
+//! created on demand as the tree is walked; cloning one clones a pointer.
+
+use std::fmt;
+use std::ops::Range;
+use std::rc::Rc;
+
+use syntax::lexer::lossless::RawKind;
+use syntax::parser::Rule;
+
+use super::green::{GreenElement, GreenNode, GreenToken};
+
+/// A node of the tree, with its location.
+#[derive(Clone)]
+pub struct SyntaxNode(Rc<NodeData>);
+
+struct NodeData {
+    green:  Rc<GreenNode>,
+    parent: Option<SyntaxNode>,
+    /// Position among the children of the parent.
+    index:  usize,
+    offset: usize,
+}
+
+impl SyntaxNode {
+    /// The root of the tree made of `green`, starting at offset 0.
+    pub fn new_root(green: Rc<GreenNode>) -> SyntaxNode {
+        SyntaxNode(Rc::new(NodeData { green, parent: None, index: 0, offset: 0 }))
+    }
+
+    pub fn green(&self) -> &Rc<GreenNode> {
+        &self.0.green
+    }
+
+    pub fn kind(&self) -> Rule {
+        self.0.green.kind()
+    }
+
+    /// Byte offsets of the text of the node.
+    pub fn range(&self) -> Range<usize> {
+        self.0.offset..self.0.offset + self.0.green.len()
+    }
+
+    pub fn text(&self) -> String {
+        self.0.green.text()
+    }
+
+    pub fn parent(&self) -> Option<SyntaxNode> {
+        self.0.parent.clone()
+    }
+
+    /// Position of the node among the children of its parent.
+    pub fn index(&self) -> usize {
+        self.0.index
+    }
+
+    /// The node and its ancestors, innermost first.
+    pub fn ancestors(&self) -> Vec<SyntaxNode> {
+        let mut ancestors = vec![self.clone()];
+        while let Some(parent) = ancestors[ancestors.len() - 1].parent() {
+            ancestors.push(parent);
+        }
+        ancestors
+    }
+
+    /// The child nodes and tokens, in order.
+    pub fn children_with_tokens(&self) -> Vec<SyntaxElement> {
+        let mut offset = self.0.offset;
+        let mut children = Vec::with_capacity(self.0.green.children().len());
+        for (index, child) in self.0.green.children().iter().enumerate() {
+            children.push(match *child {
+                GreenElement::Node(ref green) => SyntaxElement::Node(SyntaxNode(Rc::new(NodeData {
+                    green: green.clone(),
+                    parent: Some(self.clone()),
+                    index,
+                    offset,
+                }))),
+                GreenElement::Token(ref green) => SyntaxElement::Token(SyntaxToken {
+                    green: green.clone(),
+                    parent: self.clone(),
+                    index,
+                    offset,
+                }),
+            });
+            offset += child.len();
+        }
+        children
+    }
+
+    /// The child nodes, in order.
+    pub fn children(&self) -> Vec<SyntaxNode> {
+        self.children_with_tokens().into_iter().filter_map(|child| match child {
+            SyntaxElement::Node(node) => Some(node),
+            SyntaxElement::Token(_)   => None,
+        }).collect()
+    }
+
+    /// The node and the nodes below it, in preorder.
+    pub fn descendants(&self) -> Vec<SyntaxNode> {
+        let mut descendants = vec![];
+        let mut stack = vec![self.clone()];
+        while let Some(node) = stack.pop() {
+            stack.extend(node.children().into_iter().rev());
+            descendants.push(node);
+        }
+        descendants
+    }
+
+    /// The tokens below the node, in source order.
+    pub fn tokens(&self) -> Vec<SyntaxToken> {
+        let mut tokens = vec![];
+        for child in self.children_with_tokens() {
+            match child {
+                SyntaxElement::Node(node)   => tokens.extend(node.tokens()),
+                SyntaxElement::Token(token) => tokens.push(token),
+            }
+        }
+        tokens
+    }
+
+    /// The smallest node below this one whose text contains `range`.
+    pub fn covering_node(&self, range: Range<usize>) -> SyntaxNode {
+        let mut node = self.clone();
+        'descend: loop {
+            for child in node.children() {
+                let child_range = child.range();
+                if child_range.start <= range.start && range.end <= child_range.end {
+                    node = child;
+                    continue 'descend;
+                }
+            }
+            return node;
+        }
+    }
+
+    /// The root of a new tree, where `green` stands in for this node.  The
+    /// nodes off the path from this node to the root are shared.
+    pub fn replace_with(&self, green: GreenNode) -> Rc<GreenNode> {
+        let mut green = Rc::new(green);
+        let mut node = self.clone();
+        while let Some(parent) = node.parent() {
+            let child = GreenElement::Node(green);
+            green = Rc::new(parent.green().replace_child(node.index(), child));
+            node = parent;
+        }
+        green
+    }
+}
+
+/// Nodes are equal when they are the same node of the same tree.
+impl PartialEq for SyntaxNode {
+    fn eq(&self, other: &SyntaxNode) -> bool {
+        Rc::ptr_eq(&self.0.green, &other.0.green) && self.0.offset == other.0.offset
+    }
+}
+
+impl fmt::Debug for SyntaxNode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let range = self.range();
+        write!(f, "{:?}@{}..{}", self.kind(), range.start, range.end)
+    }
+}
+
+/// A token of the tree, with its location.
+#[derive(Clone)]
+pub struct SyntaxToken {
+    green:  Rc<GreenToken>,
+    parent: SyntaxNode,
+    index:  usize,
+    offset: usize,
+}
+
+impl SyntaxToken {
+    pub fn green(&self) -> &Rc<GreenToken> {
+        &self.green
+    }
+
+    pub fn kind(&self) -> RawKind {
+        self.green.kind()
+    }
+
+    pub fn text(&self) -> &str {
+        self.green.text()
+    }
+
+    pub fn range(&self) -> Range<usize> {
+        self.offset..self.offset + self.green.len()
+    }
+
+    pub fn parent(&self) -> SyntaxNode {
+        self.parent.clone()
+    }
+
+    /// Position of the token among the children of its parent.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+}
+
+impl fmt::Debug for SyntaxToken {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let range = self.range();
+        write!(f, "{:?}@{}..{} {:?}", self.kind(), range.start, range.end, self.text())
+    }
+}
+
+/// A child of a node.
+#[derive(Clone, Debug)]
+pub enum SyntaxElement {
+    Node(SyntaxNode),
+    Token(SyntaxToken),
+}
+
+impl SyntaxElement {
+    pub fn range(&self) -> Range<usize> {
+        match *self {
+            SyntaxElement::Node(ref node)   => node.range(),
+            SyntaxElement::Token(ref token) => token.range(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use syntax::cst::parse;
+
+    #[test]
+    fn navigation() {
+        let root = parse("let x = 1;\nfn f(a: i32) { a }\n").unwrap();
+        assert_eq!(root.kind(), Rule::top_lvl);
+        assert_eq!(root.range(), 0..30);
+
+        let fn_dcl = root.descendants().into_iter().find(|node| node.kind() == Rule::fn_dcl)
+            .unwrap();
+        assert_eq!(fn_dcl.text(), "fn f(a: i32) { a }");
+        assert_eq!(fn_dcl.range(), 11..29);
+        assert_eq!(fn_dcl.ancestors().last(), Some(&root));
+
+        let tokens = fn_dcl.tokens();
+        assert_eq!(tokens[0].text(), "fn");
+        assert_eq!(tokens[0].range(), 11..13);
+        assert_eq!(tokens[1].kind(), RawKind::Whitespace);
+
+        let a = root.covering_node(26..27);
+        assert_eq!(a.text(), "a");
+        assert!(a.ancestors().iter().any(|node| node.kind() == Rule::code_block));
+        assert_eq!(root.covering_node(9..12), root);
+    }
+
+    #[test]
+    fn replacing_a_node_rebuilds_its_ancestors() {
+        let root = parse("let x = 1;\nlet y = 2;\n").unwrap();
+        let statements = root.children();
+        let replacement = parse("let y = 20;").unwrap().children()[0].green().as_ref().clone();
+        let edited = SyntaxNode::new_root(statements[1].replace_with(replacement));
+        assert_eq!(edited.text(), "let x = 1;\nlet y = 20;\n");
+        assert!(Rc::ptr_eq(edited.children()[0].green(), statements[0].green()));
+        assert_eq!(root.text(), "let x = 1;\nlet y = 2;\n");
+    }
+}
@@ -0,0 +1,149 @@
+//! Incremental reparsing.
+//!
+//! After an edit, only the smallest block or item around it is relexed and
+//! reparsed; the rest of the tree is shared with the previous one.  A block
+//! or item is reparsed on its own when the edit leaves its first and last
+//! tokens alone, so that the tokens around it stay the same, and when the new
+//! text still parses as a whole block or item, which then fits where the old
+//! one was.  Otherwise the next block or item out is tried, and failing all,
+//! the whole text is parsed again.
+
+use std::ops::Range;
+use std::rc::Rc;
+
+use syntax::parser::Rule;
+
+use super::green::GreenNode;
+use super::red::SyntaxNode;
+use super::{parse, parse_rule};
+
+/// The replacement of a range of bytes of the text with another text.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TextEdit {
+    pub range: Range<usize>,
+    pub text:  String,
+}
+
+impl TextEdit {
+    pub fn new<S: Into<String>>(range: Range<usize>, text: S) -> TextEdit {
+        TextEdit { range, text: text.into() }
+    }
+
+    /// The edited `text`.  Panics if the range does not fall on character
+    /// boundaries of `text`.
+    pub fn apply(&self, text: &str) -> String {
+        let mut edited = String::with_capacity(text.len() + self.text.len());
+        edited.push_str(&text[..self.range.start]);
+        edited.push_str(&self.text);
+        edited.push_str(&text[self.range.end..]);
+        edited
+    }
+}
+
+/// The tree of the text of `root` after `edit`, as `parse` would build it.
+/// Syntax errors in the edited text are returned as messages.
+pub fn reparse(root: &SyntaxNode, edit: &TextEdit) -> Result<SyntaxNode, String> {
+    let reparsed = root.covering_node(edit.range.clone()).ancestors().into_iter()
+        .filter(|node| node.kind() == Rule::code_block || node.kind() == Rule::item)
+        .filter_map(|node| reparse_node(&node, edit))
+        .next();
+    match reparsed {
+        Some(green) => Ok(SyntaxNode::new_root(green)),
+        None        => parse(&edit.apply(&root.text())).map_err(|err| err.to_string()),
+    }
+}
+
+/// The new root, if `node` can be reparsed on its own after `edit`.
+fn reparse_node(node: &SyntaxNode, edit: &TextEdit) -> Option<Rc<GreenNode>> {
+    let tokens = node.tokens();
+    let (first, last) = (tokens.first()?, tokens.last()?);
+    if edit.range.start < first.range().end || last.range().start < edit.range.end {
+        return None;
+    }
+    let start = node.range().start;
+    let local = TextEdit::new(edit.range.start - start..edit.range.end - start, edit.text.clone());
+    let green = parse_rule(node.kind(), &local.apply(&node.text())).ok()?;
+    Some(node.replace_with(green))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::fs;
+
+    /// A xorshift generator, so that failures can be replayed.
+    struct Rng(u64);
+
+    impl Rng {
+        fn below(&mut self, n: usize) -> usize {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            (self.0 % n as u64) as usize
+        }
+    }
+
+    const INSERTIONS: [&str; 16] = [
+        "", "x", "1", " ", "\n", ";", "{", "}", "(", ")", "#", "\"", "+ 2",
+        "let y = 2;", "fn g() { }", "if a { b } else { c }",
+    ];
+
+    /// A random edit of `text`, on character boundaries.
+    fn random_edit(rng: &mut Rng, text: &str) -> TextEdit {
+        let boundaries: Vec<usize> = text.char_indices().map(|(index, _)| index)
+            .chain(Some(text.len())).collect();
+        let start = boundaries[rng.below(boundaries.len())];
+        let end = boundaries.iter().cloned()
+            .filter(|&end| start <= end && end <= start + 8)
+            .nth(rng.below(3)).unwrap_or(start);
+        TextEdit::new(start..end, INSERTIONS[rng.below(INSERTIONS.len())])
+    }
+
+    #[test]
+    fn reuses_the_unchanged_statements() {
+        let text = "let a = 1;\nfn f(x: i32) -> i32 {\n    let y = x;\n    y\n}\nlet b = 2;\n";
+        let root = parse(text).unwrap();
+        let offset = text.find("y\n}").unwrap();
+        let edited = reparse(&root, &TextEdit::new(offset..offset + 1, "y + 1")).unwrap();
+        assert_eq!(edited.text(), text.replace("y\n}", "y + 1\n}"));
+        assert_eq!(edited.green(), parse(&edited.text()).unwrap().green());
+
+        let (before, after) = (root.children(), edited.children());
+        assert!(Rc::ptr_eq(before[0].green(), after[0].green()));
+        assert!(!Rc::ptr_eq(before[1].green(), after[1].green()));
+        assert!(Rc::ptr_eq(before[2].green(), after[2].green()));
+    }
+
+    #[test]
+    fn falls_back_to_a_full_parse() {
+        let root = parse("fn f() { 1 }\nlet b = 2;\n").unwrap();
+        // The first token of the item changes.
+        let edited = reparse(&root, &TextEdit::new(0..2, "pub fn")).unwrap();
+        assert_eq!(edited.text(), "pub fn f() { 1 }\nlet b = 2;\n");
+        assert_eq!(edited.green(), parse(&edited.text()).unwrap().green());
+        // Without its closing brace, the block no longer parses.
+        assert!(reparse(&root, &TextEdit::new(11..12, "")).is_err());
+    }
+
+    #[test]
+    fn matches_a_full_parse_after_random_edits() {
+        let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+        for file in &["comment", "modules/main", "modules/geometry/shapes", "modules/util"] {
+            let path = format!("tests/fixtures/{}.ag", file);
+            let mut root = parse(&fs::read_to_string(&path).unwrap()).unwrap();
+            for _ in 0..200 {
+                let edit = random_edit(&mut rng, &root.text());
+                let text = edit.apply(&root.text());
+                match (reparse(&root, &edit), parse(&text)) {
+                    (Ok(incremental), Ok(full)) => {
+                        assert_eq!(incremental.green(), full.green(), "{:?} in {}", edit, path);
+                        root = incremental;
+                    },
+                    (Err(_), Err(_)) => {},
+                    (incremental, full) => panic!("{:?} in {}: {:?} but {:?}",
+                                                  edit, path, incremental.err(), full.err()),
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,166 @@
+//! Typed views over syntax nodes.
+//!
+//! A view wraps a node of a given kind and names its parts, so that code
+//! walking the tree does not have to know in which order the grammar lists
+//! them.  Parts are looked up when asked for, and are optional, as a view
+//! may wrap a tree that was edited by hand.
+
+use syntax::parser::Rule;
+
+use super::red::SyntaxNode;
+
+/// A view over nodes of one kind.
+pub trait AstNode: Sized {
+    /// The view over `node`, if it has the right kind.
+    fn cast(node: SyntaxNode) -> Option<Self>;
+
+    fn syntax(&self) -> &SyntaxNode;
+
+    fn text(&self) -> String {
+        self.syntax().text()
+    }
+}
+
+macro_rules! ast_node {
+    ($(#[$attr:meta])* $name:ident, $rule:ident) => {
+        $(#[$attr])*
+        #[derive(Clone, Debug, PartialEq)]
+        pub struct $name(SyntaxNode);
+
+        impl AstNode for $name {
+            fn cast(node: SyntaxNode) -> Option<$name> {
+                if node.kind() == Rule::$rule { Some($name(node)) } else { None }
+            }
+
+            fn syntax(&self) -> &SyntaxNode {
+                &self.0
+            }
+        }
+    };
+}
+
+ast_node!(/// A whole file.
+          SourceFile, top_lvl);
+ast_node!(Statement, statement);
+ast_node!(/// A module level item, possibly exported.
+          Item, item);
+ast_node!(FnDecl, fn_dcl);
+ast_node!(VarDecl, var_dcl);
+ast_node!(/// A block, with its statements and its trailing expression.
+          CodeBlock, code_block);
+ast_node!(Expr, expr);
+
+/// The first child of `node` that `N` views.
+fn child<N: AstNode>(node: &SyntaxNode) -> Option<N> {
+    node.children().into_iter().filter_map(N::cast).next()
+}
+
+/// The children of `node` that `N` views.
+fn children<N: AstNode>(node: &SyntaxNode) -> Vec<N> {
+    node.children().into_iter().filter_map(N::cast).collect()
+}
+
+/// The text of the first child of `node` of kind `rule`.
+fn child_text(node: &SyntaxNode, rule: Rule) -> Option<String> {
+    node.children().into_iter().find(|child| child.kind() == rule).map(|child| child.text())
+}
+
+impl SourceFile {
+    pub fn statements(&self) -> Vec<Statement> {
+        children(&self.0)
+    }
+}
+
+impl Statement {
+    pub fn item(&self) -> Option<Item> {
+        child(&self.0)
+    }
+
+    pub fn var_decl(&self) -> Option<VarDecl> {
+        child(&self.0)
+    }
+}
+
+impl Item {
+    pub fn is_public(&self) -> bool {
+        child_text(&self.0, Rule::visibility).is_some()
+    }
+
+    pub fn fn_decl(&self) -> Option<FnDecl> {
+        child(&self.0)
+    }
+}
+
+impl FnDecl {
+    pub fn name(&self) -> Option<String> {
+        child_text(&self.0, Rule::ident)
+    }
+
+    /// The parameter declarations, without the parentheses.
+    pub fn params(&self) -> Vec<SyntaxNode> {
+        self.0.children().into_iter()
+            .filter(|child| child.kind() == Rule::fn_call)
+            .flat_map(|call| call.children())
+            .filter(|param| param.kind() == Rule::arg_dcl)
+            .collect()
+    }
+
+    pub fn body(&self) -> Option<CodeBlock> {
+        child(&self.0)
+    }
+}
+
+impl VarDecl {
+    /// The bound pattern, such as `x` or `(a, b)`.
+    pub fn pattern(&self) -> Option<SyntaxNode> {
+        self.0.children().into_iter().find(|child| child.kind() == Rule::pattern)
+    }
+
+    pub fn initializer(&self) -> Option<Expr> {
+        child(&self.0)
+    }
+}
+
+impl CodeBlock {
+    pub fn statements(&self) -> Vec<Statement> {
+        children(&self.0)
+    }
+
+    /// The trailing expression the block evaluates to.
+    pub fn tail(&self) -> Option<Expr> {
+        self.0.children().into_iter()
+            .find(|child| child.kind() == Rule::tail_expr)
+            .and_then(|tail| child(&tail))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use syntax::cst::parse;
+
+    #[test]
+    fn views_name_the_parts() {
+        let input = "let n = 2;\npub fn twice(x: i32) -> i32 {\n    let y = x;\n    y * n\n}\n";
+        let root = parse(input).unwrap();
+        let file = SourceFile::cast(root.clone()).unwrap();
+        assert!(Statement::cast(root).is_none());
+        let statements = file.statements();
+        assert_eq!(statements.len(), 2);
+
+        let var = statements[0].var_decl().unwrap();
+        assert_eq!(var.pattern().unwrap().text(), "n");
+        assert_eq!(var.initializer().unwrap().text(), "2");
+        assert!(statements[0].item().is_none());
+
+        let item = statements[1].item().unwrap();
+        assert!(item.is_public());
+        let decl = item.fn_decl().unwrap();
+        assert_eq!(decl.name(), Some("twice".to_string()));
+        let params: Vec<_> = decl.params().iter().map(|param| param.text()).collect();
+        assert_eq!(params, vec!["x: i32"]);
+        let body = decl.body().unwrap();
+        assert_eq!(body.statements().len(), 1);
+        assert_eq!(body.tail().unwrap().text(), "y * n");
+    }
+}
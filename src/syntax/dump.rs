@@ -0,0 +1,603 @@
+//! Dumps of the syntax tree, for debugging the parser and golden tests.
+//!
+//! Every node is written with its kind, the span it covers and its fields in
+//! declaration order.  Operators are named after the variants of their enums
+//! in `binop.rs` and `unop.rs`, i.e. `Add` or `Minus`.  Spans are written as
+//! start row, start column, end row and end column.
+//!
+//! JSON nodes are objects whose `kind` and `span` come first:
+//!
+//! ```text
+//! {"kind": "Identifier", "span": [1, 1, 1, 2], "name": "a"}
+//! ```
+//!
+//! S-expressions leave the field names out:
+//!
+//! ```text
+//! (Binary@1:1-1:6 Add (Identifier@1:1-1:2 "a") (Literal@1:5-1:6 Int 1))
+//! ```
+
+use std::fmt::Write;
+
+use syntax::ast::{BinaryOp, Block, Expr, ExprKind, Fragment, Item, ItemKind, Literal, Module,
+                  Param, Pattern, PatternKind, Stmt, StmtKind, Type, TypeKind, TypeParam};
+use utils::{Position, Span};
+
+/// Renders a module as an indented JSON document.
+pub fn module_to_json(module: &Module) -> String {
+    let mut out = String::new();
+    write_json(&mut out, &lower_module(module), 0);
+    out.push('\n');
+    out
+}
+
+/// Renders a module as an S-expression, one statement per line.
+pub fn module_to_sexp(module: &Module) -> String {
+    let mut out = String::from("(Module");
+    for stmt in module.stmts {
+        out.push_str("\n  ");
+        write_sexp(&mut out, &lower_stmt(stmt));
+    }
+    out.push_str(")\n");
+    out
+}
+
+/// Renders a single expression as an indented JSON document.
+pub fn expr_to_json(expr: &Expr) -> String {
+    let mut out = String::new();
+    write_json(&mut out, &lower_expr(expr), 0);
+    out
+}
+
+/// Renders a single expression as an S-expression on one line.
+pub fn expr_to_sexp(expr: &Expr) -> String {
+    let mut out = String::new();
+    write_sexp(&mut out, &lower_expr(expr));
+    out
+}
+
+/// The shape shared by both formats.
+enum Tree {
+    /// Kind, span, when the node has one, and named fields.
+    Node(&'static str, Option<Span>, Vec<(&'static str, Tree)>),
+    List(Vec<Tree>),
+    Str(String),
+    /// Name of an enum variant, such as an operator.
+    Sym(String),
+    Int(i128),
+    Float(f64),
+    Bool(bool),
+    Null,
+}
+
+impl Tree {
+    fn is_scalar(&self) -> bool {
+        !matches!(*self, Tree::Node(..) | Tree::List(_))
+    }
+}
+
+fn node(kind: &'static str, span: Span, fields: Vec<(&'static str, Tree)>) -> Tree {
+    Tree::Node(kind, Some(span), fields)
+}
+
+fn string<S: Into<String>>(text: S) -> Tree {
+    Tree::Str(text.into())
+}
+
+fn optional<T, F: FnOnce(&T) -> Tree>(value: &Option<T>, lower: F) -> Tree {
+    value.as_ref().map_or(Tree::Null, lower)
+}
+
+fn list<T, F: FnMut(&T) -> Tree>(values: &[T], lower: F) -> Tree {
+    Tree::List(values.iter().map(lower).collect())
+}
+
+fn lower_module(module: &Module) -> Tree {
+    Tree::Node("Module", None, vec![("stmts", list(module.stmts, lower_stmt))])
+}
+
+fn lower_stmt(stmt: &Stmt) -> Tree {
+    let label = |label: &Option<String>| optional(label, |label| string(label.clone()));
+    let span = stmt.span;
+    match stmt.kind {
+        StmtKind::Item(ref item) => lower_item(item),
+        StmtKind::Let(ref local) => node("Let", span, vec![
+            ("pat", lower_pat(&local.pat)),
+            ("ty", optional(&local.ty, lower_type)),
+            ("init", optional(&local.init, lower_expr)),
+        ]),
+        StmtKind::Expr(ref expr) => node("Expr", span, vec![("expr", lower_expr(expr))]),
+        StmtKind::Return(ref value) => {
+            node("Return", span, vec![("value", optional(value, lower_expr))])
+        },
+        StmtKind::For(ref for_loop) => node("For", span, vec![
+            ("label", label(&for_loop.label)),
+            ("pat", lower_pat(&for_loop.pat)),
+            ("iter", lower_expr(&for_loop.iter)),
+            ("body", lower_block(&for_loop.body)),
+        ]),
+        StmtKind::While(ref while_loop) => node("While", span, vec![
+            ("label", label(&while_loop.label)),
+            ("cond", lower_expr(&while_loop.cond)),
+            ("body", lower_block(&while_loop.body)),
+        ]),
+        StmtKind::Break(ref name, ref value) => node("Break", span, vec![
+            ("label", label(name)),
+            ("value", optional(value, lower_expr)),
+        ]),
+        StmtKind::Continue(ref name) => node("Continue", span, vec![("label", label(name))]),
+        StmtKind::Print(ref print) => node("Print", span, vec![
+            ("newline", Tree::Bool(print.newline)),
+            ("format", optional(&print.format, |format| string(format.to_string()))),
+            ("args", list(print.args, lower_expr)),
+        ]),
+    }
+}
+
+fn lower_item(item: &Item) -> Tree {
+    let public = ("pub", Tree::Bool(item.is_pub()));
+    let span = item.span;
+    match item.kind {
+        ItemKind::Use(ref decl) => {
+            node("Use", span, vec![public, ("path", string(decl.path.to_string()))])
+        },
+        ItemKind::Const(ref decl) => node("Const", span, vec![
+            public,
+            ("name", string(decl.name.clone())),
+            ("ty", lower_type(&decl.ty)),
+            ("value", lower_expr(&decl.value)),
+        ]),
+        ItemKind::Type(ref decl) => node("Type", span, vec![
+            public,
+            ("name", string(decl.name.clone())),
+            ("ty", lower_type(&decl.ty)),
+        ]),
+        ItemKind::Struct(ref decl) => node("Struct", span, vec![
+            public,
+            ("name", string(decl.name.clone())),
+            ("generics", list(decl.generics, lower_type_param)),
+            ("fields", list(decl.fields, |field| node("Field", field.span, vec![
+                ("name", string(field.name.clone())),
+                ("ty", lower_type(&field.ty)),
+            ]))),
+        ]),
+        ItemKind::Spec(ref decl) => node("Spec", span, vec![
+            public,
+            ("name", string(decl.name.clone())),
+            ("generics", list(decl.generics, lower_type_param)),
+            ("variants", list(decl.variants, |variant| node("Variant", variant.span, vec![
+                ("name", string(variant.name.clone())),
+                ("fields", list(variant.fields, lower_type)),
+            ]))),
+        ]),
+        ItemKind::Fn(ref decl) => node("Fn", span, vec![
+            public,
+            ("name", string(decl.name.clone())),
+            ("generics", list(decl.generics, lower_type_param)),
+            ("params", list(decl.params, lower_param)),
+            ("ret", optional(&decl.ret, lower_type)),
+            ("body", lower_block(&decl.body)),
+        ]),
+    }
+}
+
+fn lower_type_param(param: &TypeParam) -> Tree {
+    Tree::Node("TypeParam", None, vec![
+        ("name", string(param.name.clone())),
+        ("bounds", list(&param.bounds, |bound| string(bound.clone()))),
+    ])
+}
+
+fn lower_param(param: &Param) -> Tree {
+    Tree::Node("Param", None, vec![
+        ("pat", lower_pat(&param.pat)),
+        ("ty", optional(&param.ty, lower_type)),
+    ])
+}
+
+fn lower_block(block: &Block) -> Tree {
+    Tree::Node("Block", None, vec![
+        ("stmts", list(block.stmts, lower_stmt)),
+        ("expr", optional(&block.expr, |expr| lower_expr(expr))),
+    ])
+}
+
+fn lower_pat(pat: &Pattern) -> Tree {
+    match pat.kind {
+        PatternKind::Ident(ref name) => {
+            node("Ident", pat.span, vec![("name", string(name.clone()))])
+        },
+        PatternKind::Tuple(pats) => {
+            node("Tuple", pat.span, vec![("elems", list(pats, lower_pat))])
+        },
+        PatternKind::Variant(ref path, pats) => node("Variant", pat.span, vec![
+            ("path", string(path.to_string())),
+            ("elems", list(pats, lower_pat)),
+        ]),
+    }
+}
+
+fn lower_type(ty: &Type) -> Tree {
+    let span = ty.span;
+    match ty.kind {
+        TypeKind::Prim(prim) => node("Prim", span, vec![("prim", Tree::Sym(prim.to_string()))]),
+        TypeKind::Fn(params, ret) => node("Fn", span, vec![
+            ("params", list(params, lower_type)),
+            ("ret", lower_type(ret)),
+        ]),
+        TypeKind::Array(elem) => node("Array", span, vec![("elem", lower_type(elem))]),
+        TypeKind::Map(key, value) => node("Map", span, vec![
+            ("key", lower_type(key)),
+            ("value", lower_type(value)),
+        ]),
+        TypeKind::Tuple(elems) => node("Tuple", span, vec![("elems", list(elems, lower_type))]),
+        TypeKind::Optional(inner) => node("Optional", span, vec![("ty", lower_type(inner))]),
+        TypeKind::Named(ref name, args) => node("Named", span, vec![
+            ("name", string(name.clone())),
+            ("args", list(args, lower_type)),
+        ]),
+    }
+}
+
+/// The variant name of a binary operator, without its family.
+fn binary_op(op: BinaryOp) -> Tree {
+    Tree::Sym(match op {
+        BinaryOp::Arithmetic(op) => format!("{:?}", op),
+        BinaryOp::BitWise(op)    => format!("{:?}", op),
+        BinaryOp::Comparison(op) => format!("{:?}", op),
+        BinaryOp::Logical(op)    => format!("{:?}", op),
+    })
+}
+
+fn lower_literal(lit: &Literal) -> Vec<(&'static str, Tree)> {
+    let (kind, value) = match *lit {
+        Literal::Nil               => ("Nil", Tree::Null),
+        Literal::Boolean(value)    => ("Boolean", Tree::Bool(value)),
+        Literal::Char(value)       => ("Char", string(value.to_string())),
+        Literal::Int(value)        => ("Int", Tree::Int(value)),
+        Literal::Float(value)      => ("Float", Tree::Float(value)),
+        Literal::Str(ref value)    => ("Str", string(value.clone())),
+    };
+    vec![("type", Tree::Sym(kind.to_string())), ("value", value)]
+}
+
+fn lower_expr(expr: &Expr) -> Tree {
+    let span = expr.span;
+    match expr.kind {
+        ExprKind::Literal(ref lit) => node("Literal", span, lower_literal(lit)),
+        ExprKind::Interpolated(fragments) => {
+            let fragments = list(fragments, |fragment| match *fragment {
+                Fragment::Str(ref text, span) => {
+                    node("Str", span, vec![("text", string(text.clone()))])
+                },
+                Fragment::Expr(ref expr, span) => {
+                    node("Expr", span, vec![("expr", lower_expr(expr))])
+                },
+            });
+            node("Interpolated", span, vec![("fragments", fragments)])
+        },
+        ExprKind::Identifier(ref name) => {
+            node("Identifier", span, vec![("name", string(name.clone()))])
+        },
+        ExprKind::Path(ref path) => node("Path", span, vec![("path", string(path.to_string()))]),
+        ExprKind::Unary(op, operand) => node("Unary", span, vec![
+            ("op", Tree::Sym(format!("{:?}", op))),
+            ("operand", lower_expr(operand)),
+        ]),
+        ExprKind::Binary(op, lhs, rhs) => node("Binary", span, vec![
+            ("op", binary_op(op)),
+            ("lhs", lower_expr(lhs)),
+            ("rhs", lower_expr(rhs)),
+        ]),
+        ExprKind::Cast(operand, prim) => node("Cast", span, vec![
+            ("expr", lower_expr(operand)),
+            ("prim", Tree::Sym(prim.to_string())),
+        ]),
+        ExprKind::Call(callee, args) => node("Call", span, vec![
+            ("callee", lower_expr(callee)),
+            ("args", list(args, lower_expr)),
+        ]),
+        ExprKind::MethodCall(receiver, ref name, args) => node("MethodCall", span, vec![
+            ("receiver", lower_expr(receiver)),
+            ("method", string(name.clone())),
+            ("args", list(args, lower_expr)),
+        ]),
+        ExprKind::SafeMethodCall(receiver, ref name, args) => {
+            node("SafeMethodCall", span, vec![
+                ("receiver", lower_expr(receiver)),
+                ("method", string(name.clone())),
+                ("args", list(args, lower_expr)),
+            ])
+        },
+        ExprKind::Assign(op, place, value) => node("Assign", span, vec![
+            ("op", Tree::Sym(format!("{:?}", op))),
+            ("place", lower_expr(place)),
+            ("value", lower_expr(value)),
+        ]),
+        ExprKind::Range(start, end, limits) => node("Range", span, vec![
+            ("start", lower_expr(start)),
+            ("end", lower_expr(end)),
+            ("limits", Tree::Sym(format!("{:?}", limits))),
+        ]),
+        ExprKind::Array(elems) => node("Array", span, vec![("elems", list(elems, lower_expr))]),
+        ExprKind::Map(entries) => {
+            let entries = list(entries, |(key, value)| Tree::Node("Entry", None, vec![
+                ("key", lower_expr(key)),
+                ("value", lower_expr(value)),
+            ]));
+            node("Map", span, vec![("entries", entries)])
+        },
+        ExprKind::Index(base, index) => node("Index", span, vec![
+            ("expr", lower_expr(base)),
+            ("index", lower_expr(index)),
+        ]),
+        ExprKind::Tuple(elems) => node("Tuple", span, vec![("elems", list(elems, lower_expr))]),
+        ExprKind::Field(base, index) => node("Field", span, vec![
+            ("expr", lower_expr(base)),
+            ("index", Tree::Int(index as i128)),
+        ]),
+        ExprKind::Member(base, ref name) => node("Member", span, vec![
+            ("expr", lower_expr(base)),
+            ("name", string(name.clone())),
+        ]),
+        ExprKind::Struct(ref name, fields) => {
+            let fields = list(fields, |(field, value)| Tree::Node("FieldInit", None, vec![
+                ("name", string(field.clone())),
+                ("value", lower_expr(value)),
+            ]));
+            node("Struct", span, vec![("name", string(name.clone())), ("fields", fields)])
+        },
+        ExprKind::Closure(closure) => node("Closure", span, vec![
+            ("params", list(closure.params, lower_param)),
+            ("ret", optional(&closure.ret, lower_type)),
+            ("body", lower_block(&closure.body)),
+        ]),
+        ExprKind::Ternary(cond, then, other) => node("Ternary", span, vec![
+            ("cond", lower_expr(cond)),
+            ("then", lower_expr(then)),
+            ("else", lower_expr(other)),
+        ]),
+        ExprKind::If(cond, ref then, ref other) => node("If", span, vec![
+            ("cond", lower_expr(cond)),
+            ("then", lower_block(then)),
+            ("else", optional(other, |expr| lower_expr(expr))),
+        ]),
+        ExprKind::Match(scrutinee, arms) => node("Match", span, vec![
+            ("expr", lower_expr(scrutinee)),
+            ("arms", list(arms, |arm| node("Arm", arm.span, vec![
+                ("pat", lower_pat(&arm.pat)),
+                ("body", lower_expr(&arm.body)),
+            ]))),
+        ]),
+        ExprKind::Loop(ref label, ref body) => node("Loop", span, vec![
+            ("label", optional(label, |label| string(label.clone()))),
+            ("body", lower_block(body)),
+        ]),
+        ExprKind::Block(ref block) => node("Block", span, vec![("block", lower_block(block))]),
+    }
+}
+
+/// Writes `text` as a quoted string with JSON escapes, which S-expressions
+/// share.
+fn write_str(out: &mut String, text: &str) {
+    out.push('"');
+    for chr in text.chars() {
+        match chr {
+            '"'  => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            '\r' => out.push_str("\\r"),
+            chr if chr.is_control() => {
+                let _ = write!(out, "\\u{:04x}", chr as u32);
+            },
+            chr => out.push(chr),
+        }
+    }
+    out.push('"');
+}
+
+fn write_float(out: &mut String, value: f64) {
+    let _ = write!(out, "{:?}", value);
+}
+
+fn write_json(out: &mut String, tree: &Tree, indent: usize) {
+    let pad = |out: &mut String, indent: usize| {
+        for _ in 0..indent {
+            out.push_str("  ");
+        }
+    };
+    match *tree {
+        Tree::Node(kind, span, ref fields) => {
+            out.push_str("{\n");
+            pad(out, indent + 1);
+            out.push_str("\"kind\": ");
+            write_str(out, kind);
+            if let Some(Span(Position(start_row, start_col), Position(end_row, end_col))) = span {
+                out.push_str(",\n");
+                pad(out, indent + 1);
+                let _ = write!(out, "\"span\": [{}, {}, {}, {}]",
+                               start_row, start_col, end_row, end_col);
+            }
+            for &(name, ref value) in fields {
+                out.push_str(",\n");
+                pad(out, indent + 1);
+                write_str(out, name);
+                out.push_str(": ");
+                write_json(out, value, indent + 1);
+            }
+            out.push('\n');
+            pad(out, indent);
+            out.push('}');
+        },
+        Tree::List(ref items) if items.iter().all(Tree::is_scalar) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push_str(", ");
+                }
+                write_json(out, item, indent);
+            }
+            out.push(']');
+        },
+        Tree::List(ref items) => {
+            out.push_str("[\n");
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push_str(",\n");
+                }
+                pad(out, indent + 1);
+                write_json(out, item, indent + 1);
+            }
+            out.push('\n');
+            pad(out, indent);
+            out.push(']');
+        },
+        Tree::Str(ref text) | Tree::Sym(ref text) => write_str(out, text),
+        Tree::Int(value) => {
+            let _ = write!(out, "{}", value);
+        },
+        // JSON has no infinities nor NaN.
+        Tree::Float(value) if !value.is_finite() => out.push_str("null"),
+        Tree::Float(value) => write_float(out, value),
+        Tree::Bool(value) => {
+            let _ = write!(out, "{}", value);
+        },
+        Tree::Null => out.push_str("null"),
+    }
+}
+
+fn write_sexp(out: &mut String, tree: &Tree) {
+    match *tree {
+        Tree::Node(kind, span, ref fields) => {
+            out.push('(');
+            out.push_str(kind);
+            if let Some(Span(Position(start_row, start_col), Position(end_row, end_col))) = span {
+                let _ = write!(out, "@{}:{}-{}:{}", start_row, start_col, end_row, end_col);
+            }
+            for (_, value) in fields {
+                out.push(' ');
+                write_sexp(out, value);
+            }
+            out.push(')');
+        },
+        Tree::List(ref items) => {
+            out.push('(');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(' ');
+                }
+                write_sexp(out, item);
+            }
+            out.push(')');
+        },
+        Tree::Str(ref text) => write_str(out, text),
+        Tree::Sym(ref name) => out.push_str(name),
+        Tree::Int(value) => {
+            let _ = write!(out, "{}", value);
+        },
+        Tree::Float(value) => write_float(out, value),
+        Tree::Bool(value) => {
+            let _ = write!(out, "{}", value);
+        },
+        Tree::Null => out.push_str("nil"),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use syntax::ast::Arena;
+    use syntax::parser::parse_module;
+
+    /// Parses the expression `source` into an arena that is never freed.
+    fn expr(source: &str) -> Expr<'static> {
+        let arena: &'static Arena<'static> = Box::leak(Box::default());
+        let module = parse_module(arena, &format!("{};", source)).unwrap();
+        match module.stmts[0].kind {
+            StmtKind::Expr(ref expr) => expr.clone(),
+            ref kind                 => panic!("not an expression: {:?}", kind),
+        }
+    }
+
+    #[test]
+    fn sexp() {
+        assert_eq!(expr_to_sexp(&expr("-a + 1")),
+                   "(Binary@1:1-1:7 Add (Unary@1:1-1:3 Minus (Identifier@1:2-1:3 \"a\")) \
+                    (Literal@1:6-1:7 Int 1))");
+        assert_eq!(expr_to_sexp(&expr("x.f(2.5, \"a\\n\")")),
+                   "(MethodCall@1:1-1:16 (Identifier@1:1-1:2 \"x\") \"f\" \
+                    ((Literal@1:5-1:8 Float 2.5) (Literal@1:10-1:15 Str \"a\\n\")))");
+        let arena = Arena::new();
+        let source = "let (a, b): (i32, bool?) = (1, a != 2);\nb >> 1;";
+        let module = parse_module(&arena, source).unwrap();
+        assert_eq!(module_to_sexp(&module), "\
+            (Module\n  \
+              (Let@1:1-1:40 (Tuple@1:5-1:11 ((Ident@1:6-1:7 \"a\") (Ident@1:9-1:10 \"b\"))) \
+                (Tuple@1:13-1:25 ((Prim@1:14-1:17 i32) \
+                  (Optional@1:19-1:24 (Prim@1:19-1:23 bool)))) \
+                (Tuple@1:28-1:39 ((Literal@1:29-1:30 Int 1) (Binary@1:32-1:38 NotEql \
+                  (Identifier@1:32-1:33 \"a\") (Literal@1:37-1:38 Int 2)))))\n  \
+              (Expr@2:1-2:8 (Binary@2:1-2:7 BwShftR (Identifier@2:1-2:2 \"b\") \
+                (Literal@2:6-2:7 Int 1))))\n");
+    }
+
+    #[test]
+    fn json() {
+        assert_eq!(expr_to_json(&expr("f(!a, [])")), r#"{
+  "kind": "Call",
+  "span": [1, 1, 1, 10],
+  "callee": {
+    "kind": "Identifier",
+    "span": [1, 1, 1, 2],
+    "name": "f"
+  },
+  "args": [
+    {
+      "kind": "Unary",
+      "span": [1, 3, 1, 5],
+      "op": "Not",
+      "operand": {
+        "kind": "Identifier",
+        "span": [1, 4, 1, 5],
+        "name": "a"
+      }
+    },
+    {
+      "kind": "Array",
+      "span": [1, 7, 1, 9],
+      "elems": []
+    }
+  ]
+}"#);
+        let arena = Arena::new();
+        let module = parse_module(&arena, "fn f() { }").unwrap();
+        assert_eq!(module_to_json(&module), r#"{
+  "kind": "Module",
+  "stmts": [
+    {
+      "kind": "Fn",
+      "span": [1, 1, 1, 11],
+      "pub": false,
+      "name": "f",
+      "generics": [],
+      "params": [],
+      "ret": null,
+      "body": {
+        "kind": "Block",
+        "stmts": [],
+        "expr": null
+      }
+    }
+  ]
+}
+"#);
+    }
+
+    #[test]
+    fn escapes() {
+        let mut out = String::new();
+        write_str(&mut out, "\"q\" \\ \t \u{7} é");
+        assert_eq!(out, r#""\"q\" \\ \t \u0007 é""#);
+    }
+}
@@ -0,0 +1,249 @@
+//! Layout documents and their rendering to a line width.
+//!
+//! A document is text with the places where lines may break.  Groups are
+//! laid out on one line when they fit in the remaining width, and broken at
+//! each of their `Line`s otherwise, in the manner of Wadler's prettier
+//! printer.  Hard breaks, such as those after statements, do not force their
+//! groups to break: a group fits when its text up to the first line break
+//! does.
+
+use super::Config;
+
+#[derive(Clone, Debug)]
+pub enum Doc {
+    Text(String),
+    /// A space, dropped at the start of a line.
+    Space,
+    /// Breaks in a broken group, indented by `extra` more levels, and renders
+    /// as `flat` otherwise.
+    Line { flat: &'static str, extra: usize },
+    /// Always breaks.
+    HardLine,
+    /// Leaves an empty line, unless at the start of the output.
+    BlankLine,
+    /// Indents its lines one level.
+    Nest(Vec<Doc>),
+    /// Indents its lines one level when the enclosing group is broken.
+    BreakNest(Vec<Doc>),
+    Concat(Vec<Doc>),
+    /// Breaks all its lines or none.  A forced group always breaks.
+    Group { docs: Vec<Doc>, forced: bool },
+}
+
+impl Doc {
+    pub fn text<S: Into<String>>(text: S) -> Doc {
+        Doc::Text(text.into())
+    }
+
+    /// A space, or a line break in a broken group.
+    pub fn line() -> Doc {
+        Doc::Line { flat: " ", extra: 0 }
+    }
+
+    /// Nothing, or a line break in a broken group.
+    pub fn soft_line() -> Doc {
+        Doc::Line { flat: "", extra: 0 }
+    }
+
+    pub fn group(docs: Vec<Doc>) -> Doc {
+        Doc::Group { docs, forced: false }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Mode {
+    Flat,
+    Break,
+}
+
+type Command<'d> = (usize, Mode, &'d Doc);
+
+/// Lays out `doc` in `config.width` columns.  The output has no trailing
+/// whitespace and ends with a single line break, unless it is empty.
+pub fn render(doc: &Doc, config: &Config) -> String {
+    let mut printer = Printer {
+        out:           String::new(),
+        width:         config.width,
+        indent_width:  config.indent,
+        col:           0,
+        indent:        0,
+        at_line_start: true,
+    };
+    let mut commands: Vec<Command> = vec![(0, Mode::Break, doc)];
+    while let Some((indent, mode, doc)) = commands.pop() {
+        match *doc {
+            Doc::Text(ref text) => printer.text(text),
+            Doc::Space => {
+                if !printer.at_line_start {
+                    printer.text(" ");
+                }
+            },
+            Doc::Line { flat, extra } => match mode {
+                Mode::Flat  => {
+                    if !printer.at_line_start {
+                        printer.text(flat);
+                    }
+                },
+                Mode::Break => printer.newline(indent + extra),
+            },
+            Doc::HardLine => printer.newline(indent),
+            Doc::BlankLine => printer.blank_line(indent),
+            Doc::Nest(ref docs) => push(&mut commands, indent + 1, mode, docs),
+            Doc::BreakNest(ref docs) => {
+                let indent = if mode == Mode::Break { indent + 1 } else { indent };
+                push(&mut commands, indent, mode, docs)
+            },
+            Doc::Concat(ref docs) => push(&mut commands, indent, mode, docs),
+            Doc::Group { ref docs, forced } => {
+                let mode = if forced {
+                    Mode::Break
+                } else if printer.fits(docs, &commands) {
+                    Mode::Flat
+                } else {
+                    Mode::Break
+                };
+                push(&mut commands, indent, mode, docs)
+            },
+        }
+    }
+    let len = printer.out.trim_end().len();
+    printer.out.truncate(len);
+    if !printer.out.is_empty() {
+        printer.out.push('\n');
+    }
+    printer.out
+}
+
+fn push<'d>(commands: &mut Vec<Command<'d>>, indent: usize, mode: Mode, docs: &'d [Doc]) {
+    commands.extend(docs.iter().rev().map(|doc| (indent, mode, doc)));
+}
+
+struct Printer {
+    out:           String,
+    width:         usize,
+    indent_width:  usize,
+    /// Column where the next text goes.
+    col:           usize,
+    /// Indentation of the current line, written along with its first text.
+    indent:        usize,
+    at_line_start: bool,
+}
+
+impl Printer {
+    fn text(&mut self, text: &str) {
+        if self.at_line_start {
+            for _ in 0..self.indent * self.indent_width {
+                self.out.push(' ');
+            }
+            self.at_line_start = false;
+        }
+        self.out.push_str(text);
+        self.col = match text.rfind('\n') {
+            Some(newline) => text[newline + 1..].chars().count(),
+            None          => self.col + text.chars().count(),
+        };
+    }
+
+    /// Starts a line indented `indent` levels.  Consecutive breaks make a
+    /// single one.
+    fn newline(&mut self, indent: usize) {
+        if !self.at_line_start {
+            let len = self.out.trim_end_matches(' ').len();
+            self.out.truncate(len);
+            self.out.push('\n');
+            self.at_line_start = true;
+        }
+        self.indent = indent;
+        self.col = indent * self.indent_width;
+    }
+
+    fn blank_line(&mut self, indent: usize) {
+        if self.out.is_empty() {
+            return;
+        }
+        self.newline(indent);
+        if !self.out.ends_with("\n\n") {
+            self.out.push('\n');
+        }
+    }
+
+    /// Whether `docs` laid out flat, followed by the pending `rest`, fit on
+    /// the current line up to its next break.
+    fn fits(&self, docs: &[Doc], rest: &[Command]) -> bool {
+        let mut remaining = self.width as isize - self.col as isize;
+        let mut stack: Vec<(Mode, &Doc)> = docs.iter().rev().map(|doc| (Mode::Flat, doc)).collect();
+        let mut rest = rest.iter().rev();
+        loop {
+            let (mode, doc) = match stack.pop() {
+                Some(command) => command,
+                None          => match rest.next() {
+                    Some(&(_, mode, doc)) => (mode, doc),
+                    None                  => return true,
+                },
+            };
+            match *doc {
+                Doc::Text(ref text) => {
+                    let first_line = text.split('\n').next().unwrap_or("");
+                    remaining -= first_line.chars().count() as isize;
+                    if text.contains('\n') {
+                        return remaining >= 0;
+                    }
+                },
+                Doc::Space => remaining -= 1,
+                Doc::Line { flat, .. } => match mode {
+                    Mode::Flat  => remaining -= flat.len() as isize,
+                    Mode::Break => return remaining >= 0,
+                },
+                Doc::HardLine | Doc::BlankLine => return remaining >= 0,
+                Doc::Nest(ref docs) | Doc::BreakNest(ref docs) | Doc::Concat(ref docs) => {
+                    stack.extend(docs.iter().rev().map(|doc| (mode, doc)));
+                },
+                Doc::Group { ref docs, forced } => {
+                    let mode = if forced { Mode::Break } else { mode };
+                    stack.extend(docs.iter().rev().map(|doc| (mode, doc)));
+                },
+            }
+            if remaining < 0 {
+                return false;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn call(args: &[&str]) -> Doc {
+        let mut inner = vec![Doc::soft_line()];
+        for (i, arg) in args.iter().enumerate() {
+            if i > 0 {
+                inner.push(Doc::text(","));
+                inner.push(Doc::line());
+            }
+            inner.push(Doc::text(*arg));
+        }
+        Doc::group(vec![
+            Doc::text("f("), Doc::BreakNest(inner), Doc::soft_line(), Doc::text(");"),
+        ])
+    }
+
+    #[test]
+    fn groups_break_when_too_wide() {
+        let config = Config { width: 16, indent: 2 };
+        assert_eq!(render(&call(&["a", "b"]), &config), "f(a, b);\n");
+        assert_eq!(render(&call(&["first", "second"]), &config), "f(\n  first,\n  second\n);\n");
+    }
+
+    #[test]
+    fn hard_lines_and_blank_lines() {
+        let config = Config::default();
+        let doc = Doc::Concat(vec![
+            Doc::BlankLine, Doc::text("{"),
+            Doc::Nest(vec![Doc::HardLine, Doc::text("a;"), Doc::HardLine, Doc::BlankLine,
+                           Doc::BlankLine, Doc::text("b;"), Doc::Space]),
+            Doc::HardLine, Doc::HardLine, Doc::text("}"),
+        ]);
+        assert_eq!(render(&doc, &config), "{\n    a;\n\n    b;\n}\n");
+    }
+}
@@ -0,0 +1,761 @@
+//! Canonical formatting of source files.
+//!
+//! The formatter works on the lossless token stream rather than on the
+//! syntax tree, so that comments stay where they were written.  Tokens are
+//! laid out statement by statement: spacing around operators, brackets and
+//! braces is normalized, blocks are indented, and expressions that do not fit
+//! in the configured width are broken before their binary operators and
+//! between the items of their brackets.  Single blank lines between
+//! statements are kept; everything else about the original layout is not.
+
+mod doc;
+
+use std::mem;
+
+use syntax::lexer::lossless::{self, RawKind};
+use syntax::parser;
+
+use self::doc::Doc;
+
+/// Layout options.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Config {
+    /// Column past which lines are broken, when possible.
+    pub width:  usize,
+    /// Spaces per indentation level.
+    pub indent: usize,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config { width: 100, indent: 4 }
+    }
+}
+
+/// Formats `text`, read from `source`.  Fails with the rendered parse error
+/// when the text does not parse.
+pub fn format_source(source: &str, text: &str, config: &Config) -> Result<String, String> {
+    if let Err(err) = parser::parse(text) {
+        return Err(format!("{}: {}", source, err));
+    }
+    let raw = lossless::tokenize(source, text).map_err(|err| err.to_string())?;
+    let (toks, end) = attach_comments(&raw);
+    let mut builder = Builder { toks, pos: 0, comments: 0 };
+    let doc = builder.module(end);
+    Ok(doc::render(&doc, config))
+}
+
+/// Whether `text` is already formatted.
+pub fn is_formatted(source: &str, text: &str, config: &Config) -> Result<bool, String> {
+    format_source(source, text, config).map(|formatted| formatted == text)
+}
+
+/// A comment, attached to the token that it precedes or follows.
+#[derive(Clone, Debug)]
+struct Comment<'a> {
+    text:          &'a str,
+    block:         bool,
+    /// Whether an empty line separates it from what comes before.
+    blank_before:  bool,
+    /// Whether a line break follows it in the source.
+    newline_after: bool,
+}
+
+/// A token other than whitespace and comments.
+#[derive(Clone, Debug)]
+struct Tok<'a> {
+    kind:         RawKind,
+    text:         &'a str,
+    blank_before: bool,
+    /// Comments on the lines above the token.
+    leading:      Vec<Comment<'a>>,
+    /// Comments after the token, on the same line.
+    trailing:     Vec<Comment<'a>>,
+}
+
+/// Drops whitespace, attaching each comment to a token.  Comments after the
+/// last token are returned separately.
+fn attach_comments<'a>(raw: &[lossless::RawToken<'a>]) -> (Vec<Tok<'a>>, Vec<Comment<'a>>) {
+    let mut toks: Vec<Tok> = Vec::new();
+    let mut pending = Vec::new();
+    let mut newlines = 0;
+    let mut after_trailing = false;
+    for token in raw {
+        match token.kind {
+            RawKind::Whitespace => {
+                let count = token.text.matches('\n').count();
+                if count > 0 && after_trailing {
+                    if let Some(comment) = toks.last_mut().and_then(|tok| tok.trailing.last_mut()) {
+                        comment.newline_after = true;
+                    }
+                }
+                newlines += count;
+            },
+            RawKind::LineComment | RawKind::BlockComment => {
+                let comment = Comment {
+                    text:          token.text.trim_end(),
+                    block:         token.kind == RawKind::BlockComment,
+                    blank_before:  newlines > 1,
+                    newline_after: false,
+                };
+                after_trailing = newlines == 0 && pending.is_empty() && !toks.is_empty();
+                match toks.last_mut() {
+                    Some(ref mut tok) if after_trailing => tok.trailing.push(comment),
+                    _                                   => pending.push(comment),
+                }
+                newlines = 0;
+            },
+            _ => {
+                toks.push(Tok {
+                    kind:         token.kind,
+                    text:         token.text,
+                    blank_before: newlines > 1,
+                    leading:      mem::take(&mut pending),
+                    trailing:     Vec::new(),
+                });
+                newlines = 0;
+                after_trailing = false;
+            },
+        }
+    }
+    (toks, pending)
+}
+
+const KEYWORDS: [&str; 20] = [
+    "break", "const", "continue", "else", "fn", "for", "if", "in", "let", "loop", "match",
+    "print", "println", "pub", "return", "spec", "struct", "type", "use", "while",
+];
+
+const BINARY: [&str; 18] = [
+    "+", "-", "*", "/", "%", "^", "&", "|", "&&", "||", "==", "!=", "<", "<=", ">", ">=", "<<",
+    ">>",
+];
+
+/// What a token is to its neighbours, as far as spacing goes.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Role {
+    Operand,
+    Keyword,
+    Fn,
+    Open,
+    Close,
+    Brace,
+    Unary,
+    Binary,
+    Assign,
+    /// `.`  `?.`  `..`  `..=`
+    Tight,
+    Path,
+    OpenPipe,
+    ClosePipe,
+    Comma,
+    Semi,
+    /// The colon of a type, map entry or label.
+    Colon,
+    Ternary,
+    TernaryColon,
+    /// The `?` of an optional type.
+    Optional,
+    GenericOpen,
+    GenericClose,
+    Other,
+}
+
+/// What separates two tokens on a line.
+enum Sep {
+    Nothing,
+    Space,
+    /// A space where the line may break.
+    Line,
+}
+
+fn separator(prev: Option<Role>, role: Role) -> Sep {
+    let prev = match prev {
+        Some(prev) => prev,
+        None       => return Sep::Nothing,
+    };
+    match prev {
+        Role::Open | Role::Unary | Role::Tight | Role::Path | Role::OpenPipe
+            | Role::GenericOpen => return Sep::Nothing,
+        _ => {},
+    }
+    match role {
+        Role::Comma | Role::Semi | Role::Close | Role::Tight | Role::Colon | Role::Optional
+            | Role::GenericOpen | Role::GenericClose | Role::ClosePipe => return Sep::Nothing,
+        _ => {},
+    }
+    if prev == Role::Assign {
+        return Sep::Line;
+    }
+    match role {
+        Role::Open => match prev {
+            Role::Operand | Role::Close | Role::GenericClose | Role::Fn => Sep::Nothing,
+            _ => Sep::Space,
+        },
+        Role::Path if prev == Role::Operand => Sep::Nothing,
+        Role::Binary | Role::Ternary | Role::TernaryColon => Sep::Line,
+        _ => Sep::Space,
+    }
+}
+
+/// Spacing state of a statement or of a bracket item.
+#[derive(Default)]
+struct Level {
+    prev:      Option<Role>,
+    before:    Option<Role>,
+    ternaries: usize,
+    pipe:      bool,
+    /// Depth of the type parameters or arguments around the token.
+    generics:  usize,
+    /// Whether the next brace opens the body of an item.
+    body:      bool,
+    /// Whether the next brace opens a list of fields, variants or arms.
+    list:      bool,
+}
+
+impl Level {
+    fn after_operand(&self) -> bool {
+        matches!(self.prev, Some(Role::Operand) | Some(Role::Close) | Some(Role::GenericClose))
+    }
+}
+
+/// How a statement ends.
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum StmtKind {
+    /// With its first block:  `for` and `while`.
+    Block,
+    /// With a block that nothing continues:  `if` and `loop`.
+    BlockExpr,
+    /// With its body:  functions, structs and specs.
+    Item,
+    Other,
+}
+
+struct Stmt {
+    docs:   Vec<Doc>,
+    blank:  bool,
+    /// Whether the statement has no blocks, so it may share a line with the
+    /// braces around it.
+    simple: bool,
+}
+
+struct Builder<'a> {
+    toks:     Vec<Tok<'a>>,
+    pos:      usize,
+    /// Comments laid out so far, to force open the groups holding them.
+    comments: usize,
+}
+
+impl<'a> Builder<'a> {
+    fn text(&self, i: usize) -> &'a str {
+        self.toks.get(i).map_or("", |tok| tok.text)
+    }
+
+    fn module(&mut self, end: Vec<Comment>) -> Doc {
+        let stmts = self.statements();
+        let mut docs = vec![];
+        let empty = stmts.is_empty();
+        join(&mut docs, stmts);
+        for (n, comment) in end.iter().enumerate() {
+            let blank = comment.blank_before && (n > 0 || !empty);
+            docs.push(if blank { Doc::BlankLine } else { Doc::HardLine });
+            docs.push(Doc::text(comment.text));
+        }
+        Doc::Concat(docs)
+    }
+
+    /// Statements up to the closing brace of the block or the end of input.
+    fn statements(&mut self) -> Vec<Stmt> {
+        let mut stmts = vec![];
+        while self.pos < self.toks.len() && self.text(self.pos) != "}" {
+            stmts.push(self.statement());
+        }
+        stmts
+    }
+
+    fn kind(&self) -> StmtKind {
+        let mut i = self.pos;
+        if self.toks[i].kind == RawKind::Label && self.text(i + 1) == ":" {
+            i += 2;
+        }
+        if self.text(i) == "pub" {
+            i += 1;
+        }
+        match self.text(i) {
+            "fn" if self.toks.get(i + 1).is_some_and(|tok| tok.kind == RawKind::Word) => {
+                StmtKind::Item
+            },
+            "struct" | "spec"        => StmtKind::Item,
+            "for" | "while"          => StmtKind::Block,
+            "if" | "loop" | "match"  => StmtKind::BlockExpr,
+            _                        => StmtKind::Other,
+        }
+    }
+
+    /// Whether a statement of `kind` ends with the block just closed.
+    fn ends_after_block(&self, kind: StmtKind) -> bool {
+        match kind {
+            StmtKind::Block | StmtKind::Item => true,
+            StmtKind::BlockExpr => {
+                let next = self.text(self.pos);
+                !(next == "else" || next == "?" || next == "?." || next == "=" || next == "."
+                    || BINARY.contains(&next))
+            },
+            StmtKind::Other => false,
+        }
+    }
+
+    /// One statement, grouped so that it breaks after its first token when
+    /// it does not fit.  In statements made of blocks, such as `if`, the
+    /// blocks stand apart from the groups of the lines that open them.
+    fn statement(&mut self) -> Stmt {
+        let first = self.pos;
+        let blank = {
+            let tok = &self.toks[first];
+            tok.leading.first().map_or(tok.blank_before, |comment| comment.blank_before)
+        };
+        let kind = self.kind();
+        let mut docs = self.leading(first, true);
+        let mut saved = self.comments;
+        let mut level = Level { body: kind == StmtKind::Item, ..Level::default() };
+        let mut group = vec![];
+        let mut head = None;
+        let mut simple = true;
+        let mut last = None;
+        while self.pos < self.toks.len() && self.text(self.pos) != "}" {
+            let i = self.pos;
+            if self.text(i) == ";" {
+                group.extend(self.leading(i, false));
+                group.push(Doc::text(";"));
+                self.pos += 1;
+                last = Some(i);
+                break;
+            }
+            let closed = self.token(&mut level, &mut group);
+            head = head.or(Some(group.len()));
+            if let Some(close) = closed {
+                simple = false;
+                if kind != StmtKind::Other {
+                    let block = group.pop();
+                    let line = mem::take(&mut group);
+                    docs.push(self.group(line, head, saved));
+                    docs.extend(block);
+                    head = None;
+                }
+                if self.ends_after_block(kind) {
+                    last = Some(close);
+                    break;
+                }
+                saved = self.comments;
+                group.extend(self.trailing(close));
+            }
+        }
+        if !group.is_empty() {
+            docs.push(self.group(group, head, saved));
+        }
+        if let Some(last) = last {
+            docs.extend(self.trailing(last));
+        }
+        Stmt { docs, blank, simple }
+    }
+
+    /// Groups the line of a statement that starts with `docs[..head]`,
+    /// indenting the rest when the line breaks.  The group is forced open by
+    /// comments laid out since `saved`.
+    fn group(&self, mut docs: Vec<Doc>, head: Option<usize>, saved: usize) -> Doc {
+        let rest = docs.split_off(head.unwrap_or(docs.len()));
+        if rest.iter().any(|doc| matches!(*doc, Doc::Line { .. })) {
+            docs.push(Doc::BreakNest(rest));
+        } else {
+            docs.extend(rest);
+        }
+        Doc::Group { docs, forced: self.comments != saved }
+    }
+
+    /// Lays out the token at the current position, and the brackets or
+    /// block that it opens.  Returns the index of the closing brace of a
+    /// block, whose trailing comments end the statement when the block does.
+    fn token(&mut self, level: &mut Level, docs: &mut Vec<Doc>) -> Option<usize> {
+        let i = self.pos;
+        let role = self.role(level, i);
+        docs.extend(self.leading(i, false));
+        match separator(level.prev, role) {
+            Sep::Nothing => {},
+            Sep::Space   => docs.push(Doc::Space),
+            Sep::Line    => docs.push(Doc::line()),
+        }
+        match role {
+            Role::Ternary      => level.ternaries += 1,
+            Role::TernaryColon => level.ternaries -= 1,
+            Role::OpenPipe     => level.pipe = true,
+            Role::ClosePipe    => level.pipe = false,
+            Role::GenericOpen  => level.generics += 1,
+            Role::GenericClose => level.generics -= self.toks[i].text.len(),
+            Role::Keyword      => {
+                level.list = matches!(self.toks[i].text, "struct" | "spec" | "match");
+            },
+            _                  => {},
+        }
+        level.before = level.prev;
+        level.prev = Some(role);
+        match role {
+            Role::Open => {
+                docs.push(self.bracket(false));
+                level.prev = Some(Role::Close);
+                None
+            },
+            Role::Brace if level.list || self.struct_lit(level, i) => {
+                docs.push(self.bracket(level.body));
+                level.body = false;
+                level.list = false;
+                level.prev = Some(Role::Close);
+                Some(self.pos - 1)
+            },
+            Role::Brace => {
+                docs.push(self.block(level.body));
+                level.body = false;
+                level.prev = Some(Role::Close);
+                Some(self.pos - 1)
+            },
+            _ => {
+                docs.push(Doc::text(self.toks[i].text));
+                self.pos += 1;
+                docs.extend(self.trailing(i));
+                None
+            },
+        }
+    }
+
+    fn role(&self, level: &Level, i: usize) -> Role {
+        let tok = &self.toks[i];
+        match tok.kind {
+            RawKind::Word if tok.text == "fn" => return Role::Fn,
+            RawKind::Word if KEYWORDS.contains(&tok.text) => return Role::Keyword,
+            RawKind::Punct => {},
+            _ => return Role::Operand,
+        }
+        let unary = !level.after_operand();
+        match tok.text {
+            "(" | "[" => Role::Open,
+            ")" | "]" | "}" => Role::Close,
+            "{" => Role::Brace,
+            "," => Role::Comma,
+            ";" => Role::Semi,
+            "." | "?." | ".." | "..=" => Role::Tight,
+            "::" => Role::Path,
+            "=" => Role::Assign,
+            "->" | "=>" => Role::Other,
+            "?" => match self.text(i + 1) {
+                ")" | "," | "]" | "=" | ";" | "{" | ":" | "|" | "" => Role::Optional,
+                _ => Role::Ternary,
+            },
+            ":" if level.ternaries > 0 => Role::TernaryColon,
+            ":" => Role::Colon,
+            "<" if self.generic(level, i) => Role::GenericOpen,
+            ">" if level.generics > 0 => Role::GenericClose,
+            ">>" if level.generics > 1 => Role::GenericClose,
+            "+" if level.generics > 0 => Role::Other,
+            "|" if level.pipe => Role::ClosePipe,
+            "|" if unary => Role::OpenPipe,
+            "||" if unary => Role::Other,
+            "-" | "!" if unary => Role::Unary,
+            _ => Role::Binary,
+        }
+    }
+
+    /// Whether the `<` at `i` opens the type parameters of an item, or the
+    /// type arguments of a name such as `Option`.
+    fn generic(&self, level: &Level, i: usize) -> bool {
+        if level.prev != Some(Role::Operand) {
+            return false;
+        }
+        let name = self.text(i - 1);
+        let camel = name.starts_with(|c: char| c.is_ascii_uppercase())
+            && name.contains(|c: char| c.is_ascii_lowercase());
+        camel || level.before == Some(Role::Fn)
+            || i > 1 && matches!(self.text(i - 2), "struct" | "spec")
+    }
+
+    /// Whether the brace at `i` opens a struct literal:  `Pair { first: 1 }`.
+    fn struct_lit(&self, level: &Level, i: usize) -> bool {
+        level.prev == Some(Role::Operand) && self.toks[i - 1].kind == RawKind::Word
+            && self.toks.get(i + 1).is_some_and(|tok| tok.kind == RawKind::Word)
+            && self.text(i + 2) == ":"
+    }
+
+    /// A parenthesized, bracketed or braced list, broken between its items
+    /// when it does not fit or when `forced`.  Braced lists holding blocks,
+    /// such as the arms of a `match`, are always broken.
+    fn bracket(&mut self, mut forced: bool) -> Doc {
+        let open = self.pos;
+        self.pos += 1;
+        let saved = self.comments;
+        let brace = self.text(open) == "{";
+        let line = if brace { Doc::line } else { Doc::soft_line };
+        let mut inner = vec![line()];
+        inner.extend(self.trailing(open));
+        let mut level = Level::default();
+        let mut item = vec![];
+        loop {
+            let i = self.pos;
+            match self.text(i) {
+                ")" | "]" | "}" | "" => break,
+                "," if !level.pipe => {
+                    inner.push(Doc::group(mem::take(&mut item)));
+                    inner.extend(self.leading(i, false));
+                    inner.push(Doc::text(","));
+                    self.pos += 1;
+                    inner.extend(self.trailing(i));
+                    match self.text(self.pos) {
+                        ")" | "]" | "}" => {},
+                        _         => inner.push(Doc::line()),
+                    }
+                    level = Level::default();
+                },
+                _ => if let Some(close) = self.token(&mut level, &mut item) {
+                    forced |= brace;
+                    item.extend(self.trailing(close));
+                },
+            }
+        }
+        if !item.is_empty() {
+            inner.push(Doc::group(item));
+        }
+        let close = self.pos;
+        inner.extend(self.closing(close, false));
+        self.pos += 1;
+        let forced = forced || self.comments != saved;
+        self.comments = saved;
+        let (open, close) = (self.text(open), self.text(close));
+        if inner.len() == 1 {
+            return Doc::text(format!("{}{}", open, close));
+        }
+        Doc::Group {
+            docs: vec![Doc::text(open), Doc::BreakNest(inner), line(), Doc::text(close)],
+            forced,
+        }
+    }
+
+    /// A block, kept on one line when it holds a single simple statement that
+    /// fits.  Function bodies always take several lines.  The comments after
+    /// the closing brace are left to the caller.
+    fn block(&mut self, body: bool) -> Doc {
+        let open = self.pos;
+        self.pos += 1;
+        let saved = self.comments;
+        let open_trailing = self.trailing(open);
+        let stmts = self.statements();
+        let close = self.pos;
+        let closing = self.closing(close, !stmts.is_empty());
+        self.pos += 1;
+        let commented = self.comments != saved;
+        self.comments = saved;
+
+        if stmts.is_empty() && !commented {
+            return Doc::text("{ }");
+        }
+        if !body && !commented && stmts.len() == 1 && stmts[0].simple {
+            let mut inner = vec![Doc::line()];
+            join(&mut inner, stmts);
+            let docs = vec![Doc::text("{"), Doc::Nest(inner), Doc::line(), Doc::text("}")];
+            return Doc::group(docs);
+        }
+        let mut inner = vec![Doc::HardLine];
+        join(&mut inner, stmts);
+        inner.extend(closing);
+        let mut docs = vec![Doc::text("{")];
+        docs.extend(open_trailing);
+        docs.extend(vec![Doc::Nest(inner), Doc::HardLine, Doc::text("}")]);
+        Doc::Concat(docs)
+    }
+
+    /// The comments on the lines above token `i`, each on its own line.  At
+    /// the start of a statement, `blanks` keeps the empty lines among them.
+    fn leading(&mut self, i: usize, blanks: bool) -> Vec<Doc> {
+        let comments = mem::take(&mut self.toks[i].leading);
+        let mut docs = vec![];
+        for (n, comment) in comments.iter().enumerate() {
+            self.comments += 1;
+            let blank = blanks && n > 0 && comment.blank_before;
+            docs.push(if blank { Doc::BlankLine } else { Doc::HardLine });
+            docs.push(Doc::text(comment.text));
+            docs.push(Doc::HardLine);
+        }
+        if blanks && !comments.is_empty() && self.toks[i].blank_before {
+            docs.push(Doc::BlankLine);
+        }
+        docs
+    }
+
+    /// The comments above the closing bracket or brace `i`, laid out with the
+    /// lines that they close.
+    fn closing(&mut self, i: usize, blanks: bool) -> Vec<Doc> {
+        let comments = mem::take(&mut self.toks[i].leading);
+        let mut docs = vec![];
+        for comment in &comments {
+            self.comments += 1;
+            let blank = blanks && comment.blank_before;
+            docs.push(if blank { Doc::BlankLine } else { Doc::HardLine });
+            docs.push(Doc::text(comment.text));
+        }
+        docs
+    }
+
+    /// The comments after token `i` on its line.  A line comment, or one that
+    /// was followed by a line break, ends the line.
+    fn trailing(&mut self, i: usize) -> Vec<Doc> {
+        let comments = mem::take(&mut self.toks[i].trailing);
+        let mut docs = vec![];
+        for comment in &comments {
+            self.comments += 1;
+            docs.push(Doc::Space);
+            docs.push(Doc::text(comment.text));
+            if !comment.block || comment.newline_after {
+                docs.push(Doc::HardLine);
+            }
+        }
+        docs
+    }
+}
+
+/// Appends `stmts`, one per line.
+fn join(docs: &mut Vec<Doc>, stmts: Vec<Stmt>) {
+    for (n, stmt) in stmts.into_iter().enumerate() {
+        if n > 0 {
+            docs.push(if stmt.blank { Doc::BlankLine } else { Doc::HardLine });
+        }
+        docs.extend(stmt.docs);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn format(text: &str) -> String {
+        format_source("test", text, &Config::default()).unwrap()
+    }
+
+    fn narrow(text: &str, width: usize) -> String {
+        format_source("test", text, &Config { width, indent: 4 }).unwrap()
+    }
+
+    /// The text of the tokens other than whitespace.
+    fn tokens(text: &str) -> Vec<String> {
+        lossless::tokenize("test", text).unwrap().into_iter()
+            .filter(|token| token.kind != RawKind::Whitespace)
+            .map(|token| token.text.trim_end().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn spacing() {
+        assert_eq!(format("let   x=1+2*-y ;"), "let x = 1 + 2 * -y;\n");
+        assert_eq!(format("fn max < T:Ord+Show,U > ( a:T , b : U )->T{a>b?a:b}"),
+                   "fn max<T: Ord + Show, U>(a: T, b: U) -> T {\n    a > b ? a : b\n}\n");
+        assert_eq!(format("let f=| a , b:i32 |a+b ;let o:i32 ?=x ?. y ( 0..= 2 ) [ 1 ];"),
+                   "let f = |a, b: i32| a + b;\nlet o: i32? = x?.y(0..=2)[1];\n");
+        assert_eq!(format("let m = [ : ] ; let n = [ \"a\" : ( 1 , ) , 'b' : ! c ];"),
+                   "let m = [:];\nlet n = [\"a\": (1,), 'b': !c];\n");
+        assert_eq!(format("x = ::std::io::read ( ) -> u8 ; use ::a::b ;"),
+                   "x = ::std::io::read() -> u8;\nuse ::a::b;\n");
+        assert_eq!(format("return -1 ; print  ; println \"{}\" ,x ;"),
+                   "return -1;\nprint;\nprintln \"{}\", x;\n");
+    }
+
+    #[test]
+    fn blocks() {
+        assert_eq!(format("'outer:for i in 0..10{if i==2{continue 'outer;}else{break;}}"),
+                   "'outer: for i in 0..10 {\n    \
+                        if i == 2 { continue 'outer; } else { break; }\n\
+                    }\n");
+        assert_eq!(format("loop{}\nwhile x{let y=1;y}"),
+                   "loop { }\nwhile x {\n    let y = 1;\n    y\n}\n");
+        assert_eq!(format("let f = fn(a: i32) -> i32 {\n a * 2\n};\nif a { b } else { c } + 1;"),
+                   "let f = fn(a: i32) -> i32 { a * 2 };\nif a { b } else { c } + 1;\n");
+        assert_eq!(format("fn f() { 1 }\n\n\n\nfn g() {}"), "fn f() {\n    1\n}\n\nfn g() { }\n");
+    }
+
+    #[test]
+    fn structs_and_specs() {
+        assert_eq!(format("struct Pair<T>{first:T,second:T}spec Tree<T>{Leaf,Node(Pair<Tree<T>>)}"),
+                   "struct Pair<T> {\n    first: T,\n    second: T\n}\n\
+                    spec Tree<T> {\n    Leaf,\n    Node(Pair<Tree<T>>)\n}\n");
+        assert_eq!(format("let p:Pair<i32> =Pair{first:a<b?1:2,second:2};"),
+                   "let p: Pair<i32> = Pair { first: a < b ? 1 : 2, second: 2 };\n");
+        assert_eq!(format("match s{A::B(x)=>x,_y=>{f();0},}"),
+                   "match s {\n    A::B(x) => x,\n    _y => {\n        f();\n        0\n    \
+                    },\n}\n");
+    }
+
+    #[test]
+    fn comments() {
+        let text = "\
+            # header\n\
+            \n\
+            let x = 1; # one\n\
+            \n\
+            \n\
+            # before\n\
+            fn f(a: i32, ## inline ## b: i32) {\n\
+            # inside\n\
+            a + # plus\n\
+            b\n\
+            # last\n\
+            }\n\
+            ## end ##\n";
+        assert_eq!(format(text), "\
+            # header\n\
+            \n\
+            let x = 1; # one\n\
+            \n\
+            # before\n\
+            fn f(\n    \
+                a: i32, ## inline ##\n    \
+                b: i32\n\
+            ) {\n    \
+                # inside\n    \
+                a\n        \
+                + # plus\n        \
+                b\n    \
+                # last\n\
+            }\n\
+            ## end ##\n");
+        assert_eq!(tokens(text), tokens(&format(text)));
+    }
+
+    #[test]
+    fn reflow() {
+        let text = "let total = first_value + second_value * third_value;";
+        assert_eq!(narrow(text, 40),
+                   "let total =\n    first_value\n    + second_value\n    * third_value;\n");
+        let text = "call(first_argument, second_argument, [1, 2, 3]);";
+        assert_eq!(narrow(text, 30),
+                   "call(\n    first_argument,\n    second_argument,\n    [1, 2, 3]\n);\n");
+        assert_eq!(narrow(text, 60), format!("{}\n", text));
+    }
+
+    #[test]
+    fn idempotent() {
+        let text = include_str!("../../../tests/fixtures/comment.ag");
+        let once = format(text);
+        assert_eq!(format(&once), once);
+        assert_eq!(tokens(text), tokens(&once));
+        let text = "fn long(argument: i32, another: [str: i32?], third: (i32, f64)) -> i32 { \
+                    let value = argument * another.len() - third.0 -> i32 + helper(argument); \
+                    value > 0 ? value : -value }";
+        for &width in &[20, 40, 60, 100] {
+            let once = narrow(text, width);
+            assert_eq!(narrow(&once, width), once);
+            assert_eq!(tokens(text), tokens(&once));
+        }
+    }
+
+    #[test]
+    fn check() {
+        let config = Config::default();
+        assert_eq!(is_formatted("test", "let x = 1;\n", &config), Ok(true));
+        assert_eq!(is_formatted("test", "let x=1;", &config), Ok(false));
+        assert!(format_source("test", "let = ;", &config).unwrap_err().starts_with("test: "));
+    }
+}
@@ -0,0 +1,322 @@
+//! Lossless tokenization, for tools that rewrite source instead of running it.
+//!
+//! Unlike the `Lexer`, which decodes literals and skips comments, every
+//! character of the input belongs to exactly one `RawToken`, whitespace and
+//! comments included, so that joining the text of the tokens gives back the
+//! source.  Literals are kept as written; the parser is expected to have
+//! validated them.
+
+use error;
+use utils::{LexicalDiagnostic, Position, Severity, Span};
+
+/// The kind of text a `RawToken` covers.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum RawKind {
+	/// Spaces, tabs, form feeds and line breaks.
+	Whitespace,
+	/// `#` up to the end of the line, the line break excluded.
+	LineComment,
+	/// `##` up to the next `##`, possibly over several lines.
+	BlockComment,
+	/// Identifier, keyword or primitive type name.
+	Word,
+	/// Integer or float literal:  1_000  0xFF  2.5e3
+	Number,
+	/// String literal of any form:  "a ${b}"  r#"c"#  """d"""
+	Str,
+	/// Character literal:  'c'  '\n'
+	Char,
+	/// Loop label:  'outer
+	Label,
+	/// Operator or punctuation, the longest that matches:  ..=  ->  ;
+	Punct,
+}
+
+/// A slice of the source with its kind and location.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RawToken<'a> {
+	pub kind: RawKind,
+	pub text: &'a str,
+	pub span: Span,
+}
+
+impl<'a> RawToken<'a> {
+	/// Whether the token is whitespace or a comment.
+	pub fn is_trivia(&self) -> bool {
+		matches!(self.kind, RawKind::Whitespace | RawKind::LineComment | RawKind::BlockComment)
+	}
+}
+
+/// Operators and punctuation, longer ones first.
+const PUNCTUATION: [&str; 37] = [
+	"..=", "::", "->", "=>", "..", "?.", "==", "!=", "<=", ">=", "&&", "||", "<<", ">>",
+	"(", ")", "{", "}", "[", "]", ",", ";", ":", ".", "?",
+	"+", "-", "*", "/", "%", "^", "&", "|", "!", "=", "<", ">",
+];
+
+/// Splits `stream` into tokens.  `source` names the input in diagnostics.
+pub fn tokenize<'a>(source: &str, stream: &'a str)
+	-> Result<Vec<RawToken<'a>>, LexicalDiagnostic> {
+	let mut scanner = Scanner {
+		source: source.to_string(),
+		stream,
+		offset: 0,
+		pos:    Position(1, 1),
+	};
+	let mut tokens = Vec::new();
+	while scanner.offset < stream.len() {
+		tokens.push(scanner.token()?);
+	}
+	Ok(tokens)
+}
+
+struct Scanner<'a> {
+	source: String,
+	stream: &'a str,
+	offset: usize,
+	pos:    Position,
+}
+
+impl<'a> Scanner<'a> {
+	fn token(&mut self) -> Result<RawToken<'a>, LexicalDiagnostic> {
+		let rest = &self.stream[self.offset..];
+		let chr = rest.chars().next().expect("the stream is not exhausted");
+		let (kind, len) = match chr {
+			chr if is_space(chr) => {
+				(RawKind::Whitespace, rest.find(|chr| !is_space(chr)).unwrap_or(rest.len()))
+			},
+			'#' if rest.starts_with("##") => match rest[2..].find("##") {
+				Some(end) => (RawKind::BlockComment, end + 4),
+				None      => {
+					return self.err(error::LexerErrorKind::UnterminatedBlockComment, rest)
+				},
+			},
+			'#' => {
+				let len = rest.find(['\n', '\r']).unwrap_or(rest.len());
+				(RawKind::LineComment, len)
+			},
+			'r' if rest[1..].starts_with(['"', '#']) => {
+				match raw_string_len(rest) {
+					Ok(len)   => (RawKind::Str, len),
+					Err(kind) => return self.err(kind, rest),
+				}
+			},
+			chr if chr.is_alphabetic() || chr == '_' => (RawKind::Word, word_len(rest)),
+			chr if chr.is_ascii_digit() => (RawKind::Number, number_len(rest)),
+			'"' => match string_len(rest) {
+				Ok(len)   => (RawKind::Str, len),
+				Err(kind) => return self.err(kind, rest),
+			},
+			'\'' => match char_or_label(rest) {
+				Some(token) => token,
+				None        => return self.err(error::LexerErrorKind::UnknownChar, &rest[..1]),
+			},
+			_ => match PUNCTUATION.iter().find(|punct| rest.starts_with(*punct)) {
+				Some(punct) => (RawKind::Punct, punct.len()),
+				None        => {
+					return self.err(error::LexerErrorKind::UnknownChar, &rest[..chr.len_utf8()])
+				},
+			},
+		};
+		let text = &rest[..len];
+		let start = self.pos;
+		self.advance(text);
+		Ok(RawToken { kind, text, span: Span(start, self.pos) })
+	}
+
+	/// Moves past `text`, counting its lines and columns.
+	fn advance(&mut self, text: &str) {
+		for chr in text.chars() {
+			if chr == '\n' {
+				self.pos = Position(self.pos.0 + 1, 1);
+			} else {
+				self.pos.1 += 1;
+			}
+		}
+		self.offset += text.len();
+	}
+
+	/// Reports an error over `text`, which starts at the current position.
+	fn err<T>(&mut self, kind: error::LexerErrorKind, text: &str) -> Result<T, LexicalDiagnostic> {
+		let start = self.pos;
+		self.advance(text);
+		Err(From::from(error::LexerError {
+			source:   self.source.clone(),
+			span:     Span(start, self.pos),
+			severity: Severity::Error,
+			kind,
+		}))
+	}
+}
+
+fn is_space(chr: char) -> bool {
+	chr == ' ' || chr == '\t' || chr == '\u{000C}' || chr == '\n' || chr == '\r'
+}
+
+fn is_word_char(chr: char) -> bool {
+	chr.is_alphanumeric() || chr == '_'
+}
+
+fn word_len(text: &str) -> usize {
+	text.find(|chr| !is_word_char(chr)).unwrap_or(text.len())
+}
+
+/// Length of the number `text` starts with.  A dot continues the number
+/// unless it starts a range or a method call:  1.5  1.  0..n  a.0
+fn number_len(text: &str) -> usize {
+	let mut len = word_len(text);
+	let mut chars = text[len..].chars();
+	if chars.next() == Some('.') {
+		match chars.next() {
+			Some(chr) if chr == '.' || chr.is_alphabetic() || chr == '_' => return len,
+			_ => len += 1 + word_len(&text[len + 1..]),
+		}
+		// A signed exponent:  2.5e-3
+		let rest = &text[len..];
+		if text[..len].ends_with(['e', 'E']) &&
+			(rest.starts_with('-') || rest.starts_with('+')) &&
+			rest[1..].starts_with(|chr: char| chr.is_ascii_digit()) {
+			len += 1 + word_len(&rest[1..]);
+		}
+	}
+	len
+}
+
+/// Length of the raw string `text` starts with:  r"a"  r#"b"#
+fn raw_string_len(text: &str) -> Result<usize, error::LexerErrorKind> {
+	let hashes = text[1..].find(|chr| chr != '#').unwrap_or(text.len() - 1);
+	if !text[1 + hashes..].starts_with('"') {
+		return Err(error::LexerErrorKind::InvalidRawStringDelimiter);
+	}
+	let body = 2 + hashes;
+	let end = format!("\"{}", &text[1..1 + hashes]);
+	text[body..].find(&end)
+		.map(|len| body + len + end.len())
+		.ok_or(error::LexerErrorKind::UnterminatedStringLiteral)
+}
+
+/// Length of the string `text` starts with, multi-line strings and embedded
+/// expressions included.
+fn string_len(text: &str) -> Result<usize, error::LexerErrorKind> {
+	let unterminated = error::LexerErrorKind::UnterminatedStringLiteral;
+	if text.starts_with("\"\"\"") {
+		let mut offset = 3;
+		while offset < text.len() {
+			let rest = &text[offset..];
+			if rest.starts_with("\"\"\"") {
+				return Ok(offset + 3);
+			}
+			offset += escape_or_char_len(rest);
+		}
+		return Err(unterminated);
+	}
+	let mut offset = 1;
+	while offset < text.len() {
+		let rest = &text[offset..];
+		if rest.starts_with('"') {
+			return Ok(offset + 1);
+		}
+		offset += if rest.starts_with("${") {
+			interpolation_len(rest)?
+		} else {
+			escape_or_char_len(rest)
+		};
+	}
+	Err(unterminated)
+}
+
+/// Length of the `${ ... }` that `text` starts with.
+fn interpolation_len(text: &str) -> Result<usize, error::LexerErrorKind> {
+	let mut depth = 0;
+	let mut offset = 2;
+	while offset < text.len() {
+		let rest = &text[offset..];
+		let chr = rest.chars().next().expect("offset is within the text");
+		offset += match chr {
+			'{' => { depth += 1; 1 },
+			'}' if depth == 0 => return Ok(offset + 1),
+			'}' => { depth -= 1; 1 },
+			'"' => string_len(rest)?,
+			'\'' => char_or_label(rest).map_or(1, |(_, len)| len),
+			chr => chr.len_utf8(),
+		};
+	}
+	Err(error::LexerErrorKind::UnterminatedInterpolation)
+}
+
+/// Length of the escape sequence or the character `text` starts with.  The
+/// digits of numeric escapes are ordinary characters.
+fn escape_or_char_len(text: &str) -> usize {
+	let mut chars = text.chars();
+	match chars.next() {
+		Some('\\') => 1 + chars.next().map_or(0, |chr| chr.len_utf8()),
+		Some(chr)  => chr.len_utf8(),
+		None       => 0,
+	}
+}
+
+/// The char literal or the label `text` starts with.
+fn char_or_label(text: &str) -> Option<(RawKind, usize)> {
+	let body = &text[1..];
+	if body.starts_with('\\') {
+		let len = escape_or_char_len(body);
+		return body[len..].find('\'').map(|end| (RawKind::Char, 1 + len + end + 1));
+	}
+	let chr = body.chars().next()?;
+	if body[chr.len_utf8()..].starts_with('\'') {
+		return Some((RawKind::Char, 1 + chr.len_utf8() + 1));
+	}
+	match word_len(body) {
+		0   => None,
+		len => Some((RawKind::Label, 1 + len)),
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	fn kinds(src: &str) -> Vec<(RawKind, &str)> {
+		tokenize("test", src).unwrap().into_iter()
+			.filter(|token| token.kind != RawKind::Whitespace)
+			.map(|token| (token.kind, token.text))
+			.collect()
+	}
+
+	#[test]
+	fn joins_back_into_the_source() {
+		let src = "## block\n comment ##\nlet s = \"a ${f(\"}\", '}')} \\\" b\"; # trailing\r\n\
+		           'outer: for x in 0..=10 { t.0 = r#\"raw \"q\"\"# ++ \"\"\"\n  \"\"\"; }\n";
+		let tokens = tokenize("test", src).unwrap();
+		assert_eq!(tokens.iter().map(|token| token.text).collect::<String>(), src);
+		assert_eq!(tokens[2].span, Span(Position(3, 1), Position(3, 4)));
+	}
+
+	#[test]
+	fn token_kinds() {
+		assert_eq!(kinds("x = 1.5e-3 - 2..a.0 -> u8; # c"), vec![
+			(RawKind::Word, "x"), (RawKind::Punct, "="), (RawKind::Number, "1.5e-3"),
+			(RawKind::Punct, "-"), (RawKind::Number, "2"), (RawKind::Punct, ".."),
+			(RawKind::Word, "a"), (RawKind::Punct, "."), (RawKind::Number, "0"),
+			(RawKind::Punct, "->"), (RawKind::Word, "u8"), (RawKind::Punct, ";"),
+			(RawKind::LineComment, "# c"),
+		]);
+		assert_eq!(kinds("break 'a; '\\''; 'b'"), vec![
+			(RawKind::Word, "break"), (RawKind::Label, "'a"), (RawKind::Punct, ";"),
+			(RawKind::Char, "'\\''"), (RawKind::Punct, ";"), (RawKind::Char, "'b'"),
+		]);
+		assert_eq!(kinds("r \"s\""), vec![(RawKind::Word, "r"), (RawKind::Str, "\"s\"")]);
+	}
+
+	#[test]
+	fn unterminated() {
+		let err = tokenize("test", "## open").unwrap_err();
+		assert_eq!(err.msg, "unexpected EOF while scanning block comment");
+		assert_eq!(err.span, Span(Position(1, 1), Position(1, 8)));
+		assert!(tokenize("test", "\"${x\"").is_err());
+		assert!(tokenize("test", "r#\"x\"").is_err());
+		let err = tokenize("test", "r#x").unwrap_err();
+		assert_eq!(err.msg, "expected `\"` after the `#`s of a raw string");
+		assert!(tokenize("test", "@").is_err());
+	}
+}
@@ -31,13 +31,16 @@
 //! ## Lexical specification
 //! There is no official lexical specification for 'Silver' yet, when the syntax
 //! is solidified, details will be specified here.
+pub mod lossless;
 pub mod token;
 
 use std::iter::Peekable;
+use std::mem;
 use std::string::String;
 use std::str::Chars;
 
 // use self::token;
+use self::token::Fragment;
 use self::token::TokenRule::*;
 use error;
 
@@ -69,9 +72,11 @@ impl<'a> Iterator for Lexer<'a> {
 	fn next(&mut self) -> Option<Self::Item> {
 		loop {
 			match self.iter.peek().clone() {
-				Some(&'#') => self.skip_comment_line_and_ws(),
-				Some(_)    => return Some(self.token_stream_state()),
-				None       => return None
+				Some(&chr) if chr == '#' || chr.is_whitespace() => {
+					self.skip_comment_line_and_ws()
+				},
+				Some(_) => return Some(self.token_stream_state()),
+				None    => return None
 			}
 
 		}
@@ -146,11 +151,8 @@ impl<'a> Lexer<'a> {
 	/// to advance next character read.
 	fn skip_comment_line_and_ws(&mut self) {
 		while let Some(&chr) = self.iter.peek() {
-			// if a newline is encountered, increment line position by one (1).
-			if chr == '\n' {
-				self.char_pos.0 += 1;
-				self.char_pos.1  = 0;
-			} else if chr == '#' || self.peek_char_eq('#') {
+			// newlines are counted by bump().
+			if chr == '#' {
 				// skip line if `char` is a comment.  Argentum uses '#' as the
 				// comment `char` identifier token.
 				self.skip_line();
@@ -177,15 +179,20 @@ impl<'a> Lexer<'a> {
 			'[' => Ok(token::Token::new(LeftSquare, self.char_pos, self.char_pos)),
 			']' => Ok(token::Token::new(RightSquare, self.char_pos, self.char_pos)),
 			';' => Ok(token::Token::new(SemiColon, self.char_pos, self.char_pos)),
-			'.' => Ok(token::Token::new(Dot, self.char_pos, self.char_pos)),
+			',' => Ok(token::Token::new(Comma, self.char_pos, self.char_pos)),
+			'.' => self.dot_or_range_op(pos),
 			'*' => Ok(token::Token::new(Asterik, self.char_pos, self.char_pos)),
 			'%' => Ok(token::Token::new(Percentage, self.char_pos, self.char_pos)),
-			'?' => Ok(token::Token::new(QuestionMark, self.char_pos, self.char_pos)),
+			'?' => self.question_or_safe_nav_op(pos),
 			'=' => self.assignment_or_equal_op(pos),
 			'>' => self.gt_or_gteq_op(pos),
 			'<' => self.lt_or_lteq_op(pos),
 			'-' => self.minus_or_cast_op(pos),
+			':' => self.colon_or_path_op(pos),
+			'|' => self.pipe_or_logical_or_op(pos),
 			'"' => self.string_literal(pos),
+			'\'' => self.char_literal(pos),
+			'r' if self.peek_char_eq('"') || self.peek_char_eq('#') => self.raw_string_literal(pos),
 			chr if chr.is_alphabetic() => self.ident(chr, pos),
 			_   => {
 				self.span_err(error::LexerErrorKind::UnknownChar, pos, self.char_pos)
@@ -206,6 +213,65 @@ impl<'a> Lexer<'a> {
 			}
 		}
 
+	/// Emits a match on a colon ':' or path separator '::'.
+	fn colon_or_path_op(&mut self, start: Position) ->
+		Result<token::Token, LexicalDiagnostic> {
+			if let Some(&':') = self.iter.peek() {
+				// It is a `::` token.
+				let _ = self.bump();
+				Ok(token::Token::new(Path, start, self.char_pos))
+			} else {
+				// It is a `:` token.
+				Ok(token::Token::new(Colon, start, self.char_pos))
+			}
+		}
+
+	/// Emits a match on a dot '.', or a range operator '..' or '..='.
+	fn dot_or_range_op(&mut self, start: Position) ->
+		Result<token::Token, LexicalDiagnostic> {
+			if let Some(&'.') = self.iter.peek() {
+				let _ = self.bump();
+				if let Some(&'=') = self.iter.peek() {
+					// It is a `..=` token.
+					let _ = self.bump();
+					Ok(token::Token::new(DotDotEqual, start, self.char_pos))
+				} else {
+					// It is a `..` token.
+					Ok(token::Token::new(DotDot, start, self.char_pos))
+				}
+			} else {
+				// It is a `.` token.
+				Ok(token::Token::new(Dot, start, self.char_pos))
+			}
+		}
+
+	/// Emits a match on a question mark '?' or a safe navigation operator '?.'.
+	fn question_or_safe_nav_op(&mut self, start: Position) ->
+		Result<token::Token, LexicalDiagnostic> {
+			if let Some(&'.') = self.iter.peek() {
+				// It is a `?.` token.
+				let _ = self.bump();
+				Ok(token::Token::new(QuestionDot, start, self.char_pos))
+			} else {
+				// It is a `?` token.
+				Ok(token::Token::new(QuestionMark, start, self.char_pos))
+			}
+		}
+
+	/// Emits a match on a pipe '|', which delimits closure arguments, or a
+	/// logical or operator '||'.
+	fn pipe_or_logical_or_op(&mut self, start: Position) ->
+		Result<token::Token, LexicalDiagnostic> {
+			if let Some(&'|') = self.iter.peek() {
+				// It is a `||` token.
+				let _ = self.bump();
+				Ok(token::Token::new(LogicalOr, start, self.char_pos))
+			} else {
+				// It is a `|` token.
+				Ok(token::Token::new(Pipe, start, self.char_pos))
+			}
+		}
+
 	/// Assignment operator '=' OR equal operator '=='.
 	fn assignment_or_equal_op(&mut self, start: Position) ->
 		Result<token::Token, LexicalDiagnostic> {
@@ -247,10 +313,23 @@ impl<'a> Lexer<'a> {
 		}
 
 	/// string literal match on paired `chars`. Allows escape characters and
-	/// items that are not end quotes.
+	/// items that are not end quotes. A `${` starts an embedded expression, which
+	/// turns the literal into an interpolated string of fragments.
 	fn string_literal(&mut self, start: Position) ->
 		Result<token::Token, LexicalDiagnostic> {
+			// `""` is empty, unless a third quote opens a multi-line string.
+			if self.peek_char_eq('"') {
+				let _ = self.bump();
+				if self.peek_char_eq('"') {
+					let _ = self.bump();
+					return self.multiline_string_literal(start);
+				}
+				return Ok(token::Token::new(StringLiteral(String::new()), start, self.char_pos));
+			}
+			let mut fragments = Vec::new();
 			let mut buffer = String::new();
+			// span of the text scanned into the buffer.
+			let mut text = Span(start, start);
 			loop {
 				let paired = self.iter.peek().map(|x| *x);
 				let chr = match paired {
@@ -270,21 +349,234 @@ impl<'a> Lexer<'a> {
 						let _ = self.bump().unwrap();
 						break;
 					},
-					'\\' | '/' | 'b' | 'f' => {
+					'\\' => {
 						let _ = self.bump().unwrap();
+						if buffer.is_empty() {
+							text.0 = self.char_pos;
+						}
 						let actual = self.escape_char(start)?;
 						buffer.push(actual)
 					},
+					'$' => {
+						let _ = self.bump().unwrap();
+						let dollar = self.char_pos;
+						if self.peek_char_eq('{') {
+							if !buffer.is_empty() {
+								let value = mem::take(&mut buffer);
+								fragments.push(Fragment::Str(value, text));
+							}
+							let expr = self.interpolation(dollar)?;
+							fragments.push(expr);
+							continue;
+						}
+						// A lone `$` is plain text.
+						if buffer.is_empty() {
+							text.0 = dollar;
+						}
+						buffer.push('$');
+					},
 					chr => {
 						let _ = self.bump().unwrap();
+						if buffer.is_empty() {
+							text.0 = self.char_pos;
+						}
 						buffer.push(chr);
 					}
 				}
+				text.1 = self.char_pos;
 			}
 			// If code reached here, scanner has already found a closing double quote.
+			if fragments.is_empty() {
+				return Ok(token::Token::new(StringLiteral(buffer), start, self.char_pos));
+			}
+			if !buffer.is_empty() {
+				fragments.push(Fragment::Str(buffer, text));
+			}
+			Ok(token::Token::new(InterpolatedString(fragments), start, self.char_pos))
+		}
+
+	/// Triple-quoted string literal, which may span several lines. The body is
+	/// stripped of its layout by `dedent` before its escapes are decoded.
+	fn multiline_string_literal(&mut self, start: Position) ->
+		Result<token::Token, LexicalDiagnostic> {
+			let body_start = self.char_pos;
+			let mut body = String::new();
+			let mut quotes = 0;
+			while quotes < 3 {
+				let chr = match self.bump() {
+					Some(chr) => chr,
+					None => {
+						return self.span_err(
+							error::LexerErrorKind::UnterminatedStringLiteral,
+							start,
+							self.char_pos
+							)
+					}
+				};
+				if chr == '"' {
+					quotes += 1;
+					continue;
+				}
+				// fewer than three quotes are part of the text.
+				body.push_str(&"\"".repeat(quotes));
+				quotes = 0;
+				body.push(chr);
+				if chr == '\\' {
+					if let Some(escaped) = self.bump() {
+						body.push(escaped);
+					}
+				}
+			}
+			let text = dedent(&body);
+			let mut decoder = Lexer {
+				source:   self.source.clone(),
+				iter:     text.chars().peekable(),
+				char_pos: body_start
+			};
+			let mut buffer = String::new();
+			while let Some(chr) = decoder.bump() {
+				if chr == '\\' {
+					buffer.push(decoder.escape_char(start)?);
+				} else {
+					buffer.push(chr);
+				}
+			}
+			Ok(token::Token::new(StringLiteral(buffer), start, self.char_pos))
+		}
+
+	/// raw string literal `r"..."`, or `r#"..."#` with as many `#`s on both
+	/// ends as needed to write quotes in the text. Escapes are not processed.
+	fn raw_string_literal(&mut self, start: Position) ->
+		Result<token::Token, LexicalDiagnostic> {
+			let mut hashes = 0;
+			while self.peek_char_eq('#') {
+				self.skip();
+				hashes += 1;
+			}
+			if !self.peek_char_eq('"') {
+				return self.span_err(
+					error::LexerErrorKind::InvalidRawStringDelimiter,
+					start,
+					self.char_pos
+					)
+			}
+			self.skip();
+			let mut buffer = String::new();
+			loop {
+				match self.bump() {
+					Some('"') => {
+						let mut closing = 0;
+						while closing < hashes && self.peek_char_eq('#') {
+							self.skip();
+							closing += 1;
+						}
+						if closing == hashes {
+							break;
+						}
+						buffer.push('"');
+						buffer.push_str(&"#".repeat(closing));
+					},
+					Some(chr) => buffer.push(chr),
+					None => {
+						return self.span_err(
+							error::LexerErrorKind::UnterminatedStringLiteral,
+							start,
+							self.char_pos
+							)
+					}
+				}
+			}
 			Ok(token::Token::new(StringLiteral(buffer), start, self.char_pos))
 		}
 
+	/// Embedded expression of a string literal, `start` is the position of the
+	/// `$`. The source up to the matching `}` is scanned by a nested lexer;
+	/// braces of blocks, strings and chars within the expression are skipped
+	/// over.
+	fn interpolation(&mut self, start: Position) ->
+		Result<Fragment, LexicalDiagnostic> {
+			// consumes the `{` following the `$`.
+			let _ = self.bump();
+			let expr_start = self.char_pos;
+			let mut expr = String::new();
+			let mut depth = 0;
+			// the quote of the string or char literal being skipped.
+			let mut quote = None;
+			loop {
+				let chr = match self.bump() {
+					Some(chr) => chr,
+					None => {
+						return self.span_err(
+							error::LexerErrorKind::UnterminatedInterpolation,
+							start,
+							self.char_pos
+							)
+					}
+				};
+				let quoted = quote.is_some();
+				match chr {
+					'\\' if quoted => {
+						expr.push(chr);
+						if let Some(escaped) = self.bump() {
+							expr.push(escaped);
+						}
+						continue;
+					},
+					'"' | '\'' if quote == Some(chr) => quote = None,
+					'"' if !quoted                    => quote = Some(chr),
+					'\'' if !quoted && self.char_follows() => quote = Some(chr),
+					'{' if !quoted                    => depth += 1,
+					'}' if !quoted && depth == 0      => break,
+					'}' if !quoted                    => depth -= 1,
+					_                                 => {}
+				}
+				expr.push(chr);
+			}
+			let lexer = Lexer {
+				source:   self.source.clone(),
+				iter:     expr.chars().peekable(),
+				char_pos: expr_start
+			};
+			let tokens = lexer.collect::<Result<Vec<_>, _>>()?;
+			Ok(Fragment::Expr(tokens, Span(start, self.char_pos)))
+		}
+
+	/// Emits a char literal, `'c'` or an escape such as `'\n'`.
+	fn char_literal(&mut self, start: Position) ->
+		Result<token::Token, LexicalDiagnostic> {
+			let chr = match self.bump() {
+				Some('\\') => self.escape_char(start)?,
+				Some(chr)  => chr,
+				None       => {
+					return self.span_err(
+						error::LexerErrorKind::UnterminatedCharLiteral,
+						start,
+						self.char_pos
+						)
+				}
+			};
+			if !self.peek_char_eq('\'') {
+				return self.span_err(
+					error::LexerErrorKind::UnterminatedCharLiteral,
+					start,
+					self.char_pos
+					)
+			}
+			let _ = self.bump();
+			Ok(token::Token::new(CharLiteral(chr), start, self.char_pos))
+		}
+
+	/// whether the `'` just consumed opens a char literal, `'c'` or `'\n'`,
+	/// rather than a loop label.
+	fn char_follows(&self) -> bool {
+		let mut ahead = self.iter.clone();
+		match (ahead.next(), ahead.next()) {
+			(Some('\\'), _)        => true,
+			(Some(_), Some('\'')) => true,
+			_                      => false
+		}
+	}
+
 	/// identifier.
 	fn ident(&mut self, chr: char, start: Position) ->
 		Result<token::Token, LexicalDiagnostic> {
@@ -319,6 +611,7 @@ impl<'a> Lexer<'a> {
 			// match `chr` emits actual escape character representation.
 			Some(chr) => match chr {
 				'"'  => Ok('"'),
+				'\'' => Ok('\''),
 				'\\' => Ok('\\'),
 				'/'  => Ok('/'),
 				'b'  => Ok('\u{0008}'),
@@ -326,6 +619,7 @@ impl<'a> Lexer<'a> {
 				't'  => Ok('\t'),
 				'n'  => Ok('\n'),
 				'r'  => Ok('\r'),
+				'$'  => Ok('$'),
 				_    => {
 					self.span_err(
 						error::LexerErrorKind::InvalidEscapeChar,
@@ -355,3 +649,95 @@ impl<'a> Lexer<'a> {
 			}))
 		}
 }
+
+/// Strips the layout of a multi-line string body: the line break after the
+/// opening quotes, the blank line before the closing quotes and the
+/// indentation shared by all non-blank lines. Blank lines are emptied.
+pub fn dedent(body: &str) -> String {
+	let body = body.strip_prefix("\r\n")
+		.or_else(|| body.strip_prefix('\n'))
+		.unwrap_or(body);
+	let mut lines: Vec<&str> = body.split('\n').collect();
+	if lines.len() > 1 && lines[lines.len() - 1].trim().is_empty() {
+		lines.pop();
+	}
+	let indent = lines.iter()
+		.filter(|line| !line.trim().is_empty())
+		.map(|line| line.chars().take_while(|&chr| chr == ' ' || chr == '\t').count())
+		.min()
+		.unwrap_or(0);
+	let lines: Vec<&str> = lines.iter()
+		.map(|line| if line.trim().is_empty() { "" } else { &line[indent..] })
+		.collect();
+	lines.join("\n")
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use self::token::Token;
+
+	fn lex(src: &str) -> Vec<Token> {
+		Lexer::new("test".to_string(), src).collect::<Result<_, _>>().unwrap()
+	}
+
+	#[test]
+	fn string_literal() {
+		assert_eq!(lex(r#""a\tb $5""#)[0].kind, StringLiteral("a\tb $5".to_string()));
+		assert_eq!(lex(r#""\${x}""#)[0].kind, StringLiteral("${x}".to_string()));
+		assert_eq!(lex(r#""" "a""#)[1].kind, StringLiteral("a".to_string()));
+	}
+
+	#[test]
+	fn raw_string_literal() {
+		assert_eq!(lex(r#"r"C:\path""#)[0].kind, StringLiteral(r"C:\path".to_string()));
+		assert_eq!(lex(r###"r##"say "#hi"#"##"###)[0].kind,
+		           StringLiteral(r##"say "#hi"#"##.to_string()));
+		let err = Lexer::new("test".to_string(), "r#x").next().unwrap().unwrap_err();
+		assert_eq!(err.msg, "expected `\"` after the `#`s of a raw string");
+	}
+
+	#[test]
+	fn multiline_string_literal() {
+		let src = "\"\"\"\n    first\n      \\\"indented\\\"\n\n    last\\n\n    \"\"\"";
+		assert_eq!(lex(src)[0].kind,
+		           StringLiteral("first\n  \"indented\"\n\nlast\n".to_string()));
+		assert_eq!(lex(r#""""a ""b""""#)[0].kind, StringLiteral("a \"\"b".to_string()));
+		assert_eq!(dedent("\n\tx\n\t\ty\n\t"), "x\n\ty");
+	}
+
+	#[test]
+	fn interpolated_string() {
+		let tokens = lex(r#""${'}'}${'{'}${'\''}""#);
+		let fragments = match tokens[0].kind {
+			InterpolatedString(ref fragments) => fragments,
+			ref other => panic!("expected an interpolated string, found {:?}", other),
+		};
+		let chars: Vec<_> = fragments.iter().map(|fragment| match *fragment {
+			Fragment::Expr(ref tokens, _) => tokens.iter().map(|t| &t.kind).collect::<Vec<_>>(),
+			ref other => panic!("expected an expression, found {:?}", other),
+		}).collect();
+		assert_eq!(chars, vec![vec![&CharLiteral('}')], vec![&CharLiteral('{')],
+		                       vec![&CharLiteral('\'')]]);
+
+		let tokens = lex(r#""hi ${name}, ${ {x} }!""#);
+		let fragments = match tokens[0].kind {
+			InterpolatedString(ref fragments) => fragments,
+			ref other => panic!("expected an interpolated string, found {:?}", other),
+		};
+		assert_eq!(fragments.len(), 5);
+		assert_eq!(fragments[0], Fragment::Str("hi ".to_string(), Span(Position(1, 1), Position(1, 3))));
+		match fragments[1] {
+			Fragment::Expr(ref tokens, span) => {
+				assert_eq!(tokens.iter().map(|t| &t.kind).collect::<Vec<_>>(),
+				           vec![&Identifier("name".to_string())]);
+				assert_eq!(span, Span(Position(1, 4), Position(1, 10)));
+			},
+			ref other => panic!("expected an expression, found {:?}", other),
+		}
+		assert_eq!(tokens[0].kind.to_string(), r#""hi ${name}, ${{ x }}!""#);
+
+		let err = Lexer::new("test".to_string(), r#""${x"#).next().unwrap().unwrap_err();
+		assert_eq!(err.msg, "unexpected EOF while scanning string interpolation");
+	}
+}
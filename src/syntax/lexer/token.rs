@@ -47,6 +47,8 @@ pub enum TokenRule {
     Bang,
     BangEqual,
     Cast,
+    DotDot,
+    DotDotEqual,
     Equal,
     EqualEqual,
     GreaterThan,
@@ -61,33 +63,71 @@ pub enum TokenRule {
     Pipe,
     Plus,
     PlusPlus,
+    QuestionDot,
 
     // Literals.
     Identifier(String),
     StringLiteral(String),
+    CharLiteral(char),
+    InterpolatedString(Vec<Fragment>),
     NumberLiteral(f64),
     // Comment(String),
 
     // Keywords.
+    BreakKw,
+    ConstKw,
+    ContinueKw,
     ElseKw,
     FalseKw,
     ForKw,
     FnKw,
     IfKw,
+    InKw,
+    LoopKw,
     NilKw,
     PrintKw,
+    PrintlnKw,
     PubKw,
     ReturnKw,
     StructKw,
     SelfKw,
     SpecKw,
     TrueKw,
+    TypeKw,
+    UseKw,
     LetKw,
     ModelKw,
     MountKw,
     WhileKw,
 }
 
+/// Piece of an interpolated string literal:  "hello ${name}"
+#[derive(Debug, PartialEq)]
+pub enum Fragment {
+    /// Text between the embedded expressions, with its escapes decoded.
+    Str(String, Span),
+    /// Tokens of an embedded expression, the span covers `${` to `}`.
+    Expr(Vec<Token>, Span),
+}
+
+impl fmt::Display for Fragment {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Fragment::Str(ref s, _)       => s.fmt(fmt),
+            Fragment::Expr(ref tokens, _) => {
+                write!(fmt, "${{")?;
+                for (i, token) in tokens.iter().enumerate() {
+                    if i > 0 {
+                        write!(fmt, " ")?;
+                    }
+                    token.kind.fmt(fmt)?;
+                }
+                write!(fmt, "}}")
+            },
+        }
+    }
+}
+
 impl fmt::Display for TokenRule {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
         match *self {
@@ -109,6 +149,8 @@ impl fmt::Display for TokenRule {
             TokenRule::Bang                   => write!(fmt, "!"),
             TokenRule::BangEqual              => write!(fmt, "!="),
             TokenRule::Cast                   => write!(fmt, "->"),
+            TokenRule::DotDot                 => write!(fmt, ".."),
+            TokenRule::DotDotEqual            => write!(fmt, "..="),
             TokenRule::Equal                  => write!(fmt, "="),
             TokenRule::EqualEqual             => write!(fmt, "=="),
             TokenRule::GreaterThan            => write!(fmt, ">"),
@@ -123,27 +165,44 @@ impl fmt::Display for TokenRule {
             TokenRule::Pipe                   => write!(fmt, "|"),
             TokenRule::Plus                   => write!(fmt, "+"),
             TokenRule::PlusPlus               => write!(fmt, "++"),
+            TokenRule::QuestionDot            => write!(fmt, "?."),
 
             TokenRule::Identifier(ref id)     => id.fmt(fmt),
             TokenRule::StringLiteral(ref s)   => write!(fmt, "\"{}\"", s),
+            TokenRule::CharLiteral(ref c)     => write!(fmt, "{:?}", c),
+            TokenRule::InterpolatedString(ref fragments) => {
+                write!(fmt, "\"")?;
+                for fragment in fragments {
+                    fragment.fmt(fmt)?;
+                }
+                write!(fmt, "\"")
+            },
             TokenRule::NumberLiteral(ref num) => num.fmt(fmt),
 
+            TokenRule::BreakKw                => write!(fmt, "break"),
+            TokenRule::ConstKw                => write!(fmt, "const"),
+            TokenRule::ContinueKw             => write!(fmt, "continue"),
             TokenRule::ElseKw                 => write!(fmt, "else"),
             TokenRule::FalseKw                => write!(fmt, "false"),
             TokenRule::FnKw                   => write!(fmt, "fn"),
             TokenRule::ForKw                  => write!(fmt, "for"),
             TokenRule::IfKw                   => write!(fmt, "if"),
+            TokenRule::InKw                   => write!(fmt, "in"),
             TokenRule::LetKw                  => write!(fmt, "let"),
+            TokenRule::LoopKw                 => write!(fmt, "loop"),
             TokenRule::ModelKw                => write!(fmt, "model"),
             TokenRule::MountKw                => write!(fmt, "mount"),
             TokenRule::NilKw                  => write!(fmt, "nil"),
             TokenRule::PrintKw                => write!(fmt, "print"),
+            TokenRule::PrintlnKw              => write!(fmt, "println"),
             TokenRule::PubKw                  => write!(fmt, "pub"),
             TokenRule::ReturnKw               => write!(fmt, "return"),
             TokenRule::SelfKw                 => write!(fmt, "self"),
             TokenRule::SpecKw                 => write!(fmt, "spec"),
             TokenRule::StructKw               => write!(fmt, "struct"),
             TokenRule::TrueKw                 => write!(fmt, "true"),
+            TokenRule::TypeKw                 => write!(fmt, "type"),
+            TokenRule::UseKw                  => write!(fmt, "use"),
             TokenRule::WhileKw                => write!(fmt, "while"),
         }
     }
@@ -151,21 +210,30 @@ impl fmt::Display for TokenRule {
 
 pub fn keyword_dict(string: &str) -> Option<TokenRule> {
     match string {
+        "break"  => Some(TokenRule::BreakKw),
+        "const"  => Some(TokenRule::ConstKw),
+        "continue" => Some(TokenRule::ContinueKw),
         "else"   => Some(TokenRule::ElseKw),
         "false"  => Some(TokenRule::FalseKw),
         "fn"     => Some(TokenRule::FnKw),
+        "for"    => Some(TokenRule::ForKw),
         "if"     => Some(TokenRule::IfKw),
+        "in"     => Some(TokenRule::InKw),
         "let"    => Some(TokenRule::LetKw),
+        "loop"   => Some(TokenRule::LoopKw),
         "model"  => Some(TokenRule::ModelKw),  // trait equivalent.
         "mount"  => Some(TokenRule::MountKw),  // impl equivalent.
         "nil"    => Some(TokenRule::NilKw),
         "print"  => Some(TokenRule::PrintKw),
+        "println" => Some(TokenRule::PrintlnKw),
         "pub"    => Some(TokenRule::PubKw),
         "return" => Some(TokenRule::ReturnKw),
         "self"   => Some(TokenRule::SelfKw),
         "spec"   => Some(TokenRule::SpecKw),   // enum equivalent.
         "struct" => Some(TokenRule::StructKw),
         "true"   => Some(TokenRule::TrueKw),
+        "type"   => Some(TokenRule::TypeKw),
+        "use"    => Some(TokenRule::UseKw),
         "while"  => Some(TokenRule::WhileKw),
         _        => None
     }
@@ -1,5 +1,9 @@
 pub mod ast;
+pub mod cst;
+pub mod dump;
+pub mod fmt;
 pub mod lexer;
 pub mod parser;
+pub mod print;
 
 pub use self::lexer::token::{Token, TokenRule};
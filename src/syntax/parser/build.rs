@@ -0,0 +1,1015 @@
+//! Builds the abstract syntax tree from the pairs produced by `SilverParser`.
+//!
+//! Each `Builder` method consumes the pair of the grammar rule it is named
+//! after, and stamps the nodes it creates with their span and the file being
+//! built.  Binary expressions are flattened by the grammar
+//! (`term ~ (binary_op ~ term)*`) and regrouped here by precedence using a
+//! `PrecClimber`.
+use std::cell::Cell;
+
+use pest::iterators::{Pair, Pairs};
+use pest::prec_climber::{Assoc, Operator, PrecClimber};
+
+use syntax::ast::binop::{ArithmeticOp, BitWiseOp, ComparisonOp, LogicalOp};
+use syntax::ast::unop::UnaryOp;
+use syntax::ast::arena::Alloc;
+use syntax::ast::{Arena, Arm, AssignmentOp, BinaryOp, Block, Closure, ConstDecl, Expr, ExprKind,
+                  FieldDecl, FnDecl, ForLoop, FormatError, Fragment, Item, ItemKind, Literal,
+                  Local, Module, NodeId, Param, Pattern, PatternKind, Print, PrimType,
+                  RangeLimits, SpecDecl, Stmt, StmtKind, StructDecl, Type, TypeAlias, TypeKind,
+                  TypeParam, UseDecl, Variant, Visibility, WhileLoop};
+use syntax::lexer::dedent;
+use utils::{FileId, Path, Position, Span};
+
+use super::{ParseError, Rule};
+
+lazy_static! {
+    // Lowest precedence first.
+    static ref EXPR_CLIMBER: PrecClimber<Rule> = PrecClimber::new(
+        vec![
+        Operator::new(Rule::lazy_or, Assoc::Left),
+        Operator::new(Rule::lazy_and, Assoc::Left),
+        Operator::new(Rule::cmp_eql, Assoc::Left) |
+        Operator::new(Rule::cmp_not_eql, Assoc::Left),
+        Operator::new(Rule::cmp_gt, Assoc::Left) |
+        Operator::new(Rule::cmp_gt_eql, Assoc::Left) |
+        Operator::new(Rule::cmp_lt, Assoc::Left) |
+        Operator::new(Rule::cmp_lt_eql, Assoc::Left),
+        Operator::new(Rule::bwor, Assoc::Left),
+        Operator::new(Rule::bwxor, Assoc::Left),
+        Operator::new(Rule::bwand, Assoc::Left),
+        Operator::new(Rule::lshft, Assoc::Left) | Operator::new(Rule::rshft, Assoc::Left),
+        Operator::new(Rule::add, Assoc::Left) | Operator::new(Rule::sub, Assoc::Left),
+        Operator::new(Rule::mult, Assoc::Left) |
+        Operator::new(Rule::div, Assoc::Left) |
+        Operator::new(Rule::modulo, Assoc::Left),
+        ]);
+}
+
+/// Converts the pest span of `pair` into a row/column `Span`.
+pub fn span_of(pair: &Pair<Rule>) -> Span {
+    let span = pair.clone().into_span();
+    let (start_row, start_col) = span.start_pos().line_col();
+    let (end_row, end_col) = span.end_pos().line_col();
+    Span(Position(start_row as u32, start_col as u32),
+         Position(end_row as u32, end_col as u32))
+}
+
+/// Builds a module of `file` from the pairs of a `top_lvl` parse, allocating
+/// its nodes in `arena`.
+pub fn module<'ast, 'i>(arena: &'ast Arena<'ast>, pairs: Pairs<'i, Rule>, file: FileId)
+    -> Result<Module<'ast>, ParseError<'i>> {
+    let builder = Builder { arena, file, next_id: Cell::new(0) };
+    let mut stmts = Vec::new();
+    for pair in pairs.flat_map(|top_lvl| top_lvl.into_inner()) {
+        if pair.as_rule() == Rule::statement {
+            if let Some(stmt) = builder.statement(pair)? {
+                stmts.push(stmt);
+            }
+        }
+    }
+    Ok(Module { stmts: arena.alloc_vec(stmts) })
+}
+
+/// Builds the nodes of a single source file.
+struct Builder<'ast> {
+    arena:   &'ast Arena<'ast>,
+    file:    FileId,
+    next_id: Cell<u32>,
+}
+
+impl<'ast> Builder<'ast> {
+    fn next_id(&self) -> NodeId {
+        let id = self.next_id.get();
+        self.next_id.set(id + 1);
+        NodeId(id)
+    }
+
+    fn alloc<T: Alloc<'ast>>(&self, node: T) -> &'ast T {
+        self.arena.alloc(node)
+    }
+
+    fn alloc_vec<T: Alloc<'ast>>(&self, nodes: Vec<T>) -> &'ast [T] {
+        self.arena.alloc_vec(nodes)
+    }
+
+    fn expr_node(&self, kind: ExprKind<'ast>, span: Span) -> Expr<'ast> {
+        Expr::new(self.next_id(), kind, span, self.file)
+    }
+
+    fn stmt_node(&self, kind: StmtKind<'ast>, span: Span) -> Stmt<'ast> {
+        Stmt::new(self.next_id(), kind, span, self.file)
+    }
+
+    fn pattern_node(&self, kind: PatternKind<'ast>, span: Span) -> Pattern<'ast> {
+        Pattern::new(self.next_id(), kind, span, self.file)
+    }
+
+    fn type_node(&self, kind: TypeKind<'ast>, span: Span) -> Type<'ast> {
+        Type::new(self.next_id(), kind, span, self.file)
+    }
+
+    /// Builds a statement.  Empty statements (a lone `;`) yield `None`.
+    fn statement<'i>(&self, pair: Pair<'i, Rule>) -> Result<Option<Stmt<'ast>>, ParseError<'i>> {
+        let span = span_of(&pair);
+        let inner = match pair.into_inner().next() {
+            Some(inner) => inner,
+            None        => return Ok(None),
+        };
+        let kind = match inner.as_rule() {
+            Rule::item      => StmtKind::Item(self.item(inner)?),
+            Rule::var_dcl   => StmtKind::Let(self.var_dcl(inner)?),
+            Rule::expr_stmt => StmtKind::Expr(self.expr(first(inner))?),
+            Rule::block_stmt => StmtKind::Expr(self.block_expr(first(inner))?),
+            Rule::for_stmt  => StmtKind::For(self.for_stmt(inner)?),
+            Rule::while_stmt => StmtKind::While(self.while_stmt(inner)?),
+            Rule::break_stmt => {
+                let mut label = None;
+                let mut value = None;
+                for pair in inner.into_inner() {
+                    match pair.as_rule() {
+                        Rule::break_kw => {},
+                        Rule::label    => label = Some(label_name(pair)),
+                        Rule::expr     => value = Some(self.expr(pair)?),
+                        _              => unexpected(pair),
+                    }
+                }
+                StmtKind::Break(label, value)
+            },
+            Rule::print_stmt => StmtKind::Print(self.print_stmt(inner)?),
+            Rule::continue_stmt => {
+                let label = inner.into_inner().find(|pair| pair.as_rule() == Rule::label);
+                StmtKind::Continue(label.map(label_name))
+            },
+            Rule::return_stmt => {
+                let value = inner.into_inner()
+                    .find(|pair| pair.as_rule() == Rule::expr)
+                    .map(|pair| self.expr(pair))
+                    .map_or(Ok(None), |value| value.map(Some))?;
+                StmtKind::Return(value)
+            },
+            _               => unexpected(inner),
+        };
+        Ok(Some(self.stmt_node(kind, span)))
+    }
+
+    fn item<'i>(&self, pair: Pair<'i, Rule>) -> Result<Item<'ast>, ParseError<'i>> {
+        let span = span_of(&pair);
+        let mut vis = Visibility::Private;
+        for inner in pair.into_inner() {
+            let kind = match inner.as_rule() {
+                Rule::visibility => {
+                    vis = Visibility::Public;
+                    continue;
+                },
+                Rule::use_dcl => ItemKind::Use(use_dcl(inner)),
+                Rule::const_dcl => ItemKind::Const(self.const_dcl(inner)?),
+                Rule::type_alias => ItemKind::Type(self.type_alias(inner)),
+                Rule::struct_dcl => ItemKind::Struct(self.struct_dcl(inner)),
+                Rule::spec_dcl => ItemKind::Spec(self.spec_dcl(inner)),
+                Rule::fn_dcl  => ItemKind::Fn(self.fn_dcl(inner)?),
+                _             => unexpected(inner),
+            };
+            return Ok(Item { id: self.next_id(), vis, kind, span, file: self.file });
+        }
+        unreachable!("grammar guarantees an item declaration")
+    }
+
+    fn const_dcl<'i>(&self, pair: Pair<'i, Rule>) -> Result<ConstDecl<'ast>, ParseError<'i>> {
+        let mut inner = pair.into_inner().skip(1);
+        let name = inner.next().unwrap().as_str().to_string();
+        let ty = self.type_dcl(inner.next().unwrap());
+        let value = self.expr(inner.next().unwrap())?;
+        Ok(ConstDecl { name, ty, value })
+    }
+
+    fn type_alias(&self, pair: Pair<Rule>) -> TypeAlias<'ast> {
+        let mut inner = pair.into_inner().skip(1);
+        let name = inner.next().unwrap().as_str().to_string();
+        let ty = self.ty(inner.next().unwrap());
+        TypeAlias { name, ty }
+    }
+
+    fn struct_dcl(&self, pair: Pair<Rule>) -> StructDecl<'ast> {
+        let mut name = String::new();
+        let mut generics = Vec::new();
+        let mut fields = Vec::new();
+        for inner in pair.into_inner() {
+            match inner.as_rule() {
+                Rule::struct_kw   => {},
+                Rule::ident       => name = inner.as_str().to_string(),
+                Rule::type_params => generics = inner.into_inner().map(type_param).collect(),
+                Rule::field_dcl   => {
+                    let span = span_of(&inner);
+                    let mut field = inner.into_inner();
+                    let name = field.next().unwrap().as_str().to_string();
+                    fields.push(FieldDecl { name, ty: self.type_dcl(field.next().unwrap()), span });
+                },
+                _                 => unexpected(inner),
+            }
+        }
+        StructDecl { name, generics: self.alloc_vec(generics), fields: self.alloc_vec(fields) }
+    }
+
+    fn spec_dcl(&self, pair: Pair<Rule>) -> SpecDecl<'ast> {
+        let mut name = String::new();
+        let mut generics = Vec::new();
+        let mut variants = Vec::new();
+        for inner in pair.into_inner() {
+            match inner.as_rule() {
+                Rule::spec_kw     => {},
+                Rule::ident       => name = inner.as_str().to_string(),
+                Rule::type_params => generics = inner.into_inner().map(type_param).collect(),
+                Rule::variant     => {
+                    let mut span = span_of(&inner);
+                    let mut variant = inner.into_inner();
+                    let ident = variant.next().unwrap();
+                    let fields = self.alloc_vec(variant.map(|ty| self.ty(ty)).collect());
+                    // Without values the pair also spans the whitespace
+                    // skipped looking for them.
+                    if fields.is_empty() {
+                        span = span_of(&ident);
+                    }
+                    let name = ident.as_str().to_string();
+                    variants.push(Variant { name, fields, span });
+                },
+                _                 => unexpected(inner),
+            }
+        }
+        SpecDecl { name, generics: self.alloc_vec(generics), variants: self.alloc_vec(variants) }
+    }
+
+    fn var_dcl<'i>(&self, pair: Pair<'i, Rule>) -> Result<Local<'ast>, ParseError<'i>> {
+        let mut pat = None;
+        let mut ty = None;
+        let mut init = None;
+        for inner in pair.into_inner() {
+            match inner.as_rule() {
+                Rule::let_kw   => {},
+                Rule::pattern  => pat = Some(self.pattern(inner)),
+                Rule::type_dcl => ty = Some(self.type_dcl(inner)),
+                Rule::expr     => init = Some(self.expr(inner)?),
+                _              => unexpected(inner),
+            }
+        }
+        let pat = pat.expect("grammar guarantees a pattern");
+        Ok(Local { pat, ty, init })
+    }
+
+    fn print_stmt<'i>(&self, pair: Pair<'i, Rule>) -> Result<Print<'ast>, ParseError<'i>> {
+        let mut newline = false;
+        let mut args = Vec::new();
+        for inner in pair.into_inner() {
+            match inner.as_rule() {
+                Rule::print_kw   => {},
+                Rule::println_kw => newline = true,
+                Rule::expr       => args.push(self.expr(inner)?),
+                _                => unexpected(inner),
+            }
+        }
+        let format = match args.first() {
+            Some(&Expr { kind: ExprKind::Literal(Literal::Str(ref text)), span, .. }) => {
+                Some(text.parse().map_err(|err| ParseError::InvalidFormat(span, err))?)
+            },
+            // The placeholders would take the arguments out of order with the
+            // interpolated values, so an interpolated string is not a format.
+            Some(&Expr { kind: ExprKind::Interpolated(_), span, .. }) if args.len() > 1 => {
+                let err = FormatError("interpolations cannot be mixed with arguments".to_string());
+                return Err(ParseError::InvalidFormat(span, err));
+            },
+            _ => None,
+        };
+        if format.is_some() {
+            args.remove(0);
+        }
+        Ok(Print { newline, format, args: self.alloc_vec(args) })
+    }
+
+    fn for_stmt<'i>(&self, pair: Pair<'i, Rule>) -> Result<ForLoop<'ast>, ParseError<'i>> {
+        let mut label = None;
+        let mut pat = None;
+        let mut iter = None;
+        let mut body = None;
+        for inner in pair.into_inner() {
+            match inner.as_rule() {
+                Rule::for_kw | Rule::in_kw => {},
+                Rule::label_dcl  => label = Some(label_name(first(inner))),
+                Rule::pattern    => pat = Some(self.pattern(inner)),
+                Rule::expr       => iter = Some(self.expr(inner)?),
+                Rule::code_block => body = Some(self.code_block(inner)?),
+                _                => unexpected(inner),
+            }
+        }
+        Ok(ForLoop {
+            label,
+            pat:  pat.expect("grammar guarantees a pattern"),
+            iter: iter.expect("grammar guarantees an iterable"),
+            body: body.expect("grammar guarantees a loop body"),
+        })
+    }
+
+    fn while_stmt<'i>(&self, pair: Pair<'i, Rule>) -> Result<WhileLoop<'ast>, ParseError<'i>> {
+        let mut label = None;
+        let mut cond = None;
+        let mut body = None;
+        for inner in pair.into_inner() {
+            match inner.as_rule() {
+                Rule::while_kw   => {},
+                Rule::label_dcl  => label = Some(label_name(first(inner))),
+                Rule::expr       => cond = Some(self.expr(inner)?),
+                Rule::code_block => body = Some(self.code_block(inner)?),
+                _                => unexpected(inner),
+            }
+        }
+        Ok(WhileLoop {
+            label,
+            cond: cond.expect("grammar guarantees a condition"),
+            body: body.expect("grammar guarantees a loop body"),
+        })
+    }
+
+    fn loop_expr<'i>(&self, pair: Pair<'i, Rule>) -> Result<Expr<'ast>, ParseError<'i>> {
+        let span = span_of(&pair);
+        let mut label = None;
+        let mut body = None;
+        for inner in pair.into_inner() {
+            match inner.as_rule() {
+                Rule::loop_kw    => {},
+                Rule::label_dcl  => label = Some(label_name(first(inner))),
+                Rule::code_block => body = Some(self.code_block(inner)?),
+                _                => unexpected(inner),
+            }
+        }
+        let body = body.expect("grammar guarantees a loop body");
+        Ok(self.expr_node(ExprKind::Loop(label, body), span))
+    }
+
+    fn fn_dcl<'i>(&self, pair: Pair<'i, Rule>) -> Result<FnDecl<'ast>, ParseError<'i>> {
+        let mut name = String::new();
+        let mut generics = Vec::new();
+        let mut params = Vec::new();
+        let mut ret = None;
+        let mut body = None;
+        for pair in pair.into_inner() {
+            match pair.as_rule() {
+                Rule::fn_kw      => {},
+                Rule::ident      => name = pair.as_str().to_string(),
+                Rule::type_params => generics = pair.into_inner().map(type_param).collect(),
+                Rule::fn_call    => params = self.fn_call(pair),
+                Rule::ret_ty     => ret = Some(self.ty(first(pair))),
+                Rule::code_block => body = Some(self.code_block(pair)?),
+                _                => unexpected(pair),
+            }
+        }
+        let body = body.expect("grammar guarantees a function body");
+        let (generics, params) = (self.alloc_vec(generics), self.alloc_vec(params));
+        Ok(FnDecl { name, generics, params, ret, body })
+    }
+
+    /// Builds the parameter list of `fn_call`, and of closures through `param`.
+    fn fn_call(&self, pair: Pair<Rule>) -> Vec<Param<'ast>> {
+        pair.into_inner().map(|param| self.param(param)).collect()
+    }
+
+    /// Builds an `arg_dcl` or `lambda_arg` parameter.
+    fn param(&self, pair: Pair<Rule>) -> Param<'ast> {
+        let span = span_of(&pair);
+        let mut inner = pair.into_inner();
+        let pat = self.pattern(inner.next().unwrap());
+        let ty = inner.next().map(|ty| self.type_dcl(ty));
+        Param { pat, ty, span }
+    }
+
+    fn pattern(&self, pair: Pair<Rule>) -> Pattern<'ast> {
+        let span = span_of(&pair);
+        let inner = first(pair);
+        let kind = match inner.as_rule() {
+            Rule::ident     => PatternKind::Ident(inner.as_str().to_string()),
+            Rule::tuple_pat => {
+                let pats = inner.into_inner().map(|pat| self.pattern(pat)).collect();
+                PatternKind::Tuple(self.alloc_vec(pats))
+            },
+            _               => unexpected(inner),
+        };
+        self.pattern_node(kind, span)
+    }
+
+    fn code_block<'i>(&self, pair: Pair<'i, Rule>) -> Result<Block<'ast>, ParseError<'i>> {
+        let span = span_of(&pair);
+        let mut stmts = Vec::new();
+        let mut tail = None;
+        let mut ends_block_like = false;
+        for inner in pair.into_inner() {
+            match inner.as_rule() {
+                Rule::statement => {
+                    ends_block_like = is_block_stmt(&inner);
+                    if let Some(stmt) = self.statement(inner)? {
+                        stmts.push(stmt);
+                    }
+                },
+                Rule::tail_expr => tail = Some(self.alloc(self.expr(first(inner))?)),
+                _               => unexpected(inner),
+            }
+        }
+        // A trailing `if` or `loop` without semi colon is the value of the block.
+        if tail.is_none() && ends_block_like {
+            if let Some(Stmt { kind: StmtKind::Expr(value), .. }) = stmts.pop() {
+                tail = Some(self.alloc(value));
+            }
+        }
+        Ok(Block { stmts: self.alloc_vec(stmts), expr: tail, span })
+    }
+
+    fn type_dcl(&self, pair: Pair<Rule>) -> Type<'ast> {
+        self.ty(first(pair))
+    }
+
+    fn ty(&self, pair: Pair<Rule>) -> Type<'ast> {
+        let span = span_of(&pair);
+        let mut inner_pairs = pair.into_inner();
+        let inner = inner_pairs.next().unwrap();
+        let mut base_span = span_of(&inner);
+        let base = match inner.as_rule() {
+            Rule::prim_type => TypeKind::Prim(prim_type(inner)),
+            Rule::fn_type   => {
+                let mut params = Vec::new();
+                let mut ret = None;
+                for pair in inner.into_inner() {
+                    match pair.as_rule() {
+                        Rule::fn_kw  => {},
+                        Rule::ty     => params.push(self.ty(pair)),
+                        Rule::ret_ty => ret = Some(self.ty(first(pair))),
+                        _            => unexpected(pair),
+                    }
+                }
+                // A function without return type returns void.
+                let ret = ret.unwrap_or_else(|| {
+                    self.type_node(TypeKind::Prim(PrimType::Void), base_span)
+                });
+                TypeKind::Fn(self.alloc_vec(params), self.alloc(ret))
+            },
+            Rule::tuple_type => {
+                TypeKind::Tuple(self.alloc_vec(inner.into_inner().map(|ty| self.ty(ty)).collect()))
+            },
+            Rule::array_type => TypeKind::Array(self.alloc(self.ty(first(inner)))),
+            Rule::named_type => {
+                let mut named = inner.into_inner();
+                let ident = named.next().unwrap();
+                let args = match named.next() {
+                    Some(args) => args.into_inner().map(|ty| self.ty(ty)).collect(),
+                    // Without type arguments the pair also spans the
+                    // whitespace skipped looking for them.
+                    None       => {
+                        base_span = span_of(&ident);
+                        Vec::new()
+                    },
+                };
+                TypeKind::Named(ident.as_str().to_string(), self.alloc_vec(args))
+            },
+            Rule::map_type   => {
+                let mut types = inner.into_inner().map(|ty| self.ty(ty));
+                let key = types.next().unwrap();
+                TypeKind::Map(self.alloc(key), self.alloc(types.next().unwrap()))
+            },
+            _               => unexpected(inner),
+        };
+        // Without the `?` the pair also spans the whitespace skipped looking
+        // for it.
+        match inner_pairs.next() {
+            Some(_) => {
+                let base = self.type_node(base, base_span);
+                self.type_node(TypeKind::Optional(self.alloc(base)), span)
+            },
+            None    => self.type_node(base, base_span),
+        }
+    }
+
+    /// Builds an expression from an `expr` pair.
+    fn expr<'i>(&self, pair: Pair<'i, Rule>) -> Result<Expr<'ast>, ParseError<'i>> {
+        let span = span_of(&pair);
+        let mut inner = pair.into_inner();
+        let lhs = self.cond_expr(inner.next().unwrap())?;
+        match inner.next() {
+            Some(rhs) => {
+                let rhs = self.expr(rhs)?;
+                let kind = ExprKind::Assign(AssignmentOp::Assign, self.alloc(lhs), self.alloc(rhs));
+                Ok(self.expr_node(kind, span))
+            },
+            None      => Ok(lhs),
+        }
+    }
+
+    /// Builds the `if_expr`, `loop_expr` or `match_expr` of a `block_stmt`.
+    fn block_expr<'i>(&self, pair: Pair<'i, Rule>) -> Result<Expr<'ast>, ParseError<'i>> {
+        match pair.as_rule() {
+            Rule::if_expr    => self.if_expr(pair),
+            Rule::loop_expr  => self.loop_expr(pair),
+            Rule::match_expr => self.match_expr(pair),
+            _                => unexpected(pair),
+        }
+    }
+
+    /// Builds `binary ? expr : cond_expr`, or the plain binary expression.
+    fn cond_expr<'i>(&self, pair: Pair<'i, Rule>) -> Result<Expr<'ast>, ParseError<'i>> {
+        let span = span_of(&pair);
+        let mut inner = pair.into_inner();
+        let cond = self.range_expr(inner.next().unwrap())?;
+        match (inner.next(), inner.next()) {
+            (Some(then), Some(otherwise)) => {
+                let kind = ExprKind::Ternary(
+                    self.alloc(cond),
+                    self.alloc(self.expr(then)?),
+                    self.alloc(self.cond_expr(otherwise)?));
+                Ok(self.expr_node(kind, span))
+            },
+            _ => Ok(cond),
+        }
+    }
+
+    /// Builds `binary .. binary`, or the plain binary expression.
+    fn range_expr<'i>(&self, pair: Pair<'i, Rule>) -> Result<Expr<'ast>, ParseError<'i>> {
+        let span = span_of(&pair);
+        let mut inner = pair.into_inner();
+        let start = self.binary(inner.next().unwrap())?;
+        let limits = match inner.next() {
+            Some(ref op) if op.as_rule() == Rule::range_incl => RangeLimits::Closed,
+            Some(_) => RangeLimits::HalfOpen,
+            None    => return Ok(start),
+        };
+        let end = self.binary(inner.next().unwrap())?;
+        Ok(self.expr_node(ExprKind::Range(self.alloc(start), self.alloc(end), limits), span))
+    }
+
+    fn if_expr<'i>(&self, pair: Pair<'i, Rule>) -> Result<Expr<'ast>, ParseError<'i>> {
+        let span = span_of(&pair);
+        let mut cond = None;
+        let mut then = None;
+        let mut otherwise = None;
+        for inner in pair.into_inner() {
+            match inner.as_rule() {
+                Rule::if_kw | Rule::else_kw       => {},
+                Rule::expr                        => cond = Some(self.expr(inner)?),
+                Rule::code_block if then.is_none() => then = Some(self.code_block(inner)?),
+                Rule::code_block                  => {
+                    let span = span_of(&inner);
+                    let block = ExprKind::Block(self.code_block(inner)?);
+                    otherwise = Some(self.expr_node(block, span));
+                },
+                Rule::if_expr                     => otherwise = Some(self.if_expr(inner)?),
+                _                                 => unexpected(inner),
+            }
+        }
+        let kind = ExprKind::If(
+            self.alloc(cond.expect("grammar guarantees a condition")),
+            then.expect("grammar guarantees a block"),
+            otherwise.map(|otherwise| self.alloc(otherwise)));
+        Ok(self.expr_node(kind, span))
+    }
+
+    fn match_expr<'i>(&self, pair: Pair<'i, Rule>) -> Result<Expr<'ast>, ParseError<'i>> {
+        let span = span_of(&pair);
+        let mut scrutinee = None;
+        let mut arms = Vec::new();
+        for inner in pair.into_inner() {
+            match inner.as_rule() {
+                Rule::match_kw  => {},
+                Rule::expr      => scrutinee = Some(self.expr(inner)?),
+                Rule::match_arm => arms.push(self.match_arm(inner)?),
+                _               => unexpected(inner),
+            }
+        }
+        let scrutinee = scrutinee.expect("grammar guarantees a scrutinee");
+        Ok(self.expr_node(ExprKind::Match(self.alloc(scrutinee), self.alloc_vec(arms)), span))
+    }
+
+    /// Builds an arm, a block body is a `Block` expression.
+    fn match_arm<'i>(&self, pair: Pair<'i, Rule>) -> Result<Arm<'ast>, ParseError<'i>> {
+        let span = span_of(&pair);
+        let mut inner = pair.into_inner();
+        let pat = self.arm_pat(inner.next().unwrap());
+        let body = inner.next().unwrap();
+        let body = match body.as_rule() {
+            Rule::code_block => {
+                let span = span_of(&body);
+                self.expr_node(ExprKind::Block(self.code_block(body)?), span)
+            },
+            _                => self.expr(body)?,
+        };
+        Ok(Arm { pat, body, span })
+    }
+
+    fn arm_pat(&self, pair: Pair<Rule>) -> Pattern<'ast> {
+        let inner = first(pair);
+        if inner.as_rule() == Rule::pattern {
+            return self.pattern(inner);
+        }
+        let mut span = span_of(&inner);
+        let with_values = inner.as_str().ends_with(')');
+        let mut variant = inner.into_inner();
+        let path = variant.next().unwrap();
+        // Without values the pair also spans the whitespace skipped looking
+        // for them.
+        if !with_values {
+            span = span_of(&path);
+        }
+        let pats = self.alloc_vec(variant.map(|pat| self.arm_pat(pat)).collect());
+        self.pattern_node(PatternKind::Variant(path_name(path), pats), span)
+    }
+
+    /// Builds the operator tree of a `binary` pair by precedence climbing.
+    fn binary<'i>(&self, pair: Pair<'i, Rule>) -> Result<Expr<'ast>, ParseError<'i>> {
+        EXPR_CLIMBER.climb(
+            pair.into_inner(),
+            |pair| self.cast_expr(pair),
+            |lhs, op, rhs| {
+                let (lhs, rhs) = (lhs?, rhs?);
+                let span = lhs.span.to(rhs.span);
+                let kind = ExprKind::Binary(binary_op(op), self.alloc(lhs), self.alloc(rhs));
+                Ok(self.expr_node(kind, span))
+            },
+            )
+    }
+
+    /// Builds `term -> prim_type -> ...`, casts applying left to right.
+    fn cast_expr<'i>(&self, pair: Pair<'i, Rule>) -> Result<Expr<'ast>, ParseError<'i>> {
+        let mut inner = pair.into_inner();
+        let mut expr = self.term(inner.next().unwrap())?;
+        for target in inner {
+            let span = expr.span.to(span_of(&target));
+            expr = self.expr_node(ExprKind::Cast(self.alloc(expr), prim_type(target)), span);
+        }
+        Ok(expr)
+    }
+
+    /// Builds a `term`: prefix operators, an operand and its call suffixes.
+    /// Each suffix extends the span of the operand it applies to.
+    fn term<'i>(&self, pair: Pair<'i, Rule>) -> Result<Expr<'ast>, ParseError<'i>> {
+        let mut prefix = Vec::new();
+        let mut operand: Option<Expr> = None;
+        for inner in pair.into_inner() {
+            let span = span_of(&inner);
+            let suffixed = operand.as_ref().map(|operand| operand.span.to(span));
+            let kind = match inner.as_rule() {
+                Rule::unary_minus => { prefix.push((UnaryOp::Minus, span)); continue; },
+                Rule::unary_not   => { prefix.push((UnaryOp::Not, span)); continue; },
+                Rule::closure     => { operand = Some(self.closure(first(inner))?); continue; },
+                Rule::if_expr     => { operand = Some(self.if_expr(inner)?); continue; },
+                Rule::loop_expr   => { operand = Some(self.loop_expr(inner)?); continue; },
+                Rule::match_expr  => { operand = Some(self.match_expr(inner)?); continue; },
+                Rule::literal     => { operand = Some(self.literal(inner)?); continue; },
+                // A parenthesized expression spans its parentheses.
+                Rule::expr_call   => {
+                    let mut expr = self.expr(first(inner))?;
+                    expr.span = span;
+                    operand = Some(expr);
+                    continue;
+                },
+                Rule::path_expr   => ExprKind::Path(path_name(inner)),
+                Rule::struct_lit  => {
+                    let mut lit = inner.into_inner();
+                    let name = lit.next().unwrap().as_str().to_string();
+                    let mut fields = Vec::new();
+                    for field in lit {
+                        let mut field = field.into_inner();
+                        let name = field.next().unwrap().as_str().to_string();
+                        fields.push((name, self.expr(field.next().unwrap())?));
+                    }
+                    ExprKind::Struct(name, self.alloc_vec(fields))
+                },
+                Rule::tuple       => ExprKind::Tuple(self.args(inner)?),
+                Rule::array       => ExprKind::Array(self.args(inner)?),
+                Rule::map         => {
+                    let mut entries = Vec::new();
+                    for entry in inner.into_inner() {
+                        let mut entry = entry.into_inner();
+                        let key = self.expr(entry.next().unwrap())?;
+                        entries.push((key, self.expr(entry.next().unwrap())?));
+                    }
+                    ExprKind::Map(self.alloc_vec(entries))
+                },
+                Rule::index       => {
+                    let indexed = operand.take().expect("index without operand");
+                    ExprKind::Index(self.alloc(indexed), self.alloc(self.expr(first(inner))?))
+                },
+                Rule::call_args   => {
+                    let callee = operand.take().expect("call without callee");
+                    ExprKind::Call(self.alloc(callee), self.args(inner)?)
+                },
+                Rule::call        => {
+                    let receiver = operand.take().expect("method call without receiver");
+                    let (name, args) = self.method_call(inner)?;
+                    ExprKind::MethodCall(self.alloc(receiver), name, args)
+                },
+                Rule::field       => {
+                    let tuple = operand.take().expect("field without tuple");
+                    let index = inner.as_str().parse()
+                        .map_err(|_| ParseError::InvalidLiteral(span, inner.as_str().to_string()))?;
+                    ExprKind::Field(self.alloc(tuple), index)
+                },
+                Rule::member      => {
+                    let object = operand.take().expect("member without struct");
+                    ExprKind::Member(self.alloc(object), inner.as_str().to_string())
+                },
+                Rule::safe_call   => {
+                    let receiver = operand.take().expect("method call without receiver");
+                    let (name, args) = self.method_call(first(inner))?;
+                    ExprKind::SafeMethodCall(self.alloc(receiver), name, args)
+                },
+                _                 => unexpected(inner),
+            };
+            operand = Some(self.expr_node(kind, suffixed.unwrap_or(span)));
+        }
+        let mut operand = operand.expect("grammar guarantees an operand");
+        // Prefix operators apply right to left: `-!a` is `-(!a)`.
+        while let Some((op, span)) = prefix.pop() {
+            let span = span.to(operand.span);
+            operand = self.expr_node(ExprKind::Unary(op, self.alloc(operand)), span);
+        }
+        Ok(operand)
+    }
+
+    /// Splits a `call` into the method name and its arguments.
+    fn method_call<'i>(&self, pair: Pair<'i, Rule>)
+        -> Result<(String, &'ast [Expr<'ast>]), ParseError<'i>> {
+        let mut call = pair.into_inner();
+        let name = call.next().unwrap().as_str().to_string();
+        let args = call.map(|arg| self.expr(arg)).collect::<Result<_, _>>()?;
+        Ok((name, self.alloc_vec(args)))
+    }
+
+    /// Builds a `fn_expr` or `lambda` closure.
+    fn closure<'i>(&self, pair: Pair<'i, Rule>) -> Result<Expr<'ast>, ParseError<'i>> {
+        let span = span_of(&pair);
+        let mut params = Vec::new();
+        let mut ret = None;
+        let mut body = None;
+        for inner in pair.into_inner() {
+            match inner.as_rule() {
+                Rule::fn_kw      => {},
+                Rule::fn_call    => params = self.fn_call(inner),
+                Rule::lambda_arg => params.push(self.param(inner)),
+                Rule::ret_ty     => ret = Some(self.ty(first(inner))),
+                Rule::code_block => body = Some(self.code_block(inner)?),
+                Rule::expr       => body = Some(Block {
+                    stmts: &[],
+                    span:  span_of(&inner),
+                    expr:  Some(self.alloc(self.expr(inner)?)),
+                }),
+                _                => unexpected(inner),
+            }
+        }
+        let body = body.expect("grammar guarantees a closure body");
+        let params = self.alloc_vec(params);
+        let kind = ExprKind::Closure(self.alloc(Closure { params, ret, body, span }));
+        Ok(self.expr_node(kind, span))
+    }
+
+    fn args<'i>(&self, pair: Pair<'i, Rule>) -> Result<&'ast [Expr<'ast>], ParseError<'i>> {
+        let args = pair.into_inner().map(|arg| self.expr(arg)).collect::<Result<_, _>>()?;
+        Ok(self.alloc_vec(args))
+    }
+
+    fn literal<'i>(&self, pair: Pair<'i, Rule>) -> Result<Expr<'ast>, ParseError<'i>> {
+        let span = span_of(&pair);
+        let inner = first(pair);
+        let lit = match inner.as_rule() {
+            Rule::nil      => Literal::Nil,
+            Rule::boolean  => Literal::Boolean(first(inner).as_rule() == Rule::true_value),
+            Rule::ident |
+            Rule::constant => {
+                let kind = ExprKind::Identifier(inner.as_str().to_string());
+                return Ok(self.expr_node(kind, span));
+            },
+            Rule::number   => number(first(inner))?,
+            Rule::string   => return Ok(self.expr_node(self.string(inner)?, span)),
+            Rule::raw_string    => Literal::Str(first(inner).as_str().to_string()),
+            Rule::multiline_str => {
+                let body = first(inner);
+                Literal::Str(decode_escapes(&dedent(body.as_str()), span_of(&body))?)
+            },
+            Rule::char     => {
+                let value = string_value(inner)?;
+                match value.chars().next() {
+                    Some(chr) if value.chars().count() == 1 => Literal::Char(chr),
+                    _ => return Err(ParseError::InvalidLiteral(span, value)),
+                }
+            },
+            _              => unexpected(inner),
+        };
+        Ok(self.expr_node(ExprKind::Literal(lit), span))
+    }
+
+    /// Builds a string literal, or an interpolated string when it embeds
+    /// expressions. Adjacent text and escapes form a single fragment.
+    fn string<'i>(&self, pair: Pair<'i, Rule>) -> Result<ExprKind<'ast>, ParseError<'i>> {
+        if !pair.clone().into_inner().any(|inner| inner.as_rule() == Rule::interpolation) {
+            return Ok(ExprKind::Literal(Literal::Str(string_value(pair)?)));
+        }
+        let mut fragments = Vec::new();
+        let mut text: Option<(String, Span)> = None;
+        for inner in pair.into_inner() {
+            let span = span_of(&inner);
+            if inner.as_rule() == Rule::interpolation {
+                if let Some((value, span)) = text.take() {
+                    fragments.push(Fragment::Str(value, span));
+                }
+                fragments.push(Fragment::Expr(self.expr(first(inner))?, span));
+                continue;
+            }
+            let &mut (ref mut value, ref mut text_span) =
+                text.get_or_insert_with(|| (String::new(), span));
+            text_span.1 = span.1;
+            push_text(value, inner)?;
+        }
+        if let Some((value, span)) = text {
+            fragments.push(Fragment::Str(value, span));
+        }
+        Ok(ExprKind::Interpolated(self.alloc_vec(fragments)))
+    }
+}
+
+fn use_dcl(pair: Pair<Rule>) -> UseDecl {
+    let span = span_of(&pair);
+    let path = pair.into_inner()
+        .find(|inner| inner.as_rule() == Rule::path_name)
+        .map(path_name)
+        .expect("grammar guarantees a use path");
+    UseDecl { path, span }
+}
+
+/// Builds a `Path` from a `path_name` or `path_expr` pair.  Both rules are
+/// compound-atomic, so the pair text is the path exactly as written.
+pub fn path_name(pair: Pair<Rule>) -> Path {
+    pair.as_str().parse().expect("grammar guarantees a well formed path")
+}
+
+/// The name of a `label`, without its leading quote.
+fn label_name(pair: Pair<Rule>) -> String {
+    pair.as_str()[1..].to_string()
+}
+
+fn type_param(pair: Pair<Rule>) -> TypeParam {
+    let span = span_of(&pair);
+    let mut names = pair.into_inner().map(|ident| ident.as_str().to_string());
+    let name = names.next().unwrap();
+    TypeParam { name, bounds: names.collect(), span }
+}
+
+fn is_block_stmt(pair: &Pair<Rule>) -> bool {
+    pair.clone().into_inner().next().is_some_and(|inner| inner.as_rule() == Rule::block_stmt)
+}
+
+fn prim_type(pair: Pair<Rule>) -> PrimType {
+    let inner = first(pair);
+    match inner.as_rule() {
+        Rule::void_type => PrimType::Void,
+        Rule::str_type  => PrimType::Str,
+        Rule::char_type => PrimType::Char,
+        Rule::bool_type => PrimType::Bool,
+        Rule::i8_type   => PrimType::I8,
+        Rule::u8_type   => PrimType::U8,
+        Rule::i16_type  => PrimType::I16,
+        Rule::u16_type  => PrimType::U16,
+        Rule::i32_type  => PrimType::I32,
+        Rule::u32_type  => PrimType::U32,
+        Rule::i64_type  => PrimType::I64,
+        Rule::u64_type  => PrimType::U64,
+        Rule::f32_type  => PrimType::F32,
+        Rule::f64_type  => PrimType::F64,
+        Rule::nil_type  => PrimType::Nil,
+        _               => unexpected(inner),
+    }
+}
+
+fn binary_op(pair: Pair<Rule>) -> BinaryOp {
+    match pair.as_rule() {
+        Rule::add         => BinaryOp::Arithmetic(ArithmeticOp::Add),
+        Rule::sub         => BinaryOp::Arithmetic(ArithmeticOp::Sub),
+        Rule::mult        => BinaryOp::Arithmetic(ArithmeticOp::Mul),
+        Rule::div         => BinaryOp::Arithmetic(ArithmeticOp::Div),
+        Rule::modulo      => BinaryOp::Arithmetic(ArithmeticOp::Modulo),
+        Rule::bwand       => BinaryOp::BitWise(BitWiseOp::BwAnd),
+        Rule::bwor        => BinaryOp::BitWise(BitWiseOp::BwOr),
+        Rule::bwxor       => BinaryOp::BitWise(BitWiseOp::BwXor),
+        Rule::lshft       => BinaryOp::BitWise(BitWiseOp::BwShftL),
+        Rule::rshft       => BinaryOp::BitWise(BitWiseOp::BwShftR),
+        Rule::cmp_eql     => BinaryOp::Comparison(ComparisonOp::Eql),
+        Rule::cmp_not_eql => BinaryOp::Comparison(ComparisonOp::NotEql),
+        Rule::cmp_gt      => BinaryOp::Comparison(ComparisonOp::Gt),
+        Rule::cmp_gt_eql  => BinaryOp::Comparison(ComparisonOp::GtEql),
+        Rule::cmp_lt      => BinaryOp::Comparison(ComparisonOp::Lt),
+        Rule::cmp_lt_eql  => BinaryOp::Comparison(ComparisonOp::LtEql),
+        Rule::lazy_and    => BinaryOp::Logical(LogicalOp::And),
+        Rule::lazy_or     => BinaryOp::Logical(LogicalOp::Or),
+        _                 => unexpected(pair),
+    }
+}
+
+fn number<'i>(pair: Pair<'i, Rule>) -> Result<Literal, ParseError<'i>> {
+    let span = span_of(&pair);
+    let text = pair.as_str().replace("_", "");
+    let parsed = match pair.as_rule() {
+        Rule::float           => return text.parse().map(Literal::Float)
+            .map_err(|_| ParseError::InvalidLiteral(span, text.clone())),
+        Rule::integer         => text.parse(),
+        Rule::int_hexadecimal => u64::from_str_radix(&text[2..], 16),
+        Rule::int_octal       => u64::from_str_radix(&text[2..], 8),
+        Rule::int_binary      => u64::from_str_radix(&text[2..], 2),
+        _                     => unexpected(pair),
+    };
+    // Integer literals range up to the largest `u64`.
+    parsed.map(|i: u64| Literal::Int(i128::from(i)))
+        .map_err(|_| ParseError::InvalidLiteral(span, text))
+}
+
+/// Decodes the contents of a `string` or `char` pair.
+fn string_value<'i>(pair: Pair<'i, Rule>) -> Result<String, ParseError<'i>> {
+    let mut value = String::new();
+    for inner in pair.into_inner() {
+        push_text(&mut value, inner)?;
+    }
+    Ok(value)
+}
+
+/// Appends raw text or the character of an escape sequence to `value`.
+fn push_text<'i>(value: &mut String, pair: Pair<'i, Rule>) -> Result<(), ParseError<'i>> {
+    match pair.as_rule() {
+        Rule::raw_str | Rule::raw_char => value.push_str(pair.as_str()),
+        Rule::escape => match unescape(pair.as_str()) {
+            Some(chr) => value.push(chr),
+            None      => {
+                return Err(ParseError::InvalidLiteral(span_of(&pair), pair.as_str().to_string()))
+            },
+        },
+        _ => unexpected(pair),
+    }
+    Ok(())
+}
+
+/// Decodes the escape sequences of text that was rewritten after parsing,
+/// such as the dedented body of a multi-line string. Each sequence is
+/// delimited as the `escape` rule does.
+fn decode_escapes<'i>(text: &str, span: Span) -> Result<String, ParseError<'i>> {
+    let mut value = String::new();
+    let mut chars = text.chars().peekable();
+    while let Some(chr) = chars.next() {
+        if chr != '\\' {
+            value.push(chr);
+            continue;
+        }
+        let mut seq = String::from("\\");
+        let (radix, len) = match chars.next() {
+            Some('x')                      => { seq.push('x'); (16, 4) },
+            Some('u')                      => { seq.push('u'); (16, 6) },
+            Some(chr) if chr.is_digit(8)   => { seq.push(chr); (8, 4) },
+            Some(chr)                      => { seq.push(chr); (10, 0) },
+            None                           => (10, 0),
+        };
+        while seq.len() < len && chars.peek().is_some_and(|chr| chr.is_digit(radix)) {
+            seq.push(chars.next().unwrap());
+        }
+        match unescape(&seq) {
+            Some(chr) => value.push(chr),
+            None      => return Err(ParseError::InvalidLiteral(span, seq)),
+        }
+    }
+    Ok(value)
+}
+
+/// Maps an escape sequence such as `\n`, `\x0F`, `\u2107` or `\0` to the
+/// character it represents.
+fn unescape(escape: &str) -> Option<char> {
+    let seq = &escape[1..];
+    let chr = match seq {
+        "n"  => '\n',
+        "t"  => '\t',
+        "v"  => '\u{000B}',
+        "b"  => '\u{0008}',
+        "r"  => '\r',
+        "f"  => '\u{000C}',
+        "a"  => '\u{0007}',
+        "\\" => '\\',
+        "?"  => '?',
+        "'"  => '\'',
+        "\"" => '"',
+        "$"  => '$',
+        _    => {
+            let code = if seq.starts_with('x') || seq.starts_with('u') {
+                u32::from_str_radix(&seq[1..], 16)
+            } else {
+                u32::from_str_radix(seq, 8)
+            };
+            return code.ok().and_then(::std::char::from_u32);
+        },
+    };
+    Some(chr)
+}
+
+/// The first inner pair of rules that wrap a single child.
+fn first(pair: Pair<Rule>) -> Pair<Rule> {
+    pair.into_inner().next().expect("grammar guarantees an inner rule")
+}
+
+fn unexpected(pair: Pair<Rule>) -> ! {
+    panic!("unexpected rule {:?}: {:?}", pair.as_rule(), pair.as_str())
+}
@@ -1,8 +1,15 @@
+use std::fmt;
+
 use pest;
 use pest::Parser;
 // use pest::prec_climber::{Assoc, Operator, PrecClimber};
 use pest::iterators::Pairs;
 
+use syntax::ast::{Arena, FormatError, Module};
+use utils::{FileId, LexicalDiagnostic, Position, Span};
+
+pub mod build;
+
 // This include forces recompiling if grammar file changes.
 #[cfg(debug_assertions)]
 const _GRAMMAR: &'static str = include_str!("argentum.pest");
@@ -40,13 +47,114 @@ pub struct SilverParser;
 #[derive(Debug)]
 pub enum ParseError<'i> {
     Pest(pest::Error<'i, Rule>),
+    /// A literal that matches the grammar but has no valid value, such as an
+    /// out of range integer or an unknown escape sequence.
+    InvalidLiteral(Span, String),
+    /// The format string of a `print` statement is malformed.
+    InvalidFormat(Span, FormatError),
+    /// The input could not be split into tokens.
+    Lexical(LexicalDiagnostic),
+    /// A rule matched a prefix of the input, the rest starts at this offset.
+    TrailingInput(usize),
+}
+
+impl<'i> fmt::Display for ParseError<'i> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ParseError::Pest(ref err) => write!(f, "{}", err),
+            ParseError::InvalidLiteral(ref span, ref literal) => {
+                write!(f, "{}: invalid literal `{}`", span, literal)
+            },
+            ParseError::InvalidFormat(ref span, ref err) => write!(f, "{}: {}", span, err),
+            ParseError::Lexical(ref err) => write!(f, "{}", err),
+            ParseError::TrailingInput(offset) => {
+                write!(f, "unexpected input at offset {}", offset)
+            },
+        }
+    }
+}
+
+impl<'i> ParseError<'i> {
+    /// The location of the error in `input`, the source that was parsed.
+    pub fn span(&self, input: &str) -> Span {
+        match *self {
+            ParseError::Pest(ref err) => pest_span(err),
+            ParseError::InvalidLiteral(span, _) | ParseError::InvalidFormat(span, _) => span,
+            ParseError::Lexical(ref err) => err.span,
+            ParseError::TrailingInput(offset) => {
+                let before = &input[..offset];
+                let row = before.matches('\n').count() + 1;
+                let col = before.rsplit('\n').next().map_or(0, |line| line.chars().count()) + 1;
+                let pos = Position(row as u32, col as u32);
+                Span(pos, pos)
+            },
+        }
+    }
+
+    /// The error without its location, which `span` reports.
+    pub fn message(&self) -> String {
+        match *self {
+            ParseError::Pest(ref err) => pest_message(err),
+            ParseError::InvalidLiteral(_, ref literal) => format!("invalid literal `{}`", literal),
+            ParseError::InvalidFormat(_, ref err) => err.to_string(),
+            ParseError::Lexical(ref err) => err.msg.clone(),
+            ParseError::TrailingInput(_) => "unexpected input".to_string(),
+        }
+    }
+}
+
+/// The row/column span a pest error points at.
+fn pest_span(err: &pest::Error<Rule>) -> Span {
+    let (start, end) = match *err {
+        pest::Error::ParsingError { ref pos, .. } |
+        pest::Error::CustomErrorPos { ref pos, .. } => (pos.line_col(), pos.line_col()),
+        pest::Error::CustomErrorSpan { ref span, .. } => {
+            (span.start_pos().line_col(), span.end_pos().line_col())
+        },
+    };
+    Span(Position(start.0 as u32, start.1 as u32), Position(end.0 as u32, end.1 as u32))
+}
+
+/// The rules a pest error expected, or its custom message.
+fn pest_message(err: &pest::Error<Rule>) -> String {
+    let rules = |rules: &[Rule]| {
+        rules.iter().map(|rule| format!("{:?}", rule)).collect::<Vec<_>>().join(", ")
+    };
+    match *err {
+        pest::Error::ParsingError { ref positives, .. } if !positives.is_empty() => {
+            format!("expected {}", rules(positives))
+        },
+        pest::Error::ParsingError { ref negatives, .. } => {
+            format!("unexpected {}", rules(negatives))
+        },
+        pest::Error::CustomErrorPos { ref message, .. } |
+        pest::Error::CustomErrorSpan { ref message, .. } => message.clone(),
+    }
 }
 
 /// Parse Silver data contained in a string slice.
-pub fn parse(input: &str) -> Result<Pairs<Rule>, ParseError> {
+pub fn parse(input: &str) -> Result<Pairs<'_, Rule>, ParseError<'_>> {
     SilverParser::parse(Rule::top_lvl, input).map_err(|error| ParseError::Pest(error))
 }
 
+/// Parse a prefix of `input` as `rule`, such as a `statement`.
+pub fn parse_rule(rule: Rule, input: &str) -> Result<Pairs<'_, Rule>, ParseError<'_>> {
+    SilverParser::parse(rule, input).map_err(ParseError::Pest)
+}
+
+/// Parse Silver data contained in a string slice into a `Module` whose nodes
+/// are allocated in `arena`.  The nodes are located in file 0.
+pub fn parse_module<'ast, 'i>(arena: &'ast Arena<'ast>, input: &'i str)
+    -> Result<Module<'ast>, ParseError<'i>> {
+    parse_file(arena, input, FileId::default())
+}
+
+/// Parse the contents of `file` into a `Module` whose nodes are located in it.
+pub fn parse_file<'ast, 'i>(arena: &'ast Arena<'ast>, input: &'i str, file: FileId)
+    -> Result<Module<'ast>, ParseError<'i>> {
+    build::module(arena, parse(input)?, file)
+}
+
 #[test]
 fn bool_literal_true() {
     parses_to! {
@@ -548,3 +656,649 @@ fn typecheck_f64() {
         ]
     };
 }
+
+#[test]
+fn use_dcl_absolute_path() {
+    parses_to! {
+        parser: SilverParser,
+        input: "use ::geometry::area;",
+        rule: Rule::use_dcl,
+        tokens: [
+            use_dcl(0, 21, [
+                    use_kw(0, 3),
+                    path_name(4, 20, [
+                              path_root(4, 6),
+                              ident(6, 14),
+                              ident(16, 20)
+                    ])
+            ])
+        ]
+    };
+}
+
+#[test]
+fn module_pub_items() {
+    use syntax::ast::{ItemKind, StmtKind, Visibility};
+
+    let arena = Arena::new();
+    let module = parse_module(&arena, "pub use shapes::area; fn helper() { }").unwrap();
+    let items = module.items();
+    assert_eq!(items.len(), 2);
+    assert_eq!(items[0].vis, Visibility::Public);
+    assert_eq!(items[0].name(), "area");
+    match items[0].kind {
+        ItemKind::Use(ref decl) => assert_eq!(decl.path.clone().as_str(), "shapes::area"),
+        _ => panic!("expected a use declaration"),
+    }
+    assert_eq!(items[1].vis, Visibility::Private);
+    assert!(matches!(module.stmts[1].kind, StmtKind::Item(_)));
+}
+
+#[test]
+fn keyword_prefixed_ident() {
+    use syntax::ast::StmtKind;
+
+    let arena = Arena::new();
+    let module = parse_module(&arena, "letter;").unwrap();
+    assert!(matches!(module.stmts[0].kind, StmtKind::Expr(_)));
+}
+
+#[test]
+fn binary_precedence() {
+    use syntax::ast::binop::{ArithmeticOp, LogicalOp};
+    use syntax::ast::{BinaryOp, Expr, ExprKind, Literal, Stmt, StmtKind};
+
+    let arena = Arena::new();
+    let module = parse_module(&arena, "1 + 2 * 3 || b;").unwrap();
+    let int = |value| arena.alloc(Expr::from(ExprKind::Literal(Literal::Int(value))));
+    let sum = Expr::from(ExprKind::Binary(
+        BinaryOp::Arithmetic(ArithmeticOp::Add),
+        int(1),
+        arena.alloc(Expr::from(
+            ExprKind::Binary(BinaryOp::Arithmetic(ArithmeticOp::Mul), int(2), int(3))))));
+    let expected = Expr::from(ExprKind::Binary(
+        BinaryOp::Logical(LogicalOp::Or),
+        arena.alloc(sum),
+        arena.alloc(Expr::from(ExprKind::Identifier("b".to_string())))));
+    assert_eq!(module.stmts, vec![Stmt::from(StmtKind::Expr(expected))]);
+}
+
+#[test]
+fn qualified_path_expr() {
+    use syntax::ast::{Expr, ExprKind, Stmt, StmtKind};
+
+    let arena = Arena::new();
+    let module = parse_module(&arena, "::std::io::print(Shape::Circle);").unwrap();
+    let expected = Expr::from(ExprKind::Call(
+        arena.alloc(Expr::from(ExprKind::Path("::std::io::print".parse().unwrap()))),
+        arena.alloc_vec(vec![Expr::from(ExprKind::Path("Shape::Circle".parse().unwrap()))])));
+    assert_eq!(module.stmts, vec![Stmt::from(StmtKind::Expr(expected))]);
+}
+
+#[test]
+fn ternary_right_associative() {
+    use syntax::ast::binop::LogicalOp;
+    use syntax::ast::{BinaryOp, Expr, ExprKind, Stmt, StmtKind};
+
+    let arena = Arena::new();
+    let module = parse_module(&arena, "a || b ? c : d ? e : f;").unwrap();
+    let ident = |name: &str| arena.alloc(Expr::from(ExprKind::Identifier(name.to_string())));
+    let expected = Expr::from(ExprKind::Ternary(
+        arena.alloc(Expr::from(
+            ExprKind::Binary(BinaryOp::Logical(LogicalOp::Or), ident("a"), ident("b")))),
+        ident("c"),
+        arena.alloc(Expr::from(ExprKind::Ternary(ident("d"), ident("e"), ident("f"))))));
+    assert_eq!(module.stmts, vec![Stmt::from(StmtKind::Expr(expected))]);
+}
+
+#[test]
+fn if_expr_as_block_tail() {
+    use syntax::ast::{Block, Expr, ExprKind, Literal, StmtKind};
+
+    let arena = Arena::new();
+    let module = parse_module(&arena, "fn f() { if a { 1 } else if b { 2 } }").unwrap();
+    let int = |value| Block {
+        stmts: &[],
+        expr:  Some(arena.alloc(Expr::from(ExprKind::Literal(Literal::Int(value))))),
+        span:  Span::default(),
+    };
+    let ident = |name: &str| arena.alloc(Expr::from(ExprKind::Identifier(name.to_string())));
+    let expected = Expr::from(ExprKind::If(
+        ident("a"),
+        int(1),
+        Some(arena.alloc(Expr::from(ExprKind::If(ident("b"), int(2), None))))));
+    match module.stmts[0].kind {
+        StmtKind::Item(ref item) => match item.kind {
+            ::syntax::ast::ItemKind::Fn(ref decl) => {
+                assert!(decl.body.stmts.is_empty());
+                assert_eq!(decl.body.expr, Some(arena.alloc(expected)));
+            },
+            _ => panic!("expected a function"),
+        },
+        _ => panic!("expected an item"),
+    }
+}
+
+#[test]
+fn cast_binds_tighter_than_binary() {
+    use syntax::ast::binop::ArithmeticOp;
+    use syntax::ast::unop::UnaryOp;
+    use syntax::ast::{BinaryOp, Expr, ExprKind, Literal, PrimType, Stmt, StmtKind};
+
+    let arena = Arena::new();
+    let module = parse_module(&arena, "-a -> u8 -> i32 * 2;").unwrap();
+    let ident = Expr::from(ExprKind::Identifier("a".to_string()));
+    let neg = Expr::from(ExprKind::Unary(UnaryOp::Minus, arena.alloc(ident)));
+    let cast = Expr::from(ExprKind::Cast(arena.alloc(neg), PrimType::U8));
+    let cast = Expr::from(ExprKind::Cast(arena.alloc(cast), PrimType::I32));
+    let expected = Expr::from(ExprKind::Binary(
+        BinaryOp::Arithmetic(ArithmeticOp::Mul),
+        arena.alloc(cast),
+        arena.alloc(Expr::from(ExprKind::Literal(Literal::Int(2))))));
+    assert_eq!(module.stmts, vec![Stmt::from(StmtKind::Expr(expected))]);
+}
+
+#[test]
+fn optional_types_and_safe_calls() {
+    use syntax::ast::{Expr, ExprKind, Literal, PrimType, Stmt, StmtKind, Type, TypeKind};
+
+    let arena = Arena::new();
+    let module = parse_module(&arena, "let a: i32? = nil; a?.f(nil).g();").unwrap();
+    match module.stmts[0].kind {
+        StmtKind::Let(ref local) => {
+            let i32_ty = Type::from(TypeKind::Prim(PrimType::I32));
+            assert_eq!(local.ty, Some(Type::from(TypeKind::Optional(arena.alloc(i32_ty)))));
+            assert_eq!(local.init, Some(Expr::from(ExprKind::Literal(Literal::Nil))));
+        },
+        _ => panic!("expected a let statement"),
+    }
+    let safe = Expr::from(ExprKind::SafeMethodCall(
+        arena.alloc(Expr::from(ExprKind::Identifier("a".to_string()))),
+        "f".to_string(),
+        arena.alloc_vec(vec![Expr::from(ExprKind::Literal(Literal::Nil))])));
+    let expected = Expr::from(ExprKind::MethodCall(arena.alloc(safe), "g".to_string(), &[]));
+    assert_eq!(module.stmts[1], Stmt::from(StmtKind::Expr(expected)));
+}
+
+#[test]
+fn tuples_and_patterns() {
+    use syntax::ast::{Expr, ExprKind, Literal, Pattern, PatternKind, PrimType, Stmt, StmtKind, Type,
+                      TypeKind};
+
+    let arena = Arena::new();
+    let source = "let (a, (b,)): (i32, (str,)) = (1, (\"b\",)); (a).0; ();";
+    let module = parse_module(&arena, source).unwrap();
+    let ident = |name: &str| Pattern::from(PatternKind::Ident(name.to_string()));
+    match module.stmts[0].kind {
+        StmtKind::Let(ref local) => {
+            assert_eq!(local.pat, Pattern::from(PatternKind::Tuple(arena.alloc_vec(vec![
+                ident("a"),
+                Pattern::from(PatternKind::Tuple(arena.alloc_vec(vec![ident("b")])))]))));
+            assert_eq!(local.ty, Some(Type::from(TypeKind::Tuple(arena.alloc_vec(vec![
+                Type::from(TypeKind::Prim(PrimType::I32)),
+                Type::from(TypeKind::Tuple(arena.alloc_vec(vec![
+                    Type::from(TypeKind::Prim(PrimType::Str))])))])))));
+            assert_eq!(local.init, Some(Expr::from(ExprKind::Tuple(arena.alloc_vec(vec![
+                Expr::from(ExprKind::Literal(Literal::Int(1))),
+                Expr::from(ExprKind::Tuple(arena.alloc_vec(vec![
+                    Expr::from(ExprKind::Literal(Literal::Str("b".to_string())))])))])))));
+        },
+        _ => panic!("expected a let statement"),
+    }
+    let tuple = Expr::from(ExprKind::Identifier("a".to_string()));
+    let field = Expr::from(ExprKind::Field(arena.alloc(tuple), 0));
+    assert_eq!(module.stmts[1], Stmt::from(StmtKind::Expr(field)));
+    assert_eq!(module.stmts[2], Stmt::from(StmtKind::Expr(Expr::from(ExprKind::Tuple(&[])))));
+}
+
+#[test]
+fn ranges_collections_and_for() {
+    use syntax::ast::binop::ArithmeticOp;
+    use syntax::ast::{BinaryOp, Expr, ExprKind, Literal, Pattern, PatternKind, RangeLimits, Stmt,
+                      StmtKind};
+
+    let arena = Arena::new();
+    let source = "for (k, v) in [\"a\": 0..n + 1] { xs[0..=2]; } [1, 2,]; [:]; [];";
+    let module = parse_module(&arena, source).unwrap();
+    let int = |value| Expr::from(ExprKind::Literal(Literal::Int(value)));
+    let ident = |name: &str| Expr::from(ExprKind::Identifier(name.to_string()));
+    let range = Expr::from(ExprKind::Range(
+        arena.alloc(int(0)),
+        arena.alloc(Expr::from(ExprKind::Binary(
+            BinaryOp::Arithmetic(ArithmeticOp::Add),
+            arena.alloc(ident("n")),
+            arena.alloc(int(1))))),
+        RangeLimits::HalfOpen));
+    match module.stmts[0].kind {
+        StmtKind::For(ref for_loop) => {
+            assert_eq!(for_loop.pat, Pattern::from(PatternKind::Tuple(arena.alloc_vec(vec![
+                Pattern::from(PatternKind::Ident("k".to_string())),
+                Pattern::from(PatternKind::Ident("v".to_string()))]))));
+            let key = Expr::from(ExprKind::Literal(Literal::Str("a".to_string())));
+            let map = arena.alloc_vec(vec![(key, range)]);
+            assert_eq!(for_loop.iter, Expr::from(ExprKind::Map(map)));
+            let index = Expr::from(ExprKind::Index(
+                arena.alloc(ident("xs")),
+                arena.alloc(Expr::from(ExprKind::Range(
+                    arena.alloc(int(0)), arena.alloc(int(2)), RangeLimits::Closed)))));
+            assert_eq!(for_loop.body.stmts, vec![Stmt::from(StmtKind::Expr(index))]);
+        },
+        _ => panic!("expected a for loop"),
+    }
+    let array = Expr::from(ExprKind::Array(arena.alloc_vec(vec![int(1), int(2)])));
+    assert_eq!(module.stmts[1], Stmt::from(StmtKind::Expr(array)));
+    assert_eq!(module.stmts[2], Stmt::from(StmtKind::Expr(Expr::from(ExprKind::Map(&[])))));
+    assert_eq!(module.stmts[3], Stmt::from(StmtKind::Expr(Expr::from(ExprKind::Array(&[])))));
+}
+
+#[test]
+fn const_dcl() {
+    use syntax::ast::binop::BitWiseOp;
+    use syntax::ast::{BinaryOp, ConstDecl, Expr, ExprKind, ItemKind, Literal, PrimType, Stmt,
+                      StmtKind, Type, TypeKind};
+
+    let arena = Arena::new();
+    let module = parse_module(&arena, "pub const MAX_LEN: u32 = 1 << 8; MAX_LEN;").unwrap();
+    match module.stmts[0].kind {
+        StmtKind::Item(ref item) => {
+            assert!(item.is_pub());
+            assert_eq!(item.kind, ItemKind::Const(ConstDecl {
+                name:  "MAX_LEN".to_string(),
+                ty:    Type::from(TypeKind::Prim(PrimType::U32)),
+                value: Expr::from(ExprKind::Binary(BinaryOp::BitWise(BitWiseOp::BwShftL),
+                                    arena.alloc(Expr::from(ExprKind::Literal(Literal::Int(1)))),
+                                    arena.alloc(Expr::from(ExprKind::Literal(Literal::Int(8)))))),
+            }));
+        },
+        _ => panic!("expected a const item"),
+    }
+    let ident = Expr::from(ExprKind::Identifier("MAX_LEN".to_string()));
+    assert_eq!(module.stmts[1], Stmt::from(StmtKind::Expr(ident)));
+    assert!(parse_module(&arena, "const X = 1;").is_err());
+    // Constant names are upper case.
+    assert!(parse_module(&arena, "const max: u32 = 1;").is_err());
+}
+
+#[test]
+fn type_alias() {
+    use syntax::ast::{ItemKind, PrimType, StmtKind, Type, TypeAlias, TypeKind};
+
+    let arena = Arena::new();
+    let source = "type Callback = fn(Id) -> bool?; let f: [Callback] = [];";
+    let module = parse_module(&arena, source).unwrap();
+    let named = |name: &str| Type::from(TypeKind::Named(name.to_string(), &[]));
+    let bool_ty = Type::from(TypeKind::Prim(PrimType::Bool));
+    let alias = TypeAlias {
+        name: "Callback".to_string(),
+        ty:   Type::from(TypeKind::Fn(arena.alloc_vec(vec![named("Id")]),
+                       arena.alloc(Type::from(TypeKind::Optional(arena.alloc(bool_ty)))))),
+    };
+    match module.stmts[0].kind {
+        StmtKind::Item(ref item) => assert_eq!(item.kind, ItemKind::Type(alias)),
+        _                        => panic!("expected a type alias"),
+    }
+    match module.stmts[1].kind {
+        StmtKind::Let(ref local) => {
+            assert_eq!(local.ty, Some(Type::from(TypeKind::Array(arena.alloc(named("Callback"))))));
+        },
+        _ => panic!("expected a let statement"),
+    }
+    assert!(parse_module(&arena, "type i32 = u8;").is_err());
+}
+
+#[test]
+fn generic_fn_dcl() {
+    use syntax::ast::{ItemKind, StmtKind, Type, TypeKind, TypeParam};
+
+    let arena = Arena::new();
+    let module = parse_module(&arena, "fn pick<T, U: Ord + Show,>(a: T, b: U) -> T { a }").unwrap();
+    let named = |name: &str| Type::from(TypeKind::Named(name.to_string(), &[]));
+    match module.stmts[0].kind {
+        StmtKind::Item(ref item) => match item.kind {
+            ItemKind::Fn(ref decl) => {
+                assert_eq!(decl.generics, vec![
+                    TypeParam { name: "T".to_string(), bounds: vec![], span: Span::default() },
+                    TypeParam {
+                        name:   "U".to_string(),
+                        bounds: vec!["Ord".to_string(), "Show".to_string()],
+                        span:   Span::default(),
+                    },
+                ]);
+                assert_eq!(decl.params[1].ty, Some(named("U")));
+                assert_eq!(decl.ret, Some(named("T")));
+            },
+            _ => panic!("expected a function"),
+        },
+        _ => panic!("expected an item"),
+    }
+}
+
+#[test]
+fn struct_and_spec_dcl() {
+    use syntax::ast::{FieldDecl, ItemKind, StmtKind, Type, TypeKind, Variant};
+
+    let arena = Arena::new();
+    let module = parse_module(&arena, "
+        struct Pair<T> { first: T, second: Option<T>, }
+        spec Option<T: Eq> { Some(T), None }
+    ").unwrap();
+    let named = |name: &str, args| Type::from(TypeKind::Named(name.to_string(), args));
+    let items: Vec<_> = module.stmts.iter().map(|stmt| match stmt.kind {
+        StmtKind::Item(ref item) => &item.kind,
+        _                        => panic!("expected an item"),
+    }).collect();
+    match *items[0] {
+        ItemKind::Struct(ref decl) => {
+            assert_eq!(decl.name, "Pair");
+            assert_eq!(decl.generics.len(), 1);
+            assert_eq!(decl.fields, vec![
+                FieldDecl {
+                    name: "first".to_string(), ty: named("T", &[]), span: Span::default(),
+                },
+                FieldDecl {
+                    name: "second".to_string(),
+                    ty:   named("Option", arena.alloc_vec(vec![named("T", &[])])),
+                    span: Span::default(),
+                },
+            ]);
+        },
+        _ => panic!("expected a struct"),
+    }
+    match *items[1] {
+        ItemKind::Spec(ref decl) => {
+            assert_eq!(decl.generics[0].bounds, vec!["Eq".to_string()]);
+            assert_eq!(decl.variants, vec![
+                Variant {
+                    name:   "Some".to_string(),
+                    fields: arena.alloc_vec(vec![named("T", &[])]),
+                    span:   Span::default(),
+                },
+                Variant { name: "None".to_string(), fields: &[], span: Span::default() },
+            ]);
+            // A variant without values does not span the whitespace after it.
+            let none = &decl.variants[1].span;
+            assert_eq!((none.0 .1, none.1 .1), (39, 43));
+        },
+        _ => panic!("expected a spec"),
+    }
+    assert!(parse_module(&arena, "struct Empty { }").is_err());
+    assert!(parse_module(&arena, "spec match { A }").is_err());
+}
+
+#[test]
+fn match_and_struct_exprs() {
+    use syntax::ast::{ExprKind, PatternKind, StmtKind};
+
+    let arena = Arena::new();
+    let module = parse_module(&arena, "
+        let area = match s { Shape::Circle(r) => r * r, Shape::Empty => { 0.0 }, other => 1.0 };
+        let p = Pair { first: 1, second: 2 }.first;
+        if p { x: 1 }
+    ");
+    // A block following a condition is not a struct literal.
+    assert!(module.is_err());
+    let module = parse_module(&arena, "
+        let area = match s { Shape::Circle(r) => r * r, Shape::Empty => { 0.0 }, other => 1.0 };
+        let p = Pair { first: 1, second: 2 }.first;
+        match p { x => x }
+    ").unwrap();
+    match module.stmts[0].kind {
+        StmtKind::Let(ref local) => match local.init.as_ref().unwrap().kind {
+            ExprKind::Match(scrutinee, arms) => {
+                assert!(matches!(scrutinee.kind, ExprKind::Identifier(ref name) if name == "s"));
+                let pats: Vec<_> = arms.iter().map(|arm| arm.pat.to_string()).collect();
+                assert_eq!(pats, vec!["Shape::Circle(r)", "Shape::Empty", "other"]);
+                match arms[0].pat.kind {
+                    PatternKind::Variant(ref path, pats) => {
+                        assert_eq!(path.to_string(), "Shape::Circle");
+                        assert_eq!(pats.len(), 1);
+                    },
+                    _ => panic!("expected a variant pattern"),
+                }
+                assert!(matches!(arms[1].body.kind, ExprKind::Block(_)));
+            },
+            _ => panic!("expected a match"),
+        },
+        _ => panic!("expected a let statement"),
+    }
+    match module.stmts[1].kind {
+        StmtKind::Let(ref local) => match local.init.as_ref().unwrap().kind {
+            ExprKind::Member(object, ref field) => {
+                assert_eq!(field, "first");
+                match object.kind {
+                    ExprKind::Struct(ref name, fields) => {
+                        assert_eq!(name, "Pair");
+                        assert_eq!(fields.len(), 2);
+                    },
+                    _ => panic!("expected a struct literal"),
+                }
+            },
+            _ => panic!("expected a member"),
+        },
+        _ => panic!("expected a let statement"),
+    }
+    // `match` as a statement needs no semi colon.
+    match module.stmts[2].kind {
+        StmtKind::Expr(ref expr) => assert!(matches!(expr.kind, ExprKind::Match(..))),
+        _                        => panic!("expected an expression statement"),
+    }
+}
+
+#[test]
+fn labeled_loops() {
+    use syntax::ast::{Block, Expr, ExprKind, Literal, Local, Pattern, PatternKind, Stmt, StmtKind};
+
+    let arena = Arena::new();
+    let module = parse_module(&arena, "
+        'outer: while go { continue 'outer; }
+        let x = 'l: loop { break 'l 'c'; };
+        loop { break; }
+    ").unwrap();
+    match module.stmts[0].kind {
+        StmtKind::While(ref while_loop) => {
+            assert_eq!(while_loop.label, Some("outer".to_string()));
+            assert_eq!(while_loop.cond, Expr::from(ExprKind::Identifier("go".to_string())));
+            let stmt = Stmt::from(StmtKind::Continue(Some("outer".to_string())));
+            assert_eq!(while_loop.body.stmts, vec![stmt]);
+        },
+        _ => panic!("expected a while loop"),
+    }
+    let value = Expr::from(ExprKind::Literal(Literal::Char('c')));
+    let body = Block {
+        stmts: arena.alloc_vec(vec![
+            Stmt::from(StmtKind::Break(Some("l".to_string()), Some(value)))]),
+        expr:  None,
+        span:  Span::default(),
+    };
+    assert_eq!(module.stmts[1], Stmt::from(StmtKind::Let(Local {
+        pat:  Pattern::from(PatternKind::Ident("x".to_string())),
+        ty:   None,
+        init: Some(Expr::from(ExprKind::Loop(Some("l".to_string()), body))),
+    })));
+    let body = Block {
+        stmts: arena.alloc_vec(vec![Stmt::from(StmtKind::Break(None, None))]),
+        expr:  None,
+        span:  Span::default(),
+    };
+    assert_eq!(module.stmts[2], Stmt::from(StmtKind::Expr(Expr::from(ExprKind::Loop(None, body)))));
+}
+
+#[test]
+fn parenthesized_spans() {
+    use syntax::ast::{ExprKind, StmtKind};
+    use utils::{Position, Span};
+
+    let arena = Arena::new();
+    let module = parse_module(&arena, "let y = -(1 + x);\n(a + b).len();").unwrap();
+    let span = |row, start, end| Span(Position(row, start), Position(row, end));
+    let init = match module.stmts[0].kind {
+        StmtKind::Let(ref local) => local.init.clone().unwrap(),
+        _ => panic!("expected a let statement"),
+    };
+    assert_eq!(init.span, span(1, 9, 17));
+    match init.kind {
+        ExprKind::Unary(_, operand) => assert_eq!(operand.span, span(1, 10, 17)),
+        other => panic!("expected a negation, found {:?}", other),
+    }
+    match module.stmts[1].kind {
+        StmtKind::Expr(ref call) => {
+            assert_eq!(call.span, span(2, 1, 14));
+            match call.kind {
+                ExprKind::MethodCall(receiver, ..) => assert_eq!(receiver.span, span(2, 1, 8)),
+                ref other => panic!("expected a method call, found {:?}", other),
+            }
+        },
+        _ => panic!("expected an expression statement"),
+    }
+}
+
+#[test]
+fn parameter_and_block_spans() {
+    use syntax::ast::{ExprKind, ItemKind, StmtKind};
+    use utils::{Position, Span};
+
+    let arena = Arena::new();
+    let module = parse_module(&arena, "fn f<T: Ord>(a: T) { }\nlet g = |x| x;").unwrap();
+    let span = |row, start, end| Span(Position(row, start), Position(row, end));
+    match module.stmts[0].kind {
+        StmtKind::Item(ref item) => match item.kind {
+            ItemKind::Fn(ref decl) => {
+                assert_eq!(decl.generics[0].span, span(1, 6, 12));
+                assert_eq!(decl.params[0].span, span(1, 14, 18));
+                assert_eq!(decl.body.span, span(1, 20, 23));
+            },
+            _ => panic!("expected a function"),
+        },
+        _ => panic!("expected an item"),
+    }
+    match module.stmts[1].kind {
+        StmtKind::Let(ref local) => match local.init.as_ref().unwrap().kind {
+            ExprKind::Closure(closure) => {
+                assert_eq!(closure.span, span(2, 9, 14));
+                assert_eq!(closure.params[0].span, span(2, 10, 11));
+                assert_eq!(closure.body.span, span(2, 13, 14));
+            },
+            ref other => panic!("expected a closure, found {:?}", other),
+        },
+        _ => panic!("expected a let statement"),
+    }
+}
+
+#[test]
+fn print_stmt() {
+    use syntax::ast::{Expr, ExprKind, Format, Print, Stmt, StmtKind};
+    use utils::{Position, Span};
+
+    let arena = Arena::new();
+    let source = "print; println \"{:3} {}\", a, \"b\"; print \"s\" + t, u;";
+    let module = parse_module(&arena, source).unwrap();
+    let ident = |name: &str| Expr::from(ExprKind::Identifier(name.to_string()));
+    let print = Print { newline: false, format: None, args: &[] };
+    assert_eq!(module.stmts[0], Stmt::from(StmtKind::Print(print)));
+    match module.stmts[1].kind {
+        StmtKind::Print(ref print) => {
+            assert!(print.newline);
+            assert_eq!(print.format, Some("{:3} {}".parse::<Format>().unwrap()));
+            assert_eq!(print.args.len(), 2);
+        },
+        _ => panic!("expected a print statement"),
+    }
+    match module.stmts[2].kind {
+        StmtKind::Print(ref print) => {
+            assert_eq!(print.format, None);
+            assert_eq!(print.args[1], ident("u"));
+        },
+        _ => panic!("expected a print statement"),
+    }
+    match parse_module(&arena, "print \"{x}\";") {
+        Err(ParseError::InvalidFormat(_, err)) => {
+            assert_eq!(err.to_string(), "invalid format string: unknown specifier `{x}`");
+        },
+        _ => panic!("expected an invalid format"),
+    }
+    match parse_module(&arena, "println \"${m[\"k\"]} and {}\", 5;") {
+        Err(ParseError::InvalidFormat(span, err)) => {
+            assert_eq!(span, Span(Position(1, 9), Position(1, 27)));
+            assert_eq!(err.to_string(),
+                       "invalid format string: interpolations cannot be mixed with arguments");
+        },
+        _ => panic!("expected an invalid format"),
+    }
+    match parse_module(&arena, "println \"${1} {}\";").unwrap().stmts[0].kind {
+        StmtKind::Print(ref print) => {
+            assert_eq!(print.format, None);
+            assert_eq!(print.args.len(), 1);
+        },
+        _ => panic!("expected a print statement"),
+    }
+    assert!(parse_module(&arena, "let println = 1;").is_err());
+}
+
+#[test]
+fn interpolated_string() {
+    use syntax::ast::{Expr, ExprKind, Fragment, Literal, StmtKind};
+    use utils::{Position, Span};
+
+    let arena = Arena::new();
+    let source = r#"let s = "hi ${name}, ${ n + 1 }!\${x}"; let t = "\$5";"#;
+    let module = parse_module(&arena, source).unwrap();
+    let init = match module.stmts[0].kind {
+        StmtKind::Let(ref local) => local.init.clone().unwrap(),
+        _ => panic!("expected a let statement"),
+    };
+    let fragments = match init.kind {
+        ExprKind::Interpolated(fragments) => fragments,
+        other => panic!("expected an interpolated string, found {:?}", other),
+    };
+    let span = |start, end| Span(Position(1, start), Position(1, end));
+    assert_eq!(fragments.len(), 5);
+    assert_eq!(fragments[0], Fragment::Str("hi ".to_string(), span(10, 13)));
+    let name = Expr::from(ExprKind::Identifier("name".to_string()));
+    assert_eq!(fragments[1], Fragment::Expr(name, span(13, 20)));
+    assert_eq!(fragments[2], Fragment::Str(", ".to_string(), span(20, 22)));
+    match fragments[3] {
+        Fragment::Expr(Expr { kind: ExprKind::Binary(..), .. }, sp) => assert_eq!(sp, span(22, 32)),
+        ref other => panic!("expected an embedded expression, found {:?}", other),
+    }
+    assert_eq!(fragments[4], Fragment::Str("!${x}".to_string(), span(32, 38)));
+    // Fragments compare equal wherever they appear, their spans are checked apart.
+    let spans: Vec<Span> = fragments.iter().map(|fragment| match *fragment {
+        Fragment::Str(_, sp) | Fragment::Expr(_, sp) => sp,
+    }).collect();
+    assert_eq!(spans, vec![span(10, 13), span(13, 20), span(20, 22), span(22, 32), span(32, 38)]);
+    match module.stmts[1].kind {
+        StmtKind::Let(ref local) => {
+            let text = Literal::Str("$5".to_string());
+            assert_eq!(local.init, Some(Expr::from(ExprKind::Literal(text))))
+        },
+        _ => panic!("expected a let statement"),
+    }
+    assert!(parse_module(&arena, r#"let s = "${";"#).is_err());
+}
+
+#[test]
+fn raw_and_multiline_strings() {
+    use syntax::ast::{Expr, ExprKind, Literal, Stmt, StmtKind};
+
+    let arena = Arena::new();
+    let src = r####"
+        r"C:\path\${x}";
+        r##"say "#hi"#"##;
+        """
+            Dear ${name},
+              \tindented\x21
+            """;
+        """a "quoted" word""";
+        r;
+    "####;
+    let module = parse_module(&arena, src).unwrap();
+    let expr = |kind| Stmt::from(StmtKind::Expr(Expr::from(kind)));
+    let string = |value: &str| expr(ExprKind::Literal(Literal::Str(value.to_string())));
+    assert_eq!(module.stmts[0], string(r"C:\path\${x}"));
+    assert_eq!(module.stmts[1], string(r##"say "#hi"#"##));
+    assert_eq!(module.stmts[2], string("Dear ${name},\n  \tindented!"));
+    assert_eq!(module.stmts[3], string("a \"quoted\" word"));
+    assert_eq!(module.stmts[4], expr(ExprKind::Identifier("r".to_string())));
+    assert!(parse_module(&arena, r##"r#"unterminated";"##).is_err());
+    assert!(parse_module(&arena, "\"\"\"\n  bad \\q\n\"\"\";").is_err());
+}
@@ -0,0 +1,775 @@
+//! Unparser, turning syntax trees back into source.
+//!
+//! The output parses back into the same tree.  Parentheses are only emitted
+//! where the precedence or associativity of the operators requires them, and
+//! blocks are laid out with four spaces of indentation.
+
+use syntax::ast::{BinaryOp, Block, Closure, Expr, ExprKind, FnDecl, Fragment, Item, ItemKind,
+                  Literal, Module, Param, Print, Stmt, StmtKind, SymbolOp, TypeParam};
+use syntax::ast::binop::{ArithmeticOp, BitWiseOp, ComparisonOp, LogicalOp};
+
+const INDENT: &str = "    ";
+
+/// Renders a module as source, one statement per line.
+pub fn module_to_string(module: &Module) -> String {
+    let mut printer = Printer::new();
+    printer.module(module);
+    printer.out
+}
+
+/// Renders a single expression as source.
+pub fn expr_to_string(expr: &Expr) -> String {
+    let mut printer = Printer::new();
+    printer.expr(expr);
+    printer.out
+}
+
+/// Binding strength of the expression forms, from the loosest to the
+/// tightest, as given by the grammar and the precedence climber.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Prec {
+    /// Assignments and lambdas, whose right side extends as far as possible.
+    Assign,
+    Ternary,
+    Range,
+    Or,
+    And,
+    Equality,
+    Relational,
+    BitOr,
+    BitXor,
+    BitAnd,
+    Shift,
+    Additive,
+    Multiplicative,
+    Cast,
+    Unary,
+    /// Calls, method calls, indexing and fields.
+    Postfix,
+    Primary,
+}
+
+impl Prec {
+    fn of(expr: &Expr) -> Prec {
+        match expr.kind {
+            ExprKind::Assign(..)            => Prec::Assign,
+            ExprKind::Closure(closure)      => {
+                if is_lambda(closure) { Prec::Assign } else { Prec::Primary }
+            },
+            ExprKind::Ternary(..)           => Prec::Ternary,
+            ExprKind::Range(..)             => Prec::Range,
+            ExprKind::Binary(op, _, _)      => Prec::of_binary(op),
+            ExprKind::Cast(..)              => Prec::Cast,
+            ExprKind::Unary(..)             => Prec::Unary,
+            // Negative numbers are written with a leading minus.
+            ExprKind::Literal(Literal::Int(value)) if value < 0 => Prec::Unary,
+            ExprKind::Literal(Literal::Float(value)) if value.is_sign_negative() => Prec::Unary,
+            ExprKind::Call(..) |
+            ExprKind::MethodCall(..) |
+            ExprKind::SafeMethodCall(..) |
+            ExprKind::Index(..) |
+            ExprKind::Field(..) |
+            ExprKind::Member(..)            => Prec::Postfix,
+            _                               => Prec::Primary,
+        }
+    }
+
+    fn of_binary(op: BinaryOp) -> Prec {
+        match op {
+            BinaryOp::Logical(LogicalOp::Or)           => Prec::Or,
+            BinaryOp::Logical(LogicalOp::And)          => Prec::And,
+            BinaryOp::Comparison(ComparisonOp::Eql) |
+            BinaryOp::Comparison(ComparisonOp::NotEql) => Prec::Equality,
+            BinaryOp::Comparison(_)                    => Prec::Relational,
+            BinaryOp::BitWise(BitWiseOp::BwOr)         => Prec::BitOr,
+            BinaryOp::BitWise(BitWiseOp::BwXor)        => Prec::BitXor,
+            BinaryOp::BitWise(BitWiseOp::BwAnd)        => Prec::BitAnd,
+            BinaryOp::BitWise(_)                       => Prec::Shift,
+            BinaryOp::Arithmetic(ArithmeticOp::Add) |
+            BinaryOp::Arithmetic(ArithmeticOp::Sub)    => Prec::Additive,
+            BinaryOp::Arithmetic(_)                    => Prec::Multiplicative,
+        }
+    }
+}
+
+/// Closures whose body is a single expression are written `|x| x + 1`, the
+/// others `fn(x: i32) -> i32 { x + 1 }`.
+fn is_lambda(closure: &Closure) -> bool {
+    closure.ret.is_none() && closure.body.stmts.is_empty() && closure.body.expr.is_some()
+}
+
+/// Whether the source of `expr` starts with an `if`, a `loop` or a `match`,
+/// which the grammar would take for a statement at the start of a statement.
+fn starts_with_block(expr: &Expr) -> bool {
+    match expr.kind {
+        ExprKind::If(..) | ExprKind::Loop(..) | ExprKind::Match(..) => true,
+        ExprKind::Binary(_, lhs, _) |
+        ExprKind::Assign(_, lhs, _) |
+        ExprKind::Range(lhs, _, _) |
+        ExprKind::Ternary(lhs, _, _) |
+        ExprKind::Cast(lhs, _) |
+        ExprKind::Call(lhs, _) |
+        ExprKind::MethodCall(lhs, _, _) |
+        ExprKind::SafeMethodCall(lhs, _, _) |
+        ExprKind::Index(lhs, _) |
+        ExprKind::Field(lhs, _) |
+        ExprKind::Member(lhs, _) => Prec::of(lhs) >= Prec::of(expr) && starts_with_block(lhs),
+        _ => false,
+    }
+}
+
+fn is_block_like(expr: &Expr) -> bool {
+    matches!(expr.kind, ExprKind::If(..) | ExprKind::Loop(..) | ExprKind::Match(..))
+}
+
+struct Printer {
+    out:    String,
+    indent: usize,
+}
+
+impl Printer {
+    fn new() -> Printer {
+        Printer { out: String::new(), indent: 0 }
+    }
+
+    fn word(&mut self, text: &str) {
+        self.out.push_str(text);
+    }
+
+    fn symbol(&mut self, symbol: SymbolOp) {
+        self.out.push_str(&symbol.to_string());
+    }
+
+    fn newline(&mut self) {
+        self.out.push('\n');
+        for _ in 0..self.indent {
+            self.out.push_str(INDENT);
+        }
+    }
+
+    /// Writes `items` separated by commas.
+    fn commas<T, F: FnMut(&mut Printer, &T)>(&mut self, items: &[T], mut print: F) {
+        for (i, item) in items.iter().enumerate() {
+            if i > 0 {
+                self.symbol(SymbolOp::Comma);
+                self.word(" ");
+            }
+            print(self, item);
+        }
+    }
+
+    fn module(&mut self, module: &Module) {
+        for (i, stmt) in module.stmts.iter().enumerate() {
+            // Functions are set apart from their neighbours by a blank line.
+            if i > 0 && (is_fn(stmt) || is_fn(&module.stmts[i - 1])) {
+                self.newline();
+            }
+            self.stmt(stmt, false);
+            self.newline();
+        }
+    }
+
+    /// Writes a statement.  A trailing `if` or `loop` would be taken for the
+    /// value of its block, `last` keeps it a statement with a semi colon.
+    fn stmt(&mut self, stmt: &Stmt, last: bool) {
+        match stmt.kind {
+            StmtKind::Item(ref item) => self.item(item),
+            StmtKind::Let(ref local) => {
+                self.word("let ");
+                self.word(&local.pat.to_string());
+                if let Some(ref ty) = local.ty {
+                    self.word(&format!(": {}", ty));
+                }
+                if let Some(ref init) = local.init {
+                    self.word(" = ");
+                    self.expr(init);
+                }
+                self.symbol(SymbolOp::SemiColon);
+            },
+            StmtKind::Expr(ref expr) => {
+                if is_block_like(expr) {
+                    self.expr(expr);
+                    if last {
+                        self.symbol(SymbolOp::SemiColon);
+                    }
+                } else {
+                    self.leading(expr);
+                    self.symbol(SymbolOp::SemiColon);
+                }
+            },
+            StmtKind::Return(ref value) => {
+                self.word("return");
+                if let Some(ref value) = *value {
+                    self.word(" ");
+                    self.expr(value);
+                }
+                self.symbol(SymbolOp::SemiColon);
+            },
+            StmtKind::For(ref for_loop) => {
+                self.label(&for_loop.label);
+                self.word("for ");
+                self.word(&for_loop.pat.to_string());
+                self.word(" in ");
+                self.expr(&for_loop.iter);
+                self.word(" ");
+                self.block(&for_loop.body, true);
+            },
+            StmtKind::While(ref while_loop) => {
+                self.label(&while_loop.label);
+                self.word("while ");
+                self.expr(&while_loop.cond);
+                self.word(" ");
+                self.block(&while_loop.body, true);
+            },
+            StmtKind::Break(ref label, ref value) => {
+                self.word("break");
+                if let Some(ref label) = *label {
+                    self.word(&format!(" '{}", label));
+                }
+                if let Some(ref value) = *value {
+                    self.word(" ");
+                    self.expr(value);
+                }
+                self.symbol(SymbolOp::SemiColon);
+            },
+            StmtKind::Continue(ref label) => {
+                self.word("continue");
+                if let Some(ref label) = *label {
+                    self.word(&format!(" '{}", label));
+                }
+                self.symbol(SymbolOp::SemiColon);
+            },
+            StmtKind::Print(ref print) => self.print(print),
+        }
+    }
+
+    fn item(&mut self, item: &Item) {
+        if item.is_pub() {
+            self.word(&format!("{} ", item.vis));
+        }
+        match item.kind {
+            ItemKind::Use(ref decl) => {
+                self.word(&format!("use {}", decl.path));
+                self.symbol(SymbolOp::SemiColon);
+            },
+            ItemKind::Const(ref decl) => {
+                self.word(&format!("const {}: {} = ", decl.name, decl.ty));
+                self.expr(&decl.value);
+                self.symbol(SymbolOp::SemiColon);
+            },
+            ItemKind::Type(ref decl) => {
+                self.word(&format!("type {} = {}", decl.name, decl.ty));
+                self.symbol(SymbolOp::SemiColon);
+            },
+            ItemKind::Struct(ref decl) => {
+                self.word(&format!("struct {}", decl.name));
+                self.generics(decl.generics);
+                self.word(" { ");
+                self.commas(decl.fields, |printer, field| {
+                    printer.word(&format!("{}: {}", field.name, field.ty));
+                });
+                self.word(" }");
+            },
+            ItemKind::Spec(ref decl) => {
+                self.word(&format!("spec {}", decl.name));
+                self.generics(decl.generics);
+                self.word(" { ");
+                self.commas(decl.variants, |printer, variant| {
+                    printer.word(&variant.name);
+                    if !variant.fields.is_empty() {
+                        printer.word("(");
+                        printer.commas(variant.fields, |printer, ty| {
+                            printer.word(&ty.to_string())
+                        });
+                        printer.word(")");
+                    }
+                });
+                self.word(" }");
+            },
+            ItemKind::Fn(ref decl) => self.fn_decl(decl),
+        }
+    }
+
+    fn generics(&mut self, generics: &[TypeParam]) {
+        if generics.is_empty() {
+            return;
+        }
+        self.word("<");
+        self.commas(generics, |printer, param| {
+            printer.word(&param.name);
+            if !param.bounds.is_empty() {
+                printer.word(&format!(": {}", param.bounds.join(" + ")));
+            }
+        });
+        self.word(">");
+    }
+
+    fn fn_decl(&mut self, decl: &FnDecl) {
+        self.word("fn ");
+        self.word(&decl.name);
+        self.generics(decl.generics);
+        self.word("(");
+        self.commas(decl.params, |printer, param| {
+            printer.word(&param.pat.to_string());
+            if let Some(ref ty) = param.ty {
+                printer.word(&format!(": {}", ty));
+            }
+        });
+        self.word(")");
+        if let Some(ref ret) = decl.ret {
+            self.word(&format!(" {} {}", SymbolOp::Cast, ret));
+        }
+        self.word(" ");
+        self.block(&decl.body, false);
+    }
+
+    fn print(&mut self, print: &Print) {
+        self.word(if print.newline { "println" } else { "print" });
+        let mut first = true;
+        if let Some(ref format) = print.format {
+            self.word(" ");
+            self.string(&format.to_string());
+            first = false;
+        }
+        for arg in print.args {
+            if !first {
+                self.symbol(SymbolOp::Comma);
+            }
+            self.word(" ");
+            self.expr(arg);
+            first = false;
+        }
+        self.symbol(SymbolOp::SemiColon);
+    }
+
+    fn label(&mut self, label: &Option<String>) {
+        if let Some(ref label) = *label {
+            self.word(&format!("'{}: ", label));
+        }
+    }
+
+    /// Writes a block.  Blocks without statements stay on one line when
+    /// `inline` is set.
+    fn block(&mut self, block: &Block, inline: bool) {
+        if block.stmts.is_empty() && (inline || block.expr.is_none()) {
+            self.symbol(SymbolOp::LBrace);
+            if let Some(expr) = block.expr {
+                self.word(" ");
+                self.leading(expr);
+            }
+            self.word(" ");
+            self.symbol(SymbolOp::RBrace);
+            return;
+        }
+        self.symbol(SymbolOp::LBrace);
+        self.indent += 1;
+        for (i, stmt) in block.stmts.iter().enumerate() {
+            self.newline();
+            self.stmt(stmt, block.expr.is_none() && i + 1 == block.stmts.len());
+        }
+        if let Some(expr) = block.expr {
+            self.newline();
+            self.leading(expr);
+        }
+        self.indent -= 1;
+        self.newline();
+        self.symbol(SymbolOp::RBrace);
+    }
+
+    /// Writes an expression found at the start of a statement.
+    fn leading(&mut self, expr: &Expr) {
+        if !is_block_like(expr) && starts_with_block(expr) {
+            self.parens(expr);
+        } else {
+            self.expr(expr);
+        }
+    }
+
+    fn parens(&mut self, expr: &Expr) {
+        self.word("(");
+        self.expr(expr);
+        self.word(")");
+    }
+
+    /// Writes `expr`, in parentheses when it binds looser than `min`, or as
+    /// loosely when `strict` is set.
+    fn operand(&mut self, expr: &Expr, min: Prec, strict: bool) {
+        let prec = Prec::of(expr);
+        if prec < min || (strict && prec == min) {
+            self.parens(expr);
+        } else {
+            self.expr(expr);
+        }
+    }
+
+    /// Writes the receiver of a call, an index or a field.
+    fn receiver(&mut self, expr: &Expr) {
+        match expr.kind {
+            // `1.f()` and `1.0` would read as floats.
+            ExprKind::Literal(Literal::Int(_)) => self.parens(expr),
+            _                                  => self.operand(expr, Prec::Postfix, false),
+        }
+    }
+
+    fn expr(&mut self, expr: &Expr) {
+        match expr.kind {
+            ExprKind::Literal(ref lit) => self.literal(lit),
+            ExprKind::Interpolated(fragments) => {
+                self.word("\"");
+                for fragment in fragments {
+                    match *fragment {
+                        Fragment::Str(ref text, _) => self.escaped(text, '"'),
+                        Fragment::Expr(ref expr, _) => {
+                            self.word("${");
+                            self.expr(expr);
+                            self.word("}");
+                        },
+                    }
+                }
+                self.word("\"");
+            },
+            ExprKind::Identifier(ref name) => self.word(name),
+            ExprKind::Path(ref path) => self.word(&path.to_string()),
+            ExprKind::Unary(op, operand) => {
+                self.word(&op.to_string());
+                self.operand(operand, Prec::Unary, false);
+            },
+            ExprKind::Binary(op, lhs, rhs) => {
+                let prec = Prec::of_binary(op);
+                self.operand(lhs, prec, false);
+                self.word(&format!(" {} ", op));
+                self.operand(rhs, prec, true);
+            },
+            ExprKind::Cast(operand, prim) => {
+                self.operand(operand, Prec::Cast, false);
+                self.word(&format!(" {} {}", SymbolOp::Cast, prim));
+            },
+            ExprKind::Call(callee, args) => {
+                self.receiver(callee);
+                self.args(args);
+            },
+            ExprKind::MethodCall(receiver, ref name, args) => {
+                self.receiver(receiver);
+                self.word(".");
+                self.word(name);
+                self.args(args);
+            },
+            ExprKind::SafeMethodCall(receiver, ref name, args) => {
+                self.operand(receiver, Prec::Postfix, false);
+                self.word("?.");
+                self.word(name);
+                self.args(args);
+            },
+            ExprKind::Assign(op, place, value) => {
+                self.operand(place, Prec::Ternary, false);
+                self.word(&format!(" {} ", op));
+                self.expr(value);
+            },
+            ExprKind::Range(start, end, limits) => {
+                self.operand(start, Prec::Or, false);
+                self.word(&limits.to_string());
+                self.operand(end, Prec::Or, false);
+            },
+            ExprKind::Array(elems) => {
+                self.symbol(SymbolOp::LSquare);
+                self.commas(elems, Printer::expr);
+                self.symbol(SymbolOp::RSquare);
+            },
+            ExprKind::Map(entries) => {
+                self.symbol(SymbolOp::LSquare);
+                if entries.is_empty() {
+                    self.word(":");
+                }
+                self.commas(entries, |printer, (key, value)| {
+                    printer.expr(key);
+                    printer.word(": ");
+                    printer.expr(value);
+                });
+                self.symbol(SymbolOp::RSquare);
+            },
+            ExprKind::Index(base, index) => {
+                self.receiver(base);
+                self.symbol(SymbolOp::LSquare);
+                self.expr(index);
+                self.symbol(SymbolOp::RSquare);
+            },
+            ExprKind::Tuple(elems) => {
+                self.word("(");
+                self.commas(elems, Printer::expr);
+                if elems.len() == 1 {
+                    self.symbol(SymbolOp::Comma);
+                }
+                self.word(")");
+            },
+            ExprKind::Field(base, index) => {
+                self.receiver(base);
+                self.word(&format!(".{}", index));
+            },
+            ExprKind::Member(base, ref name) => {
+                self.receiver(base);
+                self.word(&format!(".{}", name));
+            },
+            ExprKind::Struct(ref name, fields) => {
+                self.word(name);
+                self.word(" { ");
+                self.commas(fields, |printer, (field, value)| {
+                    printer.word(&format!("{}: ", field));
+                    printer.expr(value);
+                });
+                self.word(" }");
+            },
+            ExprKind::Closure(closure) => self.closure(closure),
+            ExprKind::Ternary(cond, then, otherwise) => {
+                self.operand(cond, Prec::Range, false);
+                self.word(" ? ");
+                self.expr(then);
+                self.word(" : ");
+                self.operand(otherwise, Prec::Ternary, false);
+            },
+            ExprKind::If(cond, ref then, ref otherwise) => {
+                self.word("if ");
+                self.expr(cond);
+                self.word(" ");
+                self.block(then, true);
+                if let Some(otherwise) = *otherwise {
+                    self.word(" else ");
+                    self.expr(otherwise);
+                }
+            },
+            ExprKind::Loop(ref label, ref body) => {
+                self.label(label);
+                self.word("loop ");
+                self.block(body, true);
+            },
+            ExprKind::Match(scrutinee, arms) => {
+                self.word("match ");
+                self.expr(scrutinee);
+                self.word(" ");
+                self.symbol(SymbolOp::LBrace);
+                self.indent += 1;
+                for arm in arms {
+                    self.newline();
+                    self.word(&format!("{} => ", arm.pat));
+                    self.expr(&arm.body);
+                    self.symbol(SymbolOp::Comma);
+                }
+                self.indent -= 1;
+                self.newline();
+                self.symbol(SymbolOp::RBrace);
+            },
+            ExprKind::Block(ref block) => self.block(block, true),
+        }
+    }
+
+    fn args(&mut self, args: &[Expr]) {
+        self.word("(");
+        self.commas(args, Printer::expr);
+        self.word(")");
+    }
+
+    fn closure(&mut self, closure: &Closure) {
+        let param = |printer: &mut Printer, param: &Param| {
+            printer.word(&param.pat.to_string());
+            if let Some(ref ty) = param.ty {
+                printer.word(&format!(": {}", ty));
+            }
+        };
+        if is_lambda(closure) {
+            self.word("|");
+            self.commas(closure.params, param);
+            self.word("| ");
+            if let Some(body) = closure.body.expr {
+                self.expr(body);
+            }
+            return;
+        }
+        self.word("fn(");
+        self.commas(closure.params, param);
+        self.word(")");
+        if let Some(ref ret) = closure.ret {
+            self.word(&format!(" {} {}", SymbolOp::Cast, ret));
+        }
+        self.word(" ");
+        self.block(&closure.body, true);
+    }
+
+    fn literal(&mut self, lit: &Literal) {
+        match *lit {
+            Literal::Nil            => self.word("nil"),
+            Literal::Boolean(value) => self.word(if value { "true" } else { "false" }),
+            Literal::Char(chr)      => {
+                self.word("'");
+                self.escaped(&chr.to_string(), '\'');
+                self.word("'");
+            },
+            Literal::Int(value)     => self.word(&value.to_string()),
+            Literal::Float(value)   => {
+                let mut text = value.to_string();
+                // Floats need a decimal point, which whole values print without.
+                if value.is_finite() && !text.contains('.') {
+                    text.push_str(".0");
+                }
+                self.word(&text);
+            },
+            Literal::Str(ref text)  => self.string(text),
+        }
+    }
+
+    fn string(&mut self, text: &str) {
+        self.word("\"");
+        self.escaped(text, '"');
+        self.word("\"");
+    }
+
+    /// Writes the text of a string or char literal delimited by `quote`,
+    /// escaping the quote, backslashes, control characters and, in strings,
+    /// the `$` of a `${` that would start an interpolation.
+    fn escaped(&mut self, text: &str, quote: char) {
+        let mut chars = text.chars().peekable();
+        while let Some(chr) = chars.next() {
+            match chr {
+                '\\'                   => self.word("\\\\"),
+                '\n'                   => self.word("\\n"),
+                '\t'                   => self.word("\\t"),
+                '\r'                   => self.word("\\r"),
+                '$' if quote == '"' && chars.peek() == Some(&'{') => self.word("\\$"),
+                chr if chr == quote    => {
+                    self.out.push('\\');
+                    self.out.push(chr);
+                },
+                chr if chr.is_control() => self.word(&format!("\\u{:04x}", chr as u32)),
+                chr                    => self.out.push(chr),
+            }
+        }
+    }
+}
+
+fn is_fn(stmt: &Stmt) -> bool {
+    matches!(stmt.kind, StmtKind::Item(Item { kind: ItemKind::Fn(_), .. }))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use syntax::ast::{Arena, ExprKind, StmtKind};
+    use syntax::parser::parse_module;
+    use utils::Span;
+
+    /// Prints the module of `source`, checking that the output parses back
+    /// into the same tree and prints the same again.
+    fn round_trip(source: &str) -> String {
+        let arena = Arena::new();
+        let module = parse_module(&arena, source).unwrap();
+        let printed = module_to_string(&module);
+        let reparsed = parse_module(&arena, &printed)
+            .unwrap_or_else(|err| panic!("{}\nin:\n{}", err, printed));
+        assert_eq!(reparsed, module, "in:\n{}", printed);
+        assert_eq!(module_to_string(&reparsed), printed);
+        printed
+    }
+
+    fn expr(source: &str) -> String {
+        let printed = round_trip(&format!("{};", source));
+        printed.trim_end().trim_end_matches(';').to_string()
+    }
+
+    #[test]
+    fn whole_language() {
+        round_trip("
+            pub use ::geometry::shapes::area;
+            use io;
+            const MAX: i32 = 1 << 8;
+            pub type Callback = fn(i32, [str: i32?]) -> (bool,);
+            fn max<T: Ord + Show, U>(a: T, (b, (c,)): (T, U)) -> T { a > b ? a : b }
+            fn empty() { }
+            struct Pair<T: Eq> { first: T, second: Option<[T]> }
+            pub spec Option<T> { Some(T), None }
+            let p = Pair { first: 1, second: Option::None }.first;
+            match Option::Some(p) { Option::Some(q) => { q }, Option::None => 0, other => -1 }
+            let (x, y): (i32, f64) = (1, 2.5e3);
+            let s = \"tab\\there ${x + 1} \\${y} $ \\\" \\\\ \\u0007\";
+            let raw = r#\"C:\\path \"quoted\"\"#;
+            let c = ['\\'', '\\n', '\"', 'x'];
+            let m = [:];
+            let n = [\"a\": 1.0, \"b\": -0.5];
+            let f = fn(a: i32) -> i32 { let b = a; b * 2 };
+            let g = |a, b: i32| a + b;
+            'outer: for i in 0..=10 {
+                while i < 3 {
+                    if i == 2 { continue 'outer; } else if i > 8 { break 'outer; }
+                    println \"{{}} {} of {:8.2}\", i, MAX;
+                    print;
+                }
+                loop { break i; }
+            }
+            x = y = ::std::io::read(m[0].len(), s?.trim(), (x, y).1);
+            return f(1) -> u8;
+        ");
+    }
+
+    #[test]
+    fn minimal_parentheses() {
+        assert_eq!(expr("(a + b) * c"), "(a + b) * c");
+        assert_eq!(expr("a + (b * c)"), "a + b * c");
+        assert_eq!(expr("(a - b) - c"), "a - b - c");
+        assert_eq!(expr("a - (b - c)"), "a - (b - c)");
+        assert_eq!(expr("(a || b) && !(c | d ^ e & f << 1 == g)"),
+                   "(a || b) && !(c | d ^ e & f << 1 == g)");
+        assert_eq!(expr("((a & b) ^ c) | (d < e) == f"), "a & b ^ c | (d < e) == f");
+        assert_eq!(expr("-(a -> u8)"), "-(a -> u8)");
+        assert_eq!(expr("((-a) -> u8) -> i32"), "-a -> u8 -> i32");
+        assert_eq!(expr("(-a).abs()[0]"), "(-a).abs()[0]");
+        assert_eq!(expr("a = (b = c)"), "a = b = c");
+        assert_eq!(expr("(a = b) = c"), "(a = b) = c");
+        assert_eq!(expr("a ? b : (c ? d : e)"), "a ? b : c ? d : e");
+        assert_eq!(expr("(a ? b : c) ? d : e"), "(a ? b : c) ? d : e");
+        assert_eq!(expr("(0..n).len() + (a..b)"), "(0..n).len() + (a..b)");
+        assert_eq!(expr("(|x| x)(1)"), "(|x| x)(1)");
+        assert_eq!(expr("(1).0 + (1.5).f()"), "(1).0 + 1.5.f()");
+        assert_eq!(expr("((if a { b } else { c }) + 1)"), "(if a { b } else { c } + 1)");
+    }
+
+    #[test]
+    fn layout() {
+        let printed = round_trip("
+            let a = 1; fn f(x: i32) -> i32 { let y = x; if y > a { y } else { a } }
+            fn g() { f(1); loop { } }
+        ");
+        assert_eq!(printed, "let a = 1;\n\
+                             \n\
+                             fn f(x: i32) -> i32 {\n    \
+                                 let y = x;\n    \
+                                 if y > a { y } else { a }\n\
+                             }\n\
+                             \n\
+                             fn g() {\n    \
+                                 f(1);\n    \
+                                 loop { }\n\
+                             }\n");
+    }
+
+    #[test]
+    fn trees_built_by_hand() {
+        let arena = Arena::new();
+        let module = parse_module(&arena, "fn f() { if a { b } }").unwrap();
+        let mut body = match module.stmts[0].kind {
+            StmtKind::Item(Item { kind: ItemKind::Fn(ref decl), .. }) => decl.body,
+            _ => panic!("expected a function"),
+        };
+        // A trailing `if` kept as a statement.
+        let tail = body.expr.take().unwrap();
+        let mut stmts = body.stmts.to_vec();
+        stmts.push(Stmt::from(StmtKind::Expr(tail.clone())));
+        body.stmts = arena.alloc_vec(stmts);
+        let closure = Closure { params: &[], ret: None, body, span: Span::default() };
+        let call = Expr::from(ExprKind::Call(
+            arena.alloc(Expr::from(ExprKind::Closure(arena.alloc(closure)))), &[]));
+        assert_eq!(expr_to_string(&call), "fn() {\n    if a { b };\n}()");
+        let field = Expr::from(ExprKind::Field(tail, 0));
+        let stmts = arena.alloc_vec(vec![Stmt::from(StmtKind::Expr(field))]);
+        assert_eq!(module_to_string(&Module { stmts }), "(if a { b }.0);\n");
+        assert_eq!(expr_to_string(&Expr::from(ExprKind::MethodCall(
+            &Expr::from(ExprKind::Literal(Literal::Int(-1))), "abs".to_string(),
+            &[]))), "(-1).abs()");
+    }
+}
@@ -0,0 +1,2084 @@
+//! The "typeck" module infers expression types over the syntax tree and
+//! reports the places where they disagree with their context.
+//!
+//! ## Inference
+//! Annotated parameters, `let` bindings and return types give their declared
+//! type.  Everything else is inferred bottom up from literals and operators;
+//! what cannot be inferred yet is `Ty::Unknown`, which is accepted anywhere so
+//! that a single missing annotation does not cascade into many errors.
+//! An assignment must store a value of the type its target was declared with.
+//!
+//! ## Modules
+//! The checker of a module is given the types of the names it imports,
+//! items and qualified names alike, as found by checking the exporting
+//! modules first.  A call to an imported function is then checked like a
+//! call to a local one.
+//!
+//! ## Conditionals
+//! The condition of `cond ? a : b` and of `if cond { a } else { b }` must be a
+//! `bool`, and both branches must have the same type when the value of the
+//! conditional is used.  An `if` without an else branch is nil when its
+//! condition does not hold, so its value is the optional of the value of its
+//! branch, or `void` when the branch has none.
+//!
+//! ## Operators
+//! `!`, `&&` and `||` take `bool`s, bitwise operators integers, and other
+//! arithmetic operators numbers; `+` also concatenates strings and arrays.
+//! Ordering comparisons take numbers, `char`s and `str`s.  Both operands of
+//! a binary operator must have the same type, except for the shift amount.
+//! An integer literal must fit the integer type it is stored as.  The integer
+//! type of each arithmetic expression, or the type its value is stored as when
+//! it only involves literals, is kept for evaluation to check for overflow.
+//!
+//! ## Functions
+//! A call must pass as many arguments as the function has parameters, each
+//! of the type of its parameter.  In a function declared with a return type,
+//! every `return` and the trailing expression of the body must have it.  A
+//! body without a trailing expression must not reach its end, by ending in a
+//! `return`, in an `if` whose branches all return, or in a `loop` that is
+//! never left with `break`.  A body ending in an `if` without an else branch
+//! reaches its end when the condition does not hold, unless the value of the
+//! `if` is accepted as is.
+//!
+//! ## Optionals
+//! A value of type `T?` may be nil, so it cannot be used where a `T` is
+//! expected until a nil check narrows it: within the branch where
+//! `x != nil` (or `x == nil` in the else branch) holds, and on the right of
+//! `x != nil && ...`, the variable `x` has type `T`.  Comparing against nil,
+//! storing into another optional and `x?.f()` are allowed without narrowing.
+//! Assigning to `x` ends its narrowing, for it may have been assigned nil.
+//!
+//! ## Type aliases
+//! `type Name = T;` makes `Name` stand for `T` in every annotation of the
+//! module, before or after the alias declaration.  Aliases may refer to other
+//! aliases but not, directly or indirectly, to themselves.  Mismatches against
+//! an annotation that uses an alias show the alias next to what it stands for.
+//!
+//! ## Loops
+//! `break` and `continue` must be inside a loop of the same function, and a
+//! label must name one of the enclosing loops.  Only `loop` has a value: the
+//! values of all the `break`s leaving it must have the same type.
+//!
+//! ## Generic functions
+//! Within `fn f<T>(x: T) -> T`, `T` is a type of its own that only equals
+//! itself.  At each call the type arguments are inferred from the arguments,
+//! so `f(1)` has type `{integer}`.
+//!
+//! ## Models
+//! A bound names one of the built-in models, which say what operators apply
+//! to the values of a type: `Eq` allows `==` and `!=`, `Ord` also ordering
+//! comparisons, and `Num` also arithmetic.  Numbers mount all three, `char`
+//! and `str` mount `Ord`, and compound types mount `Eq` when their parts do.
+//! A type parameter mounts only what its bounds imply, so no operator applies
+//! to an unbounded `T`.  Each inferred type argument must mount the bounds of
+//! its parameter.
+//!
+//! ## Structs and specs
+//! `struct Pair<T> { first: T, second: T }` declares a type with named
+//! fields; a literal must give every field once, and `p.first` reads one.
+//! `spec Option<T> { Some(T), None }` declares a type whose values are one of
+//! its variants: `Option::Some(1)` builds one, and a `match` takes it apart.
+//! The arms of a `match` must cover every variant, and their values must have
+//! the same type when the value of the `match` is used.  Type arguments are
+//! given in annotations, `Pair<i32>`, and inferred in expressions.
+mod ty;
+
+pub use self::ty::{Model, Ty, TyParam};
+
+use std::collections::HashMap;
+use std::mem;
+
+use error::{TypeError, TypeErrorKind};
+use syntax::ast::binop::{ArithmeticOp, BitWiseOp, ComparisonOp, LogicalOp};
+use syntax::ast::{Arm, AssignmentOp, BinaryOp, Block, Closure, Expr, ExprKind, FnDecl, ForLoop,
+                  Fragment, ItemKind, Literal, Module, NodeId, Param, Pattern, PatternKind,
+                  PrimType, Stmt, StmtKind, Type, TypeParam, Variant, WhileLoop};
+use syntax::ast::unop::UnaryOp;
+use syntax::ast::visit::{self, Visitor};
+use utils::{Diagnostic, FileId, Severity, Span};
+
+/// Checker struct holds the scopes of the names visible at the checked node.
+pub struct Checker<'ast> {
+    source:    String,
+    scopes:    Vec<HashMap<String, Ty>>,
+    /// Indices of the scopes holding variables narrowed by a nil check.
+    narrowed:  Vec<usize>,
+    /// Type alias declarations, and the types they resolve to so far.
+    aliases:   HashMap<String, Type<'ast>>,
+    resolved:  HashMap<String, Ty>,
+    /// Aliases being resolved, used to detect cycles.
+    resolving: Vec<String>,
+    /// Struct and spec declarations, by name.
+    structs:   HashMap<String, StructTy>,
+    specs:     HashMap<String, SpecTy>,
+    /// Type parameters of the generic function, struct or spec being checked.
+    generics:  Vec<TyParam>,
+    /// Loops enclosing the checked node in the current function, innermost last.
+    loops:     Vec<LoopCx>,
+    /// Declared return type of the function being checked, if any.
+    ret:       Option<Ty>,
+    /// Integer types of the arithmetic expressions, by file and node.
+    ints:      HashMap<(FileId, NodeId), PrimType>,
+    /// Location of the checked node, where errors point.
+    span:      Span,
+    errors:    Vec<Diagnostic>,
+}
+
+impl<'ast> Checker<'ast> {
+    /// Creates a checker; `source` names the checked file in diagnostics.
+    pub fn new<S: Into<String>>(source: S) -> Checker<'ast> {
+        Checker {
+            source:    source.into(),
+            scopes:    vec![HashMap::new()],
+            narrowed:  Vec::new(),
+            aliases:   HashMap::new(),
+            resolved:  HashMap::new(),
+            resolving: Vec::new(),
+            structs:   HashMap::new(),
+            specs:     HashMap::new(),
+            generics:  Vec::new(),
+            loops:     Vec::new(),
+            ret:       None,
+            ints:      HashMap::new(),
+            span:      Span::default(),
+            errors:    Vec::new(),
+        }
+    }
+
+    /// Declares a name imported by the checked module, an item or a qualified
+    /// name as written, i.e. `shapes::area`.
+    pub fn define_import(&mut self, name: &str, ty: Ty) {
+        self.scopes[0].insert(name.to_string(), ty);
+    }
+
+    /// The type of the global `name` of a checked module, `Unknown` if the
+    /// module declares no such name.
+    pub fn global(&self, name: &str) -> Ty {
+        self.scopes[0].get(name).cloned().unwrap_or(Ty::Unknown)
+    }
+
+    /// The integer types of the arithmetic expressions checked so far, for
+    /// the interpreter to check their values against.
+    pub fn int_types(&self) -> &HashMap<(FileId, NodeId), PrimType> {
+        &self.ints
+    }
+
+    /// Checks every statement of `module`, returning all diagnostics found.
+    pub fn check_module(&mut self, module: &Module<'ast>) -> Result<(), Vec<Diagnostic>> {
+        self.declare_items(module.stmts);
+        for stmt in module.stmts {
+            self.stmt(stmt);
+        }
+        if self.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(self.errors.drain(..).collect())
+        }
+    }
+
+    /// Declares the type aliases, structs, specs, functions and constants of
+    /// a statement list up front, so that uses may precede the declaration.
+    /// The names of types are known before any annotation is converted, so
+    /// that they may refer to each other.
+    fn declare_items(&mut self, stmts: &[Stmt<'ast>]) {
+        let items: Vec<_> = stmts.iter().filter_map(|stmt| match stmt.kind {
+            StmtKind::Item(ref item) => Some(item),
+            _                        => None,
+        }).collect();
+        for item in &items {
+            self.span = item.span;
+            match item.kind {
+                ItemKind::Type(ref alias) => {
+                    self.aliases.insert(alias.name.clone(), alias.ty.clone());
+                },
+                ItemKind::Struct(ref decl) => {
+                    let params = self.ty_params(decl.generics);
+                    self.structs.insert(decl.name.clone(), StructTy { params, fields: Vec::new() });
+                },
+                ItemKind::Spec(ref decl) => {
+                    let params = self.ty_params(decl.generics);
+                    self.specs.insert(decl.name.clone(), SpecTy { params, variants: Vec::new() });
+                },
+                _ => {},
+            }
+        }
+        for item in items {
+            self.span = item.span;
+            match item.kind {
+                ItemKind::Type(ref alias) => {
+                    self.alias(&alias.name);
+                },
+                ItemKind::Struct(ref decl) => {
+                    let params = self.structs[&decl.name].params.clone();
+                    let outer = mem::replace(&mut self.generics, params);
+                    let fields = decl.fields.iter()
+                        .map(|field| (field.name.clone(), self.annotation(&field.ty)))
+                        .collect();
+                    self.generics = outer;
+                    if let Some(decl) = self.structs.get_mut(&decl.name) {
+                        decl.fields = fields;
+                    }
+                },
+                ItemKind::Spec(ref decl) => self.spec_decl(&decl.name, decl.variants),
+                ItemKind::Fn(ref decl) => {
+                    let params = self.ty_params(decl.generics);
+                    let outer = mem::replace(&mut self.generics, params);
+                    let ty = self.fn_ty(decl.params, &decl.ret);
+                    let generics = mem::replace(&mut self.generics, outer);
+                    if generics.is_empty() {
+                        self.define(&decl.name, ty);
+                    } else {
+                        self.define(&decl.name, Ty::Generic(generics, Box::new(ty)));
+                    }
+                },
+                ItemKind::Const(ref decl) => {
+                    let ty = self.annotation(&decl.ty);
+                    self.define(&decl.name, ty);
+                },
+                ItemKind::Use(_) => {},
+            }
+        }
+    }
+
+    /// Declares the variants of the spec `name`, each as `Spec::Variant`.
+    /// A variant holding values is a function building the spec, generic
+    /// over the type parameters of the spec; one without is a value whose
+    /// type arguments are left to inference.
+    fn spec_decl(&mut self, name: &str, variants: &[Variant<'ast>]) {
+        let params = self.specs[name].params.clone();
+        let outer = mem::replace(&mut self.generics, params);
+        let mut tys = Vec::new();
+        for variant in variants {
+            let fields: Vec<_> = variant.fields.iter().map(|ty| self.annotation(ty)).collect();
+            let path = format!("{}::{}", name, variant.name);
+            let ty = if fields.is_empty() {
+                Ty::Named(name.to_string(), vec![Ty::Unknown; self.generics.len()])
+            } else {
+                let args = self.generics.iter().map(|param| Ty::Param(param.name.clone()));
+                let spec = Ty::Named(name.to_string(), args.collect());
+                let ty = Ty::Fn(fields.clone(), Box::new(spec));
+                if self.generics.is_empty() {
+                    ty
+                } else {
+                    Ty::Generic(self.generics.clone(), Box::new(ty))
+                }
+            };
+            self.define(&path, ty);
+            tys.push((variant.name.clone(), fields));
+        }
+        self.generics = outer;
+        if let Some(spec) = self.specs.get_mut(name) {
+            spec.variants = tys;
+        }
+    }
+
+    /// Converts the type parameters of a declaration, reporting the bounds
+    /// that name no model.
+    fn ty_params(&mut self, params: &[TypeParam]) -> Vec<TyParam> {
+        let mut ty_params = Vec::with_capacity(params.len());
+        for param in params {
+            let mut bounds = Vec::new();
+            for bound in &param.bounds {
+                match Model::from_name(bound) {
+                    Some(model) => bounds.push(model),
+                    None        => self.at(param.span, |checker| {
+                        checker.error(TypeErrorKind::UnknownModel(bound.clone()))
+                    }),
+                }
+            }
+            ty_params.push(TyParam { name: param.name.clone(), bounds });
+        }
+        ty_params
+    }
+
+    /// Converts a type annotation, resolving the names it uses.
+    fn annotation(&mut self, ty: &Type<'ast>) -> Ty {
+        self.at(ty.span, |checker| Ty::from_ast(ty, &mut |name, args| checker.named(name, args)))
+    }
+
+    /// Converts an optional annotation, `Unknown` when it is missing.
+    fn opt_annotation(&mut self, ty: &Option<Type<'ast>>) -> Ty {
+        match *ty {
+            Some(ref ty) => self.annotation(ty),
+            None         => Ty::Unknown,
+        }
+    }
+
+    /// The type of a function declared with `params` and return annotation `ret`.
+    fn fn_ty(&mut self, params: &[Param<'ast>], ret: &Option<Type<'ast>>) -> Ty {
+        let params = params.iter().map(|param| self.opt_annotation(&param.ty)).collect();
+        let ret = match *ret {
+            Some(ref ret) => self.annotation(ret),
+            None          => Ty::void(),
+        };
+        Ty::Fn(params, Box::new(ret))
+    }
+
+    /// Resolves a type parameter, struct, spec or alias named in an
+    /// annotation with the type arguments `args`.  The arguments of a struct
+    /// or spec must mount the models its parameters are bounded by.
+    fn named(&mut self, name: &str, args: Vec<Ty>) -> Ty {
+        if self.generics.iter().any(|param| param.name == name) {
+            self.type_args(name, &[], &args);
+            return Ty::Param(name.to_string());
+        }
+        let params = match (self.structs.get(name), self.specs.get(name)) {
+            (Some(decl), _) => decl.params.clone(),
+            (_, Some(decl)) => decl.params.clone(),
+            (None, None)    => {
+                if self.aliases.contains_key(name) {
+                    self.type_args(name, &[], &args);
+                }
+                return self.alias(name);
+            },
+        };
+        if self.type_args(name, &params, &args) {
+            Ty::Named(name.to_string(), args)
+        } else {
+            Ty::Named(name.to_string(), vec![Ty::Unknown; params.len()])
+        }
+    }
+
+    /// Checks the type arguments given to `name` against its type
+    /// parameters, returning whether there are as many of both.
+    fn type_args(&mut self, name: &str, params: &[TyParam], args: &[Ty]) -> bool {
+        if params.len() != args.len() {
+            self.error(TypeErrorKind::TypeArgCount {
+                name:     name.to_string(),
+                expected: params.len(),
+                found:    args.len(),
+            });
+            return false;
+        }
+        self.bounds(params, args);
+        true
+    }
+
+    /// Checks that each type argument mounts the models its parameter is
+    /// bounded by.
+    fn bounds(&mut self, params: &[TyParam], args: &[Ty]) {
+        for (param, arg) in params.iter().zip(args) {
+            for &model in &param.bounds {
+                if !arg.mounts(model, &self.generics) {
+                    self.error(TypeErrorKind::Unmounted {
+                        ty:    arg.to_string(),
+                        model: model.to_string(),
+                    });
+                }
+            }
+        }
+    }
+
+    /// Resolves the type alias `name`.  Each alias is resolved once, so an
+    /// unknown name or a cycle is reported a single time.
+    fn alias(&mut self, name: &str) -> Ty {
+        if let Some(ty) = self.resolved.get(name) {
+            return ty.clone();
+        }
+        let target = match self.aliases.get(name) {
+            Some(target) => target.clone(),
+            None         => {
+                self.error(TypeErrorKind::UnknownType(name.to_string()));
+                return Ty::Unknown;
+            },
+        };
+        if let Some(start) = self.resolving.iter().position(|alias| alias == name) {
+            let mut chain = self.resolving[start..].to_vec();
+            chain.push(name.to_string());
+            self.error(TypeErrorKind::AliasCycle(chain));
+            return Ty::Unknown;
+        }
+        // Aliases are declared outside of generic functions, so they cannot
+        // name the type parameters of the function using them.
+        let generics = mem::take(&mut self.generics);
+        self.resolving.push(name.to_string());
+        let ty = self.annotation(&target);
+        self.resolving.pop();
+        self.generics = generics;
+        self.resolved.insert(name.to_string(), ty.clone());
+        ty
+    }
+
+    fn stmt(&mut self, stmt: &Stmt<'ast>) {
+        self.at(stmt.span, |checker| checker.stmt_kind(&stmt.kind))
+    }
+
+    fn stmt_kind(&mut self, stmt: &StmtKind<'ast>) {
+        match *stmt {
+            StmtKind::Item(ref item) => match item.kind {
+                ItemKind::Fn(ref decl) => self.fn_decl(decl),
+                ItemKind::Const(ref decl) => {
+                    let value = self.expr(&decl.value);
+                    let ty = self.expect_annotated(&decl.ty, &value);
+                    self.literal_range(&decl.value, &ty);
+                },
+                ItemKind::Use(_) | ItemKind::Type(_) | ItemKind::Struct(_)
+                    | ItemKind::Spec(_) => {},
+            },
+            StmtKind::Let(ref local) => {
+                let init = match local.init {
+                    Some(ref init) => self.expr(init),
+                    None           => Ty::Unknown,
+                };
+                let ty = match local.ty {
+                    Some(ref ty) => self.expect_annotated(ty, &init),
+                    None         => init,
+                };
+                if let Some(ref init) = local.init {
+                    self.literal_range(init, &ty);
+                }
+                self.bind(&local.pat, ty);
+            },
+            // The value of an expression statement is discarded, so branches
+            // need not agree.
+            StmtKind::Expr(ref expr) => {
+                self.discarded(expr);
+            },
+            StmtKind::Return(ref value) => {
+                let ty = match *value {
+                    Some(ref value) => self.expr(value),
+                    None            => Ty::void(),
+                };
+                if let Some(ret) = self.ret.clone() {
+                    self.expect(&ret, &ty);
+                }
+            },
+            StmtKind::For(ref for_loop) => {
+                let iterable = self.value(&for_loop.iter);
+                let elem = match iterable.elem() {
+                    Some(elem) => elem,
+                    None       => {
+                        self.error(TypeErrorKind::NotIterable(iterable.to_string()));
+                        Ty::Unknown
+                    },
+                };
+                self.scopes.push(HashMap::new());
+                self.bind(&for_loop.pat, elem);
+                self.loop_body(&for_loop.label, None, &for_loop.body);
+                self.scopes.pop();
+            },
+            StmtKind::While(ref while_loop) => {
+                self.condition(&while_loop.cond);
+                self.narrowed(&while_loop.cond, true, |checker| {
+                    checker.loop_body(&while_loop.label, None, &while_loop.body)
+                });
+            },
+            StmtKind::Break(ref label, ref value) => {
+                let ty = match *value {
+                    Some(ref value) => self.expr(value),
+                    None            => Ty::void(),
+                };
+                if let Some(target) = self.target(label, "break") {
+                    match self.loops[target].value.take() {
+                        Some(prev) => self.loops[target].value = Some(self.join(prev, ty)),
+                        None if value.is_some() => self.error(TypeErrorKind::BreakWithValue),
+                        None => {},
+                    }
+                }
+            },
+            StmtKind::Continue(ref label) => {
+                self.target(label, "continue");
+            },
+            // Every value can be printed, optionals included.
+            StmtKind::Print(ref print) => {
+                for arg in print.args {
+                    self.expr(arg);
+                }
+                if let Some(ref format) = print.format {
+                    if format.args() != print.args.len() {
+                        self.error(TypeErrorKind::FormatArgs {
+                            placeholders: format.args(),
+                            args:         print.args.len(),
+                        });
+                    }
+                }
+            },
+        }
+    }
+
+    /// Checks the body of a loop.  `value` is the type of the loop's value
+    /// for `loop`, and `None` for loops without a value.  Returns the type
+    /// the `break`s give the loop.
+    fn loop_body(&mut self, label: &Option<String>, value: Option<Ty>, body: &Block<'ast>) -> Ty {
+        self.loops.push(LoopCx { label: label.clone(), value });
+        self.block(body);
+        let cx = self.loops.pop().expect("loop pushed above");
+        cx.value.unwrap_or_else(Ty::void)
+    }
+
+    /// Finds the loop a `break` or `continue` with `label` leaves.
+    fn target(&mut self, label: &Option<String>, keyword: &str) -> Option<usize> {
+        let target = match *label {
+            Some(ref label) => {
+                self.loops.iter().rposition(|cx| cx.label.as_ref() == Some(label))
+            },
+            None => self.loops.len().checked_sub(1),
+        };
+        if target.is_none() {
+            match *label {
+                Some(ref label) if !self.loops.is_empty() => {
+                    self.error(TypeErrorKind::UndeclaredLabel(label.clone()))
+                },
+                _ => self.error(TypeErrorKind::OutsideLoop(keyword.to_string())),
+            }
+        }
+        target
+    }
+
+    fn fn_decl(&mut self, decl: &FnDecl<'ast>) {
+        let params = self.ty_params(decl.generics);
+        let generics = mem::replace(&mut self.generics, params);
+        let ret = decl.ret.as_ref().map(|ret| self.annotation(ret));
+        let ret = mem::replace(&mut self.ret, ret);
+        let loops = mem::take(&mut self.loops);
+        self.scopes.push(HashMap::new());
+        self.params(decl.params);
+        let body = self.block(&decl.body);
+        self.returned(&decl.body, &body);
+        self.scopes.pop();
+        self.loops = loops;
+        self.ret = ret;
+        self.generics = generics;
+    }
+
+    /// Checks the type of the trailing expression of a function body against
+    /// the declared return type.  A body without one must return before its
+    /// end, unless the return type is `void`.
+    fn returned(&mut self, body: &Block<'ast>, ty: &Ty) {
+        let ret = match self.ret.clone() {
+            Some(ret) => ret,
+            None      => return,
+        };
+        match body.expr {
+            // The body reaches its end when the condition of a trailing `if`
+            // without an else branch does not hold.
+            Some(tail) if open_if(tail) && !ret.accepts(ty) => {
+                self.error(TypeErrorKind::MissingReturn(ret.to_string()))
+            },
+            Some(tail) => self.at(tail.span, |checker| checker.expect(&ret, ty)),
+            None if !ret.accepts(&Ty::void()) && !diverges(body) => {
+                self.error(TypeErrorKind::MissingReturn(ret.to_string()))
+            },
+            None => {},
+        }
+    }
+
+    fn params(&mut self, params: &[Param<'ast>]) {
+        for param in params {
+            let ty = self.opt_annotation(&param.ty);
+            self.bind(&param.pat, ty);
+        }
+    }
+
+    fn block(&mut self, block: &Block<'ast>) -> Ty {
+        self.scopes.push(HashMap::new());
+        self.declare_items(block.stmts);
+        for stmt in block.stmts {
+            self.stmt(stmt);
+        }
+        let ty = match block.expr {
+            Some(expr) => self.expr(expr),
+            None       => Ty::void(),
+        };
+        self.scopes.pop();
+        ty
+    }
+
+    /// Checks an expression whose value is not used.
+    fn discarded(&mut self, expr: &Expr<'ast>) {
+        match expr.kind {
+            ExprKind::If(cond, ref then, ref otherwise) => {
+                self.condition(cond);
+                self.narrowed(cond, true, |checker| checker.block(then));
+                if let Some(otherwise) = *otherwise {
+                    self.narrowed(cond, false, |checker| {
+                        checker.discarded(otherwise);
+                        Ty::Unknown
+                    });
+                }
+            },
+            ExprKind::Block(ref block) => {
+                self.block(block);
+            },
+            ExprKind::Match(scrutinee, arms) => {
+                self.at(expr.span, |checker| checker.match_expr(scrutinee, arms, false));
+            },
+            _ => {
+                self.expr(expr);
+            },
+        }
+    }
+
+    fn expr(&mut self, expr: &Expr<'ast>) -> Ty {
+        let ty = self.at(expr.span, |checker| checker.expr_kind(&expr.kind));
+        if let Ty::Prim(prim) = ty {
+            if ty.is_int() && is_arithmetic(expr) {
+                self.ints.insert((expr.file, expr.id), prim);
+            }
+        }
+        ty
+    }
+
+    fn expr_kind(&mut self, expr: &ExprKind<'ast>) -> Ty {
+        match *expr {
+            ExprKind::Literal(ref lit) => match *lit {
+                Literal::Nil        => Ty::nil(),
+                Literal::Boolean(_) => Ty::bool(),
+                Literal::Char(_)    => Ty::Prim(PrimType::Char),
+                Literal::Int(_)     => Ty::IntLit,
+                Literal::Float(_)   => Ty::FloatLit,
+                Literal::Str(_)     => Ty::Prim(PrimType::Str),
+            },
+            // Embedded values of any type are rendered into the string, nil included.
+            ExprKind::Interpolated(fragments) => {
+                for fragment in fragments {
+                    if let Fragment::Expr(ref expr, _) = *fragment {
+                        self.expr(expr);
+                    }
+                }
+                Ty::Prim(PrimType::Str)
+            },
+            ExprKind::Identifier(ref name) => self.lookup(name),
+            // Qualified names are declared by the importer, as written.
+            ExprKind::Path(ref path) => self.lookup(&path.to_string()),
+            ExprKind::Unary(op, operand) => {
+                let operand = self.value(operand);
+                let valid = match op {
+                    UnaryOp::Not   => operand.unify(&Ty::bool()).is_some(),
+                    UnaryOp::Minus => operand.mounts(Model::Num, &self.generics),
+                };
+                if !valid && operand != Ty::Unknown {
+                    self.error(TypeErrorKind::InvalidOperand {
+                        op: op.to_string(),
+                        ty: operand.to_string(),
+                    });
+                }
+                match op {
+                    UnaryOp::Not   => Ty::bool(),
+                    UnaryOp::Minus => operand,
+                }
+            },
+            // Equality holds between an optional and nil, every other
+            // operator needs values.  Either way both operands must have the
+            // same type, which mounts `Eq` unless it is compared with nil.
+            ExprKind::Binary(op @ BinaryOp::Comparison(ComparisonOp::Eql), lhs, rhs) |
+            ExprKind::Binary(op @ BinaryOp::Comparison(ComparisonOp::NotEql), lhs, rhs) => {
+                let lhs = self.expr(lhs);
+                let rhs = self.expr(rhs);
+                let nil_check = lhs == Ty::nil() || rhs == Ty::nil();
+                let unmounted = [&lhs, &rhs].iter()
+                    .find(|ty| !nil_check && !ty.mounts(Model::Eq, &self.generics))
+                    .map(|ty| ty.to_string());
+                match unmounted {
+                    Some(ty) => {
+                        self.error(TypeErrorKind::InvalidOperand { op: op.to_string(), ty })
+                    },
+                    None => {
+                        self.join(lhs, rhs);
+                    },
+                }
+                Ty::bool()
+            },
+            ExprKind::Binary(BinaryOp::Logical(op), lhs, rhs) => {
+                let lhs_ty = self.value(lhs);
+                // `rhs` only runs when `lhs` is true for `&&`, false for `||`.
+                let when = op == LogicalOp::And;
+                let rhs_ty = self.narrowed(lhs, when, |checker| checker.value(rhs));
+                self.binary(BinaryOp::Logical(op), lhs_ty, rhs_ty)
+            },
+            ExprKind::Binary(op, lhs, rhs) => {
+                let lhs = self.value(lhs);
+                let rhs = self.value(rhs);
+                self.binary(op, lhs, rhs)
+            },
+            ExprKind::Cast(operand, target) => {
+                let from = self.value(operand);
+                if !from.can_cast(target) {
+                    self.error(TypeErrorKind::InvalidCast {
+                        from: from.to_string(),
+                        to:   target.to_string(),
+                    });
+                }
+                Ty::Prim(target)
+            },
+            ExprKind::Call(callee, args) => {
+                let callee = self.value(callee);
+                let arg_tys: Vec<Ty> = args.iter().map(|arg| self.expr(arg)).collect();
+                match callee {
+                    Ty::Generic(params, generic) => self.instantiate(&params, *generic, &arg_tys),
+                    Ty::Fn(params, ret) => {
+                        if self.arity(params.len(), args.len()) {
+                            for ((param, arg), expr) in params.iter().zip(&arg_tys).zip(args) {
+                                self.expect(param, arg);
+                                self.literal_range(expr, param);
+                            }
+                        }
+                        *ret
+                    },
+                    Ty::Unknown | Ty::Param(_) => Ty::Unknown,
+                    ty => {
+                        self.error(TypeErrorKind::NotCallable(ty.to_string()));
+                        Ty::Unknown
+                    },
+                }
+            },
+            ExprKind::MethodCall(receiver, _, args) => {
+                self.value(receiver);
+                for arg in args {
+                    self.expr(arg);
+                }
+                Ty::Unknown
+            },
+            ExprKind::SafeMethodCall(receiver, _, args) => {
+                self.expr(receiver);
+                for arg in args {
+                    self.expr(arg);
+                }
+                Ty::Unknown
+            },
+            ExprKind::Assign(op, target, value) => {
+                // A narrowed optional may be assigned nil again, so it is no
+                // longer narrowed after the assignment.
+                let target = match target.kind {
+                    ExprKind::Identifier(ref name) => {
+                        self.unnarrow(name);
+                        self.declared(name)
+                    },
+                    _ => self.expr(target),
+                };
+                let ty = self.expr(value);
+                match op {
+                    AssignmentOp::Assign => {
+                        self.expect(&target, &ty);
+                        self.literal_range(value, &target);
+                    },
+                }
+                ty
+            },
+            ExprKind::Range(start, end, _) => {
+                let start = self.value(start);
+                let end = self.value(end);
+                let elem = self.join(start, end);
+                if !elem.is_int() && elem != Ty::Unknown {
+                    self.error(TypeErrorKind::Mismatch {
+                        expected: Ty::IntLit.to_string(),
+                        found:    elem.to_string(),
+                    });
+                }
+                Ty::Range(Box::new(elem))
+            },
+            ExprKind::Array(elems) => {
+                let mut elem = Ty::Unknown;
+                for value in elems {
+                    let value = self.expr(value);
+                    elem = self.join(elem, value);
+                }
+                Ty::Array(Box::new(elem))
+            },
+            ExprKind::Map(entries) => {
+                let (mut key, mut elem) = (Ty::Unknown, Ty::Unknown);
+                for (entry_key, value) in entries {
+                    let entry_key = self.expr(entry_key);
+                    key = self.join(key, entry_key);
+                    let value = self.expr(value);
+                    elem = self.join(elem, value);
+                }
+                Ty::Map(Box::new(key), Box::new(elem))
+            },
+            ExprKind::Index(indexed, index) => {
+                let indexed = self.value(indexed);
+                let index = self.expr(index);
+                match indexed {
+                    Ty::Array(elem) => {
+                        if !index.is_int() && index != Ty::Unknown {
+                            self.error(TypeErrorKind::Mismatch {
+                                expected: Ty::IntLit.to_string(),
+                                found:    index.to_string(),
+                            });
+                        }
+                        *elem
+                    },
+                    // Missing keys read as nil.
+                    Ty::Map(key, elem) => {
+                        self.expect(&key, &index);
+                        elem.optional()
+                    },
+                    Ty::Unknown => Ty::Unknown,
+                    ty => {
+                        self.error(TypeErrorKind::NotIndexable(ty.to_string()));
+                        Ty::Unknown
+                    },
+                }
+            },
+            ExprKind::Tuple(elems) => Ty::Tuple(elems.iter().map(|elem| self.expr(elem)).collect()),
+            ExprKind::Field(tuple, index) => match self.value(tuple) {
+                Ty::Tuple(ref elems) if index < elems.len() => elems[index].clone(),
+                Ty::Unknown => Ty::Unknown,
+                ty => {
+                    self.error(TypeErrorKind::UnknownField {
+                        ty:    ty.to_string(),
+                        field: index.to_string(),
+                    });
+                    Ty::Unknown
+                },
+            },
+            ExprKind::Closure(closure) => self.closure(closure),
+            ExprKind::Ternary(cond, then, otherwise) => {
+                self.condition(cond);
+                let then = self.narrowed(cond, true, |checker| checker.expr(then));
+                let otherwise = self.narrowed(cond, false, |checker| checker.expr(otherwise));
+                self.branches(then, otherwise)
+            },
+            ExprKind::If(cond, ref then, ref otherwise) => {
+                self.condition(cond);
+                let then = self.narrowed(cond, true, |checker| checker.block(then));
+                match *otherwise {
+                    Some(otherwise) => {
+                        let otherwise = self.narrowed(cond, false, |checker| checker.expr(otherwise));
+                        self.branches(then, otherwise)
+                    },
+                    // Without an else branch the value is nil when the
+                    // condition does not hold.
+                    None if then == Ty::void() => then,
+                    None                       => then.optional(),
+                }
+            },
+            ExprKind::Member(object, ref field) => match self.value(object) {
+                Ty::Named(ref name, ref args) if self.structs.contains_key(name) => {
+                    let decl = &self.structs[name];
+                    let ty = decl.fields.iter()
+                        .find(|(decl, _)| decl == field)
+                        .map(|(_, ty)| ty.subst(&decl.subst(args)));
+                    match ty {
+                        Some(ty) => ty,
+                        None     => {
+                            self.error(TypeErrorKind::UnknownField {
+                                ty:    Ty::Named(name.clone(), args.clone()).to_string(),
+                                field: field.clone(),
+                            });
+                            Ty::Unknown
+                        },
+                    }
+                },
+                Ty::Unknown => Ty::Unknown,
+                ty => {
+                    let field = field.clone();
+                    self.error(TypeErrorKind::UnknownField { ty: ty.to_string(), field });
+                    Ty::Unknown
+                },
+            },
+            ExprKind::Struct(ref name, fields) => self.struct_lit(name, fields),
+            ExprKind::Loop(ref label, ref body) => self.loop_body(label, Some(Ty::Unknown), body),
+            ExprKind::Match(scrutinee, arms) => self.match_expr(scrutinee, arms, true),
+            ExprKind::Block(ref block) => self.block(block),
+        }
+    }
+
+    /// Checks a struct literal, inferring the type arguments of the struct
+    /// from the values of its fields.
+    fn struct_lit(&mut self, name: &str, fields: &[(String, Expr<'ast>)]) -> Ty {
+        let values: Vec<_> = fields.iter().map(|(_, value)| self.expr(value)).collect();
+        let decl = match self.structs.get(name) {
+            Some(decl) => decl.clone(),
+            None       => {
+                let declared = self.specs.contains_key(name) || self.aliases.contains_key(name);
+                self.error(match declared {
+                    true  => TypeErrorKind::NotAStruct(name.to_string()),
+                    false => TypeErrorKind::UnknownType(name.to_string()),
+                });
+                return Ty::Unknown;
+            },
+        };
+        let mut subst = HashMap::new();
+        for ((field, _), value) in fields.iter().zip(&values) {
+            if let Some((_, ty)) = decl.fields.iter().find(|(decl, _)| decl == field) {
+                ty.infer(value, &mut subst);
+            }
+        }
+        for (i, ((field, expr), value)) in fields.iter().zip(&values).enumerate() {
+            if fields[..i].iter().any(|(given, _)| given == field) {
+                self.error(TypeErrorKind::DuplicateField(field.clone()));
+                continue;
+            }
+            match decl.fields.iter().find(|(decl, _)| decl == field) {
+                Some((_, ty)) => self.at(expr.span, |checker| {
+                    let ty = ty.subst(&subst);
+                    checker.expect(&ty, value);
+                    checker.literal_range(expr, &ty);
+                }),
+                None => self.error(TypeErrorKind::UnknownField {
+                    ty:    name.to_string(),
+                    field: field.clone(),
+                }),
+            }
+        }
+        for (field, _) in &decl.fields {
+            if !fields.iter().any(|(given, _)| given == field) {
+                self.error(TypeErrorKind::MissingField {
+                    ty:    name.to_string(),
+                    field: field.clone(),
+                });
+            }
+        }
+        let args: Vec<_> = decl.params.iter()
+            .map(|param| subst.get(&param.name).cloned().unwrap_or(Ty::Unknown))
+            .collect();
+        self.bounds(&decl.params, &args);
+        Ty::Named(name.to_string(), args)
+    }
+
+    /// Checks a `match` on a spec value, which must have an arm for each
+    /// variant.  When the value of the `match` is `used` all arms must have
+    /// the same type.
+    fn match_expr(&mut self, scrutinee: &Expr<'ast>, arms: &[Arm<'ast>], used: bool) -> Ty {
+        let ty = self.value(scrutinee);
+        let spec = match ty {
+            Ty::Named(ref name, ref args) if self.specs.contains_key(name) => Some(args.clone()),
+            Ty::Unknown => None,
+            ref ty => {
+                self.error(TypeErrorKind::NotASpec(ty.to_string()));
+                None
+            },
+        };
+        let mut value = Ty::Unknown;
+        for arm in arms {
+            self.scopes.push(HashMap::new());
+            self.at(arm.pat.span, |checker| checker.bind(&arm.pat, ty.clone()));
+            if used {
+                let body = self.expr(&arm.body);
+                value = self.at(arm.body.span, |checker| checker.join(value, body));
+            } else {
+                self.discarded(&arm.body);
+            }
+            self.scopes.pop();
+        }
+        if let Some(ref args) = spec {
+            let rows: Vec<_> = arms.iter().map(|arm| vec![Some(&arm.pat)]).collect();
+            let missing: Vec<_> = self.variants(&ty, args).into_iter()
+                .filter(|(path, fields)| {
+                    !self.exhaustive(&variant_rows(&rows, path, fields.len()), fields)
+                })
+                .map(|(path, _)| path)
+                .collect();
+            if !missing.is_empty() {
+                self.error(TypeErrorKind::NonExhaustive(missing));
+            }
+        }
+        if used { value } else { Ty::void() }
+    }
+
+    /// The variants of the spec type `ty`, by path, with the types of their
+    /// values given the type arguments `args`.
+    fn variants(&self, ty: &Ty, args: &[Ty]) -> Vec<(String, Vec<Ty>)> {
+        let (name, decl) = match *ty {
+            Ty::Named(ref name, _) => (name, &self.specs[name]),
+            _                      => return Vec::new(),
+        };
+        let subst = decl.subst(args);
+        decl.variants.iter().map(|(variant, fields)| {
+            let fields = fields.iter().map(|field| field.subst(&subst)).collect();
+            (format!("{}::{}", name, variant), fields)
+        }).collect()
+    }
+
+    /// Whether the rows of patterns, each matching a sequence of values of
+    /// the types `tys`, together match every such sequence.  `None` stands
+    /// for a pattern matching any value.
+    fn exhaustive(&self, rows: &[Vec<Option<&Pattern<'ast>>>], tys: &[Ty]) -> bool {
+        let (ty, rest) = match tys.split_first() {
+            Some(split) => split,
+            None        => return !rows.is_empty(),
+        };
+        let with = |fields: &[Ty]| fields.iter().chain(rest).cloned().collect::<Vec<_>>();
+        let bindings = rows.iter().all(|row| match row[0] {
+            Some(pat) => matches!(pat.kind, PatternKind::Ident(_)),
+            None      => true,
+        });
+        match *ty {
+            // Splitting the column on the variants would never end for a
+            // spec holding values of its own type.
+            _ if bindings => self.exhaustive(&specialize(rows, 0, |_| None), rest),
+            Ty::Named(ref name, ref args) if self.specs.contains_key(name) => {
+                self.variants(ty, args).iter().all(|(path, fields)| {
+                    self.exhaustive(&variant_rows(rows, path, fields.len()), &with(fields))
+                })
+            },
+            Ty::Tuple(ref elems) => {
+                let rows = specialize(rows, elems.len(), |pat| match pat.kind {
+                    PatternKind::Tuple(pats) if pats.len() == elems.len() => {
+                        Some(pats.iter().map(Some).collect())
+                    },
+                    _ => None,
+                });
+                self.exhaustive(&rows, &with(elems))
+            },
+            // Values of other types, or of types not known, match any
+            // pattern that is not a variant.
+            _ => {
+                let rows = specialize(rows, 0, |pat| match pat.kind {
+                    PatternKind::Variant(..) => None,
+                    _                        => Some(Vec::new()),
+                });
+                self.exhaustive(&rows, rest)
+            },
+        }
+    }
+
+    fn closure(&mut self, closure: &Closure<'ast>) -> Ty {
+        let annotated = closure.ret.as_ref().map(|ret| self.annotation(ret));
+        let ret = mem::replace(&mut self.ret, annotated.clone());
+        let loops = mem::take(&mut self.loops);
+        self.scopes.push(HashMap::new());
+        self.params(closure.params);
+        let body = self.block(&closure.body);
+        self.returned(&closure.body, &body);
+        self.scopes.pop();
+        self.loops = loops;
+        self.ret = ret;
+        let params = closure.params.iter().map(|param| self.opt_annotation(&param.ty)).collect();
+        Ty::Fn(params, Box::new(annotated.unwrap_or(body)))
+    }
+
+    /// Checks a call to a generic function, inferring its type arguments
+    /// from `args` and checking them against the bounds of `generics`.
+    /// Returns the instantiated return type.
+    fn instantiate(&mut self, generics: &[TyParam], generic: Ty, args: &[Ty]) -> Ty {
+        let (params, ret) = match generic {
+            Ty::Fn(params, ret) => (params, ret),
+            _                   => return Ty::Unknown,
+        };
+        if !self.arity(params.len(), args.len()) {
+            return Ty::Unknown;
+        }
+        let mut subst = HashMap::new();
+        for (param, arg) in params.iter().zip(args) {
+            param.infer(arg, &mut subst);
+        }
+        for (param, arg) in params.iter().zip(args) {
+            self.expect(&param.subst(&subst), arg);
+        }
+        let ty_args: Vec<_> = generics.iter()
+            .map(|param| subst.get(&param.name).cloned().unwrap_or(Ty::Unknown))
+            .collect();
+        self.bounds(generics, &ty_args);
+        ret.subst(&subst)
+    }
+
+    /// Checks that a call passes as many arguments as the function has
+    /// parameters.
+    fn arity(&mut self, params: usize, args: usize) -> bool {
+        if params != args {
+            self.error(TypeErrorKind::ArgCount { expected: params, found: args });
+        }
+        params == args
+    }
+
+    /// Checks the operands of a binary operator and returns the type of the
+    /// operation.  Equality is checked by the caller, as it holds between any
+    /// two values.
+    fn binary(&mut self, op: BinaryOp, lhs: Ty, rhs: Ty) -> Ty {
+        let generics = &self.generics;
+        let valid = |ty: &Ty| *ty == Ty::Unknown || match op {
+            BinaryOp::Logical(_) => ty.unify(&Ty::bool()).is_some(),
+            BinaryOp::BitWise(_) => ty.is_int(),
+            BinaryOp::Arithmetic(ArithmeticOp::Add) => match *ty {
+                Ty::Prim(PrimType::Str) | Ty::Array(_) => true,
+                ref ty                                 => ty.mounts(Model::Num, generics),
+            },
+            BinaryOp::Arithmetic(_) => ty.mounts(Model::Num, generics),
+            BinaryOp::Comparison(_) => ty.mounts(Model::Ord, generics),
+        };
+        // Only the first invalid operand is reported.
+        let invalid = [&lhs, &rhs].iter().find(|operand| !valid(operand)).map(|ty| ty.to_string());
+        if let Some(ref ty) = invalid {
+            self.error(TypeErrorKind::InvalidOperand { op: op.to_string(), ty: ty.clone() });
+        }
+        let invalid = invalid.is_some();
+        match op {
+            BinaryOp::Logical(_) => Ty::bool(),
+            BinaryOp::Comparison(_) => {
+                if !invalid {
+                    self.join(lhs, rhs);
+                }
+                Ty::bool()
+            },
+            // The shift amount may be of another integer type.
+            BinaryOp::BitWise(BitWiseOp::BwShftL) | BinaryOp::BitWise(BitWiseOp::BwShftR) => lhs,
+            _ if invalid => Ty::Unknown,
+            _ => self.join(lhs, rhs),
+        }
+    }
+
+    /// Checks that an integer literal, possibly negated, fits in the integer
+    /// type `ty` it is stored as.  Arithmetic on literals alone is left to
+    /// evaluation, which checks its value against `ty`.
+    fn literal_range(&mut self, expr: &Expr<'ast>, ty: &Ty) {
+        let prim = match *ty {
+            Ty::Prim(prim) => prim,
+            Ty::Optional(ref ty) => match **ty {
+                Ty::Prim(prim) => prim,
+                _              => return,
+            },
+            _ => return,
+        };
+        let literal = match expr.kind {
+            ExprKind::Literal(Literal::Int(value)) => value,
+            ExprKind::Unary(UnaryOp::Minus, operand) => match operand.kind {
+                ExprKind::Literal(Literal::Int(value)) => value.wrapping_neg(),
+                _ => return self.store_int(expr, prim),
+            },
+            _ => return self.store_int(expr, prim),
+        };
+        let (min, max) = match prim {
+            PrimType::I8  => (i128::from(i8::MIN), i128::from(i8::MAX)),
+            PrimType::U8  => (0, i128::from(u8::MAX)),
+            PrimType::I16 => (i128::from(i16::MIN), i128::from(i16::MAX)),
+            PrimType::U16 => (0, i128::from(u16::MAX)),
+            PrimType::I32 => (i128::from(i32::MIN), i128::from(i32::MAX)),
+            PrimType::U32 => (0, i128::from(u32::MAX)),
+            PrimType::I64 => (i128::from(i64::MIN), i128::from(i64::MAX)),
+            PrimType::U64 => (0, i128::from(u64::MAX)),
+            _             => return,
+        };
+        if literal < min || max < literal {
+            self.at(expr.span, |checker| checker.error(TypeErrorKind::LiteralOutOfRange {
+                literal: literal.to_string(),
+                ty:      prim.to_string(),
+            }));
+        }
+    }
+
+    /// Keeps the integer type `prim` an arithmetic expression of literals is
+    /// stored as.
+    fn store_int(&mut self, expr: &Expr<'ast>, prim: PrimType) {
+        if Ty::Prim(prim).is_int() && is_arithmetic(expr) {
+            self.ints.entry((expr.file, expr.id)).or_insert(prim);
+        }
+    }
+
+    /// Checks an expression whose value is used as is, which rules out
+    /// optionals.  Returns the type the optional wraps to avoid reporting the
+    /// same value twice.
+    fn value(&mut self, expr: &Expr<'ast>) -> Ty {
+        match self.expr(expr) {
+            Ty::Optional(ty) => {
+                self.error(TypeErrorKind::UncheckedOptional(Ty::Optional(ty.clone()).to_string()));
+                *ty
+            },
+            ty => ty,
+        }
+    }
+
+    /// Checks that a value of type `found` can be stored as an `expected`.
+    fn expect(&mut self, expected: &Ty, found: &Ty) {
+        if expected.accepts(found) {
+            return;
+        }
+        // An optional where its wrapped type is expected lacks a nil check.
+        let kind = match *found {
+            Ty::Optional(ref ty) if expected.accepts(ty) => {
+                TypeErrorKind::UncheckedOptional(found.to_string())
+            },
+            _ => TypeErrorKind::Mismatch {
+                expected: expected.to_string(),
+                found:    found.to_string(),
+            },
+        };
+        self.error(kind);
+    }
+
+    /// Checks a value of type `found` against the annotation `ty`, returning
+    /// the annotated type.  When the annotation names an alias, a mismatch
+    /// shows the alias as written.
+    fn expect_annotated(&mut self, ty: &Type<'ast>, found: &Ty) -> Ty {
+        let expected = self.annotation(ty);
+        let written = ty.to_string();
+        let unchecked = match *found {
+            Ty::Optional(ref wrapped) => expected.accepts(wrapped),
+            _                         => false,
+        };
+        if written != expected.to_string() && !expected.accepts(found) && !unchecked {
+            self.error(TypeErrorKind::AliasMismatch {
+                alias:    written,
+                expected: expected.to_string(),
+                found:    found.to_string(),
+            });
+        } else {
+            self.expect(&expected, found);
+        }
+        expected
+    }
+
+    /// Runs `check` in a scope where the optional variables that `cond`
+    /// proves non nil, when it evaluates to `when`, have their wrapped type.
+    fn narrowed<F>(&mut self, cond: &Expr<'ast>, when: bool, check: F) -> Ty
+        where F: FnOnce(&mut Checker<'ast>) -> Ty
+    {
+        let mut names = Vec::new();
+        non_nil(cond, when, &mut names);
+        let mut scope = HashMap::new();
+        for name in names {
+            if let Ty::Optional(ty) = self.lookup(name) {
+                scope.insert(name.to_string(), *ty);
+            }
+        }
+        self.narrowed.push(self.scopes.len());
+        self.scopes.push(scope);
+        let ty = check(self);
+        self.scopes.pop();
+        self.narrowed.pop();
+        ty
+    }
+
+    /// Forgets the nil checks of `name`, which is assigned a new value.
+    fn unnarrow(&mut self, name: &str) {
+        for &depth in &self.narrowed {
+            self.scopes[depth].remove(name);
+        }
+    }
+
+    /// Checks that a condition is a `bool`.
+    fn condition(&mut self, cond: &Expr<'ast>) {
+        let ty = self.value(cond);
+        if ty.unify(&Ty::bool()).is_none() {
+            self.error(TypeErrorKind::Mismatch {
+                expected: Ty::bool().to_string(),
+                found:    ty.to_string(),
+            });
+        }
+    }
+
+    /// Joins the types of the elements of a collection, reporting the first
+    /// element that does not fit the others.
+    fn join(&mut self, elem: Ty, next: Ty) -> Ty {
+        match elem.unify(&next) {
+            Some(ty) => ty,
+            None     => {
+                self.error(TypeErrorKind::Mismatch {
+                    expected: elem.to_string(),
+                    found:    next.to_string(),
+                });
+                elem
+            },
+        }
+    }
+
+    /// Joins the types of two conditional branches.
+    fn branches(&mut self, then: Ty, otherwise: Ty) -> Ty {
+        match then.unify(&otherwise) {
+            Some(ty) => ty,
+            None     => {
+                self.error(TypeErrorKind::BranchMismatch {
+                    then:      then.to_string(),
+                    otherwise: otherwise.to_string(),
+                });
+                Ty::Unknown
+            },
+        }
+    }
+
+    /// Defines the names of `pat`, destructuring a tuple type or the values
+    /// of a spec variant.
+    fn bind(&mut self, pat: &Pattern<'ast>, ty: Ty) {
+        match (&pat.kind, ty) {
+            (PatternKind::Variant(path, pats), ty) => {
+                let path = path.to_string();
+                let fields = match ty {
+                    Ty::Named(ref spec, ref args) if self.specs.contains_key(spec) => {
+                        self.variants(&ty, args).into_iter()
+                            .find(|variant| variant.0 == path)
+                            .map(|(_, fields)| fields)
+                    },
+                    _ => Some(vec![Ty::Unknown; pats.len()]),
+                };
+                let fields = match fields {
+                    Some(ref fields) if fields.len() != pats.len() => {
+                        self.error(TypeErrorKind::VariantArity {
+                            variant:  path,
+                            expected: fields.len(),
+                            found:    pats.len(),
+                        });
+                        vec![Ty::Unknown; pats.len()]
+                    },
+                    Some(fields) => fields,
+                    None => {
+                        self.error(TypeErrorKind::UnknownVariant(path));
+                        vec![Ty::Unknown; pats.len()]
+                    },
+                };
+                for (pat, field) in pats.iter().zip(fields) {
+                    self.bind(pat, field);
+                }
+            },
+            (PatternKind::Ident(name), ty) => self.define(name, ty),
+            (PatternKind::Tuple(pats), Ty::Tuple(elems)) if pats.len() == elems.len() => {
+                for (pat, elem) in pats.iter().zip(elems) {
+                    self.bind(pat, elem);
+                }
+            },
+            (PatternKind::Tuple(pats), ty) => {
+                if ty != Ty::Unknown {
+                    self.error(TypeErrorKind::Mismatch {
+                        expected: Ty::Tuple(vec![Ty::Unknown; pats.len()]).to_string(),
+                        found:    ty.to_string(),
+                    });
+                }
+                for pat in pats.iter() {
+                    self.bind(pat, Ty::Unknown);
+                }
+            },
+        }
+    }
+
+    fn define(&mut self, name: &str, ty: Ty) {
+        self.scopes.last_mut().unwrap().insert(name.to_string(), ty);
+    }
+
+    fn lookup(&self, name: &str) -> Ty {
+        self.scopes.iter().rev()
+            .filter_map(|scope| scope.get(name))
+            .next()
+            .cloned()
+            .unwrap_or(Ty::Unknown)
+    }
+
+    /// Looks up the type `name` was declared with, ignoring nil checks.
+    fn declared(&self, name: &str) -> Ty {
+        self.scopes.iter().enumerate().rev()
+            .filter(|&(depth, _)| !self.narrowed.contains(&depth))
+            .filter_map(|(_, scope)| scope.get(name))
+            .next()
+            .cloned()
+            .unwrap_or(Ty::Unknown)
+    }
+
+    /// Runs `check` with errors pointing at `span`, then restores the
+    /// location of the enclosing node.
+    fn at<T, F>(&mut self, span: Span, check: F) -> T
+        where F: FnOnce(&mut Checker<'ast>) -> T
+    {
+        let outer = mem::replace(&mut self.span, span);
+        let result = check(self);
+        self.span = outer;
+        result
+    }
+
+    /// Reports an error at the checked node.  Annotations may be converted
+    /// more than once, so an error already reported at the same place is not
+    /// reported again.
+    fn error(&mut self, kind: TypeErrorKind) {
+        let diagnostic = From::from(TypeError {
+            source:   self.source.clone(),
+            span:     self.span,
+            severity: Severity::Error,
+            kind
+        });
+        if !self.errors.contains(&diagnostic) {
+            self.errors.push(diagnostic);
+        }
+    }
+}
+
+/// A struct declaration, its field types mentioning its type parameters.
+#[derive(Clone)]
+struct StructTy {
+    params: Vec<TyParam>,
+    fields: Vec<(String, Ty)>,
+}
+
+/// A spec declaration, with the types of the values of each variant.
+struct SpecTy {
+    params:   Vec<TyParam>,
+    variants: Vec<(String, Vec<Ty>)>,
+}
+
+/// The types `args` gives the type parameters `params`.
+fn subst(params: &[TyParam], args: &[Ty]) -> HashMap<String, Ty> {
+    params.iter().map(|param| param.name.clone()).zip(args.iter().cloned()).collect()
+}
+
+impl StructTy {
+    fn subst(&self, args: &[Ty]) -> HashMap<String, Ty> {
+        subst(&self.params, args)
+    }
+}
+
+impl SpecTy {
+    fn subst(&self, args: &[Ty]) -> HashMap<String, Ty> {
+        subst(&self.params, args)
+    }
+}
+
+/// Rows of patterns, `None` matching any value.
+type Rows<'a> = Vec<Vec<Option<&'a Pattern<'a>>>>;
+
+/// The rows whose first pattern matches a value of some shape, that pattern
+/// replaced by the `arity` patterns `parts` gives for the parts of the
+/// value, or `None` when it matches other shapes.  Bindings match them all.
+fn specialize<'a, F>(rows: &[Vec<Option<&'a Pattern<'a>>>], arity: usize, parts: F) -> Rows<'a>
+    where F: Fn(&'a Pattern<'a>) -> Option<Vec<Option<&'a Pattern<'a>>>>
+{
+    rows.iter().filter_map(|row| {
+        let mut specialized = match row[0] {
+            Some(pat) if !matches!(pat.kind, PatternKind::Ident(_)) => parts(pat)?,
+            _ => vec![None; arity],
+        };
+        specialized.extend_from_slice(&row[1..]);
+        Some(specialized)
+    }).collect()
+}
+
+/// The rows matching the variant `path` holding `arity` values.  A pattern
+/// with the wrong number of values, already reported, matches any of them.
+fn variant_rows<'a>(rows: &[Vec<Option<&'a Pattern>>], path: &str, arity: usize) -> Rows<'a> {
+    specialize(rows, arity, |pat| match pat.kind {
+        PatternKind::Variant(ref variant, pats) if variant.to_string() == path => {
+            match pats.len() == arity {
+                true  => Some(pats.iter().map(Some).collect()),
+                false => Some(vec![None; arity]),
+            }
+        },
+        _ => None,
+    })
+}
+
+/// A loop enclosing the checked node.
+struct LoopCx {
+    label: Option<String>,
+    /// Type of the values `break` leaves a `loop` with so far, `None` for the
+    /// loops that have no value.
+    value: Option<Ty>,
+}
+
+/// Collects the variables that are not nil when `cond` evaluates to `when`.
+fn non_nil<'a>(cond: &'a Expr, when: bool, names: &mut Vec<&'a str>) {
+    match cond.kind {
+        ExprKind::Binary(BinaryOp::Comparison(op), lhs, rhs) => {
+            let name = match (&lhs.kind, &rhs.kind) {
+                (&ExprKind::Identifier(ref name), &ExprKind::Literal(Literal::Nil)) |
+                (&ExprKind::Literal(Literal::Nil), &ExprKind::Identifier(ref name)) => name,
+                _ => return,
+            };
+            match (op, when) {
+                (ComparisonOp::NotEql, true) | (ComparisonOp::Eql, false) => names.push(name),
+                _ => {},
+            }
+        },
+        // Both operands hold when `a && b` is true, neither when `a || b` is
+        // false.
+        ExprKind::Binary(BinaryOp::Logical(LogicalOp::And), lhs, rhs) if when => {
+            non_nil(lhs, when, names);
+            non_nil(rhs, when, names);
+        },
+        ExprKind::Binary(BinaryOp::Logical(LogicalOp::Or), lhs, rhs) if !when => {
+            non_nil(lhs, when, names);
+            non_nil(rhs, when, names);
+        },
+        ExprKind::Unary(UnaryOp::Not, operand) => non_nil(operand, !when, names),
+        _ => {},
+    }
+}
+
+/// Whether running `block` never reaches its end:  its last statement is a
+/// `return`, an `if` whose branches all diverge or a `loop` without `break`.
+fn diverges(block: &Block) -> bool {
+    if let Some(tail) = block.expr {
+        return expr_diverges(tail);
+    }
+    match block.stmts.last().map(|stmt| &stmt.kind) {
+        Some(StmtKind::Return(_))  => true,
+        Some(StmtKind::Expr(expr)) => expr_diverges(expr),
+        _                          => false,
+    }
+}
+
+/// Whether the value of `expr` is computed by an arithmetic or bitwise
+/// operator, which may overflow the type of its operands.
+fn is_arithmetic(expr: &Expr) -> bool {
+    matches!(expr.kind,
+             ExprKind::Unary(UnaryOp::Minus, _) |
+             ExprKind::Binary(BinaryOp::Arithmetic(_), _, _) |
+             ExprKind::Binary(BinaryOp::BitWise(_), _, _))
+}
+
+/// Whether `expr` is an `if`, or a chain of `else if`s, without a final else
+/// branch.
+fn open_if(expr: &Expr) -> bool {
+    match expr.kind {
+        ExprKind::If(_, _, None)                => true,
+        ExprKind::If(_, _, Some(otherwise))     => open_if(otherwise),
+        _                                       => false,
+    }
+}
+
+fn expr_diverges(expr: &Expr) -> bool {
+    match expr.kind {
+        ExprKind::If(_, ref then, Some(otherwise)) => {
+            diverges(then) && expr_diverges(otherwise)
+        },
+        ExprKind::Block(ref block) => diverges(block),
+        // A `match` diverges when all of its arms do.
+        ExprKind::Match(_, arms) => {
+            arms.iter().all(|arm| expr_diverges(&arm.body))
+        },
+        ExprKind::Loop(ref label, ref body) => {
+            let mut breaks = Breaks { label: label.as_ref(), depth: 0, found: false };
+            breaks.visit_block(body);
+            !breaks.found
+        },
+        _ => false,
+    }
+}
+
+/// Looks for a `break` leaving the loop labeled `label` from within its
+/// body.  `depth` counts the loops nested in that body, which an unlabeled
+/// `break` leaves instead.
+struct Breaks<'a> {
+    label: Option<&'a String>,
+    depth: usize,
+    found: bool,
+}
+
+impl<'a> Breaks<'a> {
+    fn nested<F: FnOnce(&mut Breaks<'a>)>(&mut self, walk: F) {
+        self.depth += 1;
+        walk(self);
+        self.depth -= 1;
+    }
+}
+
+impl<'a, 'ast> Visitor<'ast> for Breaks<'a> {
+    fn visit_stmt(&mut self, stmt: &'ast Stmt) {
+        if let StmtKind::Break(ref label, _) = stmt.kind {
+            self.found |= match *label {
+                Some(ref label) => self.label == Some(label),
+                None            => self.depth == 0,
+            };
+        }
+        visit::walk_stmt(self, stmt);
+    }
+
+    fn visit_for_loop(&mut self, for_loop: &'ast ForLoop) {
+        self.nested(|breaks| visit::walk_for_loop(breaks, for_loop));
+    }
+
+    fn visit_while_loop(&mut self, while_loop: &'ast WhileLoop) {
+        self.nested(|breaks| visit::walk_while_loop(breaks, while_loop));
+    }
+
+    fn visit_expr(&mut self, expr: &'ast Expr) {
+        match expr.kind {
+            ExprKind::Loop(..) => self.nested(|breaks| visit::walk_expr(breaks, expr)),
+            _                  => visit::walk_expr(self, expr),
+        }
+    }
+
+    // Loops cannot be left from the closures and functions declared in them.
+    fn visit_closure(&mut self, _closure: &'ast Closure) {}
+
+    fn visit_fn_decl(&mut self, _decl: &'ast FnDecl) {}
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use syntax::ast::Arena;
+    use syntax::parser::parse_module;
+
+    fn check(src: &str) -> Result<(), Vec<String>> {
+        let arena = Arena::new();
+        let module = parse_module(&arena, src).unwrap();
+        Checker::new("test.ag").check_module(&module)
+            .map_err(|errors| errors.into_iter().map(|err| err.msg).collect())
+    }
+
+    #[test]
+    fn conditionals() {
+        assert_eq!(check("
+            fn pick(b: bool, x: i32) -> i32 { b ? x : 0 }
+            let a = pick(true, 1) > 0 ? \"yes\" : \"no\";
+            let c = if a == \"yes\" { 1.5 } else if false { 2.0 } else { 0.0 };
+            if c > 1.0 { pick(false, 2); } else { \"discarded\"; }
+        "), Ok(()));
+    }
+
+    #[test]
+    fn if_without_else() {
+        assert_eq!(check("
+            let x: i32? = if true { 1 };
+            fn f(b: bool) -> i32? { if b { 1 } }
+            if false { 1; }
+        "), Ok(()));
+        assert_eq!(check("let x: i32 = if false { 1 };"),
+                   Err(vec!["optional `{integer}?` used without checking for nil".to_string()]));
+        assert_eq!(check("let x: i32 = if false { 1; };"),
+                   Err(vec!["mismatched types: expected `i32`, found `void`".to_string()]));
+    }
+
+    #[test]
+    fn non_bool_condition() {
+        assert_eq!(check("let a = 1 ? 2 : 3;"),
+                   Err(vec!["mismatched types: expected `bool`, found `{integer}`".to_string()]));
+        assert_eq!(check("fn f() -> str { \"s\" } if f() { 1; }"),
+                   Err(vec!["mismatched types: expected `bool`, found `str`".to_string()]));
+    }
+
+    #[test]
+    fn casts() {
+        assert_eq!(check("
+            fn f(x: i32, c: char, b: bool, y: f64) -> u8 { x -> u8 }
+            let a = 300 -> u8 -> char;
+            let b = 'a' -> i64 + (true -> u8 -> i64);
+            let c = 2.5 -> i32 -> f32;
+            let d = f(1, 'c', false, 0.5) -> char;
+        "), Ok(()));
+        assert_eq!(check("let a = \"1\" -> i32;"),
+                   Err(vec!["invalid cast from `str` to `i32`".to_string()]));
+        assert_eq!(check("let a = 1 -> bool; let b = 1.5 -> char;"),
+                   Err(vec!["invalid cast from `{integer}` to `bool`".to_string(),
+                            "invalid cast from `{float}` to `char`".to_string()]));
+        assert_eq!(check("fn f(x: i32) -> char { x -> char }"),
+                   Err(vec!["invalid cast from `i32` to `char`".to_string()]));
+    }
+
+    #[test]
+    fn assignments() {
+        assert_eq!(check("
+            let x: i32 = 1;
+            x = 2;
+            let a: i32? = nil;
+            if a != nil { a = nil; }
+            let ys = [1, 2];
+            ys[0] = 3;
+        "), Ok(()));
+        assert_eq!(check("let x: i32 = 1; x = \"s\";"),
+                   Err(vec!["mismatched types: expected `i32`, found `str`".to_string()]));
+        assert_eq!(check("let ys: [str] = []; ys[0] = 'c';"),
+                   Err(vec!["mismatched types: expected `str`, found `char`".to_string()]));
+        assert_eq!(check("let b: u8 = 1; b = 300;"),
+                   Err(vec!["literal `300` does not fit in `u8`".to_string()]));
+    }
+
+    #[test]
+    fn optionals() {
+        assert_eq!(check("
+            fn find(key: str) -> i32? { key == \"a\" ? 1 : nil }
+            let a: i32? = find(\"a\");
+            let b: i32? = nil;
+            let c = a != nil ? a + 1 : 0;
+            let d = if a == nil { 0 } else { a * 2 };
+            if a != nil && b != nil { a + b; }
+            let e = a == nil || a > 1;
+            let f = if !(a == nil) { a } else { 0 };
+            let s: str? = nil;
+            let n = s?.len();
+        "), Ok(()));
+        let unchecked = |ty: &str| format!("optional `{}` used without checking for nil", ty);
+        assert_eq!(check("let a: i32? = 1; let b = a + 1;"), Err(vec![unchecked("i32?")]));
+        assert_eq!(check("let a: i32? = 1; let b: i32 = a;"), Err(vec![unchecked("i32?")]));
+        assert_eq!(check("let s: str? = nil; s.len();"), Err(vec![unchecked("str?")]));
+        assert_eq!(check("let a: i32? = 1; if a == nil { a + 1; }"), Err(vec![unchecked("i32?")]));
+        assert_eq!(check("let a: i32? = 1; let b = a != nil || a > 1;"), Err(vec![unchecked("i32?")]));
+        assert_eq!(check("fn f(x: i32) {} let a: i32? = 1; f(a);"), Err(vec![unchecked("i32?")]));
+        assert_eq!(check("let a: i32 = nil;"),
+                   Err(vec!["mismatched types: expected `i32`, found `nil`".to_string()]));
+        assert_eq!(check("let x: i32? = 3; if x != nil { x = nil; println \"{}\", x + 1; }"),
+                   Err(vec![unchecked("i32?")]));
+    }
+
+    #[test]
+    fn equality() {
+        assert_eq!(check("
+            let a: i32? = 1;
+            let b = a == nil || a == 2 || 1 != 2 || \"x\" == \"y\";
+        "), Ok(()));
+        assert_eq!(check("1 == \"x\";"),
+                   Err(vec!["mismatched types: expected `{integer}`, found `str`".to_string()]));
+        assert_eq!(check("let a: i32? = 1; let b = a != 'c';"),
+                   Err(vec!["mismatched types: expected `i32?`, found `char`".to_string()]));
+    }
+
+    #[test]
+    fn tuples() {
+        assert_eq!(check("
+            fn swap((a, b): (i32, str)) -> (str, i32) { (b, a) }
+            let pair: (str, i32) = swap((1, \"one\"));
+            let (name, n) = pair;
+            let m = n + pair.1;
+            let maybe: (i32?, bool) = (nil, true);
+            let unit = ();
+        "), Ok(()));
+        assert_eq!(check("let (a, b) = 1;"),
+                   Err(vec!["mismatched types: expected `(_, _)`, found `{integer}`".to_string()]));
+        assert_eq!(check("let (a, b) = (1, 2, 3);"),
+                   Err(vec!["mismatched types: expected `(_, _)`, found `({integer}, {integer}, {integer})`"
+                            .to_string()]));
+        assert_eq!(check("let a = (1, 'c').2;"),
+                   Err(vec!["no field `2` on type `({integer}, char)`".to_string()]));
+        assert_eq!(check("let a: (i32, str) = (1, 2);"),
+                   Err(vec!["mismatched types: expected `(i32, str)`, found `({integer}, {integer})`"
+                            .to_string()]));
+    }
+
+    #[test]
+    fn collections_and_loops() {
+        assert_eq!(check("
+            fn sum(xs: [i32]) -> i32 { let total = 0; for x in xs { total = total + x; } total }
+            let ages: [str: i32] = [\"ann\": 31];
+            let ann = ages[\"ann\"];
+            let age = ann != nil ? ann : 0;
+            for (name, years) in ages { name.len() + years; }
+            for c in \"abc\" { c -> u8; }
+            for i in 0..sum([1, 2]) { [i][0]; }
+            let empty: [i32] = [];
+        "), Ok(()));
+        assert_eq!(check("let ages = [\"ann\": 31]; ages[\"ann\"] + 1;"),
+                   Err(vec!["optional `{integer}?` used without checking for nil".to_string()]));
+        assert_eq!(check("for x in 1 { }"),
+                   Err(vec!["type `{integer}` is not iterable".to_string()]));
+        assert_eq!(check("let a = 1; a[0];"),
+                   Err(vec!["type `{integer}` cannot be indexed".to_string()]));
+        assert_eq!(check("let xs = [1, \"two\"];"),
+                   Err(vec!["mismatched types: expected `{integer}`, found `str`".to_string()]));
+        assert_eq!(check("let r = 0..1.5;"),
+                   Err(vec!["mismatched types: expected `{integer}`, found `{float}`".to_string()]));
+    }
+
+    #[test]
+    fn consts() {
+        assert_eq!(check("
+            fn area() -> i32 { SIDE * SIDE }
+            const SIDE: i32 = 4;
+            const LABEL: str = \"side\";
+        "), Ok(()));
+        assert_eq!(check("const SIDE: i32 = \"four\";"),
+                   Err(vec!["mismatched types: expected `i32`, found `str`".to_string()]));
+    }
+
+    #[test]
+    fn type_aliases() {
+        assert_eq!(check("
+            fn apply(f: Callback, x: Id) -> Id { f(x) }
+            type Id = i32;
+            type Callback = fn(Id) -> Id;
+            type Table = [str: (Id, Callback?)];
+            let table: Table = [\"inc\": (1, |x| x + 1)];
+            let entry = table[\"inc\"];
+            let applied = entry != nil ? apply(|x| x * 2, entry.0) : 0;
+        "), Ok(()));
+        assert_eq!(check("type Id = i32; let id: Id = \"one\";"),
+                   Err(vec!["mismatched types: expected `Id` (`i32`), found `str`".to_string()]));
+        assert_eq!(check("type Ids = [Id]; let ids: Ids = [1];"),
+                   Err(vec!["cannot find type `Id`".to_string()]));
+        assert_eq!(check("type A = [B]; type B = (i32, A); let a: A = [];"),
+                   Err(vec!["type alias `A` refers to itself: A -> B -> A".to_string()]));
+        assert_eq!(check("type Node = Node?;"),
+                   Err(vec!["type alias `Node` refers to itself: Node -> Node".to_string()]));
+    }
+
+    #[test]
+    fn print() {
+        assert_eq!(check("let a: i32? = nil; println \"{} and {:4.1}\", a, 1.5; print a, 1;"),
+                   Ok(()));
+        assert_eq!(check("println \"{} of {}\", 1;"),
+                   Err(vec!["format string has 2 placeholders but 1 argument was given"
+                            .to_string()]));
+        assert_eq!(check("let n: i32? = nil; let s: str = \"n = ${n}, ${n == nil}\";"), Ok(()));
+        assert_eq!(check("let s: i32 = \"${1}\";"),
+                   Err(vec!["mismatched types: expected `i32`, found `str`".to_string()]));
+        assert_eq!(check("print \"none\", 1, 2;"),
+                   Err(vec!["format string has 0 placeholders but 2 arguments were given"
+                            .to_string()]));
+    }
+
+    #[test]
+    fn loops() {
+        assert_eq!(check("
+            let i = 0;
+            let found: i32 = 'search: loop {
+                while i < 10 { i = i + 1; if i == 5 { break 'search i; } }
+                break 0;
+            };
+            'outer: for x in 0..3 { for y in 0..3 { if x == y { continue 'outer; } } }
+            let done = loop { break; };
+        "), Ok(()));
+        assert_eq!(check("let a = loop { if true { break 1; } break \"one\"; };"),
+                   Err(vec!["mismatched types: expected `{integer}`, found `str`".to_string()]));
+        assert_eq!(check("while true { break 1; }"),
+                   Err(vec!["`break` with a value is only allowed in `loop`".to_string()]));
+        assert_eq!(check("'a: loop { for x in 0..1 { break 'b; } }"),
+                   Err(vec!["use of undeclared label `'b`".to_string()]));
+        assert_eq!(check("loop { let f = fn() { continue; }; break; }"),
+                   Err(vec!["`continue` outside of a loop".to_string()]));
+    }
+
+    #[test]
+    fn generic_functions() {
+        assert_eq!(check("
+            fn pick<T>(first: bool, a: T, b: T) -> T { first ? a : b }
+            fn map<T, U>(xs: [T], f: fn(T) -> U) -> [U] {
+                let ys = [];
+                for x in xs { ys = ys + [f(x)]; }
+                ys
+            }
+            type Names = [str];
+            let n: i64 = pick(true, 1, 2);
+            let lens: [i32] = map([\"a\", \"bc\"], |s| 1);
+            let names: Names = map([1, 2], |n| \"n\");
+            let first: str? = pick(false, nil, \"a\");
+        "), Ok(()));
+        assert_eq!(check("fn max<T>(a: T, b: T) -> T { a } let m = max(1, \"one\");"),
+                   Err(vec!["mismatched types: expected `{integer}`, found `str`".to_string()]));
+        assert_eq!(check("fn id<T>(x: T) -> T { x } let s: str = id(1);"),
+                   Err(vec!["mismatched types: expected `str`, found `{integer}`".to_string()]));
+        assert_eq!(check("fn id<T>(x: T) -> T { let y: i32 = x; y }"),
+                   Err(vec!["mismatched types: expected `i32`, found `T`".to_string(),
+                            "mismatched types: expected `T`, found `i32`".to_string()]));
+        assert_eq!(check("fn f<T>(x: T, y: T) -> bool { x < y } f([1], [2]);"),
+                   Err(vec!["cannot apply `<` to `T`".to_string()]));
+        assert_eq!(check("fn neg<T>(x: T) -> T { -x }"),
+                   Err(vec!["cannot apply `-` to `T`".to_string()]));
+        assert_eq!(check("fn eq<T>(a: T, b: T) -> bool { a == b }"),
+                   Err(vec!["cannot apply `==` to `T`".to_string()]));
+        assert_eq!(check("fn max<T: Show>(a: T, b: T) -> T { a }"),
+                   Err(vec!["cannot find model `Show`".to_string()]));
+    }
+
+    #[test]
+    fn model_bounds() {
+        assert_eq!(check("
+            fn max<T: Ord>(a: T, b: T) -> T { a < b ? b : a }
+            fn sum<T: Num>(xs: [T], zero: T) -> T {
+                let total = zero;
+                for x in xs { total = total + x; }
+                -total
+            }
+            fn has<T: Eq>(xs: [T], y: T) -> bool {
+                for x in xs { if x == y { return true; } }
+                false
+            }
+            fn sorted<T: Ord>(a: T, b: T) -> bool { max(a, b) == b }
+            let a: i64 = max(1, 2);
+            let b: str = max(\"a\", \"b\");
+            let c: f64 = sum([1.5, 2.5], 0.0);
+            let d = has([(1, 'a')], (2, 'b'));
+        "), Ok(()));
+        assert_eq!(check("fn max<T: Ord>(a: T, b: T) -> T { a } max(true, false);"),
+                   Err(vec!["type `bool` does not mount model `Ord`".to_string()]));
+        assert_eq!(check("fn sum<T: Num>(a: T, b: T) -> T { a + b } sum(\"a\", \"b\");"),
+                   Err(vec!["type `str` does not mount model `Num`".to_string()]));
+        assert_eq!(check("fn max<T: Ord>(a: T) -> T { a } fn f<U: Eq>(x: U) -> U { max(x) }"),
+                   Err(vec!["type `U` does not mount model `Ord`".to_string()]));
+        assert_eq!(check("fn f<T: Ord>(a: T, b: T) -> T { a * b }"),
+                   Err(vec!["cannot apply `*` to `T`".to_string()]));
+    }
+
+    #[test]
+    fn structs() {
+        assert_eq!(check("
+            struct Point { x: f64, y: f64 }
+            struct Pair<T> { first: T, second: T }
+            struct Named<T: Ord> { name: str, value: T }
+            fn norm(p: Point) -> f64 { p.x * p.x + p.y * p.y }
+            fn swap<T>(p: Pair<T>) -> Pair<T> { Pair { first: p.second, second: p.first } }
+            let p = Point { y: 2.0, x: 1.0 };
+            p.x = 3.0;
+            let n = norm(p);
+            let pair: Pair<i32> = swap(Pair { first: 1, second: 2 });
+            let total: i32 = pair.first + pair.second;
+            let named = Named { name: \"n\", value: 'c' };
+            let same = pair == Pair { first: 2, second: 1 };
+        "), Ok(()));
+        assert_eq!(check("struct P { x: i32 } let p = P { x: 1, y: 2 };"),
+                   Err(vec!["no field `y` on type `P`".to_string()]));
+        assert_eq!(check("struct P { x: i32, y: i32 } let p = P { x: 1, x: 2 };"),
+                   Err(vec!["field `x` is given more than once".to_string(),
+                            "missing field `y` in struct `P`".to_string()]));
+        assert_eq!(check("struct P { x: i32 } let p = P { x: \"one\" };"),
+                   Err(vec!["mismatched types: expected `i32`, found `str`".to_string()]));
+        assert_eq!(check("struct P<T> { x: T } let p = P { x: 1 }; let s: str = p.x;"),
+                   Err(vec!["mismatched types: expected `str`, found `{integer}`".to_string()]));
+        assert_eq!(check("struct P<T> { x: T } fn f(p: P) {} fn g(p: P<i32, str>) {}"),
+                   Err(vec!["`P` takes 1 type argument but 0 were supplied".to_string(),
+                            "`P` takes 1 type argument but 2 were supplied".to_string()]));
+        assert_eq!(check("struct S<T: Ord> { v: T } fn f(s: S<bool>) {}"),
+                   Err(vec!["type `bool` does not mount model `Ord`".to_string()]));
+        assert_eq!(check("struct S<T: Num> { v: T } let s = S { v: \"s\" };"),
+                   Err(vec!["type `str` does not mount model `Num`".to_string()]));
+        assert_eq!(check("type A = i32; let a = A { v: 1 }; let b = B { v: 1 };"),
+                   Err(vec!["`A` is not a struct".to_string(),
+                            "cannot find type `B`".to_string()]));
+        assert_eq!(check("let t = (1, 2); let x = t.first;"),
+                   Err(vec!["no field `first` on type `({integer}, {integer})`".to_string()]));
+    }
+
+    #[test]
+    fn specs() {
+        assert_eq!(check("
+            spec Shape { Circle(f64), Rect(f64, f64), Empty }
+            spec Option<T> { Some(T), None }
+            fn area(s: Shape) -> f64 {
+                match s {
+                    Shape::Circle(r) => 3.14 * r * r,
+                    Shape::Rect(w, h) => w * h,
+                    Shape::Empty => 0.0,
+                }
+            }
+            fn unwrap_or<T>(o: Option<T>, default: T) -> T {
+                match o { Option::Some(x) => x, other => default }
+            }
+            fn first<T>(xs: [T]) -> Option<T> {
+                for x in xs { return Option::Some(x); }
+                Option::None
+            }
+            let a = area(Shape::Rect(1.0, 2.0));
+            let n: i32 = unwrap_or(first([1, 2]), 0);
+            let none: Option<str> = Option::None;
+            let nested = Option::Some(Option::Some(1));
+            let deep: i32 = match nested {
+                Option::Some(Option::Some(x)) => x,
+                Option::Some(Option::None) => 1,
+                Option::None => { 0 }
+            };
+            match none { Option::Some(s) => { print s; }, Option::None => {} }
+        "), Ok(()));
+        assert_eq!(check("spec S { A, B(i32) } let n = match S::A { S::A => 1 };"),
+                   Err(vec!["non-exhaustive match: `S::B` not covered".to_string()]));
+        assert_eq!(check("
+            spec T { X, Y }
+            spec S { A, B(T, T) }
+            let n = match S::A { S::A => 1, S::B(T::X, t) => 2, S::B(t, T::X) => 3 };
+        "), Err(vec!["non-exhaustive match: `S::B` not covered".to_string()]));
+        assert_eq!(check("spec S { A } let n = match S::A { S::C => 1, _ => 2 };"),
+                   Err(vec!["cannot find variant `S::C`".to_string()]));
+        assert_eq!(check("spec S { A(i32) } let n = match S::A(1) { S::A(x, y) => x };"),
+                   Err(vec!["variant `S::A` holds 1 value but the pattern has 2".to_string()]));
+        assert_eq!(check("spec S { A } let n = match 1 { x => x };"),
+                   Err(vec!["cannot match on type `{integer}`".to_string()]));
+        assert_eq!(check("spec S { A, B } let n = match S::A { S::A => 1, S::B => \"b\" };"),
+                   Err(vec!["mismatched types: expected `{integer}`, found `str`".to_string()]));
+        assert_eq!(check("spec S<T: Ord> { A(T) } let s = S::A(true);"),
+                   Err(vec!["type `bool` does not mount model `Ord`".to_string()]));
+        assert_eq!(check("spec S { A(i32) } let s = S::A(\"a\");"),
+                   Err(vec!["mismatched types: expected `i32`, found `str`".to_string()]));
+    }
+
+    #[test]
+    fn operators() {
+        assert_eq!(check("
+            let a = !(1 < 2) || 'a' <= 'b' && \"a\" > \"b\";
+            let b: u8 = 1 << 2 | 3 & 255 ^ 4;
+            let c = -1.5 * 2.0 + 3.0 / 4.0 - 5.0 % 6.0;
+            let d = \"a\" + \"b\";
+            let e: i64 = -9223372036854775807 - 1;
+            let f = |x, y| x + y;
+        "), Ok(()));
+        assert_eq!(check("let a = 1 && true;"),
+                   Err(vec!["cannot apply `&&` to `{integer}`".to_string()]));
+        assert_eq!(check("let b = !\"no\";"),
+                   Err(vec!["cannot apply `!` to `str`".to_string()]));
+        assert_eq!(check("let c = 1.5 | 2;"),
+                   Err(vec!["cannot apply `|` to `{float}`".to_string()]));
+        assert_eq!(check("let d = true + 1;"),
+                   Err(vec!["cannot apply `+` to `bool`".to_string()]));
+        assert_eq!(check("let e = true < false;"),
+                   Err(vec!["cannot apply `<` to `bool`".to_string()]));
+        assert_eq!(check("let f = -'c';"),
+                   Err(vec!["cannot apply `-` to `char`".to_string()]));
+        assert_eq!(check("let g = 1 + 2.0;"),
+                   Err(vec!["mismatched types: expected `{integer}`, found `{float}`"
+                            .to_string()]));
+    }
+
+    #[test]
+    fn literal_ranges() {
+        assert_eq!(check("let a: u8 = 255; let b: i8 = -128; const C: u32 = 4294967295;"),
+                   Ok(()));
+        assert_eq!(check("let a: u64 = 18446744073709551615; let b: i64 = 9223372036854775807;"),
+                   Ok(()));
+        assert_eq!(check("let a: i64 = 9223372036854775808;"),
+                   Err(vec!["literal `9223372036854775808` does not fit in `i64`".to_string()]));
+        assert_eq!(check("let a: u8 = 256;"),
+                   Err(vec!["literal `256` does not fit in `u8`".to_string()]));
+        assert_eq!(check("let b: u16 = -1;"),
+                   Err(vec!["literal `-1` does not fit in `u16`".to_string()]));
+        assert_eq!(check("const C: i8 = -129;"),
+                   Err(vec!["literal `-129` does not fit in `i8`".to_string()]));
+        assert_eq!(check("fn f(x: i16) { } f(40000);"),
+                   Err(vec!["literal `40000` does not fit in `i16`".to_string()]));
+    }
+
+    #[test]
+    fn calls_and_returns() {
+        assert_eq!(check("
+            fn add(a: i32, b: i32) -> i32 { return a + b; }
+            fn log(s: str) { if s == \"\" { return; } print(s); }
+            let twice = fn(x: i32) -> i32 { x * 2 };
+            let n: i32 = twice(add(1, 2));
+        "), Ok(()));
+        assert_eq!(check("fn add(a: i32, b: i32) -> i32 { a + b } add(1);"),
+                   Err(vec!["function takes 2 arguments but 1 was supplied".to_string()]));
+        assert_eq!(check("fn one(a: i32) { } one(1, 2);"),
+                   Err(vec!["function takes 1 argument but 2 were supplied".to_string()]));
+        assert_eq!(check("fn id<T>(x: T) -> T { x } id();"),
+                   Err(vec!["function takes 1 argument but 0 were supplied".to_string()]));
+        assert_eq!(check("let s = \"s\"; s(1);"),
+                   Err(vec!["type `str` is not a function".to_string()]));
+        assert_eq!(check("fn f() -> i32 { \"one\" }"),
+                   Err(vec!["mismatched types: expected `i32`, found `str`".to_string()]));
+        assert_eq!(check("fn f(b: bool) -> i32 { if b { return true; } 1 }"),
+                   Err(vec!["mismatched types: expected `i32`, found `bool`".to_string()]));
+        assert_eq!(check("fn f() -> i32 { return; }"),
+                   Err(vec!["mismatched types: expected `i32`, found `void`".to_string()]));
+        assert_eq!(check("
+            fn sign(x: i32) -> i32 { if x < 0 { return -1; } else { return 1; }; }
+            fn first(xs: [i32]) -> i32 { for x in xs { return x; } return 0; }
+            fn spin() -> i32 { 'outer: loop { loop { break; } for x in [1] { break; } }; }
+            fn done() -> void { let x = 1; }
+        "), Ok(()));
+        let missing = "function may end without returning a value of type `i32`".to_string();
+        assert_eq!(check("fn f() -> i32 { let x = 1; } let y: i32 = f();"),
+                   Err(vec![missing.clone()]));
+        assert_eq!(check("fn f(b: bool) -> i32 { if b { return 1; }; }"),
+                   Err(vec![missing.clone()]));
+        assert_eq!(check("fn f(a: i32) -> i32 { if a > 0 { return 1; } }"),
+                   Err(vec![missing.clone()]));
+        assert_eq!(check("fn f(a: i32) -> i32 { if a > 0 { 1 } else if a < 0 { -1 } }"),
+                   Err(vec![missing.clone()]));
+        assert_eq!(check("fn f() -> i32 { 'outer: loop { loop { break 'outer; } }; }"),
+                   Err(vec![missing.clone()]));
+        assert_eq!(check("let f = fn() -> i32 { loop { break; }; };"), Err(vec![missing]));
+        assert_eq!(check("let f = fn(x: i32) -> bool { x };"),
+                   Err(vec!["mismatched types: expected `bool`, found `i32`".to_string()]));
+        // Each annotation is reported once.
+        assert_eq!(check("fn f(x: Missing) -> Missing { }"),
+                   Err(vec!["cannot find type `Missing`".to_string(); 2]));
+    }
+
+    #[test]
+    fn mismatched_branches() {
+        assert_eq!(check("let a = true ? 1 : \"one\";"),
+                   Err(vec!["conditional branches have different types: `{integer}` and `str`"
+                            .to_string()]));
+        assert_eq!(check("let b = if true { 'c' } else { false };"),
+                   Err(vec!["conditional branches have different types: `char` and `bool`"
+                            .to_string()]));
+    }
+
+    #[test]
+    fn error_locations() {
+        use utils::Position;
+
+        let arena = Arena::new();
+        let module = parse_module(&arena, "let a = 1;\nlet b = a -> bool;\nlet c: Missing = nil;\n\
+                                           fn max<T: Show>(a: T, b: T) -> T { a }").unwrap();
+        let errors = Checker::new("test.ag").check_module(&module).unwrap_err();
+        let spans: Vec<_> = errors.iter().map(|err| err.span).collect();
+        // Bounds are checked as the function is declared, before any statement.
+        assert_eq!(spans, vec![
+            Span(Position(4, 8), Position(4, 15)),
+            Span(Position(2, 9), Position(2, 18)),
+            Span(Position(3, 8), Position(3, 15)),
+        ]);
+    }
+}
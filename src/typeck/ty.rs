@@ -0,0 +1,401 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use syntax::ast::{PrimType, Type, TypeKind};
+
+/// Types inferred by the checker.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Ty {
+    /// One of the built-in primitive types.
+    Prim(PrimType),
+    /// Function type with parameter and return types.
+    Fn(Vec<Ty>, Box<Ty>),
+    /// Array type with the type of its elements.
+    Array(Box<Ty>),
+    /// Map type with the types of its keys and values.
+    Map(Box<Ty>, Box<Ty>),
+    /// Range over integers of the given type.
+    Range(Box<Ty>),
+    /// Tuple type with the types of its elements.
+    Tuple(Vec<Ty>),
+    /// Type admitting nil besides its own values.
+    Optional(Box<Ty>),
+    /// Type parameter of the enclosing generic function, equal only to itself.
+    Param(String),
+    /// Generic function type with its type parameters, instantiated anew at
+    /// each call.
+    Generic(Vec<TyParam>, Box<Ty>),
+    /// Struct or spec with its type arguments:  Pair<i32>
+    Named(String, Vec<Ty>),
+    /// Integer literal without annotation, fits any integer type.
+    IntLit,
+    /// Float literal without annotation, fits any float type.
+    FloatLit,
+    /// Type that is not known yet, compatible with every type.
+    Unknown,
+}
+
+/// The models built into the language, which bound type parameters.  Each
+/// model implies the ones before it:  a number is ordered, and ordered values
+/// can be compared.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Model {
+    /// Values compared with `==`, of every type but unbounded parameters.
+    Eq,
+    /// Values ordered with `<`:  numbers, `char`s and `str`s.
+    Ord,
+    /// Numbers, which the arithmetic operators apply to.
+    Num,
+}
+
+impl Model {
+    pub fn from_name(name: &str) -> Option<Model> {
+        match name {
+            "Eq"  => Some(Model::Eq),
+            "Ord" => Some(Model::Ord),
+            "Num" => Some(Model::Num),
+            _     => None,
+        }
+    }
+}
+
+impl fmt::Display for Model {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+/// Type parameter with the models its type argument must mount.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TyParam {
+    pub name:   String,
+    pub bounds: Vec<Model>,
+}
+
+impl fmt::Display for TyParam {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name)?;
+        for (i, bound) in self.bounds.iter().enumerate() {
+            write!(f, "{}{}", if i == 0 { ": " } else { " + " }, bound)?;
+        }
+        Ok(())
+    }
+}
+
+impl Ty {
+    pub fn bool() -> Ty {
+        Ty::Prim(PrimType::Bool)
+    }
+
+    pub fn void() -> Ty {
+        Ty::Prim(PrimType::Void)
+    }
+
+    pub fn nil() -> Ty {
+        Ty::Prim(PrimType::Nil)
+    }
+
+    /// The optional type of `self`; optional types and nil are left as is.
+    pub fn optional(self) -> Ty {
+        match self {
+            Ty::Optional(_) | Ty::Unknown => self,
+            Ty::Prim(PrimType::Nil)       => self,
+            ty                            => Ty::Optional(Box::new(ty)),
+        }
+    }
+
+    /// Converts a type annotation, calling `named` for the names of type
+    /// aliases, structs and specs, along with their converted type arguments.
+    pub fn from_ast<F>(ty: &Type, named: &mut F) -> Ty
+        where F: FnMut(&str, Vec<Ty>) -> Ty
+    {
+        let mut convert = |ty: &Type| Ty::from_ast(ty, named);
+        match ty.kind {
+            TypeKind::Prim(prim) => Ty::Prim(prim),
+            TypeKind::Fn(params, ret) => {
+                let params = params.iter().map(&mut convert).collect();
+                Ty::Fn(params, Box::new(convert(ret)))
+            },
+            TypeKind::Array(elem) => Ty::Array(Box::new(convert(elem))),
+            TypeKind::Map(key, value) => {
+                let key = convert(key);
+                Ty::Map(Box::new(key), Box::new(convert(value)))
+            },
+            TypeKind::Tuple(elems) => Ty::Tuple(elems.iter().map(convert).collect()),
+            TypeKind::Optional(ty) => convert(ty).optional(),
+            TypeKind::Named(ref name, args) => {
+                let args = args.iter().map(|arg| Ty::from_ast(arg, named)).collect();
+                named(name, args)
+            },
+        }
+    }
+
+    pub fn is_int(&self) -> bool {
+        matches!(*self,
+                 Ty::IntLit |
+                 Ty::Prim(PrimType::I8) | Ty::Prim(PrimType::U8) |
+                 Ty::Prim(PrimType::I16) | Ty::Prim(PrimType::U16) |
+                 Ty::Prim(PrimType::I32) | Ty::Prim(PrimType::U32) |
+                 Ty::Prim(PrimType::I64) | Ty::Prim(PrimType::U64))
+    }
+
+    pub fn is_float(&self) -> bool {
+        matches!(*self, Ty::FloatLit | Ty::Prim(PrimType::F32) | Ty::Prim(PrimType::F64))
+    }
+
+    /// Whether values of this type mount `model`.  A type parameter mounts
+    /// the models its bounds in `params` imply.  Tuples, collections,
+    /// optionals, structs and specs can be compared when their parts can;
+    /// functions are equal only to themselves.
+    pub fn mounts(&self, model: Model, params: &[TyParam]) -> bool {
+        let all = |tys: &[Ty]| tys.iter().all(|ty| ty.mounts(model, params));
+        match *self {
+            Ty::Unknown | Ty::IntLit | Ty::FloatLit => true,
+            Ty::Param(ref name) => params.iter()
+                .find(|param| param.name == *name)
+                .is_some_and(|param| param.bounds.iter().any(|&bound| bound >= model)),
+            ref ty if ty.is_int() || ty.is_float() => true,
+            Ty::Prim(PrimType::Char) | Ty::Prim(PrimType::Str) => model <= Model::Ord,
+            _ if model != Model::Eq => false,
+            Ty::Array(ref elem) | Ty::Range(ref elem) | Ty::Optional(ref elem) => {
+                elem.mounts(model, params)
+            },
+            Ty::Map(ref key, ref value) => {
+                key.mounts(model, params) && value.mounts(model, params)
+            },
+            Ty::Tuple(ref elems) | Ty::Named(_, ref elems) => all(elems),
+            Ty::Prim(_) | Ty::Fn(..) | Ty::Generic(..) => true,
+        }
+    }
+
+    /// Matches `self`, which may mention type parameters, against `actual`
+    /// and records in `subst` the type each parameter stands for.  A
+    /// parameter matched twice takes the type both matches fit in; when
+    /// there is none the first match is kept.
+    pub fn infer(&self, actual: &Ty, subst: &mut HashMap<String, Ty>) {
+        match (self, actual) {
+            (Ty::Param(name), actual) => {
+                let ty = match subst.get(name) {
+                    Some(bound) => bound.unify(actual),
+                    None        => Some(actual.clone()),
+                };
+                if let Some(ty) = ty {
+                    subst.insert(name.clone(), ty);
+                }
+            },
+            (Ty::Fn(params, ret), Ty::Fn(actuals, actual_ret)) => {
+                for (param, actual) in params.iter().zip(actuals) {
+                    param.infer(actual, subst);
+                }
+                ret.infer(actual_ret, subst);
+            },
+            (Ty::Tuple(elems), Ty::Tuple(actuals)) => {
+                for (elem, actual) in elems.iter().zip(actuals) {
+                    elem.infer(actual, subst);
+                }
+            },
+            (&Ty::Array(ref elem), &Ty::Array(ref actual)) |
+            (&Ty::Range(ref elem), &Ty::Range(ref actual)) => elem.infer(actual, subst),
+            (Ty::Map(key, value), Ty::Map(actual_key, actual)) => {
+                key.infer(actual_key, subst);
+                value.infer(actual, subst);
+            },
+            (&Ty::Optional(_), &Ty::Prim(PrimType::Nil)) => {},
+            (Ty::Optional(ty), Ty::Optional(actual)) => ty.infer(actual, subst),
+            (Ty::Optional(ty), actual) => ty.infer(actual, subst),
+            (Ty::Named(name, args), Ty::Named(actual_name, actuals)) if name == actual_name => {
+                for (arg, actual) in args.iter().zip(actuals) {
+                    arg.infer(actual, subst);
+                }
+            },
+            _ => {},
+        }
+    }
+
+    /// Replaces the type parameters of `self` by their types in `subst`, or
+    /// by `Unknown` when they were not inferred.
+    pub fn subst(&self, subst: &HashMap<String, Ty>) -> Ty {
+        match *self {
+            Ty::Param(ref name) => subst.get(name).cloned().unwrap_or(Ty::Unknown),
+            Ty::Fn(ref params, ref ret) => {
+                Ty::Fn(params.iter().map(|param| param.subst(subst)).collect(),
+                       Box::new(ret.subst(subst)))
+            },
+            Ty::Tuple(ref elems) => Ty::Tuple(elems.iter().map(|elem| elem.subst(subst)).collect()),
+            Ty::Array(ref elem) => Ty::Array(Box::new(elem.subst(subst))),
+            Ty::Range(ref elem) => Ty::Range(Box::new(elem.subst(subst))),
+            Ty::Map(ref key, ref value) => {
+                Ty::Map(Box::new(key.subst(subst)), Box::new(value.subst(subst)))
+            },
+            Ty::Optional(ref ty) => ty.subst(subst).optional(),
+            Ty::Named(ref name, ref args) => {
+                Ty::Named(name.clone(), args.iter().map(|arg| arg.subst(subst)).collect())
+            },
+            ref ty => ty.clone(),
+        }
+    }
+
+    /// The type of the elements a `for` loop binds, or `None` when the type
+    /// is not iterable.
+    pub fn elem(&self) -> Option<Ty> {
+        match *self {
+            Ty::Unknown                      => Some(Ty::Unknown),
+            Ty::Range(ref elem) | Ty::Array(ref elem) => Some((**elem).clone()),
+            Ty::Map(ref key, ref value)      => Some(Ty::Tuple(vec![(**key).clone(), (**value).clone()])),
+            Ty::Prim(PrimType::Str)          => Some(Ty::Prim(PrimType::Char)),
+            _                                => None,
+        }
+    }
+
+    /// Whether a value of this type may be cast to `target`.  Numbers cast
+    /// between each other, `char` and `bool` widen to integers, and only `u8`
+    /// narrows to `char`.  Every other cast must be to the same type.
+    pub fn can_cast(&self, target: PrimType) -> bool {
+        let to = Ty::Prim(target);
+        match *self {
+            Ty::Unknown => true,
+            Ty::Prim(PrimType::Char) | Ty::Prim(PrimType::Bool) if to.is_int() => true,
+            ref from if from.is_int() || from.is_float() => {
+                to.is_int() || to.is_float() ||
+                    target == PrimType::Char && from.unify(&Ty::Prim(PrimType::U8)).is_some()
+            },
+            ref from => *from == to,
+        }
+    }
+
+    /// Whether a value of type `value` may be stored where `self` is
+    /// expected.  Unlike `unify`, an optional value is not accepted in place
+    /// of the type it wraps.
+    pub fn accepts(&self, value: &Ty) -> bool {
+        match (self, value) {
+            (&Ty::Unknown, _) | (_, &Ty::Unknown) => true,
+            // A generic function stored as a value takes any type arguments.
+            (ty, Ty::Generic(_, generic)) => ty.accepts(&generic.subst(&HashMap::new())),
+            (&Ty::Optional(_), &Ty::Prim(PrimType::Nil)) => true,
+            (Ty::Optional(ty), Ty::Optional(value)) => ty.accepts(value),
+            (Ty::Optional(ty), value) => ty.accepts(value),
+            (_, &Ty::Optional(_)) => false,
+            (ty, &Ty::Prim(PrimType::Nil)) => *ty == Ty::nil(),
+            (Ty::Tuple(elems), Ty::Tuple(values)) => {
+                elems.len() == values.len() &&
+                    elems.iter().zip(values).all(|(elem, value)| elem.accepts(value))
+            },
+            (Ty::Array(elem), Ty::Array(value)) => elem.accepts(value),
+            (Ty::Map(key, elem), Ty::Map(value_key, value)) => {
+                key.accepts(value_key) && elem.accepts(value)
+            },
+            (Ty::Named(name, args), Ty::Named(value_name, values)) => {
+                name == value_name && args.len() == values.len() &&
+                    args.iter().zip(values).all(|(arg, value)| arg.accepts(value))
+            },
+            (ty, value) => ty.unify(value).is_some(),
+        }
+    }
+
+    /// The type both `self` and `other` fit in, or `None` when they are
+    /// incompatible.  Literal types give way to the concrete numeric type and
+    /// nil makes the other type optional.
+    pub fn unify(&self, other: &Ty) -> Option<Ty> {
+        match (self, other) {
+            (&Ty::Unknown, ty) | (ty, &Ty::Unknown) => Some(ty.clone()),
+            (&Ty::Prim(PrimType::Nil), ty) | (ty, &Ty::Prim(PrimType::Nil)) => {
+                Some(ty.clone().optional())
+            },
+            (Ty::Optional(lhs), Ty::Optional(rhs)) => Some(lhs.unify(rhs)?.optional()),
+            (&Ty::Optional(ref lhs), rhs) | (rhs, &Ty::Optional(ref lhs)) => {
+                Some(lhs.unify(rhs)?.optional())
+            },
+            (&Ty::IntLit, ty) | (ty, &Ty::IntLit) if ty.is_int() => Some(ty.clone()),
+            (&Ty::FloatLit, ty) | (ty, &Ty::FloatLit) if ty.is_float() => Some(ty.clone()),
+            (Ty::Fn(lparams, lret), Ty::Fn(rparams, rret)) => {
+                if lparams.len() != rparams.len() {
+                    return None;
+                }
+                let mut params = Vec::with_capacity(lparams.len());
+                for (lhs, rhs) in lparams.iter().zip(rparams) {
+                    params.push(lhs.unify(rhs)?);
+                }
+                Some(Ty::Fn(params, Box::new(lret.unify(rret)?)))
+            },
+            (Ty::Tuple(lhs), Ty::Tuple(rhs)) => {
+                if lhs.len() != rhs.len() {
+                    return None;
+                }
+                let mut elems = Vec::with_capacity(lhs.len());
+                for (lhs, rhs) in lhs.iter().zip(rhs) {
+                    elems.push(lhs.unify(rhs)?);
+                }
+                Some(Ty::Tuple(elems))
+            },
+            (Ty::Array(lhs), Ty::Array(rhs)) => Some(Ty::Array(Box::new(lhs.unify(rhs)?))),
+            (Ty::Range(lhs), Ty::Range(rhs)) => Some(Ty::Range(Box::new(lhs.unify(rhs)?))),
+            (Ty::Map(lkey, lvalue), Ty::Map(rkey, rvalue)) => {
+                Some(Ty::Map(Box::new(lkey.unify(rkey)?), Box::new(lvalue.unify(rvalue)?)))
+            },
+            (Ty::Named(lname, largs), Ty::Named(rname, rargs)) => {
+                if lname != rname || largs.len() != rargs.len() {
+                    return None;
+                }
+                let mut args = Vec::with_capacity(largs.len());
+                for (lhs, rhs) in largs.iter().zip(rargs) {
+                    args.push(lhs.unify(rhs)?);
+                }
+                Some(Ty::Named(lname.clone(), args))
+            },
+            (lhs, rhs) if lhs == rhs => Some(lhs.clone()),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Ty {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Ty::Prim(ref prim) => prim.fmt(f),
+            Ty::Fn(ref params, ref ret) => {
+                write!(f, "fn(")?;
+                for (i, param) in params.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    param.fmt(f)?;
+                }
+                write!(f, ") -> {}", ret)
+            },
+            Ty::Array(ref elem) => write!(f, "[{}]", elem),
+            Ty::Map(ref key, ref value) => write!(f, "[{}: {}]", key, value),
+            Ty::Range(_) => write!(f, "range"),
+            Ty::Tuple(ref elems) => {
+                write!(f, "(")?;
+                for (i, elem) in elems.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    elem.fmt(f)?;
+                }
+                if elems.len() == 1 {
+                    write!(f, ",")?;
+                }
+                write!(f, ")")
+            },
+            Ty::Optional(ref ty) => write!(f, "{}?", ty),
+            Ty::Param(ref name) => write!(f, "{}", name),
+            Ty::Generic(ref params, ref ty) => {
+                // `fn<T: Ord>(T) -> T`, inserting the parameters into the type.
+                let params: Vec<_> = params.iter().map(TyParam::to_string).collect();
+                write!(f, "fn<{}>{}", params.join(", "), &ty.to_string()[2..])
+            },
+            Ty::Named(ref name, ref args) => {
+                write!(f, "{}", name)?;
+                if !args.is_empty() {
+                    let args: Vec<_> = args.iter().map(Ty::to_string).collect();
+                    write!(f, "<{}>", args.join(", "))?;
+                }
+                Ok(())
+            },
+            Ty::IntLit   => write!(f, "{{integer}}"),
+            Ty::FloatLit => write!(f, "{{float}}"),
+            Ty::Unknown  => write!(f, "_"),
+        }
+    }
+}
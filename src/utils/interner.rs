@@ -2,7 +2,6 @@ use std::fmt;
 use std::cell::RefCell;
 
 use fnv::FnvHashMap;
-use internal_macro;
 
 #[derive(Clone, Copy, Eq, PartialEq, Hash)]
 pub struct Symbol(u32);
@@ -23,6 +22,16 @@ impl Symbol {
     pub fn as_str<'a>(self) -> &'a str {
         resolve(self)
     }
+
+    /// Index of the symbol in the interner's string table.
+    pub fn as_usize(self) -> usize {
+        self.0 as usize
+    }
+
+    /// Rebuilds a symbol from an index previously returned by `as_usize`.
+    pub fn from_usize(index: usize) -> Symbol {
+        Symbol(index as u32)
+    }
 }
 
 
@@ -58,7 +67,24 @@ impl Interner {
     }
 }
 
-internal_macro::declare_keywords! {
+// Declares the `keywords` constants and the prefilled `Interner::fresh`
+// constructor, so reserved words always intern to the same symbols.
+macro_rules! declare_keywords {(
+    $( ($index: expr, $konst: ident, $string: expr) )*
+) => {
+    pub mod keywords {
+        use super::Symbol;
+        $( pub const $konst: Symbol = Symbol($index); )*
+    }
+
+    impl Interner {
+        fn fresh() -> Self {
+            Interner::prefill(&[$($string,)*])
+        }
+    }
+}}
+
+declare_keywords! {
     (0,      NIL_KW,           "nil")
         (1,  IF_KEYWORD,       "if")
         (2,  ELSE_KEYWORD,     "else")
@@ -73,7 +99,7 @@ internal_macro::declare_keywords! {
         (11, CONST_RESERVED,   "const")
 }
 
-fn with_interner<T, F: FnOnce(&mut Interner) -> (f: F) -> T {
+fn with_interner<T, F: FnOnce(&mut Interner) -> T>(f: F) -> T {
     // Declares a new thread local storage key of type std::thread::LocalKey.
     // LocalKey is a thread local storage key which owns its own contents.
     thread_local!(static INTERNER: RefCell<Interner> = {
@@ -1,3 +1,14 @@
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use super::interner::{intern, resolve, Symbol};
+
+/// Extension of Argentum source files on disk.
+pub const SOURCE_EXT: &str = "ag";
+
+/// A `::` separated path such as `::geometry::shapes`.  Each segment is stored
+/// as the interner index of its identifier.
 #[derive(Clone)]
 pub struct Path {
     is_absolute: bool,
@@ -10,7 +21,217 @@ impl Path {
         Path { is_absolute, id_indexes, string: None }
     }
 
+    /// Builds a path from interned segments.
+    pub fn from_symbols(is_absolute: bool, segments: &[Symbol]) -> Path {
+        Path::new(is_absolute, segments.iter().map(|sym| sym.as_usize()).collect())
+    }
+
     pub fn is_absolute(&self) -> bool {
         self.is_absolute
     }
+
+    /// Number of segments in the path.
+    pub fn len(&self) -> usize {
+        self.id_indexes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.id_indexes.is_empty()
+    }
+
+    /// Iterates over the interned segments, first to last.
+    pub fn segments<'a>(&'a self) -> impl Iterator<Item = Symbol> + 'a {
+        self.id_indexes.iter().map(|&index| Symbol::from_usize(index))
+    }
+
+    /// The last segment, naming the item a path refers to.
+    pub fn last(&self) -> Option<Symbol> {
+        self.id_indexes.last().map(|&index| Symbol::from_usize(index))
+    }
+
+    /// The identifier of the last segment, or `""` for an empty path.
+    pub fn last_str<'a>(&self) -> &'a str {
+        self.last().map(resolve).unwrap_or("")
+    }
+
+    /// Appends a segment.
+    pub fn push(&mut self, segment: Symbol) {
+        self.id_indexes.push(segment.as_usize());
+        self.string = None;
+    }
+
+    /// Appends `other` to this path.  An absolute `other` replaces the path,
+    /// the same way `std::path::Path::join` treats absolute paths.
+    pub fn join(&self, other: &Path) -> Path {
+        if other.is_absolute {
+            return other.clone();
+        }
+        let mut id_indexes = self.id_indexes.clone();
+        id_indexes.extend(other.id_indexes.iter().cloned());
+        Path::new(self.is_absolute, id_indexes)
+    }
+
+    /// The path without its last segment, or `None` for an empty path.  For
+    /// `a::b::c` it names the module `a::b` that declares `c`.
+    pub fn parent(&self) -> Option<Path> {
+        if self.id_indexes.is_empty() {
+            return None;
+        }
+        let mut id_indexes = self.id_indexes.clone();
+        id_indexes.pop();
+        Some(Path::new(self.is_absolute, id_indexes))
+    }
+
+    /// Whether the leading segments of the path are those of `prefix`.
+    pub fn starts_with(&self, prefix: &Path) -> bool {
+        self.is_absolute == prefix.is_absolute &&
+            self.id_indexes.starts_with(&prefix.id_indexes)
+    }
+
+    /// The remainder of the path after `prefix`, as a relative path.
+    pub fn strip_prefix(&self, prefix: &Path) -> Option<Path> {
+        if !self.starts_with(prefix) {
+            return None;
+        }
+        Some(Path::new(false, self.id_indexes[prefix.len()..].to_vec()))
+    }
+
+    /// Renders the path as written in source, caching the result.
+    pub fn as_str(&mut self) -> &str {
+        if self.string.is_none() {
+            self.string = Some(self.render());
+        }
+        self.string.as_ref().unwrap()
+    }
+
+    fn render(&self) -> String {
+        let mut string = String::new();
+        for (i, segment) in self.segments().enumerate() {
+            if i > 0 || self.is_absolute {
+                string.push_str("::");
+            }
+            string.push_str(resolve(segment));
+        }
+        string
+    }
+
+    /// Maps the path onto a source file below `base`: `a::b::c` becomes
+    /// `base/a/b/c.ag`.
+    pub fn to_file_path(&self, base: &::std::path::Path) -> PathBuf {
+        let mut file = base.to_path_buf();
+        for segment in self.segments() {
+            file.push(resolve(segment));
+        }
+        file.set_extension(SOURCE_EXT);
+        file
+    }
+}
+
+/// Error returned when a string is not a well formed `::` separated path.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PathParseError(pub String);
+
+impl fmt::Display for PathParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid path `{}`", self.0)
+    }
+}
+
+impl FromStr for Path {
+    type Err = PathParseError;
+
+    /// Parses a path as written in source, i.e. `::std::io::print`.  The
+    /// source text is kept as the rendered string.  Identifiers are ASCII, as
+    /// in the grammar.
+    fn from_str(source: &str) -> Result<Path, PathParseError> {
+        let is_absolute = source.starts_with("::");
+        let body = if is_absolute { &source[2..] } else { source };
+        let mut id_indexes = Vec::new();
+        for segment in body.split("::") {
+            let mut chars = segment.chars();
+            let valid = match chars.next() {
+                Some(chr) => (chr.is_ascii_alphabetic() || chr == '_') &&
+                    chars.all(|chr| chr.is_ascii_alphanumeric() || chr == '_'),
+                None      => false,
+            };
+            if !valid {
+                return Err(PathParseError(source.to_string()));
+            }
+            id_indexes.push(intern(segment).as_usize());
+        }
+        Ok(Path { is_absolute, id_indexes, string: Some(source.to_string()) })
+    }
+}
+
+// The rendered string is a cache and does not take part in comparisons.
+impl PartialEq for Path {
+    fn eq(&self, other: &Path) -> bool {
+        self.is_absolute == other.is_absolute && self.id_indexes == other.id_indexes
+    }
+}
+
+impl fmt::Display for Path {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.string {
+            Some(ref string) => f.write_str(string),
+            None             => f.write_str(&self.render()),
+        }
+    }
+}
+
+impl fmt::Debug for Path {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Path({})", self)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn path(source: &str) -> Path {
+        source.parse().unwrap()
+    }
+
+    #[test]
+    fn parse_and_display() {
+        assert_eq!(path("::std::io::print").to_string(), "::std::io::print");
+        assert_eq!(path("Shape::Circle").to_string(), "Shape::Circle");
+        assert!(path("::std").is_absolute());
+        assert!("a::".parse::<Path>().is_err());
+        assert!("::".parse::<Path>().is_err());
+        assert!("a::1b".parse::<Path>().is_err());
+        assert!("é::a".parse::<Path>().is_err());
+        assert!("a::b٣".parse::<Path>().is_err());
+    }
+
+    #[test]
+    fn segments_are_interned() {
+        let segments: Vec<Symbol> = path("a::b::a").segments().collect();
+        assert_eq!(segments.len(), 3);
+        assert_eq!(segments[0], segments[2]);
+        assert_eq!(segments[1], intern("b"));
+    }
+
+    #[test]
+    fn join_parent_and_prefix() {
+        let base = path("::geometry");
+        assert_eq!(base.join(&path("shapes::area")), path("::geometry::shapes::area"));
+        assert_eq!(base.join(&path("::std")), path("::std"));
+        assert_eq!(path("a::b::c").parent(), Some(path("a::b")));
+        assert_eq!(Path::new(false, Vec::new()).parent(), None);
+
+        let full = path("::geometry::shapes::area");
+        assert!(full.starts_with(&base));
+        assert!(!full.starts_with(&path("geometry")));
+        assert_eq!(full.strip_prefix(&base), Some(path("shapes::area")));
+    }
+
+    #[test]
+    fn rendering_is_cached() {
+        let mut joined = path("a").join(&path("b"));
+        assert_eq!(joined.as_str(), "a::b");
+        joined.push(intern("c"));
+        assert_eq!(joined.as_str(), "a::b::c");
+    }
 }
@@ -1,3 +1,6 @@
+mod interner;
+pub use self::interner::*;
+
 mod iota;
 pub use self::iota::*;
 
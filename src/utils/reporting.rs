@@ -1,13 +1,32 @@
 use std::fmt;
 
 /// The position of a single character in a source document.
-#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
 pub struct Position (pub u32, pub u32);
 
 /// Span provides access to the start and end position of last token.
-#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+/// The default span, at row and column 0, stands for an unknown location.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
 pub struct Span(pub Position, pub Position);
 
+impl Span {
+    /// The span from the start of `self` to the end of `other`.
+    pub fn to(self, other: Span) -> Span {
+        Span(self.0, other.1)
+    }
+}
+
+/// Identifies a source file among the files read by the module loader.
+/// Sources parsed on their own, outside of a loader, are file 0.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub struct FileId(pub u32);
+
+impl fmt::Display for FileId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
+
 impl fmt::Display for Span {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let Span(Position(start_row, start_col), Position(end_row, end_col)) = *self;
@@ -55,3 +74,23 @@ impl fmt::Display for LexicalDiagnostic {
                self.msg)
     }
 }
+
+/// Diagnostic is the general purpose report emitted by the stages following
+/// the lexer (module loading, parsing, checking).
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct Diagnostic {
+    pub source: String,
+    pub span: Span,
+    pub severity: Severity,
+    pub msg: String
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {} {}: {}",
+               self.source,
+               self.span,
+               self.severity,
+               self.msg)
+    }
+}
@@ -1,8 +1,14 @@
 extern crate argentum;
 
+use std::cell::RefCell;
 use std::fs::File;
-use std::io::prelude::*;
+use std::io::{self, prelude::*};
+use std::rc::Rc;
 
+use argentum::loader::Loader;
+use argentum::program::Program;
+use argentum::utils::{Position, Span};
+use argentum::syntax::ast::Arena;
 use argentum::syntax::parser;
 
 macro_rules! integration_test {
@@ -27,3 +33,150 @@ macro_rules! integration_test {
 }
 
 integration_test!(test_comment, "comment", true);
+integration_test!(test_modules_main, "modules/main", true);
+integration_test!(test_modules_shapes, "modules/geometry/shapes", true);
+
+macro_rules! loader_test {
+  ($id:ident, $name:expr, $expect:expr) => {
+    #[test]
+    fn $id() {
+      let arena = Arena::new();
+      let mut loader = Loader::new(&arena, "tests/fixtures/modules");
+      let outcome = loader.load(format!("tests/fixtures/modules/{}.ag", $name));
+      let messages: Vec<String> = match outcome {
+        Ok(_)      => Vec::new(),
+        Err(diags) => diags.iter().map(|diag| diag.msg.clone()).collect(),
+      };
+      let expect: &[&str] = $expect;
+      assert_eq!(messages, expect, "unexpected diagnostics loading {}", $name);
+    }
+  }
+}
+
+loader_test!(test_load_main, "main", &[]);
+loader_test!(test_load_reexport, "reexport", &[]);
+loader_test!(test_load_private_item, "private", &["`geometry::shapes::helper` is private"]);
+loader_test!(test_load_unresolved, "unresolved", &["unresolved import `geometry::circle`"]);
+loader_test!(test_load_paths, "paths", &[]);
+loader_test!(test_load_private_path, "private_path", &["`shapes::helper` is private"]);
+loader_test!(test_load_unresolved_path, "unresolved_path", &[
+  "cannot find `util::nothere`",
+  "cannot find `nothere::area`",
+  "cannot find `::geometry::circle::area`",
+]);
+
+#[test]
+fn test_load_shares_modules() {
+  let arena = Arena::new();
+  let mut loader = Loader::new(&arena, "tests/fixtures/modules");
+  let main = loader.load("tests/fixtures/modules/main.ag").unwrap();
+  let imports = &loader.module(main).imports;
+  let names: Vec<&str> = imports.iter().map(|import| import.name.as_str()).collect();
+  assert_eq!(names, ["area", "shapes", "square"]);
+  // `area` and `shapes` resolve to the same module file.
+  assert_eq!(imports[0].module, imports[1].module);
+  assert_eq!(imports[0].item, Some("area".to_string()));
+  assert_eq!(imports[1].item, None);
+  assert_eq!(loader.modules().len(), 3);
+}
+
+/// Output sink whose contents remain readable after a program takes it.
+#[derive(Clone, Default)]
+struct Buffer(Rc<RefCell<Vec<u8>>>);
+
+impl Write for Buffer {
+  fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+    self.0.borrow_mut().write(buf)
+  }
+
+  fn flush(&mut self) -> io::Result<()> {
+    Ok(())
+  }
+}
+
+/// Checks and runs a program of `tests/fixtures/modules`, returning what it
+/// prints or its diagnostics.
+fn run(name: &str) -> Result<String, Vec<String>> {
+  let messages = |diags: Vec<argentum::utils::Diagnostic>| {
+    diags.into_iter().map(|diag| diag.msg).collect::<Vec<String>>()
+  };
+  let arena = Arena::new();
+  let mut program =
+    Program::load(&arena, format!("tests/fixtures/modules/{}.ag", name)).map_err(messages)?;
+  program.check().map_err(messages)?;
+  let buffer = Buffer::default();
+  program.run(Box::new(buffer.clone())).map_err(|diag| vec![diag.msg])?;
+  let bytes = buffer.0.borrow().clone();
+  Ok(String::from_utf8(bytes).unwrap())
+}
+
+macro_rules! run_test {
+  ($id:ident, $name:expr, $expect:expr) => {
+    #[test]
+    fn $id() {
+      let expect: Result<&str, Vec<&str>> = $expect;
+      let expect = expect.map(str::to_string)
+        .map_err(|msgs| msgs.into_iter().map(str::to_string).collect());
+      assert_eq!(run($name), expect, "unexpected outcome running {}", $name);
+    }
+  }
+}
+
+run_test!(test_run_main, "main", Ok("16\n25\n36\n"));
+run_test!(test_run_reexport, "reexport", Ok(""));
+run_test!(test_run_paths, "paths", Ok("4\n9\n16\n25\n"));
+run_test!(test_run_order, "order", Ok("greet runs first\norder runs next\nhello, order\n"));
+run_test!(test_run_typed_import, "typed_import", Err(vec![
+  "mismatched types: expected `str`, found `i32`",
+  "function takes 1 argument but 3 were supplied",
+  "mismatched types: expected `i32`, found `str`",
+]));
+run_test!(test_run_consts, "consts", Err(vec![
+  "initializer of `N` is not constant: `y` is not a constant",
+  "initializer of `M` is not constant: function calls",
+  "constants refer to each other: A -> B -> A",
+]));
+run_test!(test_run_const_range, "const_range", Err(vec![
+  "literal `200` does not fit in `i8`",
+  "literal `-50` does not fit in `u8`",
+  "literal `1180591620717411303424` does not fit in `i32`",
+]));
+run_test!(test_run_const_use, "const_use", Ok("36\n"));
+run_test!(test_run_overflow, "overflow", Err(vec![
+  "arithmetic overflow: the result does not fit in `u8`",
+]));
+run_test!(test_run_private_item, "private", Err(vec!["`geometry::shapes::helper` is private"]));
+
+#[test]
+fn test_run_error_location() {
+  let arena = Arena::new();
+  let mut program = Program::load(&arena, "tests/fixtures/modules/runtime_error.ag").unwrap();
+  program.check().unwrap();
+  let buffer = Buffer::default();
+  let diag = program.run(Box::new(buffer.clone())).unwrap_err();
+  assert_eq!(diag.msg, "division by zero");
+  assert!(diag.source.ends_with("divide.ag"), "error located in {}", diag.source);
+  assert_eq!(diag.span, Span(Position(2, 3), Position(2, 8)));
+  assert_eq!(*buffer.0.borrow(), b"2\n");
+}
+
+#[test]
+fn test_load_parse_error_location() {
+  let arena = Arena::new();
+  let mut loader = Loader::new(&arena, "tests/fixtures/modules");
+  let diags = loader.load("tests/fixtures/modules/parse_error.ag").unwrap_err();
+  assert_eq!(diags.len(), 1);
+  assert!(diags[0].msg.starts_with("cannot parse module: "), "{}", diags[0].msg);
+  assert!(diags[0].source.ends_with("broken.ag"), "error located in {}", diags[0].source);
+  assert_eq!(diags[0].span, Span(Position(2, 7), Position(2, 7)));
+}
+
+#[test]
+fn test_load_cycle() {
+  let arena = Arena::new();
+  let mut loader = Loader::new(&arena, "tests/fixtures/modules");
+  let diags = loader.load("tests/fixtures/modules/cycle_a.ag").unwrap_err();
+  assert_eq!(diags.len(), 1);
+  assert!(diags[0].msg.starts_with("import cycle: "), "{}", diags[0].msg);
+  assert!(diags[0].msg.ends_with("cycle_a.ag"), "{}", diags[0].msg);
+}